@@ -0,0 +1,293 @@
+//! Definition of the information that the `kernel_loader` hands to the kernel
+//!
+//! The loader prepares a [`BootInfo`] struct in physical memory which it does not hand to the kernel as free memory
+//! and passes its physical address to the kernel in the `a0` register.
+//! All addresses contained in it are physical addresses.
+#![no_std]
+
+use core::fmt;
+
+/// The maximum number of regions that a [`MemoryRegions`] list can hold
+pub const MAX_MEMORY_REGIONS: usize = 16;
+
+/// A contiguous region of physical memory
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct MemoryRegion {
+    /// The physical address at which the region starts
+    pub start: usize,
+    /// The length of the region in bytes
+    pub len: usize,
+}
+
+impl MemoryRegion {
+    /// Create a new region that starts at the given address and spans `len` bytes
+    pub const fn new(start: usize, len: usize) -> Self {
+        Self { start, len }
+    }
+
+    /// Create a new region that covers the given slice
+    pub fn from_slice(slice: &[u8]) -> Self {
+        Self::new(slice.as_ptr() as usize, slice.len())
+    }
+
+    /// Create a region that does not describe any memory
+    pub const fn empty() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// The first address after the region
+    pub const fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    /// Whether the region does not contain any memory
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the given address lies inside the region
+    pub const fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end()
+    }
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}..{:#x}", self.start, self.end())
+    }
+}
+
+/// A list of up to [`MAX_MEMORY_REGIONS`] memory regions which does not require an allocator
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MemoryRegions {
+    regions: [MemoryRegion; MAX_MEMORY_REGIONS],
+    len: usize,
+}
+
+impl MemoryRegions {
+    /// Create an empty list
+    pub const fn new() -> Self {
+        Self {
+            regions: [MemoryRegion::empty(); MAX_MEMORY_REGIONS],
+            len: 0,
+        }
+    }
+
+    /// Append a region to the list.
+    ///
+    /// If the list is already full, the region is handed back as error.
+    pub fn push(&mut self, region: MemoryRegion) -> Result<(), MemoryRegion> {
+        if self.len == MAX_MEMORY_REGIONS {
+            return Err(region);
+        }
+        self.regions[self.len] = region;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Get the regions contained in the list
+    pub fn as_slice(&self) -> &[MemoryRegion] {
+        &self.regions[..self.len]
+    }
+
+    /// Iterate over the regions contained in the list
+    pub fn iter(&self) -> core::slice::Iter<'_, MemoryRegion> {
+        self.as_slice().iter()
+    }
+
+    /// Remove the region at the given index while keeping the order of the other regions
+    fn remove(&mut self, index: usize) {
+        self.regions.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        self.regions[self.len] = MemoryRegion::empty();
+    }
+
+    /// Remove the given hole from all regions in the list.
+    ///
+    /// Regions which are partially covered by the hole are shrunk and regions which are completely covered are
+    /// removed.
+    /// If the hole lies in the middle of a region, that region is split in two which fails with the part after the
+    /// hole as error if the list is already full.
+    pub fn subtract(&mut self, hole: &MemoryRegion) -> Result<(), MemoryRegion> {
+        if hole.is_empty() {
+            return Ok(());
+        }
+
+        let mut i = 0;
+        while i < self.len {
+            let region = self.regions[i];
+            if hole.end() <= region.start || region.end() <= hole.start {
+                // no overlap
+                i += 1;
+                continue;
+            }
+
+            let before = MemoryRegion::new(region.start, hole.start.saturating_sub(region.start));
+            let after = MemoryRegion::new(hole.end(), region.end().saturating_sub(hole.end()));
+            match (before.is_empty(), after.is_empty()) {
+                (true, true) => {
+                    self.remove(i);
+                    continue;
+                }
+                (false, true) => self.regions[i] = before,
+                (true, false) => self.regions[i] = after,
+                (false, false) => {
+                    self.regions[i] = before;
+                    self.push(after)?;
+                }
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// Shrink all regions so that they start and end at multiples of `align` and remove the ones that become empty
+    pub fn shrink_to_alignment(&mut self, align: usize) {
+        let mut i = 0;
+        while i < self.len {
+            let region = self.regions[i];
+            let start = region.start.next_multiple_of(align);
+            let end = region.end() / align * align;
+            if start >= end {
+                self.remove(i);
+            } else {
+                self.regions[i] = MemoryRegion::new(start, end - start);
+                i += 1;
+            }
+        }
+    }
+
+    /// Get the region with the most bytes or the first one of them if several are equally large
+    pub fn largest(&self) -> Option<&MemoryRegion> {
+        self.iter().reduce(|largest, region| {
+            if region.len > largest.len {
+                region
+            } else {
+                largest
+            }
+        })
+    }
+}
+
+impl Default for MemoryRegions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Information about the system and the boot process that the `kernel_loader` hands to the kernel
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct BootInfo {
+    /// The flattened device tree which describes the hardware lunatix was booted on.
+    /// It is left where the bootloader placed it.
+    pub fdt: MemoryRegion,
+
+    /// All physical memory as it is described by the device tree, including reserved regions
+    pub memory: MemoryRegions,
+
+    /// Regions of physical memory that must not be used by the kernel.
+    ///
+    /// This includes the reservations of the device tree, the device tree itself as well as everything that the
+    /// loader has allocated like the kernels page tables and the boot info.
+    pub reserved: MemoryRegions,
+
+    /// All physical memory that is neither reserved nor otherwise in use and which the kernel is free to use for its
+    /// own allocations and to hand out to userspace.
+    /// The regions are page aligned and do not overlap with each other.
+    pub usable: MemoryRegions,
+
+    /// The ELF binary of the init program
+    pub init: MemoryRegion,
+
+    /// The initial ram filesystem that is handed to init or an empty region if none was given at boot.
+    /// It is page aligned so that it can be mapped into init.
+    pub initrd: MemoryRegion,
+
+    /// The command line that was given at boot as UTF-8 encoded string or an empty region if there was none.
+    /// It lies inside the device tree.
+    pub cmdline: MemoryRegion,
+
+    /// The ELF symbol table (`.symtab`) of the kernel which is used to resolve addresses in backtraces or an empty
+    /// region if the kernel binary was stripped
+    pub symtab: MemoryRegion,
+
+    /// The string table (`.strtab`) which holds the names of the symbols in [`symtab`](BootInfo::symtab) or an empty
+    /// region if the kernel binary was stripped
+    pub strtab: MemoryRegion,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_regions_are_bounded() {
+        let mut regions = MemoryRegions::new();
+        for i in 0..MAX_MEMORY_REGIONS {
+            regions.push(MemoryRegion::new(i * 0x1000, 0x1000)).unwrap();
+        }
+        let overflowing = MemoryRegion::new(0x10_0000, 0x1000);
+        assert_eq!(regions.push(overflowing), Err(overflowing));
+        assert_eq!(regions.as_slice().len(), MAX_MEMORY_REGIONS);
+        assert_eq!(
+            regions.iter().last().unwrap().end(),
+            MAX_MEMORY_REGIONS * 0x1000
+        );
+    }
+
+    fn regions(regions: &[(usize, usize)]) -> MemoryRegions {
+        let mut list = MemoryRegions::new();
+        for &(start, len) in regions {
+            list.push(MemoryRegion::new(start, len)).unwrap();
+        }
+        list
+    }
+
+    #[test]
+    fn subtract_removes_overlapping_parts() {
+        let mut list = regions(&[(0x1000, 0x3000), (0x8000, 0x1000)]);
+        // hole at the start of the first region
+        list.subtract(&MemoryRegion::new(0x0, 0x2000)).unwrap();
+        assert_eq!(list, regions(&[(0x2000, 0x2000), (0x8000, 0x1000)]));
+        // hole that covers the second region completely
+        list.subtract(&MemoryRegion::new(0x7000, 0x3000)).unwrap();
+        assert_eq!(list, regions(&[(0x2000, 0x2000)]));
+        // hole that does not overlap
+        list.subtract(&MemoryRegion::new(0x4000, 0x1000)).unwrap();
+        assert_eq!(list, regions(&[(0x2000, 0x2000)]));
+    }
+
+    #[test]
+    fn subtract_splits_regions() {
+        let mut list = regions(&[(0x0, 0x10000)]);
+        list.subtract(&MemoryRegion::new(0x2000, 0x1000)).unwrap();
+        assert_eq!(list, regions(&[(0x0, 0x2000), (0x3000, 0xd000)]));
+
+        let mut full = regions(&[(0x0, 0x10); MAX_MEMORY_REGIONS]);
+        assert_eq!(
+            full.subtract(&MemoryRegion::new(0x4, 0x4)),
+            Err(MemoryRegion::new(0x8, 0x8))
+        );
+    }
+
+    #[test]
+    fn shrink_to_alignment() {
+        let mut list = regions(&[(0x800, 0x2000), (0x4800, 0x400), (0x8000, 0x1000)]);
+        list.shrink_to_alignment(0x1000);
+        assert_eq!(list, regions(&[(0x1000, 0x1000), (0x8000, 0x1000)]));
+        assert_eq!(list.largest(), Some(&MemoryRegion::new(0x1000, 0x1000)));
+    }
+
+    #[test]
+    fn memory_region_contains() {
+        let region = MemoryRegion::new(0x1000, 0x1000);
+        assert!(!region.contains(0xfff));
+        assert!(region.contains(0x1000));
+        assert!(region.contains(0x1fff));
+        assert!(!region.contains(0x2000));
+        assert!(MemoryRegion::empty().is_empty());
+    }
+}
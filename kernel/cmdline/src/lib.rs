@@ -0,0 +1,165 @@
+//! Parsing of the command line that is passed to the kernel
+//!
+//! The command line is taken from the `bootargs` property of the device trees `/chosen` node and consists of space
+//! separated arguments which are either `key=value` pairs or flags that take effect when they are present at all.
+//! It is parsed by the `kernel_loader` for its own logging and then handed to the kernel via the boot info.
+//!
+//! See [`HELP`] for a description of all supported arguments.
+#![no_std]
+
+#[cfg(test)]
+mod tests;
+
+use bitflags::bitflags;
+use core::str::FromStr;
+use klog::LogFilter;
+use log::LevelFilter;
+use thiserror_no_std::Error;
+
+/// The level up to which log records are emitted if no `log-level` argument is given
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// How many milliseconds a task runs before it is preempted if no `tick-ms` argument is given
+pub const DEFAULT_TICK_MS: u64 = 100;
+
+/// A description of all supported arguments which is shown when the `help` flag is given
+pub const HELP: &str = "\
+Lunatix Kernel/OS
+
+This kernel and operating system is a hobby project with the goal of creating a capability based kernel and accompanying operating system.
+
+Parameters are always space-separated and either a key=value pair or have an effect when the key is present at all.
+Supported Kernel Parameter:
+  help                   Aborts the boot process and shows this help message instead.
+  log-level=FILTER       Specifies the maximum log-level of kernel related logging.
+                         FILTER is a comma-separated list of LEVEL or TARGET=LEVEL entries where LEVEL is one of OFF, ERROR, WARN, INFO, DEBUG, TRACE (case insensitive).
+                         A plain LEVEL applies to all targets without a more specific entry, e.g. log-level=warn,kernel::syscalls=trace
+  init=PATH              Start the program at PATH in the initrd as init instead of the init binary that was passed to the loader.
+  tick-ms=MILLIS         How many milliseconds a task runs before it is preempted (default 100).
+  debug=FLAGS            A comma-separated list of debugging aids to enable:
+                           syscalls  log every syscall that is made by userspace
+                           traps     log every trap that the kernel handles
+";
+
+bitflags! {
+    /// Debugging aids that can be enabled with the `debug` argument
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub struct DebugFlags: u32 {
+        /// Log every syscall that is made by userspace
+        const SYSCALLS = 1 << 0;
+        /// Log every trap that the kernel handles
+        const TRAPS = 1 << 1;
+    }
+}
+
+/// An error that occurred while parsing the command line
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum CmdLineError<'a> {
+    #[error("The argument {0:?} is not known")]
+    UnknownArgument(&'a str),
+    #[error("The argument {0} requires a value")]
+    MissingValue(&'a str),
+    #[error("The value {value:?} of argument {key} is invalid")]
+    InvalidValue { key: &'a str, value: &'a str },
+    #[error("Too many log targets are configured, {0} does not fit anymore")]
+    TooManyLogTargets(&'a str),
+}
+
+/// The arguments that were given on the kernel command line
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CmdLine<'a> {
+    /// Whether the user requested to see the help text instead of booting
+    pub help: bool,
+    /// Which log records the kernel emits
+    pub log_filter: LogFilter<'a>,
+    /// The path of a program in the initrd that should be started as init instead of the binary that was passed to
+    /// the loader
+    pub init: Option<&'a str>,
+    /// How many milliseconds a task runs before it is preempted
+    pub tick_ms: u64,
+    /// Debugging aids that should be enabled
+    pub debug: DebugFlags,
+}
+
+impl<'a> CmdLine<'a> {
+    /// Parse the given command line
+    pub fn parse(args: &'a str) -> Result<Self, CmdLineError<'a>> {
+        let mut result = Self::default();
+
+        for arg in args.split_ascii_whitespace() {
+            let (key, value) = match arg.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (arg, None),
+            };
+            let require_value = || value.ok_or(CmdLineError::MissingValue(key));
+            let invalid_value = |value| CmdLineError::InvalidValue { key, value };
+
+            match key {
+                "help" | "--help" => result.help = true,
+                "log-level" => result.log_filter = parse_log_filter(key, require_value()?)?,
+                "init" => {
+                    let path = require_value()?;
+                    if path.is_empty() {
+                        return Err(invalid_value(path));
+                    }
+                    result.init = Some(path);
+                }
+                "tick-ms" => {
+                    let value = require_value()?;
+                    result.tick_ms = match u64::from_str(value) {
+                        Ok(0) | Err(_) => return Err(invalid_value(value)),
+                        Ok(tick_ms) => tick_ms,
+                    };
+                }
+                "debug" => {
+                    for flag in require_value()?.split(',') {
+                        result.debug |= match flag {
+                            "syscalls" => DebugFlags::SYSCALLS,
+                            "traps" => DebugFlags::TRAPS,
+                            _ => return Err(invalid_value(flag)),
+                        };
+                    }
+                }
+                _ => return Err(CmdLineError::UnknownArgument(arg)),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl Default for CmdLine<'_> {
+    fn default() -> Self {
+        Self {
+            help: false,
+            log_filter: LogFilter::new(DEFAULT_LOG_LEVEL),
+            init: None,
+            tick_ms: DEFAULT_TICK_MS,
+            debug: DebugFlags::empty(),
+        }
+    }
+}
+
+/// Parse the value of a `log-level` argument
+fn parse_log_filter<'a>(key: &'a str, value: &'a str) -> Result<LogFilter<'a>, CmdLineError<'a>> {
+    let invalid_value = |value| CmdLineError::InvalidValue { key, value };
+    let mut filter = LogFilter::new(DEFAULT_LOG_LEVEL);
+
+    for entry in value.split(',') {
+        match entry.split_once('=') {
+            None => filter
+                .set_default_level(LevelFilter::from_str(entry).map_err(|_| invalid_value(entry))?),
+            Some((target, level)) => {
+                if target.is_empty() {
+                    return Err(invalid_value(entry));
+                }
+                let level = LevelFilter::from_str(level).map_err(|_| invalid_value(entry))?;
+                filter
+                    .set_target_level(target, level)
+                    .map_err(CmdLineError::TooManyLogTargets)?;
+            }
+        }
+    }
+
+    Ok(filter)
+}
@@ -0,0 +1,99 @@
+extern crate std;
+
+use crate::*;
+use klog::MAX_TARGET_FILTERS;
+use std::format;
+use std::string::String;
+
+#[test]
+fn empty_cmdline_uses_defaults() {
+    assert_eq!(CmdLine::parse(""), Ok(CmdLine::default()));
+    assert_eq!(CmdLine::parse("   "), Ok(CmdLine::default()));
+}
+
+#[test]
+fn parse_all_arguments() {
+    let cmdline =
+        CmdLine::parse("log-level=debug init=/bin/shell  tick-ms=10 debug=syscalls,traps").unwrap();
+    assert!(!cmdline.help);
+    assert_eq!(cmdline.log_filter.default_level(), LevelFilter::Debug);
+    assert_eq!(cmdline.init, Some("/bin/shell"));
+    assert_eq!(cmdline.tick_ms, 10);
+    assert_eq!(cmdline.debug, DebugFlags::SYSCALLS | DebugFlags::TRAPS);
+
+    assert!(CmdLine::parse("help").unwrap().help);
+    assert!(CmdLine::parse("log-level=info --help").unwrap().help);
+}
+
+#[test]
+fn parse_log_filter_with_targets() {
+    let cmdline =
+        CmdLine::parse("log-level=WARN,kernel::syscalls=trace,derivation_tree=off").unwrap();
+    let filter = cmdline.log_filter;
+    assert_eq!(filter.default_level(), LevelFilter::Warn);
+    assert_eq!(filter.max_level(), LevelFilter::Trace);
+    assert_eq!(filter.level_for("kernel"), LevelFilter::Warn);
+    assert_eq!(filter.level_for("kernel::syscalls"), LevelFilter::Trace);
+    assert_eq!(
+        filter.level_for("kernel::syscalls::send"),
+        LevelFilter::Trace
+    );
+    assert_eq!(filter.level_for("kernel::syscallsx"), LevelFilter::Warn);
+    assert_eq!(filter.level_for("derivation_tree::tree"), LevelFilter::Off);
+
+    // targets without a default level keep the default
+    let filter = CmdLine::parse("log-level=kernel=error").unwrap().log_filter;
+    assert_eq!(filter.default_level(), DEFAULT_LOG_LEVEL);
+    assert_eq!(filter.level_for("kernel"), LevelFilter::Error);
+}
+
+#[test]
+fn most_specific_target_level_wins() {
+    let filter =
+        CmdLine::parse("log-level=kernel::caps::memory=trace,kernel=error,kernel::caps=off")
+            .unwrap()
+            .log_filter;
+    assert_eq!(filter.level_for("kernel::init"), LevelFilter::Error);
+    assert_eq!(filter.level_for("kernel::caps::task"), LevelFilter::Off);
+    assert_eq!(filter.level_for("kernel::caps::memory"), LevelFilter::Trace);
+}
+
+#[test]
+fn invalid_arguments_are_rejected() {
+    assert_eq!(
+        CmdLine::parse("log-level=info foo"),
+        Err(CmdLineError::UnknownArgument("foo"))
+    );
+    assert_eq!(
+        CmdLine::parse("tick-ms"),
+        Err(CmdLineError::MissingValue("tick-ms"))
+    );
+    for (cmdline, key, value) in [
+        ("log-level=loud", "log-level", "loud"),
+        ("log-level=info,kernel=", "log-level", "kernel="),
+        ("log-level==info", "log-level", "=info"),
+        ("tick-ms=0", "tick-ms", "0"),
+        ("tick-ms=-1", "tick-ms", "-1"),
+        ("init=", "init", ""),
+        ("debug=syscalls,everything", "debug", "everything"),
+    ] {
+        assert_eq!(
+            CmdLine::parse(cmdline),
+            Err(CmdLineError::InvalidValue { key, value }),
+            "{cmdline}"
+        );
+    }
+}
+
+#[test]
+fn log_targets_are_bounded() {
+    let mut cmdline = String::from("log-level=info");
+    for i in 0..=MAX_TARGET_FILTERS {
+        cmdline += &format!(",target{i}=debug");
+    }
+    let overflowing = format!("target{MAX_TARGET_FILTERS}");
+    assert_eq!(
+        CmdLine::parse(&cmdline),
+        Err(CmdLineError::TooManyLogTargets(&overflowing))
+    );
+}
@@ -2,21 +2,35 @@ use riscv::cpu::{SStatus, SStatusFlags, Satp, SatpData, SatpMode};
 use riscv::mem::ptrs::PhysMutPtr;
 use riscv::pt::PageTable;
 
-pub unsafe fn use_pagetable(root: PhysMutPtr<PageTable>) {
+/// Switch to the pagetable `root` which is tagged with the address space id `asid` in the TLB
+pub unsafe fn use_pagetable(root: PhysMutPtr<PageTable>, asid: usize) {
     // enable MXR (make Executable readable) bit
     // enable SUM (premit Supervisor User Memory access) bit
     unsafe {
         SStatus::set(SStatusFlags::MXR & SStatusFlags::SUM);
     }
 
-    log::trace!("enabling new pagetable {:p}", root);
+    log::trace!("enabling new pagetable {:p} (asid = {})", root, asid);
 
     // Setup Root Page table in satp register
     unsafe {
         Satp::write(SatpData {
             mode: SatpMode::Sv39,
-            asid: 0,
+            asid: asid as u64,
             ppn: root.raw() as u64 >> 12,
         });
     }
 }
+
+/// Switch to the kernel root pagetable which maps nothing but the kernel itself
+pub unsafe fn use_kernel_pagetable() {
+    use_pagetable(
+        PhysMutPtr::from(crate::KERNEL_ROOT_PT.raw() as *mut PageTable),
+        0,
+    );
+}
+
+/// Whether `root` is the pagetable that is currently used by this hart
+pub fn is_active_pagetable(root: PhysMutPtr<PageTable>) -> bool {
+    Satp::read().ppn == root.raw() as u64 >> 12
+}
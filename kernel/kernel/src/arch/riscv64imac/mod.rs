@@ -1,2 +1,4 @@
 pub mod mmu;
 pub mod plic;
+#[cfg(feature = "kernel_tests")]
+pub mod qemu_exit;
@@ -0,0 +1,26 @@
+//! Shutting down QEMU with an exit status so that test runs can report their result to the host.
+//!
+//! OpenSBI ignores the reason of a system reset when shutting down which is why the sifive test device of QEMUs
+//! `virt` machine is used directly.
+
+use riscv::mem::ptrs::PhysMutPtr;
+use sifive_shutdown_driver::{ShutdownCode, SifiveShutdown};
+
+/// The physical address at which QEMUs `virt` machine maps its sifive test device
+const SIFIVE_TEST_ADDR: usize = 0x100000;
+
+/// Shut QEMU down and let it exit with status 0 if `success` is true or with status 1 otherwise
+pub fn exit(success: bool) -> ! {
+    let code = match success {
+        true => ShutdownCode::Pass,
+        false => ShutdownCode::Fail(1),
+    };
+    unsafe {
+        let device = SifiveShutdown::from_ptr(
+            PhysMutPtr::from(SIFIVE_TEST_ADDR as *mut u32)
+                .as_mapped()
+                .raw(),
+        );
+        device.shutdown(code)
+    }
+}
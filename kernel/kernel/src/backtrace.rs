@@ -0,0 +1,105 @@
+//! Stack unwinding and symbol resolution for diagnosing kernel panics
+//!
+//! The kernel is compiled with frame pointers (see `KERNEL_CARGO` in the Makefile) so that the stack can be unwound by
+//! following the chain of saved frame pointers.
+//! On RISC-V, the frame pointer `s0` points to the top of the current stack frame and the return address as well as
+//! the frame pointer of the calling function are saved directly below it.
+//!
+//! Addresses are resolved to function names using the ELF symbol table of the kernel which is handed over by the
+//! loader.
+//! Release builds of the kernel are stripped, in which case only raw addresses are printed.
+
+use core::arch::asm;
+use ksync::SpinLock;
+use riscv::mem::VIRT_MEM_PHYS_MAP_START;
+
+/// The maximum number of frames that are printed so that a corrupted stack cannot cause endless output
+const MAX_FRAMES: usize = 64;
+
+/// The symbol table of the kernel or `None` if the kernel binary was stripped
+static SYMBOLS: SpinLock<Option<SymbolTable>> = SpinLock::new(None);
+
+/// The ELF symbol table of the kernel together with the string table that holds the symbol names
+pub struct SymbolTable {
+    symtab: &'static [u8],
+    strtab: &'static [u8],
+}
+
+impl SymbolTable {
+    /// The size of an `Elf64_Sym` entry
+    const ENTRY_SIZE: usize = 24;
+    /// The symbol type of functions
+    const STT_FUNC: u8 = 2;
+
+    pub fn new(symtab: &'static [u8], strtab: &'static [u8]) -> Self {
+        Self { symtab, strtab }
+    }
+
+    /// Find the function which contains the given address and return its name together with the offset of the
+    /// address inside it
+    pub fn lookup(&self, addr: usize) -> Option<(&'static str, usize)> {
+        let read_u64 = |entry: &[u8], offset: usize| {
+            u64::from_le_bytes(entry[offset..offset + 8].try_into().unwrap()) as usize
+        };
+
+        self.symtab
+            .chunks_exact(Self::ENTRY_SIZE)
+            .filter(|entry| entry[4] & 0xf == Self::STT_FUNC)
+            .map(|entry| {
+                let name = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+                (name, read_u64(entry, 8), read_u64(entry, 16))
+            })
+            .find(|&(_, value, size)| value <= addr && addr < value + size)
+            .and_then(|(name, value, _)| Some((self.name(name)?, addr - value)))
+    }
+
+    /// Get the null terminated name which starts at the given offset in the string table
+    fn name(&self, offset: usize) -> Option<&'static str> {
+        let name = self.strtab.get(offset..)?;
+        let len = name.iter().position(|&byte| byte == 0)?;
+        core::str::from_utf8(&name[..len]).ok()
+    }
+}
+
+/// Install the symbol table that is used to resolve addresses in backtraces
+pub fn init_symbols(symbols: SymbolTable) {
+    *SYMBOLS.spin_lock() = Some(symbols);
+}
+
+/// Print a backtrace of the current call stack by following the chain of saved frame pointers
+pub fn print_backtrace() {
+    let mut fp: usize;
+    unsafe { asm!("mv {}, fp", out(reg) fp) };
+    // a panic while resolving a symbol leaves the lock locked so waiting for it could spin forever
+    let symbols = SYMBOLS.try_lock().ok();
+    let symbols = symbols.as_ref().and_then(|symbols| symbols.as_ref());
+
+    println!("backtrace:");
+    for i in 0..MAX_FRAMES {
+        // kernel stacks are located in high memory and frame pointers are always aligned
+        if fp < VIRT_MEM_PHYS_MAP_START || fp % 8 != 0 {
+            break;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        let next_fp = unsafe { *((fp - 16) as *const usize) };
+        if ra == 0 {
+            break;
+        }
+
+        // the return address points behind the call instruction which might already belong to another function
+        match symbols.and_then(|symbols| symbols.lookup(ra - 1)) {
+            Some((name, offset)) => println!(
+                "  {i:>2}: {ra:#018x} {:#}+{:#x}",
+                rustc_demangle::demangle(name),
+                offset + 1
+            ),
+            None => println!("  {i:>2}: {ra:#018x} <unknown>"),
+        }
+
+        // the stack grows downwards so the frames of callers are located at higher addresses
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+}
@@ -1,9 +1,12 @@
-use core::{mem::ManuallyDrop, ptr, sync::atomic::AtomicUsize};
+use core::{mem::ManuallyDrop, ptr};
 
 use derivation_tree::{caps::CapabilityIface, tree::TreeNodeOps};
+use ksync::SpinLock;
+use riscv::cpu::Satp;
 use riscv::pt::PageTable;
 
 use crate::caps::{Tag, Uninit, Variant};
+use crate::hart;
 
 use super::{Capability, SyscallError, VSpace};
 
@@ -14,50 +17,97 @@ pub struct Asid {
     pub pt: *mut PageTable,
 }
 
-pub static ASID_MARKER: AtomicUsize = AtomicUsize::new(1);
 pub static ASID_NONE: usize = 0;
 
+/// The number of bits of an asid id which hold the hardware asid.
+///
+/// The remaining upper bits hold a generation counter of the pool slot so that pages which still refer to an
+/// address space that has since been destroyed cannot find the address space that reuses its slot.
+const HARDWARE_ASID_BITS: usize = 16;
+
+const POOL_SIZE: usize = 64;
+
 pub struct AsidPool {
-    asids: [Asid; 64],
+    asids: [Asid; POOL_SIZE],
+    /// How many slots of the pool can be used, limited by the number of asid bits supported by the hardware
+    usable: usize,
 }
 
 unsafe impl Send for AsidPool {}
 unsafe impl Sync for AsidPool {}
 
-pub static mut ASID_POOL: AsidPool = AsidPool {
+/// The pool of all asids which is shared by all harts
+pub static ASID_POOL: SpinLock<AsidPool> = SpinLock::new(AsidPool {
     asids: [Asid {
         allocated: false,
         id: 0,
         pt: ptr::null_mut(),
-    }; 64],
-};
+    }; POOL_SIZE],
+    usable: 0,
+});
+
+/// Limit the asid pool to the number of asids (ASIDLEN) that is implemented by the hardware.
+///
+/// This must be called once on the boot hart before any asid is allocated.
+pub fn init_asid_pool() {
+    let asid_bits = unsafe { Satp::probe_asid_bits() } as usize;
+    // hardware asid 0 is used for ASID_NONE, i.e. by the kernel
+    let usable = POOL_SIZE.min((1 << asid_bits) - 1);
+    log::debug!("hardware supports {asid_bits} asid bits, using {usable} asids");
+    ASID_POOL.spin_lock().usable = usable;
+}
+
+/// Get the asid that tags the address space with the given id in the TLB
+pub fn hardware_asid(id: usize) -> usize {
+    id & ((1 << HARDWARE_ASID_BITS) - 1)
+}
 
 impl AsidPool {
-    pub fn alloc_asid(&mut self) -> Result<&mut Asid, SyscallError> {
-        let asid = self
-            .asids
+    /// Allocate a new asid for the address space with the given root pagetable and return its id
+    pub fn alloc_asid(&mut self, pt: *mut PageTable) -> Result<usize, SyscallError> {
+        let (idx, asid) = self.asids[..self.usable]
             .iter_mut()
-            .find(|i| !i.allocated)
+            .enumerate()
+            .find(|(_, i)| !i.allocated)
             .ok_or(SyscallError::NoAsid)?;
+        let generation = (asid.id >> HARDWARE_ASID_BITS) + 1;
         asid.allocated = true;
-        asid.id = ASID_MARKER.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
-        Ok(asid)
+        asid.id = generation << HARDWARE_ASID_BITS | (idx + 1);
+        asid.pt = pt;
+        // a hart may still have translations of the previous address space with this asid cached
+        hart::flush_tlb(asid.id, 0, 0);
+        Ok(asid.id)
     }
 
-    pub fn find_asid(&mut self, id: usize) -> Result<&Asid, SyscallError> {
-        self.asids
-            .iter()
-            .find(|i| i.allocated && i.id == id)
+    /// Release the asid with the given id so that its slot can be used by another address space
+    pub fn free_asid(&mut self, id: usize) {
+        if let Some(asid) = self.slot(id).filter(|i| i.allocated && i.id == id) {
+            asid.allocated = false;
+            asid.pt = ptr::null_mut();
+        }
+    }
+
+    pub fn find_asid(&self, id: usize) -> Result<Asid, SyscallError> {
+        hardware_asid(id)
+            .checked_sub(1)
+            .and_then(|idx| self.asids[..self.usable].get(idx))
+            .filter(|i| i.allocated && i.id == id)
+            .copied()
             .ok_or(SyscallError::NoAsid)
     }
+
+    fn slot(&mut self, id: usize) -> Option<&mut Asid> {
+        hardware_asid(id)
+            .checked_sub(1)
+            .and_then(|idx| self.asids[..self.usable].get_mut(idx))
+    }
 }
 
 pub struct AsidControl;
 
 impl AsidControl {
-    pub fn alloc_asid(&self) -> Result<&mut Asid, SyscallError> {
-        let pool = unsafe { &mut ASID_POOL };
-        pool.alloc_asid()
+    pub fn alloc_asid(&self, pt: *mut PageTable) -> Result<usize, SyscallError> {
+        ASID_POOL.spin_lock().alloc_asid(pt)
     }
 }
 
@@ -73,9 +123,10 @@ pub fn asid_control_assign(
     asid_control: &AsidControl,
     vspace: &mut VSpace,
 ) -> Result<(), SyscallError> {
-    let asid = asid_control.alloc_asid()?;
-    asid.pt = vspace.root;
-    vspace.asid = asid.id;
+    if vspace.asid != ASID_NONE {
+        return Err(SyscallError::AlreadyMapped);
+    }
+    vspace.asid = asid_control.alloc_asid(vspace.root)?;
     Ok(())
 }
 
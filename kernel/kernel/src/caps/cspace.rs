@@ -1,8 +1,9 @@
-use crate::caps::{CapCounted, KernelAlloc, Tag, Uninit, Variant};
+use crate::caps::{self, CapCounted, KernelAlloc, SyscallError, Tag, Uninit, Variant};
 use allocators::{AllocError, Box};
 use core::cell::RefCell;
 use core::mem;
 use core::mem::ManuallyDrop;
+use core::ptr;
 pub use derivation_tree::caps::CapabilityIface;
 use derivation_tree::tree::TreeNodeOps;
 use derivation_tree::{AsStaticMut, AsStaticRef, Correspondence};
@@ -12,6 +13,29 @@ use super::Capability;
 
 pub struct CSpace {
     pub slots: CapCounted<[RefCell<Capability>]>,
+    /// The value that the guard bits of a CAddr part must have to select a slot of this CSpace
+    pub guard: usize,
+    /// How many bits of a CAddr part are used for the guard, in front of the bits that select a slot
+    pub guard_bits: usize,
+}
+
+/// The reason why a CAddr could not be resolved
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ResolveFailure {
+    /// The guard bits of a part did not match the guard of the CSpace
+    GuardMismatch,
+    /// The address continues below a capability that is not a CSpace
+    NotACSpace,
+    /// The requested depth ends in the middle of a part
+    DepthMismatch,
+}
+
+/// An error that describes why and where resolving a CAddr failed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ResolveError {
+    pub reason: ResolveFailure,
+    /// How many bits of the CAddr were resolved successfully, see [`LookupError`](syscall_abi::LookupError)
+    pub depth: usize,
 }
 
 impl CSpace {
@@ -33,6 +57,8 @@ impl CSpace {
         // return result
         Ok(Self {
             slots: CapCounted::from_box(unsafe { slots.assume_init() }),
+            guard: 0,
+            guard_bits: 0,
         })
     }
 
@@ -45,37 +71,106 @@ impl CSpace {
     }
 
     /// How many bits of a CAddr this CSpace requires to index all its slots.
+    ///
+    /// This is also called the radix of the CSpace.
     pub fn addr_bits(&self) -> usize {
-        // TODO: fix this, because it might still cause of by one with
-        // empty cspaces or similar non power of 2 stuff
-        let mut len = self.slots.len() - 1;
-        let mut n_shifts = 0;
-
-        while len != 0 {
-            len = len >> 1;
-            n_shifts += 1;
+        // CSpaces always have a power of two number of slots which means that every combination of the bits indexes
+        // exactly one slot
+        debug_assert!(self.slots.len().is_power_of_two());
+        self.slots.len().trailing_zeros() as usize
+    }
+
+    /// How many bits a CAddr part that selects a slot of this CSpace has, including the guard
+    pub fn part_bits(&self) -> usize {
+        self.guard_bits + self.addr_bits()
+    }
+
+    /// Require all CAddr parts that select a slot of this CSpace to start with the given guard.
+    ///
+    /// A part must leave room for the continuation bit in a CAddr, which limits how large the guard can be.
+    pub fn set_guard(&mut self, guard: usize, guard_bits: usize) -> Result<(), SyscallError> {
+        if self.addr_bits() + guard_bits >= usize::BITS as usize
+            || guard.checked_shr(guard_bits as u32).unwrap_or(0) != 0
+        {
+            return Err(SyscallError::InvalidArg);
         }
+        self.guard = guard;
+        self.guard_bits = guard_bits;
+        Ok(())
+    }
 
-        n_shifts
+    /// Count the occupied slots of this CSpace.
+    ///
+    /// Returns how many slots are occupied as well as the index after the last occupied slot, starting at which all
+    /// slots are free.
+    pub fn occupancy(&self) -> (usize, usize) {
+        // Safety: only the tags are read which is fine even if a slot is currently borrowed by a syscall
+        let occupied = |slot: &RefCell<Capability>| unsafe { (*slot.as_ptr()).tag != Tag::Uninit };
+        let count = self.slots.iter().filter(|slot| occupied(slot)).count();
+        let free_from = self
+            .slots
+            .iter()
+            .rposition(|slot| occupied(slot))
+            .map_or(0, |i| i + 1);
+        (count, free_from)
+    }
+
+    /// Whether the given capability is stored in one of the slots of this CSpace
+    pub fn contains(&self, cap: *const Capability) -> bool {
+        self.slots.iter().any(|slot| ptr::eq(slot.as_ptr(), cap))
+    }
+
+    /// Copy the capabilities of all occupied slots into the slots with the same index of `target`.
+    ///
+    /// This is used to grow a CSpace because copying into a larger CSpace keeps single part CAddrs pointing to the
+    /// same capabilities.
+    /// Nothing is copied if `target` has fewer slots or if any of its slots that would be copied into is occupied.
+    ///
+    /// # Safety
+    /// No capability of either CSpace may currently be selected by a cursor.
+    pub unsafe fn copy_slots_into(&self, target: &CSpace) -> Result<(), SyscallError> {
+        if self.corresponds_to(target) || target.slots.len() < self.slots.len() {
+            return Err(SyscallError::InvalidArg);
+        }
+        let pairs = || {
+            self.slots
+                .iter()
+                .zip(target.slots.iter())
+                .map(|(src, dst)| (src.as_ptr(), dst.as_ptr()))
+                .filter(|&(src, _)| (*src).tag != Tag::Uninit)
+        };
+        if pairs().any(|(_, dst)| (*dst).tag != Tag::Uninit) {
+            return Err(SyscallError::OccupiedSlot);
+        }
+        for (src, dst) in pairs() {
+            caps::copy(&*src, &mut *dst);
+        }
+        Ok(())
     }
 
     /// Perform a lookup based on the given address and return a pointer to a capability if one corresponds to that
     /// address as well as the remaining part of the CAddr.
     ///
+    /// Returns `None` if the guard bits of the address don't match the guard of this CSpace.
+    ///
     /// # Safety
     /// The returned node may not be linked into a derivation tree yet.
     ///
     /// Additionally, looking up a node from the cspace may produce overlapping aliases if the node is already part of
     /// a DerivationTree.
     unsafe fn lookup_raw(&self, addr: CAddr) -> Option<(*mut Capability, Option<CAddr>)> {
-        let (slot, remainder) = addr.take_bits(self.addr_bits());
-        let slot = self.slots.get(slot)?.as_ptr();
+        let (part, remainder) = addr.take_bits(self.part_bits());
+        let radix = self.addr_bits();
+        if part >> radix != self.guard {
+            return None;
+        }
+        let slot = self.slots.get(part & ((1 << radix) - 1))?.as_ptr();
         Some((slot, remainder))
     }
 
     /// Fully resolve the given CAddr and return the capability that it points to.
     ///
-    /// This function honors the hierarchical nature of CAddrs by recursing into child CSpaces.
+    /// This function honors the hierarchical nature of CAddrs by descending into child CSpaces.
     ///
     /// # Safety
     /// The returned node may not be linked into a derivation tree yet.
@@ -83,16 +178,51 @@ impl CSpace {
     /// Additionally, looking up a node from the CSpace may produce overlapping aliases if the node is already part of
     /// a derivation tree and must be selected via with a cursor before further uses.
     pub unsafe fn resolve_caddr(&self, addr: CAddr) -> Option<*mut Capability> {
+        self.resolve_caddr_depth(addr, CAddr::FULL_DEPTH).ok()
+    }
+
+    /// Resolve at most `depth` bits of the given CAddr and return the capability that they point to.
+    ///
+    /// See the [`CAddr`] documentation for how guards and the resolution depth work.
+    ///
+    /// # Safety
+    /// The same restrictions as for [`resolve_caddr()`](Self::resolve_caddr) apply.
+    pub unsafe fn resolve_caddr_depth(
+        &self,
+        addr: CAddr,
+        depth: usize,
+    ) -> Result<*mut Capability, ResolveError> {
         // TODO Properly use cursors
-        let (slot_ptr, remainder) = self.lookup_raw(addr)?;
-        let slot = unsafe { &mut *slot_ptr };
-        match remainder {
-            Some(remainder) => {
-                assert_eq!(slot.tag, Tag::CSpace);
-                let slot_cspace = slot.get_inner_cspace().unwrap();
-                slot_cspace.resolve_caddr(remainder)
+        // every part is followed by a continuation bit which is consumed together with it, so this loop ends after
+        // at most one iteration per bit of the address even if a CSpace contains itself
+        let mut cspace = self;
+        let mut addr = addr;
+        let mut resolved = 0;
+        loop {
+            let error = |reason| ResolveError {
+                reason,
+                depth: resolved,
+            };
+            let part_bits = cspace.part_bits();
+            if part_bits > depth - resolved {
+                return Err(error(ResolveFailure::DepthMismatch));
+            }
+            let (slot_ptr, remainder) = cspace
+                .lookup_raw(addr)
+                .ok_or_else(|| error(ResolveFailure::GuardMismatch))?;
+            resolved += part_bits;
+
+            match remainder {
+                Some(remainder) if resolved < depth => {
+                    // only cspaces can contain further capabilities
+                    cspace = (*slot_ptr).get_inner_cspace().map_err(|_| ResolveError {
+                        reason: ResolveFailure::NotACSpace,
+                        depth: resolved,
+                    })?;
+                    addr = remainder;
+                }
+                _ => return Ok(slot_ptr),
             }
-            None => Some(slot_ptr),
         }
     }
 }
@@ -109,19 +239,38 @@ impl Correspondence for CSpace {
 pub struct CSpaceIface;
 
 impl CSpaceIface {
-    pub fn derive(&self, src_mem: &Capability, target_slot: &mut Capability, num_slots: usize) {
+    pub fn derive(
+        &self,
+        src_mem: &Capability,
+        target_slot: &mut Capability,
+        num_slots: usize,
+    ) -> Result<(), SyscallError> {
         assert_eq!(target_slot.tag, Tag::Uninit);
 
+        // cspaces need a power of two number of slots for caddrs to work correctly
+        if !num_slots.is_power_of_two() {
+            return Err(SyscallError::InvalidArg);
+        }
+
         // create a new cspace which is allocated from src_mem
-        let cspace = derivation_tree::caps::CSpace::alloc_new(
+        let slots = derivation_tree::caps::CSpace::alloc_new(
             &*src_mem.get_inner_memory().unwrap().allocator,
             num_slots,
         )
-        .unwrap();
+        .map_err(|_| SyscallError::NoMem)?
+        .slots;
 
         // Safety: it is safe to ignore lifetimes for this CSoace, because the derivation tree ensures correct lifetimes at runtime
-        let cspace = unsafe {
-            mem::transmute::<derivation_tree::caps::CSpace<'_, '_, Capability>, CSpace>(cspace)
+        let slots = unsafe {
+            mem::transmute::<
+                derivation_tree::CapCounted<'_, '_, [RefCell<Capability>]>,
+                CapCounted<[RefCell<Capability>]>,
+            >(slots)
+        };
+        let cspace = CSpace {
+            slots,
+            guard: 0,
+            guard_bits: 0,
         };
 
         // save the capability into the target slot
@@ -132,6 +281,8 @@ impl CSpaceIface {
         unsafe {
             src_mem.insert_derivation(target_slot);
         }
+
+        Ok(())
     }
 }
 
@@ -149,10 +300,13 @@ impl CapabilityIface<Capability> for CSpaceIface {
         assert_eq!(dst.tag, Tag::Uninit, "destination is not uninit");
 
         // semantically copy the cspace
+        let src_cspace = unsafe { &src.variant.cspace };
         dst.tag = Tag::CSpace;
         dst.variant = Variant {
             cspace: ManuallyDrop::new(CSpace {
-                slots: unsafe { &src.variant.cspace }.slots.clone(),
+                slots: src_cspace.slots.clone(),
+                guard: src_cspace.guard,
+                guard_bits: src_cspace.guard_bits,
             }),
         };
 
@@ -161,12 +315,15 @@ impl CapabilityIface<Capability> for CSpaceIface {
     }
 
     fn destroy(&self, target: &mut Capability) {
-        // TODO: handle recursive cspaces
         assert_eq!(target.tag, Tag::CSpace);
 
         if target.is_final_copy() {
-            let _cspace = target.get_inner_cspace_mut().unwrap();
-            todo!("destroy cspace slots and dealloc cspace");
+            // the capabilities in the slots cannot be reached anymore once the last copy of the cspace is gone
+            let cspace = target.get_inner_cspace_mut().unwrap();
+            for slot in cspace.slots.iter() {
+                unsafe { caps::destroy(&mut *slot.as_ptr()) };
+            }
+            unsafe { cspace.deallocate() };
         }
 
         target.tree_data.unlink();
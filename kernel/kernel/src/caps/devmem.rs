@@ -1,7 +1,7 @@
 use core::{cell::RefCell, mem::ManuallyDrop};
 
 use allocators::Box;
-use derivation_tree::{caps::CapabilityIface, tree::TreeNodeOps};
+use derivation_tree::{caps::CapabilityIface, tree::TreeNodeOps, Correspondence};
 
 use crate::caps::{Tag, Uninit, Variant};
 
@@ -17,6 +17,14 @@ pub struct Devmem {
     pub inner_state: CapCounted<[RefCell<Option<DevmemEntry>>]>,
 }
 
+impl Correspondence for Devmem {
+    fn corresponds_to(&self, other: &Self) -> bool {
+        let self_entries: &[_] = &self.inner_state;
+        let other_entries: &[_] = &other.inner_state;
+        self_entries.as_ptr() == other_entries.as_ptr()
+    }
+}
+
 impl DevmemIface {
     pub(crate) fn create_init(
         &self,
@@ -1,4 +1,6 @@
-use crate::caps::{CapCounted, Capability, Tag, Uninit, Variant};
+use crate::caps::task::TaskState;
+use crate::caps::{CapCounted, Capability, SyscallError, Tag, TaskIface, Uninit, Variant};
+use crate::sched;
 use allocators::Box;
 use core::cell::RefCell;
 use core::mem::ManuallyDrop;
@@ -13,6 +15,8 @@ pub struct EndpointState {
     pub send_set: Option<*mut Capability>,
     /// A task that is currently waiting to send data from this endpoint
     pub recv_set: Option<*mut Capability>,
+    /// A task that is blocked in `wait_any` until a sender arrives at this endpoint
+    pub watch_set: Option<*mut Capability>,
 }
 
 #[derive(Clone)]
@@ -29,7 +33,11 @@ impl Correspondence for Endpoint {
 pub struct EndpointIface;
 
 impl EndpointIface {
-    pub fn derive(&self, src_mem: &Capability, target_slot: &mut Capability) {
+    pub fn derive(
+        &self,
+        src_mem: &Capability,
+        target_slot: &mut Capability,
+    ) -> Result<(), SyscallError> {
         assert_eq!(src_mem.tag, Tag::Memory);
         assert_eq!(target_slot.tag, Tag::Uninit);
 
@@ -37,10 +45,11 @@ impl EndpointIface {
         let state = RefCell::new(EndpointState {
             send_set: None,
             recv_set: None,
+            watch_set: None,
         });
         let state = unsafe {
             Box::new(state, src_mem.get_inner_memory().unwrap().allocator.deref())
-                .unwrap()
+                .map_err(|_| SyscallError::NoMem)?
                 .ignore_lifetimes()
         };
 
@@ -56,41 +65,161 @@ impl EndpointIface {
         unsafe {
             src_mem.insert_derivation(target_slot);
         }
+
+        Ok(())
     }
 
     /// Add the given task to the endpoints send_set.
     ///
+    /// Fails with `Busy` if another task is already waiting there.
+    ///
     /// # Safety
     /// Ensure that the task also has its `waiting_on` field set to this endpoint.
-    pub unsafe fn add_sender(&self, endpoint: &Endpoint, task: *mut Capability) {
+    pub unsafe fn add_sender(
+        &self,
+        endpoint: &Endpoint,
+        task: *mut Capability,
+    ) -> Result<(), SyscallError> {
         let mut state = endpoint.state.borrow_mut();
         match state.send_set {
-            Some(existing_task) => {
-                if existing_task != task {
-                    panic!("endpoint already has a waiting sender");
-                }
+            Some(existing_task) if existing_task != task => Err(SyscallError::Busy),
+            _ => {
+                state.send_set = Some(task);
+                Ok(())
             }
-            None => state.send_set = Some(task),
         }
     }
 
     /// Add the given task to the endpoints recv_set
     ///
+    /// Fails with `Busy` if another task is already waiting there.
+    ///
     /// # Safety
     /// Ensure that the task also has its `waiting_on` field set to this endpoint.
-    pub unsafe fn add_receiver(&self, endpoint: &Endpoint, task: *mut Capability) {
+    pub unsafe fn add_receiver(
+        &self,
+        endpoint: &Endpoint,
+        task: *mut Capability,
+    ) -> Result<(), SyscallError> {
         let mut state = endpoint.state.borrow_mut();
         match state.recv_set {
-            Some(existing_task) => {
-                if existing_task != task {
-                    panic!("endpoint already has a waiting receiver");
-                }
+            Some(existing_task) if existing_task != task => Err(SyscallError::Busy),
+            _ => {
+                state.recv_set = Some(task);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add the given task to the endpoints watch_set so that it is woken once a sender arrives.
+    ///
+    /// Fails with `Busy` if another task is already watching the endpoint.
+    ///
+    /// # Safety
+    /// Ensure that the task also lists this endpoint in its `waiting_on` field.
+    pub unsafe fn add_watcher(
+        &self,
+        endpoint: &Endpoint,
+        task: *mut Capability,
+    ) -> Result<(), SyscallError> {
+        let mut state = endpoint.state.borrow_mut();
+        match state.watch_set {
+            Some(existing_task) if existing_task != task => Err(SyscallError::Busy),
+            _ => {
+                state.watch_set = Some(task);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the task owning the given state from the endpoints recv_set.
+    ///
+    /// Returns the removed task capability if the task was waiting to receive from this endpoint.
+    ///
+    /// # Safety
+    /// This function only removes the *endpoint to task* pointer.
+    /// After calling it, the tasks `waiting_on` field **must** also be cleared.
+    pub unsafe fn remove_receiver(
+        &self,
+        endpoint: &Endpoint,
+        task_state: *const RefCell<TaskState>,
+    ) -> Option<*mut Capability> {
+        let mut state = endpoint.state.borrow_mut();
+        remove_from_wait_set(&mut state.recv_set, task_state)
+    }
+
+    /// Remove the task owning the given state from the endpoints send_set.
+    ///
+    /// Returns the removed task capability if the task was waiting to send to this endpoint.
+    ///
+    /// # Safety
+    /// This function only removes the *endpoint to task* pointer.
+    /// After calling it, the tasks `waiting_on` field **must** also be cleared.
+    pub unsafe fn remove_sender(
+        &self,
+        endpoint: &Endpoint,
+        task_state: *const RefCell<TaskState>,
+    ) -> Option<*mut Capability> {
+        let mut state = endpoint.state.borrow_mut();
+        remove_from_wait_set(&mut state.send_set, task_state)
+    }
+
+    /// Remove the task owning the given state from the endpoints watch_set.
+    ///
+    /// Returns the removed task capability if the task was watching this endpoint.
+    ///
+    /// # Safety
+    /// This function only removes the *endpoint to task* pointer.
+    /// After calling it, the tasks `waiting_on` field **must** also be cleared.
+    pub unsafe fn remove_watcher(
+        &self,
+        endpoint: &Endpoint,
+        task_state: *const RefCell<TaskState>,
+    ) -> Option<*mut Capability> {
+        let mut state = endpoint.state.borrow_mut();
+        remove_from_wait_set(&mut state.watch_set, task_state)
+    }
+
+    /// Let the endpoints send_set, recv_set and watch_set point to the task capability `new` wherever they
+    /// currently point to `old`.
+    ///
+    /// # Safety
+    /// `new` must be a copy of the task capability `old`.
+    pub unsafe fn replace_waiting_task(
+        &self,
+        endpoint: &Endpoint,
+        old: *mut Capability,
+        new: *mut Capability,
+    ) {
+        let mut state = endpoint.state.borrow_mut();
+        let EndpointState {
+            send_set,
+            recv_set,
+            watch_set,
+        } = &mut *state;
+        for wait_set in [send_set, recv_set, watch_set] {
+            if *wait_set == Some(old) {
+                *wait_set = Some(new);
             }
-            None => state.recv_set = Some(task),
         }
     }
 }
 
+/// Clear the wait set if the task owning `task_state` is contained in it and return the removed task capability
+unsafe fn remove_from_wait_set(
+    wait_set: &mut Option<*mut Capability>,
+    task_state: *const RefCell<TaskState>,
+) -> Option<*mut Capability> {
+    let waiting_task_ptr = (*wait_set)?;
+    let waiting_task = unsafe { &*waiting_task_ptr }.get_inner_task().unwrap();
+    if core::ptr::eq(&*waiting_task.state, task_state) {
+        *wait_set = None;
+        Some(waiting_task_ptr)
+    } else {
+        None
+    }
+}
+
 impl CapabilityIface<Capability> for EndpointIface {
     type InitArgs = ();
 
@@ -120,20 +249,49 @@ impl CapabilityIface<Capability> for EndpointIface {
     }
 
     fn destroy(&self, target: &mut Capability) {
-        assert_eq!(target.tag, Tag::Notification);
+        assert_eq!(target.tag, Tag::Endpoint);
 
-        if target.is_final_copy() {
-            let endpoint = target.get_inner_endpoint_mut().unwrap();
-            {
-                let state = endpoint.state.borrow();
-                assert!(
-                    state.recv_set.is_none() && state.send_set.is_none(),
-                    "can't destroy endpoint with waiting tasks"
-                );
+        // Waiting tasks point to the capability instance through which they started waiting.
+        // If this is the last instance of the endpoint, they need to be woken up because the object they are waiting
+        // on vanishes. Otherwise, the ones waiting through this instance are pointed to another copy of the endpoint.
+        let is_final_copy = target.is_final_copy();
+        let target_ptr = target as *const Capability;
+        let waiting_tasks = {
+            let endpoint = target.get_inner_endpoint().unwrap();
+            let mut state = endpoint.state.borrow_mut();
+            let EndpointState {
+                send_set,
+                recv_set,
+                watch_set,
+            } = &mut *state;
+            [send_set, recv_set, watch_set].map(|wait_set| {
+                if is_final_copy {
+                    wait_set.take()
+                } else {
+                    *wait_set
+                }
+            })
+        };
+        for task_ptr in waiting_tasks.into_iter().flatten() {
+            let task = unsafe { &*task_ptr }.get_inner_task().unwrap();
+            if is_final_copy {
+                // aborting a wait removes the task from the wait sets of all objects, which includes borrowing this
+                // endpoint
+                TaskIface.abort_wait(task, SyscallError::ObjectDestroyed);
+                sched::enqueue(task_ptr);
+            } else {
+                let other_copy = unsafe { target.get_other_copy() }.unwrap();
+                task.state
+                    .borrow_mut()
+                    .waiting_on
+                    .replace(target_ptr, other_copy);
             }
+        }
 
+        if is_final_copy {
             // Safety: This is the last endpoint instance and no tasks are waiting so no pointers are left
             // pointing to this capability
+            let endpoint = target.get_inner_endpoint_mut().unwrap();
             unsafe { endpoint.state.destroy() }
         }
 
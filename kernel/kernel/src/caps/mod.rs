@@ -31,6 +31,7 @@ pub use vspace::{VSpace, VSpaceIface};
 
 use crate::caps::endpoint::Endpoint;
 pub use prelude::*;
+use syscall_abi::identify::CapabilityVariant;
 pub use syscall_abi::SyscallError;
 
 #[derive(Copy, Clone)]
@@ -53,6 +54,25 @@ pub enum Tag {
     Endpoint,
 }
 
+impl From<Tag> for CapabilityVariant {
+    fn from(tag: Tag) -> Self {
+        match tag {
+            Tag::Uninit => CapabilityVariant::Uninit,
+            Tag::Memory => CapabilityVariant::Memory,
+            Tag::CSpace => CapabilityVariant::CSpace,
+            Tag::VSpace => CapabilityVariant::VSpace,
+            Tag::Task => CapabilityVariant::Task,
+            Tag::Page => CapabilityVariant::Page,
+            Tag::IrqControl => CapabilityVariant::IrqControl,
+            Tag::Irq => CapabilityVariant::Irq,
+            Tag::Notification => CapabilityVariant::Notification,
+            Tag::Devmem => CapabilityVariant::Devmem,
+            Tag::AsidControl => CapabilityVariant::AsidControl,
+            Tag::Endpoint => CapabilityVariant::Endpoint,
+        }
+    }
+}
+
 pub union Variant {
     uninit: Uninit,
     memory: ManuallyDrop<Memory>,
@@ -99,6 +119,25 @@ impl Correspondence for Capability {
             (Tag::Page, Tag::Page) => unsafe {
                 self.variant.page.corresponds_to(&other.variant.page)
             },
+            (Tag::IrqControl, Tag::IrqControl) => unsafe {
+                self.variant
+                    .irq_control
+                    .corresponds_to(&other.variant.irq_control)
+            },
+            (Tag::Irq, Tag::Irq) => unsafe { self.variant.irq.corresponds_to(&other.variant.irq) },
+            (Tag::Notification, Tag::Notification) => unsafe {
+                self.variant
+                    .notification
+                    .corresponds_to(&other.variant.notification)
+            },
+            (Tag::Devmem, Tag::Devmem) => unsafe {
+                self.variant.devmem.corresponds_to(&other.variant.devmem)
+            },
+            (Tag::Endpoint, Tag::Endpoint) => unsafe {
+                self.variant
+                    .endpoint
+                    .corresponds_to(&other.variant.endpoint)
+            },
             // TODO Properly add other variants
             _ => false,
         }
@@ -1,4 +1,7 @@
-use crate::caps::{CapCounted, Capability, Tag, TaskIface, Uninit, Variant};
+use crate::caps::endpoint::EndpointIface;
+use crate::caps::task::{TaskExecutionState, TaskState, WaitingOn};
+use crate::caps::{CapCounted, Capability, SyscallError, Tag, TaskIface, Uninit, Variant};
+use crate::sched;
 use allocators::Box;
 use core::cell::RefCell;
 use core::mem::ManuallyDrop;
@@ -6,6 +9,8 @@ use core::ops::Deref;
 use derivation_tree::caps::CapabilityIface;
 use derivation_tree::tree::TreeNodeOps;
 use derivation_tree::{AsStaticMut, AsStaticRef, Correspondence};
+use syscall_abi::receive::ReceiveReturn;
+use syscall_abi::IntoRawSysRepsonse;
 
 #[derive(Eq, PartialEq)]
 pub struct NotificationState {
@@ -15,6 +20,12 @@ pub struct NotificationState {
 
     /// A task that is currently waiting on this notification.
     pub wait_set: Option<*mut Capability>,
+
+    /// The state of the task to which this notification is bound.
+    ///
+    /// If the notification is signaled while that task is blocked in an endpoint `receive`, the receive is
+    /// completed with the notification value instead of a message.
+    pub bound_task: Option<*const RefCell<TaskState>>,
 }
 
 /// A notification capability
@@ -33,7 +44,11 @@ pub struct NotificationIface;
 
 impl NotificationIface {
     /// Derive a new Notification capability from a memory capability.
-    pub fn derive(&self, src_mem: &Capability, target_slot: &mut Capability) {
+    pub fn derive(
+        &self,
+        src_mem: &Capability,
+        target_slot: &mut Capability,
+    ) -> Result<(), SyscallError> {
         assert_eq!(src_mem.tag, Tag::Memory);
         assert_eq!(target_slot.tag, Tag::Uninit);
 
@@ -41,10 +56,11 @@ impl NotificationIface {
         let state = RefCell::new(NotificationState {
             value: 0,
             wait_set: None,
+            bound_task: None,
         });
         let state = unsafe {
             Box::new(state, src_mem.get_inner_memory().unwrap().allocator.deref())
-                .unwrap()
+                .map_err(|_| SyscallError::NoMem)?
                 .ignore_lifetimes()
         };
 
@@ -59,6 +75,8 @@ impl NotificationIface {
         unsafe {
             src_mem.insert_derivation(target_slot);
         }
+
+        Ok(())
     }
 
     /// Set the notification to active and wake all tasks waiting on it
@@ -72,13 +90,64 @@ impl NotificationIface {
 
         // TODO support setting the notification to a specific value
         state.value = 1;
-        if let Some(task) = state.wait_set {
+        let waiting_task = state.wait_set.take();
+
+        // a task waiting on the notification re-executes `wait_on` and needs to find the value, so the bound task
+        // only gets to consume it if nobody is waiting
+        if let (None, Some(task_state)) = (waiting_task, state.bound_task) {
+            // Safety: a task unbinds itself before its state is freed so the pointer is still valid
+            let task_state = unsafe { &*task_state };
+            signal_bound_task(&mut state, task_state);
+        }
+
+        // waking a task that waits on multiple objects borrows all of them, including this notification
+        drop(state);
+        if let Some(task_ptr) = waiting_task {
             // TODO use cursor
-            let task = unsafe { &mut *task };
-            TaskIface.wake(task)
+            TaskIface.wake(unsafe { &*task_ptr });
+            sched::enqueue(task_ptr);
+        }
+    }
+
+    /// Bind the notification to the task owning the given state.
+    ///
+    /// A notification can only be bound to one task at a time which is why binding an already bound
+    /// notification to a different task fails with [`InvalidArg`](SyscallError::InvalidArg).
+    ///
+    /// # Safety
+    /// Ensure that the task also holds a copy of this notification as its `bound_notification` so that the task can
+    /// unbind it before its state is freed.
+    pub unsafe fn bind_task(
+        &self,
+        notification: &Capability,
+        task_state: *const RefCell<TaskState>,
+    ) -> Result<(), SyscallError> {
+        assert_eq!(notification.tag, Tag::Notification);
+        let mut state = notification
+            .get_inner_notification()
+            .unwrap()
+            .state
+            .borrow_mut();
+        match state.bound_task {
+            Some(existing_task) if existing_task != task_state => Err(SyscallError::InvalidArg),
+            _ => {
+                state.bound_task = Some(task_state);
+                Ok(())
+            }
         }
     }
 
+    /// Remove the binding between the notification and whatever task it is bound to.
+    pub fn unbind_task(&self, notification: &Capability) {
+        assert_eq!(notification.tag, Tag::Notification);
+        let mut state = notification
+            .get_inner_notification()
+            .unwrap()
+            .state
+            .borrow_mut();
+        state.bound_task = None;
+    }
+
     /// Get the currently contained value and clear it
     pub fn take_value(&self, notification: &Capability) -> usize {
         assert_eq!(notification.tag, Tag::Notification);
@@ -92,7 +161,7 @@ impl NotificationIface {
         value
     }
 
-    /// Remove the given task from the notifications wait_set.
+    /// Remove the task owning the given state from the notifications wait_set.
     ///
     /// If the task is not part of the wait_set, this function is a noop.
     ///
@@ -102,7 +171,11 @@ impl NotificationIface {
     /// This function only removes the pointer *notification to task* pointer which leaves the two
     /// objects in an inconsistent state.
     /// After calling this function, the *task to notification* pointer **must** also be cleared.
-    pub unsafe fn remove_from_wait_set(&self, notification: &Capability, task: *mut Capability) {
+    pub unsafe fn remove_from_wait_set(
+        &self,
+        notification: &Capability,
+        task_state: *const RefCell<TaskState>,
+    ) {
         assert_eq!(notification.tag, Tag::Notification);
         let mut state = notification
             .get_inner_notification()
@@ -112,18 +185,46 @@ impl NotificationIface {
         match state.wait_set {
             None => {}
             Some(waiting_task) => {
-                if waiting_task == task {
+                let waiting_task = unsafe { &*waiting_task }.get_inner_task().unwrap();
+                if core::ptr::eq(&*waiting_task.state, task_state) {
                     state.wait_set = None;
                 }
             }
         }
     }
 
+    /// Let the notifications wait_set point to the task capability `new` if it currently points to `old`.
+    ///
+    /// # Safety
+    /// `new` must be a copy of the task capability `old`.
+    pub unsafe fn replace_in_wait_set(
+        &self,
+        notification: &Capability,
+        old: *mut Capability,
+        new: *mut Capability,
+    ) {
+        assert_eq!(notification.tag, Tag::Notification);
+        let mut state = notification
+            .get_inner_notification()
+            .unwrap()
+            .state
+            .borrow_mut();
+        if state.wait_set == Some(old) {
+            state.wait_set = Some(new);
+        }
+    }
+
     /// Add the task to the notifications wait_set
     ///
+    /// Fails with `Busy` if another task is already waiting there.
+    ///
     /// # Safety
     /// Ensure that the task also has its `waiting_on` field set to this notification.
-    pub unsafe fn add_to_wait_set(&self, notification: &Capability, task: *mut Capability) {
+    pub unsafe fn add_to_wait_set(
+        &self,
+        notification: &Capability,
+        task: *mut Capability,
+    ) -> Result<(), SyscallError> {
         assert_eq!(notification.tag, Tag::Notification);
         let mut state = notification
             .get_inner_notification()
@@ -131,16 +232,45 @@ impl NotificationIface {
             .state
             .borrow_mut();
         match state.wait_set {
-            Some(existing_task) => {
-                if existing_task != task {
-                    panic!("notification already has a waiting task")
-                }
+            Some(existing_task) if existing_task != task => Err(SyscallError::Busy),
+            _ => {
+                state.wait_set = Some(task);
+                Ok(())
             }
-            None => state.wait_set = Some(task),
         }
     }
 }
 
+/// Complete the endpoint `receive` of a bound task with the notification value if the task is currently blocked in one.
+fn signal_bound_task(notification: &mut NotificationState, task_state: &RefCell<TaskState>) {
+    let mut task = task_state.borrow_mut();
+    if task.execution_state != TaskExecutionState::Waiting {
+        return;
+    }
+    let WaitingOn::One(waiting_on) = task.waiting_on else {
+        return;
+    };
+    let waiting_on = unsafe { &*waiting_on };
+    if waiting_on.tag != Tag::Endpoint {
+        return;
+    }
+
+    // the task might also be blocked in a send on the endpoint which must not be interrupted
+    let endpoint = waiting_on.get_inner_endpoint().unwrap();
+    let Some(task_ptr) = (unsafe { EndpointIface.remove_receiver(endpoint, task_state) }) else {
+        return;
+    };
+
+    log::debug!("waking bound task from endpoint receive");
+    let value = notification.value;
+    notification.value = 0;
+    task.waiting_on = WaitingOn::Nothing;
+    task.execution_state = TaskExecutionState::Idle;
+    task.frame
+        .write_syscall_return(Ok(ReceiveReturn::from_notification(value)).into_response());
+    sched::enqueue(task_ptr);
+}
+
 impl CapabilityIface<Capability> for NotificationIface {
     type InitArgs = ();
 
@@ -172,16 +302,39 @@ impl CapabilityIface<Capability> for NotificationIface {
     fn destroy(&self, target: &mut Capability) {
         assert_eq!(target.tag, Tag::Notification);
 
-        if target.is_final_copy() {
-            let noti = target.get_inner_notification_mut().unwrap();
-            {
-                let state = noti.state.borrow_mut();
-                assert!(
-                    state.wait_set.is_none(),
-                    "can't destory notifications with waitset yet"
-                );
+        // A waiting task points to the capability instance through which it started waiting.
+        // If this is the last instance of the notification, it needs to be woken up because the object it is waiting
+        // on vanishes. Otherwise, it is pointed to another copy of the notification if it waits through this one.
+        let is_final_copy = target.is_final_copy();
+        let target_ptr = target as *const Capability;
+        let waiting_task = {
+            let noti = target.get_inner_notification().unwrap();
+            let mut state = noti.state.borrow_mut();
+            if is_final_copy {
+                state.wait_set.take()
+            } else {
+                state.wait_set
+            }
+        };
+        if let Some(task_ptr) = waiting_task {
+            let task = unsafe { &*task_ptr }.get_inner_task().unwrap();
+            if is_final_copy {
+                // aborting a wait removes the task from the wait sets of all objects, which includes borrowing this
+                // notification
+                TaskIface.abort_wait(task, SyscallError::ObjectDestroyed);
+                sched::enqueue(task_ptr);
+            } else {
+                let other_copy = unsafe { target.get_other_copy() }.unwrap();
+                task.state
+                    .borrow_mut()
+                    .waiting_on
+                    .replace(target_ptr, other_copy);
             }
+        }
+
+        if is_final_copy {
             // free notification memory
+            let noti = target.get_inner_notification_mut().unwrap();
             unsafe { noti.state.destroy() };
         }
 
@@ -2,10 +2,10 @@ use super::{
     asid::{ASID_NONE, ASID_POOL},
     Capability, Memory, SyscallError, Tag, VSpace, Variant,
 };
-use crate::{caps::Uninit, virtmem::KernelMapper};
+use crate::{caps::Uninit, hart, virtmem::KernelMapper};
 
 use allocators::{AllocInit, Allocator};
-use core::{alloc::Layout, arch::asm, mem::ManuallyDrop, ptr};
+use core::{alloc::Layout, mem::ManuallyDrop, ptr};
 use derivation_tree::{caps::CapabilityIface, tree::TreeNodeOps, Correspondence};
 use riscv::pt::PAGESIZE;
 use riscv::{
@@ -33,7 +33,7 @@ pub struct PageIface;
 impl PageIface {
     /// Derive a page from a src memory by allocating one from it.
     /// The derived capability is then placed in `target`.
-    pub fn derive(&self, src: &Capability, target: &mut Capability) {
+    pub fn derive(&self, src: &Capability, target: &mut Capability) -> Result<(), SyscallError> {
         assert_eq!(src.tag, Tag::Memory);
         assert_eq!(target.tag, Tag::Uninit);
 
@@ -42,7 +42,7 @@ impl PageIface {
             .unwrap()
             .allocator
             .allocate(Layout::new::<MemoryPage>(), AllocInit::Zeroed)
-            .unwrap()
+            .map_err(|_| SyscallError::NoMem)?
             .as_mut_ptr()
             .cast();
 
@@ -58,6 +58,8 @@ impl PageIface {
         unsafe {
             src.insert_derivation(target);
         }
+
+        Ok(())
     }
 }
 
@@ -125,14 +127,17 @@ impl Page {
         if page.asid == ASID_NONE {
             return;
         }
-        let Ok(asid) = (unsafe { ASID_POOL.find_asid(page.asid) }) else {
+        let Ok(asid) = ASID_POOL.spin_lock().find_asid(page.asid) else {
+            // the vspace in which the page was mapped has already been destroyed
+            page.asid = ASID_NONE;
+            page.vaddr = core::ptr::null_mut();
             return;
         };
         let pt = unsafe { asid.pt.as_mut().unwrap() };
         riscv::pt::unmap(KernelMapper, pt, page.vaddr as usize, unsafe {
             KernelMapper.mapped_to_phys(page.kernel_addr) as usize
         });
-        unsafe { asm!("sfence.vma") };
+        hart::flush_tlb(page.asid, page.vaddr as usize, PAGESIZE);
         page.asid = ASID_NONE;
         page.vaddr = core::ptr::null_mut();
     }
@@ -158,11 +163,9 @@ pub fn map_page(
     }
 
     // map the page
-    assert_eq!(
-        addr & !(PAGESIZE - 1),
-        addr,
-        "page address is not page-aligned"
-    );
+    if addr & !(PAGESIZE - 1) != addr {
+        return Err(SyscallError::InvalidArg);
+    }
 
     if page.asid != ASID_NONE {
         return Err(SyscallError::AlreadyMapped);
@@ -177,6 +180,6 @@ pub fn map_page(
     vspace.map_address(mem, addr, paddr, entry_flags)?;
     page.asid = vspace.asid;
     page.vaddr = addr as *mut u8;
-    unsafe { asm!("sfence.vma") };
+    hart::flush_tlb(vspace.asid, addr, PAGESIZE);
     Ok(())
 }
@@ -1,9 +1,11 @@
 use crate::caps::endpoint::EndpointIface;
+use core::{iter, ptr};
 use derivation_tree::caps::CapabilityIface;
+use derivation_tree::tree::TreeNodeOps;
 
 use super::{
     AsidControlIface, CSpaceIface, Capability, DevmemIface, IrqControlIface, IrqIface, MemoryIface,
-    NotificationIface, PageIface, TaskIface, VSpaceIface,
+    NotificationIface, PageIface, Tag, TaskIface, VSpaceIface,
 };
 
 pub type CapCounted<T> = derivation_tree::CapCounted<'static, 'static, T>;
@@ -26,6 +28,141 @@ pub unsafe fn destroy(target: &mut Capability) {
     };
 }
 
+/// Whether [`destroy`] can handle `target` in its current state.
+///
+/// Destroying the last copy of some capabilities requires cleanup that is not implemented yet, so these are
+/// rejected before any part of them is torn down.
+/// This includes capabilities that only become the last copy while `target` is torn down because all their other
+/// copies are stored in cspaces that are destroyed together with it.
+pub fn is_destroy_supported(target: &Capability) -> bool {
+    is_teardown_supported(target, target, None)
+}
+
+/// Whether destroying `target` also destroys `cap`, either because it is `cap` itself or because `cap` is stored in a
+/// cspace whose last copy is destroyed together with `target`.
+pub fn destroys(target: &Capability, cap: *const Capability) -> bool {
+    reaches(target, target, cap, None)
+}
+
+/// A chain of capabilities on the call stack, each identified by its first copy, which breaks cycles between them
+struct Chain<'a> {
+    first_copy: *const Capability,
+    outer: Option<&'a Chain<'a>>,
+}
+
+impl Chain<'_> {
+    fn contains(chain: Option<&Self>, first_copy: *const Capability) -> bool {
+        let mut current = chain;
+        while let Some(link) = current {
+            if ptr::eq(link.first_copy, first_copy) {
+                return true;
+            }
+            current = link.outer;
+        }
+        false
+    }
+}
+
+/// All copies of `cap` in the order in which they appear in the derivation tree, including `cap` itself
+fn copies(cap: &Capability) -> impl Iterator<Item = &Capability> {
+    let first = unsafe { &*cap.get_first_copy() };
+    iter::successors(Some(first), |copy| {
+        unsafe { copy.get_next_copy() }.map(|next| unsafe { &*next })
+    })
+}
+
+/// Call `f` with every capability that is stored in `container` and destroyed once its last copy is destroyed.
+///
+/// Returns whether `f` returned true for any of them.
+fn any_content(container: &Capability, mut f: impl FnMut(&Capability) -> bool) -> bool {
+    match container.get_tag() {
+        Tag::CSpace => {
+            let cspace = container.get_inner_cspace().unwrap();
+            cspace
+                .slots
+                .iter()
+                .any(|slot| f(unsafe { &*slot.as_ptr() }))
+        }
+        Tag::Task => {
+            let state = container.get_inner_task().unwrap().state.borrow();
+            f(&state.cspace) || f(&state.vspace)
+        }
+        _ => false,
+    }
+}
+
+/// Whether all copies of `cap` are destroyed when `target` is destroyed so that one of them is destroyed as the last
+/// copy.
+///
+/// A capability whose other copies can only be reached through its own content is never torn down because its
+/// copies keep each other alive.
+fn is_torn_down(target: &Capability, cap: &Capability, evaluating: Option<&Chain>) -> bool {
+    let first_copy = unsafe { cap.get_first_copy() };
+    if Chain::contains(evaluating, first_copy) {
+        return false;
+    }
+    let evaluating = Chain {
+        first_copy,
+        outer: evaluating,
+    };
+    copies(cap).all(|copy| reaches(target, target, copy, Some(&evaluating)))
+}
+
+/// Whether destroying `target` reaches `cap` when starting at `current` which is destroyed together with `target`
+fn reaches(
+    target: &Capability,
+    current: &Capability,
+    cap: *const Capability,
+    evaluating: Option<&Chain>,
+) -> bool {
+    if ptr::eq(current, cap) {
+        return true;
+    }
+    match current.get_tag() {
+        Tag::CSpace | Tag::Task if is_torn_down(target, current, evaluating) => {
+            any_content(current, |content| reaches(target, content, cap, evaluating))
+        }
+        _ => false,
+    }
+}
+
+/// Whether [`destroy`] can handle `current` which is destroyed together with `target`.
+///
+/// `visiting` holds the containers through which `current` was reached so that each of them is only checked once.
+fn is_teardown_supported(
+    target: &Capability,
+    current: &Capability,
+    visiting: Option<&Chain>,
+) -> bool {
+    match current.get_tag() {
+        Tag::Devmem | Tag::Irq | Tag::IrqControl => !is_torn_down(target, current, None),
+        Tag::Memory => {
+            // only the last copy is asked for derivations because they are inserted behind it
+            let last_copy = copies(current).last().unwrap();
+            !last_copy.has_derivations() || !is_torn_down(target, current, None)
+        }
+        Tag::CSpace | Tag::Task => {
+            let first_copy = unsafe { current.get_first_copy() };
+            if Chain::contains(visiting, first_copy) || !is_torn_down(target, current, None) {
+                return true;
+            }
+            let visiting = Chain {
+                first_copy,
+                outer: visiting,
+            };
+            !any_content(current, |content| {
+                !is_teardown_supported(target, content, Some(&visiting))
+            })
+        }
+        Tag::Uninit
+        | Tag::VSpace
+        | Tag::Page
+        | Tag::Notification
+        | Tag::AsidControl
+        | Tag::Endpoint => true,
+    }
+}
+
 pub unsafe fn copy(src: &Capability, dst: &mut Capability) {
     match src.get_tag() {
         crate::caps::Tag::Uninit => {}
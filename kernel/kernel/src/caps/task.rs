@@ -8,9 +8,17 @@ use derivation_tree::tree::TreeNodeOps;
 use derivation_tree::Correspondence;
 use riscv::pt::MemoryPage;
 use riscv::trap::TrapFrame;
+use syscall_abi::wait_any::MAX_WAIT_OBJECTS;
+use syscall_abi::{IntoRawSysRepsonse, NoValue};
 
 use crate::caps::destroy;
+use crate::caps::endpoint::EndpointIface;
+use crate::caps::NotificationIface;
+use crate::caps::SyscallError;
 use crate::caps::Uninit;
+use crate::hart::{self, MAX_HARTS};
+use crate::sched::RUN_QUEUE;
+use ksync::SpinLock;
 
 use super::CapCounted;
 use super::Capability;
@@ -25,13 +33,77 @@ pub enum TaskExecutionState {
     Exited,
 }
 
+/// The kernel objects on which a blocked task waits
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WaitingOn {
+    /// The task is not blocked on any object
+    Nothing,
+    /// The task is blocked in a `send`, `receive` or `wait_on` on a single notification or endpoint
+    One(*const Capability),
+    /// The task is blocked in `wait_any` and is part of the wait sets of all of these objects
+    Any([Option<*const Capability>; MAX_WAIT_OBJECTS]),
+}
+
+impl WaitingOn {
+    pub fn is_nothing(&self) -> bool {
+        *self == WaitingOn::Nothing
+    }
+
+    /// Reset to [`Nothing`](WaitingOn::Nothing) and return the previous value
+    pub fn take(&mut self) -> Self {
+        core::mem::replace(self, WaitingOn::Nothing)
+    }
+
+    /// All objects that are waited on
+    pub fn objects(self) -> impl Iterator<Item = *const Capability> {
+        let objects = match self {
+            WaitingOn::Nothing => [None; MAX_WAIT_OBJECTS],
+            WaitingOn::One(object) => {
+                let mut objects = [None; MAX_WAIT_OBJECTS];
+                objects[0] = Some(object);
+                objects
+            }
+            WaitingOn::Any(objects) => objects,
+        };
+        objects.into_iter().flatten()
+    }
+
+    /// Whether `object` is one of the objects that are waited on
+    pub fn contains(self, object: *const Capability) -> bool {
+        self.objects().any(|o| o == object)
+    }
+
+    /// Wait on the capability `new` instead of `old` if `old` is one of the objects that are waited on
+    pub fn replace(&mut self, old: *const Capability, new: *const Capability) {
+        match self {
+            WaitingOn::Nothing => {}
+            WaitingOn::One(object) => {
+                if *object == old {
+                    *object = new;
+                }
+            }
+            WaitingOn::Any(objects) => {
+                for object in objects.iter_mut().flatten() {
+                    if *object == old {
+                        *object = new;
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub struct TaskState {
     pub frame: TrapFrame,
     pub cspace: Capability,
     pub vspace: Capability,
     pub ipc_buffer: Option<*mut MemoryPage>,
     pub execution_state: TaskExecutionState,
-    pub waiting_on: Option<*const Capability>,
+    pub waiting_on: WaitingOn,
+    /// A copy of the notification that is bound to this task or an uninitialized capability if none is bound.
+    ///
+    /// Signaling the bound notification while the task is blocked in an endpoint `receive` wakes it up.
+    pub bound_notification: Capability,
 }
 
 pub struct Task {
@@ -62,7 +134,11 @@ pub struct TaskIface;
 
 impl TaskIface {
     /// Derive a new [`Task`](super::Task) capability from a memory capability.
-    pub fn derive(&self, src_mem: &Capability, target_slot: &mut Capability) {
+    pub fn derive(
+        &self,
+        src_mem: &Capability,
+        target_slot: &mut Capability,
+    ) -> Result<(), SyscallError> {
         assert_eq!(target_slot.tag, Tag::Uninit);
 
         // create a new (uninitialized) task state
@@ -73,11 +149,12 @@ impl TaskIface {
                 frame: TrapFrame::null(),
                 ipc_buffer: None,
                 execution_state: TaskExecutionState::Idle,
-                waiting_on: None,
+                waiting_on: WaitingOn::Nothing,
+                bound_notification: Capability::empty(),
             }),
             src_mem.get_inner_memory().unwrap().allocator.deref(),
         )
-        .unwrap();
+        .map_err(|_| SyscallError::NoMem)?;
 
         // save the capability into the target slot
         target_slot.tag = Tag::Task;
@@ -91,14 +168,61 @@ impl TaskIface {
         unsafe {
             src_mem.insert_derivation(target_slot);
         }
+
+        Ok(())
     }
 
-    /// Wake the task from its waiting state so that it can be scheduled again
+    /// Wake the task from its waiting state so that it can be scheduled again.
+    ///
+    /// A task that is blocked in `wait_any` is removed from the wait sets of all objects it waits on so that only
+    /// one of them wakes it.
+    /// These objects must therefore not be borrowed.
     pub fn wake(&self, task: &Capability) {
         assert_eq!(task.tag, Tag::Task);
-        let mut state = task.get_inner_task().unwrap().state.borrow_mut();
+        let task = task.get_inner_task().unwrap();
         log::debug!("waking task");
+        let waiting_on = task.state.borrow().waiting_on;
+        if let WaitingOn::Any(_) = waiting_on {
+            self.stop_waiting(task);
+        }
+        task.state.borrow_mut().execution_state = TaskExecutionState::Idle;
+    }
+
+    /// Remove the task from the wait sets of all objects it waits on and clear its `waiting_on` field.
+    ///
+    /// The objects must not be borrowed.
+    pub fn stop_waiting(&self, task: &Task) {
+        let task_state = &*task.state as *const RefCell<TaskState>;
+        let waiting_on = task.state.borrow_mut().waiting_on.take();
+        for object in waiting_on.objects() {
+            // Safety: objects clear the waiting_on field of their waiting tasks before they are destroyed
+            unsafe { leave_wait_set(&*object, task_state) };
+        }
+    }
+
+    /// Abort the syscall in which the task is blocked so that it returns `error` once it is scheduled again.
+    ///
+    /// This also removes the task from the wait sets of all objects it waits on which must therefore not be
+    /// borrowed.
+    pub fn abort_wait(&self, task: &Task, error: SyscallError) {
+        log::debug!("aborting wait of task with {:?}", error);
+        // tasks that wait in wait_on or wait_any re-execute the syscall when woken up so their program counter still
+        // points to the ecall instruction
+        let re_executes = match task.state.borrow().waiting_on {
+            WaitingOn::Nothing => false,
+            WaitingOn::One(object) => unsafe { &*object }.tag == Tag::Notification,
+            WaitingOn::Any(_) => true,
+        };
+        self.stop_waiting(task);
+
+        let mut state = task.state.borrow_mut();
+        if re_executes {
+            state.frame.start_pc += 4;
+        }
         state.execution_state = TaskExecutionState::Idle;
+        state
+            .frame
+            .write_syscall_return(Err::<NoValue, SyscallError>(error).into_response());
     }
 }
 
@@ -136,25 +260,46 @@ impl CapabilityIface<Capability> for TaskIface {
 
     fn destroy(&self, target: &mut Capability) {
         assert_eq!(target.tag, Tag::Task);
+        RUN_QUEUE.spin_lock().remove(target);
+        let target_ptr = target as *mut Capability;
 
         if target.is_final_copy() {
             let task = target.get_inner_task_mut().unwrap();
-            {
-                let mut state = task.state.borrow_mut();
-                assert!(
-                    state.ipc_buffer.is_none(),
-                    "can't destroy task with ipcbuffer yet"
-                );
-                assert!(
-                    state.waiting_on.is_none(),
-                    "can't destroy waiting tasks yet"
+            let running = task.state.borrow().execution_state == TaskExecutionState::Running;
+            if running {
+                // the task is executed by another hart which still uses its trap frame and address space, so its
+                // teardown is deferred until that hart traps into the kernel
+                let hart = hart::find_active(target_ptr)
+                    .expect("a running task is the active task of some hart");
+                log::debug!(
+                    "deferring the destruction of a task that runs on hart {}",
+                    hart.hart_id
                 );
-                // TODO: handle recursive cspace destroys
-                unsafe { destroy(&mut state.cspace) };
-                unsafe { destroy(&mut state.vspace) };
+                {
+                    // signaling the bound notification must not touch the task anymore
+                    let state = task.state.borrow();
+                    if state.bound_notification.tag == Tag::Notification {
+                        NotificationIface.unbind_task(&state.bound_notification);
+                    }
+                }
+                let deferred = &mut DEFERRED_DESTROYS.spin_lock()[hart.hart_id];
+                assert!(deferred.is_none());
+                *deferred = Some(DeferredDestroy(Task {
+                    state: task.state.clone(),
+                }));
+                hart.interrupt();
+            } else {
+                unsafe { destroy_state(task) };
             }
-            // Free Task State Memory
-            unsafe { task.state.destroy() };
+        } else {
+            // wait sets and harts point to the task capability through which the task started waiting or was
+            // scheduled, so if that is this instance, they need to point to another copy of it instead
+            let waiting_on = target.get_inner_task().unwrap().state.borrow().waiting_on;
+            let other_copy = unsafe { target.get_other_copy() }.unwrap();
+            for object in waiting_on.objects() {
+                unsafe { replace_in_wait_set(&*object, target_ptr, other_copy) };
+            }
+            hart::replace_active_task(target_ptr, other_copy);
         }
 
         target.tree_data.unlink();
@@ -162,3 +307,88 @@ impl CapabilityIface<Capability> for TaskIface {
         target.variant.uninit = Uninit {};
     }
 }
+
+/// A task whose final capability was destroyed while it was running
+struct DeferredDestroy(Task);
+
+// Safety: the task is only accessed while holding the kernel lock
+unsafe impl Send for DeferredDestroy {}
+
+const NO_DEFERRED_DESTROY: Option<DeferredDestroy> = None;
+
+/// Tasks whose final capability was destroyed while they were running, indexed by the id of the hart that runs them.
+///
+/// The teardown of such a task is finished by [`finish_deferred_destroy()`] once its hart traps into the kernel.
+static DEFERRED_DESTROYS: SpinLock<[Option<DeferredDestroy>; MAX_HARTS]> =
+    SpinLock::new([NO_DEFERRED_DESTROY; MAX_HARTS]);
+
+/// Finish the teardown of the task that was destroyed while the given hart executed it.
+///
+/// This must be called whenever the hart traps into the kernel and before its active task capability is accessed
+/// because the capability no longer exists if this returns `true`.
+pub fn finish_deferred_destroy(hart_id: usize) -> bool {
+    let deferred = DEFERRED_DESTROYS.spin_lock()[hart_id].take();
+    match deferred {
+        None => false,
+        Some(DeferredDestroy(mut task)) => {
+            log::debug!("finishing the destruction of a task that was running");
+            // Safety: the hart that executed the task has trapped into the kernel
+            unsafe { destroy_state(&mut task) };
+            true
+        }
+    }
+}
+
+/// Tear down the state of a task whose final capability is destroyed and free its memory.
+///
+/// # Safety
+/// The task must not be executed by any hart.
+unsafe fn destroy_state(task: &mut Task) {
+    {
+        let task_state = &*task.state as *const RefCell<TaskState>;
+        let mut state = task.state.borrow_mut();
+
+        // remove the task from whatever it is currently blocked on
+        for object in state.waiting_on.take().objects() {
+            leave_wait_set(&*object, task_state);
+        }
+        state.execution_state = TaskExecutionState::Exited;
+
+        // the ipc buffer is owned by a page capability so only the reference to it needs to be dropped
+        state.ipc_buffer = None;
+
+        if state.bound_notification.tag == Tag::Notification {
+            NotificationIface.unbind_task(&state.bound_notification);
+        }
+        destroy(&mut state.bound_notification);
+        destroy(&mut state.cspace);
+        destroy(&mut state.vspace);
+    }
+    // Free Task State Memory
+    task.state.destroy();
+}
+
+/// Remove the task owning `task_state` from the wait set of the object that it is waiting on
+unsafe fn leave_wait_set(object: &Capability, task_state: *const RefCell<TaskState>) {
+    match object.tag {
+        Tag::Notification => NotificationIface.remove_from_wait_set(object, task_state),
+        Tag::Endpoint => {
+            let endpoint = object.get_inner_endpoint().unwrap();
+            EndpointIface.remove_sender(endpoint, task_state);
+            EndpointIface.remove_receiver(endpoint, task_state);
+            EndpointIface.remove_watcher(endpoint, task_state);
+        }
+        _ => unreachable!("tasks can only wait on notifications and endpoints"),
+    }
+}
+
+/// Let the wait set of `object` point to the task capability `new` wherever it currently points to `old`
+unsafe fn replace_in_wait_set(object: &Capability, old: *mut Capability, new: *mut Capability) {
+    match object.tag {
+        Tag::Notification => NotificationIface.replace_in_wait_set(object, old, new),
+        Tag::Endpoint => {
+            EndpointIface.replace_waiting_task(object.get_inner_endpoint().unwrap(), old, new)
+        }
+        _ => unreachable!("tasks can only wait on notifications and endpoints"),
+    }
+}
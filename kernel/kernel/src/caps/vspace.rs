@@ -1,11 +1,15 @@
+use core::alloc::Layout;
 use core::mem::{ManuallyDrop, MaybeUninit};
 
-use crate::caps::asid::ASID_NONE;
+use crate::arch_specific::mmu;
+use crate::caps::asid::{ASID_NONE, ASID_POOL};
 use crate::caps::{self, Memory, Tag, Uninit, Variant};
-use crate::virtmem;
-use allocators::Box;
+use crate::{hart, virtmem};
+use allocators::{Allocator, Box};
 use derivation_tree::{caps::CapabilityIface, tree::TreeNodeOps, Correspondence};
-use riscv::pt::{EntryFlags, PageTable};
+use riscv::mem::ptrs::MappedMutPtr;
+use riscv::mem::VIRT_MEM_USER_END;
+use riscv::pt::{EntryFlags, PageTable, PAGESIZE};
 
 use caps::SyscallError;
 
@@ -54,6 +58,8 @@ impl VSpace {
     /// Map the given physical address in this VSpace at the given virtual address.
     ///
     /// Missing intermediate page tables are automatically allocated from `mem`.
+    /// Only page-aligned addresses in the userspace part of the address space can be mapped and only if nothing is
+    /// mapped there yet.
     pub(crate) fn map_address(
         &self,
         mem: &Memory,
@@ -61,14 +67,26 @@ impl VSpace {
         paddr: usize,
         flags: EntryFlags,
     ) -> Result<(), SyscallError> {
+        if vaddr & (PAGESIZE - 1) != 0
+            || paddr & (PAGESIZE - 1) != 0
+            || vaddr > VIRT_MEM_USER_END
+            || !flags.intersects(EntryFlags::RWX)
+        {
+            return Err(SyscallError::InvalidArg);
+        }
+        let root = unsafe { &mut *self.root };
+        if virtmem::virt_to_phys(root, vaddr).is_some() {
+            return Err(SyscallError::AlreadyMapped);
+        }
+
         virtmem::map(
             &mem.allocator,
-            unsafe { &mut *self.root },
+            root,
             vaddr,
             paddr,
             flags | EntryFlags::Accessed | EntryFlags::Dirty,
-        );
-        Ok(())
+        )
+        .map_err(|_| SyscallError::NoMem)
     }
 }
 
@@ -76,11 +94,12 @@ impl VSpace {
 pub struct VSpaceIface;
 
 impl VSpaceIface {
-    pub fn derive(&self, src: &Capability, target: &mut Capability) {
+    pub fn derive(&self, src: &Capability, target: &mut Capability) -> Result<(), SyscallError> {
         assert_eq!(target.tag, Tag::Uninit);
         // TODO: make sure layout is the same
         let mut page: Box<MaybeUninit<PageTable>> =
-            Box::new_uninit(&*src.get_inner_memory().unwrap().allocator).unwrap();
+            Box::new_uninit(&*src.get_inner_memory().unwrap().allocator)
+                .map_err(|_| SyscallError::NoMem)?;
         PageTable::init_copy(page.as_mut_ptr().cast(), unsafe {
             crate::KERNEL_ROOT_PT
                 .as_mapped()
@@ -101,6 +120,8 @@ impl VSpaceIface {
         unsafe {
             src.insert_derivation(target);
         }
+
+        Ok(())
     }
 }
 
@@ -145,7 +166,32 @@ impl CapabilityIface<Capability> for VSpaceIface {
         assert_eq!(target.tag, Tag::VSpace);
 
         if target.is_final_copy() {
-            todo!("check asid stuff, free page tables");
+            let vspace = target.get_inner_vspace().unwrap();
+            if vspace.asid != ASID_NONE {
+                ASID_POOL.spin_lock().free_asid(vspace.asid);
+            }
+            // no task uses the address space anymore but other harts may still have its translations cached
+            hart::flush_tlb(vspace.asid, 0, 0);
+
+            // idle harts switch to the kernel pagetable so only this hart can still use the pagetable, e.g. if the
+            // task that it executed was just destroyed
+            let root = vspace.root;
+            if mmu::is_active_pagetable(MappedMutPtr::from(root).as_direct()) {
+                unsafe { mmu::use_kernel_pagetable() };
+            }
+
+            // the root pagetable is allocated from the memory that the vspace was derived from
+            // TODO: free intermediate page tables, they are allocated from whichever memory a page was mapped with
+            let Some(parent) = (unsafe { target.get_parent().as_ref() }) else {
+                panic!("vspace has no parent");
+            };
+            assert_eq!(parent.tag, Tag::Memory);
+            let parent = parent.get_inner_memory().unwrap();
+            unsafe {
+                parent
+                    .allocator
+                    .deallocate(root as *mut u8, Layout::new::<PageTable>())
+            };
         }
 
         target.tree_data.unlink();
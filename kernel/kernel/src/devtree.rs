@@ -75,3 +75,30 @@ pub fn get_external_devices<'buf, 'a, 'dt>(
     }
     &buf[0..pos]
 }
+
+/// Collect the ids of all harts that are described by `cpu@` nodes in the device tree
+pub fn get_hart_ids<'buf, 'dt>(fdt: &DevTree<'dt>, buf: &'buf mut [usize]) -> &'buf [usize] {
+    let mut len = 0;
+    let cpus = nodes_with_props(fdt, &["device_type", "reg"])
+        .filter(|node| node.name().map_or(false, |name| name.starts_with("cpu@")));
+    for node in cpus {
+        if len == buf.len() {
+            log::warn!(
+                "ignoring cpu {} because too many harts are present",
+                node.name().unwrap()
+            );
+            continue;
+        }
+        let mut props = node.props();
+        while let Ok(Some(prop)) = props.next() {
+            if prop.name() == Ok("reg") {
+                if let Ok(hart_id) = prop.u32(0) {
+                    buf[len] = hart_id as usize;
+                    len += 1;
+                }
+                break;
+            }
+        }
+    }
+    &buf[0..len]
+}
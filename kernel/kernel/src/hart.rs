@@ -0,0 +1,269 @@
+//! Hart local data and the bring-up of secondary harts
+//!
+//! Every hart that runs the kernel has a [`HartLocal`] struct whose address is kept in the harts thread pointer
+//! register so that kernel code can find out on which hart it is executing by calling [`current()`].
+//!
+//! The kernel is booted on a single hart while all other harts are kept stopped by the SBI implementation until the
+//! boot hart starts them via [`start_secondary_harts()`].
+//! Each secondary hart gets its own kernel stack and enters the kernel at the given entry point.
+//! Since trap frames store the kernel stack of the hart which entered a task, trapping always returns to the hart
+//! that executed the task.
+//!
+//! Harts communicate with each other via inter-processor interrupts which are used to wake up idle harts once a
+//! task becomes runnable, to make a task that was destroyed while running trap into the kernel and to flush the TLBs
+//! of other harts after a mapping has been changed.
+
+use crate::caps::{Capability, KernelAlloc};
+use crate::KERNEL_ROOT_PT;
+use allocators::Box;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use riscv::cpu::{SatpData, SatpMode, ThreadPointer};
+use riscv::hart::HartStartInfo;
+use riscv::mem::mapping::{translate, PhysMapping};
+use riscv::mem::ptrs::MappedConstPtr;
+use riscv::mem::{VIRT_MEM_PHYS_MAP_END, VIRT_MEM_PHYS_MAP_START};
+use riscv::pt::PAGESIZE;
+use riscv::trap::TrapFrame;
+
+/// The maximum number of harts that the kernel supports.
+///
+/// Since hart local data is indexed by hart id, harts with larger ids are never started.
+pub const MAX_HARTS: usize = 8;
+
+/// The size of the kernel stack of each secondary hart
+const KERNEL_STACK_SIZE: usize = 16 * PAGESIZE;
+
+/// Data that belongs to a single hart
+#[derive(Debug)]
+pub struct HartLocal {
+    /// The id of the hart as it is used by SBI and the device tree
+    pub hart_id: usize,
+    /// The PLIC context in which external interrupts are delivered to the hart while it runs in supervisor mode
+    pub plic_context: usize,
+    /// Whether the hart has been started to run the kernel
+    online: AtomicBool,
+    /// Whether the hart has nothing to execute and waits for an interrupt
+    idle: AtomicBool,
+    /// The task which the hart currently executes or whose trap it handles
+    active_task: AtomicPtr<Capability>,
+    /// The trap frame of the active task
+    active_frame: AtomicPtr<TrapFrame>,
+}
+
+impl HartLocal {
+    const fn new(hart_id: usize) -> Self {
+        Self {
+            hart_id,
+            plic_context: plic_context_of(hart_id),
+            online: AtomicBool::new(false),
+            idle: AtomicBool::new(false),
+            active_task: AtomicPtr::new(ptr::null_mut()),
+            active_frame: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Whether the hart has been started to run the kernel
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Acquire)
+    }
+
+    /// Whether the hart has nothing to execute and waits for an interrupt
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Acquire)
+    }
+
+    /// Mark the hart as idle so that it is kicked by [`kick_idle_harts()`] once a task becomes runnable
+    pub fn set_idle(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Release)
+    }
+
+    /// The task which the hart currently executes or whose trap it handles together with its trap frame.
+    ///
+    /// Both pointers are null if the hart has not executed a task yet or is idle.
+    pub fn active_task(&self) -> (*mut Capability, *mut TrapFrame) {
+        (
+            self.active_task.load(Ordering::Relaxed),
+            self.active_frame.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Remember which task the hart executes so that its trap can be handled and it can be inspected when the kernel
+    /// panics
+    pub fn set_active_task(&self, task: *mut Capability, frame: *mut TrapFrame) {
+        self.active_task.store(task, Ordering::Relaxed);
+        self.active_frame.store(frame, Ordering::Relaxed);
+    }
+
+    /// Send an inter-processor interrupt to the hart so that a task which it executes traps into the kernel
+    pub fn interrupt(&self) {
+        if let Err(e) = riscv::hart::send_ipi(core::iter::once(self.hart_id)) {
+            log::warn!("could not interrupt hart {}: {e}", self.hart_id);
+        }
+    }
+}
+
+static HARTS: [HartLocal; MAX_HARTS] = [
+    HartLocal::new(0),
+    HartLocal::new(1),
+    HartLocal::new(2),
+    HartLocal::new(3),
+    HartLocal::new(4),
+    HartLocal::new(5),
+    HartLocal::new(6),
+    HartLocal::new(7),
+];
+
+/// The PLIC context of the given harts supervisor mode.
+///
+/// On the QEMU virt machine every hart has a context for machine mode that is followed by one for supervisor mode.
+const fn plic_context_of(hart_id: usize) -> usize {
+    2 * hart_id + 1
+}
+
+/// Get the data of the hart on which the calling code is executing
+pub fn current() -> &'static HartLocal {
+    try_current().expect("hart local data is not yet set up")
+}
+
+/// Get the data of the hart on which the calling code is executing or `None` if it is not yet set up
+pub fn try_current() -> Option<&'static HartLocal> {
+    let hart = ThreadPointer::read() as *const HartLocal;
+    unsafe { hart.as_ref() }
+}
+
+/// Get the data of all harts which have been started to run the kernel
+pub fn online() -> impl Iterator<Item = &'static HartLocal> {
+    HARTS.iter().filter(|hart| hart.is_online())
+}
+
+/// Find the hart whose active task is the given task capability
+pub fn find_active(task: *mut Capability) -> Option<&'static HartLocal> {
+    online().find(|hart| hart.active_task().0 == task)
+}
+
+/// Let all harts whose active task is the task capability `old` refer to the task capability `new` instead.
+///
+/// This must be called when `old` is destroyed while a copy of it remains so that the trap of the task is handled
+/// through a capability that still exists.
+pub fn replace_active_task(old: *mut Capability, new: *mut Capability) {
+    for hart in online() {
+        let _ = hart
+            .active_task
+            .compare_exchange(old, new, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+/// Find out on which of the given harts the kernel was booted and point the thread pointer to its data.
+///
+/// The loader does not tell the kernel on which hart it was booted but since all other harts are kept stopped until
+/// the kernel starts them, the boot hart is the only one which is already started.
+pub fn init_boot_hart(hart_ids: &[usize]) -> &'static HartLocal {
+    let hart_id = hart_ids
+        .iter()
+        .copied()
+        .find(|&hart_id| riscv::hart::is_started(hart_id).unwrap_or(false))
+        .expect("Could not determine on which hart the kernel was booted");
+    assert!(
+        hart_id < MAX_HARTS,
+        "the kernel was booted on hart {hart_id} which is not supported"
+    );
+
+    let hart = &HARTS[hart_id];
+    hart.online.store(true, Ordering::Release);
+    unsafe { ThreadPointer::write(hart as *const HartLocal as usize) };
+    hart
+}
+
+/// Start all given harts except the current one so that they enter the kernel at `entry`.
+///
+/// This must be called after the kernel root pagetable has been set up because secondary harts use it until they
+/// are switched to a task.
+pub fn start_secondary_harts(
+    allocator: &'static KernelAlloc,
+    hart_ids: &[usize],
+    entry: extern "C" fn(hart_id: usize) -> !,
+) {
+    let kernel_root_pt = unsafe { KERNEL_ROOT_PT };
+    let satp = SatpData {
+        mode: SatpMode::Sv39,
+        asid: 0,
+        ppn: kernel_root_pt.raw() as u64 >> 12,
+    };
+    let satp: u64 = satp.into();
+
+    // secondary harts start with address translation disabled so the trampoline is passed by its physical address
+    let phys_trampoline = translate(
+        unsafe { &*kernel_root_pt.as_mapped().raw() },
+        &PhysMapping::new(
+            VIRT_MEM_PHYS_MAP_START as u64,
+            (VIRT_MEM_PHYS_MAP_END - VIRT_MEM_PHYS_MAP_START) as u64,
+        ),
+        riscv::hart::trampoline_addr() as u64,
+    ) as usize;
+
+    for &hart_id in hart_ids.iter().filter(|&&id| id != current().hart_id) {
+        if hart_id >= MAX_HARTS {
+            log::warn!("not starting hart {hart_id} because only {MAX_HARTS} harts are supported");
+            continue;
+        }
+        let hart = &HARTS[hart_id];
+
+        // the stack and start info are used for as long as the system runs so they are leaked
+        let stack =
+            Box::<[u8]>::new_uninit_slice_with_alignment(KERNEL_STACK_SIZE, PAGESIZE, allocator)
+                .expect("Could not allocate a kernel stack for a secondary hart")
+                .leak();
+        let stack_top = stack.as_ptr_range().end as usize;
+        let start_info = Box::new(
+            HartStartInfo::new(satp, stack_top, hart as *const HartLocal as usize, entry),
+            allocator,
+        )
+        .expect("Could not allocate the start info of a secondary hart")
+        .leak();
+        let phys_start_info = MappedConstPtr::from(start_info as *const HartStartInfo)
+            .as_direct()
+            .raw() as usize;
+
+        match unsafe { riscv::hart::start_hart(hart_id, phys_trampoline, phys_start_info) } {
+            Ok(()) => hart.online.store(true, Ordering::Release),
+            Err(e) => log::warn!("could not start hart {hart_id}: {e}"),
+        }
+    }
+}
+
+/// Get the ids of all online harts except the current one in ascending order
+fn other_harts() -> impl Iterator<Item = usize> {
+    let current = current().hart_id;
+    online()
+        .map(|hart| hart.hart_id)
+        .filter(move |&hart_id| hart_id != current)
+}
+
+/// Send an inter-processor interrupt to all other harts which are idle so that they look for a task to execute.
+///
+/// This should be called whenever a task becomes runnable.
+pub fn kick_idle_harts() {
+    let current = current().hart_id;
+    let idle_harts = online()
+        .filter(|hart| hart.hart_id != current && hart.is_idle())
+        .map(|hart| hart.hart_id);
+    if let Err(e) = riscv::hart::send_ipi(idle_harts) {
+        log::warn!("could not kick idle harts: {e}");
+    }
+}
+
+/// Flush the TLB entries of the virtual address range `vaddr..vaddr + size` in the address space `asid` on all
+/// harts.
+///
+/// A `vaddr` and `size` of 0 flushes all entries of the address space.
+/// This must be called after a mapping has been changed because other harts may still have the old mapping cached
+/// while they execute a task in the same address space.
+pub fn flush_tlb(asid: usize, vaddr: usize, size: usize) {
+    let asid = crate::caps::asid::hardware_asid(asid);
+    riscv::hart::sfence_vma_asid(vaddr, size, asid);
+    // continuing with stale mappings on other harts would break isolation so failing to flush is fatal
+    if let Err(e) = riscv::hart::remote_sfence_vma_asid(other_harts(), vaddr, size, asid) {
+        panic!("could not flush the TLBs of other harts: {e}");
+    }
+}
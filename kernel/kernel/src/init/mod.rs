@@ -5,9 +5,11 @@ mod userspace;
 mod x86_64;
 
 use allocators::{bump_allocator::BumpAllocator, Box};
+use boot_info::MemoryRegion;
 use derivation_tree::tree::DerivationTree;
+use fdt_rs::base::DevTree;
 
-use riscv::mem::ptrs::{MappedConstPtr, PhysConstPtr, PhysMutPtr};
+use riscv::mem::ptrs::{MappedConstPtr, PhysMutPtr};
 use riscv::pt::PageTable;
 #[cfg(target_arch = "riscv64")]
 pub use riscv64::*;
@@ -19,7 +21,10 @@ use crate::{
     caps::{Capability, KernelAlloc},
     KERNEL_ALLOCATOR, KERNEL_ROOT_PT,
 };
-pub use userspace::{create_init_caps, load_init_binary, map_device_tree};
+pub use userspace::{
+    create_extra_memory_caps, create_init_caps, load_init_binary, map_device_tree, map_initrd,
+    INIT_EXTRA_MEM_CADDR, INIT_MAX_EXTRA_MEM,
+};
 
 pub struct InitCaps<'alloc, 'mem> {
     pub init_task: Box<'alloc, 'mem, Capability>,
@@ -71,11 +76,10 @@ pub fn init_plic() -> &'static mut PLIC {
         plic
     };
 
-    // TODO: determine which context(s) we should enable
-    plic.set_threshold(1, 0);
-    plic.set_threshold(1, 1);
-    plic.set_threshold(1, 2);
-    plic.set_threshold(1, 3);
+    // external interrupts are delivered to the supervisor context of every hart that runs the kernel
+    for hart in crate::hart::online() {
+        plic.set_threshold(1, hart.plic_context);
+    }
     plic
 }
 
@@ -99,13 +103,21 @@ pub fn init_derivation_tree<'a>(
 pub fn load_init_task(
     derivation_tree: &DerivationTree<Capability>,
     init_caps: &mut InitCaps,
-    _dtb: PhysConstPtr<u8>,
+    dt: &DevTree<'static>,
+    init_bin: &[u8],
+    initrd: Option<&[u8]>,
+    extra_mem: &[MemoryRegion],
 ) {
     // load the init binary
     {
         let mut mem_cap = derivation_tree.get_root_cursor().unwrap();
         let mut mem_cap = mem_cap.get_exclusive().unwrap();
-        load_init_binary(&mut init_caps.init_task, &mut mem_cap);
+        load_init_binary(&mut init_caps.init_task, &mut mem_cap, init_bin);
+        map_device_tree(&mut init_caps.init_task, &mem_cap, dt);
+        if let Some(initrd) = initrd {
+            map_initrd(&mut init_caps.init_task, &mem_cap, initrd);
+        }
+        create_extra_memory_caps(&mut init_caps.init_task, &mem_cap, extra_mem);
     }
 }
 
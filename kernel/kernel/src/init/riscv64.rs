@@ -1,7 +1,9 @@
 use riscv::cpu;
 use riscv::mem::ptrs::{MappedMutPtr, PhysMutPtr};
 use riscv::pt::PageTable;
-use riscv::trap::{trap_frame_load, TrapFrame, TrapInfo};
+use riscv::trap::{
+    set_kernel_trap_handler, set_user_trap_handler, trap_frame_load, TrapFrame, TrapInfo,
+};
 
 use crate::caps::task::TaskExecutionState;
 use crate::{arch_specific::mmu, caps, virtmem};
@@ -37,26 +39,51 @@ pub fn prepare_task(task: &mut caps::Capability) {
     let vspace = vspace.as_mut();
     log::trace!("enabling task pagetable");
     unsafe {
-        mmu::use_pagetable(MappedMutPtr::from(vspace.root).as_direct());
+        mmu::use_pagetable(
+            MappedMutPtr::from(vspace.root).as_direct(),
+            caps::asid::hardware_asid(vspace.asid),
+        );
     }
 }
 
-/// Yield to the task that owns the given `trap_frame`
-#[must_use]
-pub fn yield_to_task(task: &mut caps::Capability) -> TrapInfo {
+/// Mark the given task as running and return its trap frame so that it can be executed with [`run_task`]
+pub fn enter_task(task: &mut caps::Capability) -> *mut TrapFrame {
     let mut task = task.get_task_mut().unwrap();
     let task = task.as_mut();
     let mut state = task.state.borrow_mut();
     // TODO: this assert should shouldn't be commented out, but currently that would lead to kernel crashes
     //assert_eq!(state.execution_state, TaskExecutionState::Idle);
     state.execution_state = TaskExecutionState::Running;
+    &mut state.frame as *mut TrapFrame
+}
+
+/// Execute the task that owns the given `trap_frame` until it traps back into the kernel
+///
+/// # Safety
+/// The frame must have been returned by [`enter_task`] and the task must not be destroyed while it is executing.
+/// The kernel lock must not be held so that other harts can handle traps while this one executes the task.
+#[must_use]
+pub unsafe fn run_task(trap_frame: *mut TrapFrame) -> TrapInfo {
     log::trace!("restoring trap frame, entering user space: ➡️ 👤🌍");
-    unsafe { trap_frame_load(&mut state.frame as *mut TrapFrame) };
-    state.execution_state = TaskExecutionState::Idle;
+    unsafe {
+        set_user_trap_handler();
+        trap_frame_load(trap_frame);
+        set_kernel_trap_handler();
+    }
     log::trace!("returning to kernel, handling trap: ↩️ 🌱");
     TrapInfo::from_current_regs()
 }
 
+/// Mark the given task as no longer running after it has trapped into the kernel
+pub fn leave_task(task: &mut caps::Capability) {
+    let task = task.get_inner_task_mut().unwrap();
+    let mut state = task.state.borrow_mut();
+    // the task might have been stopped by another hart while it was running
+    if state.execution_state == TaskExecutionState::Running {
+        state.execution_state = TaskExecutionState::Idle;
+    }
+}
+
 pub unsafe fn set_return_to_user() {
     log::debug!("clearing sstatus.SPP flag to enable returning to user code");
     cpu::SStatus::clear(cpu::SStatusFlags::SPP);
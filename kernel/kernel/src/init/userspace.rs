@@ -7,10 +7,11 @@ use crate::devtree::get_external_devices;
 use crate::virtmem;
 
 use crate::init::InitCaps;
-use align_data::{include_aligned, Align16};
 use allocators::Box;
+use boot_info::MemoryRegion;
+use core::mem;
 use derivation_tree::caps::CapabilityIface;
-use derivation_tree::tree::DerivationTree;
+use derivation_tree::tree::{DerivationTree, TreeNodeOps};
 use elfloader::{
     ElfBinary, ElfLoader, ElfLoaderErr, Flags, LoadableHeaders, RelocationEntry, RelocationType,
     VAddr,
@@ -19,11 +20,6 @@ use fdt_rs::base::DevTree;
 use riscv::mem::ptrs::{MappedConstPtr, PhysMutPtr};
 use riscv::pt::{EntryFlags, PAGESIZE};
 
-static INIT_BIN: &[u8] = include_aligned!(
-    Align16,
-    "../../../../target/riscv64imac-unknown-none-elf/release/init"
-);
-
 /// A struct for allocating and mapping (loading) memory so that it can be used for userspace stack
 struct StackLoader<'v, 'm> {
     vbase: u64,
@@ -204,7 +200,9 @@ pub fn create_init_caps<'dt>(
     }
 
     log::debug!("deriving task capability from root memory capability");
-    TaskIface.derive(&mem_cap, &mut init_caps.init_task);
+    TaskIface
+        .derive(&mem_cap, &mut init_caps.init_task)
+        .unwrap();
     let mut task_cap = derivation_tree
         .get_node(unsafe { init_caps.init_task.as_raw() }.0)
         .unwrap();
@@ -212,10 +210,14 @@ pub fn create_init_caps<'dt>(
     let mut task_state = task_cap.get_inner_task_mut().unwrap().state.borrow_mut();
 
     log::debug!("initializing vspace for the init task");
-    VSpaceIface.derive(&mem_cap, &mut task_state.vspace);
+    VSpaceIface
+        .derive(&mem_cap, &mut task_state.vspace)
+        .unwrap();
 
     log::debug!("initializing cspace for the init task");
-    CSpaceIface.derive(&mem_cap, &mut task_state.cspace, 128);
+    CSpaceIface
+        .derive(&mem_cap, &mut task_state.cspace, 128)
+        .unwrap();
 
     {
         let target_slot = unsafe {
@@ -310,23 +312,121 @@ pub fn create_init_caps<'dt>(
     init_caps
 }
 
-pub fn map_device_tree(mem: &caps::Memory, vspace: &caps::VSpace, dt: &DevTree<'static>) {
-    let fdtb = dt.buf();
-    let fdt_start = MappedConstPtr::from(fdtb.as_ptr()).as_direct();
+/// The CAddr in the init tasks cspace at which the memory capability of the first additional usable memory region is
+/// placed. Further regions follow in consecutive slots.
+pub const INIT_EXTRA_MEM_CADDR: usize = 9;
+
+/// How many memory capabilities for additional usable memory regions are placed into the init tasks cspace at most
+pub const INIT_MAX_EXTRA_MEM: usize = 7;
+
+/// Create a memory capability for each of the given physical memory regions and place them into the init tasks
+/// cspace starting at [`INIT_EXTRA_MEM_CADDR`].
+///
+/// The capabilities are inserted into the derivation tree as derivations of the root memory capability.
+/// Regions beyond [`INIT_MAX_EXTRA_MEM`] are not handed to init.
+pub fn create_extra_memory_caps(
+    task_cap: &mut Capability,
+    mem_cap: &Capability,
+    regions: &[MemoryRegion],
+) {
+    if regions.len() > INIT_MAX_EXTRA_MEM {
+        log::warn!(
+            "{} additional memory regions are usable but only {} can be passed to init",
+            regions.len(),
+            INIT_MAX_EXTRA_MEM
+        );
+    }
+
+    let task_state = task_cap.get_inner_task().unwrap().state.borrow();
+    for (i, region) in regions.iter().take(INIT_MAX_EXTRA_MEM).enumerate() {
+        let caddr = INIT_EXTRA_MEM_CADDR + i;
+        log::debug!("creating memory capability for {region} at caddr {caddr}");
+
+        // the kernel allocator has already handed all its memory to the root memory capability, so the allocator
+        // from which the memory capability is created is placed at the start of the region itself
+        let region_alloc: &'static KernelAlloc = unsafe {
+            let alloc_ptr = PhysMutPtr::from(region.start as *mut KernelAlloc)
+                .as_mapped()
+                .raw();
+            alloc_ptr.write(super::init_alloc(
+                PhysMutPtr::from((region.start + mem::size_of::<KernelAlloc>()) as *mut u8),
+                PhysMutPtr::from(region.end() as *mut u8),
+            ));
+            &*alloc_ptr
+        };
+
+        let target_slot = unsafe {
+            task_state
+                .cspace
+                .get_inner_cspace()
+                .unwrap()
+                .resolve_caddr(caddr.into())
+                .unwrap()
+                .as_mut()
+                .unwrap()
+        };
+        MemoryIface.create_init(target_slot, region_alloc).unwrap();
+        unsafe { mem_cap.insert_derivation(target_slot) };
+    }
+}
+
+/// Map the device tree read-only into the init tasks vspace and pass its address to init in the `a2` register.
+///
+/// The device tree is mapped where the bootloader placed it which is not necessarily page aligned so the address that
+/// init receives lies at the same offset into the first mapped page.
+pub fn map_device_tree(task_cap: &mut Capability, mem_cap: &Capability, dt: &DevTree<'static>) {
     const V_BASE: usize = 0x20_0000_0000;
-    for offset in (0..fdtb.len()).step_by(PAGESIZE) {
-        vspace
-            .map_address(
-                mem,
-                V_BASE + offset,
-                fdt_start.raw() as usize + offset,
-                EntryFlags::Read | EntryFlags::UserReadable,
-            )
-            .unwrap();
+    log::debug!("mapping the device tree into the init task");
+    let mut task_state = task_cap.get_inner_task_mut().unwrap().state.borrow_mut();
+    let fdtb = dt.buf();
+    let fdt_start = MappedConstPtr::from(fdtb.as_ptr()).as_direct().raw() as usize;
+    let page_offset = fdt_start % PAGESIZE;
+    {
+        let vspace = task_state.vspace.get_vspace_mut().unwrap();
+        for offset in (0..page_offset + fdtb.len()).step_by(PAGESIZE) {
+            vspace
+                .as_ref()
+                .map_address(
+                    mem_cap.get_inner_memory().unwrap(),
+                    V_BASE + offset,
+                    fdt_start - page_offset + offset,
+                    EntryFlags::Read | EntryFlags::UserReadable,
+                )
+                .unwrap();
+        }
     }
+
+    task_state.frame.general_purpose_regs[12] = V_BASE + page_offset;
+}
+
+/// Map the initial ram filesystem read-only into the init tasks vspace and pass its address and size to init in the
+/// `a0` and `a1` registers
+pub fn map_initrd(task_cap: &mut Capability, mem_cap: &Capability, initrd: &[u8]) {
+    const V_BASE: usize = 0x21_0000_0000;
+    log::debug!("mapping the initrd into the init task");
+    let mut task_state = task_cap.get_inner_task_mut().unwrap().state.borrow_mut();
+    let initrd_start = MappedConstPtr::from(initrd.as_ptr()).as_direct();
+    {
+        let vspace = task_state.vspace.get_vspace_mut().unwrap();
+        for offset in (0..initrd.len()).step_by(PAGESIZE) {
+            vspace
+                .as_ref()
+                .map_address(
+                    mem_cap.get_inner_memory().unwrap(),
+                    V_BASE + offset,
+                    initrd_start.raw() as usize + offset,
+                    EntryFlags::Read | EntryFlags::UserReadable,
+                )
+                .unwrap();
+        }
+    }
+
+    task_state.frame.general_purpose_regs[10] = V_BASE;
+    task_state.frame.general_purpose_regs[11] = initrd.len();
 }
 
-pub fn load_init_binary(task_cap: &mut Capability, mem_cap: &mut Capability) {
+/// Load the given init ELF binary into the init task and configure the task to execute it
+pub fn load_init_binary(task_cap: &mut Capability, mem_cap: &mut Capability, init_bin: &[u8]) {
     log::debug!("loading the init binary");
     let mut task_state = task_cap.get_inner_task_mut().unwrap().state.borrow_mut();
 
@@ -341,7 +441,7 @@ pub fn load_init_binary(task_cap: &mut Capability, mem_cap: &mut Capability) {
     .unwrap();
 
     log::debug!("loading the init binary into its vspace");
-    let elf_binary = ElfBinary::new(INIT_BIN).unwrap();
+    let elf_binary = ElfBinary::new(init_bin).expect("the init image is not a valid elf binary");
     let mut elf_loader = VSpaceLoader {
         vbase: 0x0,
         mem: &mem_cap,
@@ -2,24 +2,30 @@
 #![no_main]
 
 use crate::caps::task::TaskExecutionState;
-use crate::caps::{Capability, KernelAlloc, NotificationIface};
+use crate::caps::{Capability, KernelAlloc, NotificationIface, Tag};
 use crate::init::InitCaps;
-use crate::sched::Schedule;
+use crate::sched::{Schedule, RUN_QUEUE};
 use allocators::Box;
-use core::arch::asm;
+use boot_info::{BootInfo, MemoryRegion};
+use cmdline::{CmdLine, DebugFlags};
 use core::panic::PanicInfo;
-use derivation_tree::tree::DerivationTree;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use derivation_tree::tree::{CursorData, DerivationTree};
 use klog::KernelLogger;
+use ksync::SpinLock;
 use log::Level;
-use riscv::cpu::{Exception, Interrupt, TrapEvent};
+use riscv::cpu::{Exception, Interrupt, InterruptBits, Sip, TrapEvent};
 use riscv::mem::ptrs::{PhysConstPtr, PhysMutPtr};
 use riscv::mem::VIRT_MEM_KERNEL_START;
 use riscv::pt::PageTable;
 use riscv::timer::set_timeout;
-use riscv::trap::{set_kernel_trap_handler, set_user_trap_handler};
+use riscv::trap::{set_kernel_trap_handler, TrapFrame, TrapInfo};
 
+mod backtrace;
 mod caps;
 mod devtree;
+mod hart;
 mod init;
 mod sched;
 mod syscalls;
@@ -41,87 +47,239 @@ pub static mut KERNEL_ALLOCATOR: Option<KernelAlloc> = None;
 
 pub struct KernelContext {
     pub plic: &'static mut arch_specific::plic::PLIC,
+    pub cmdline: CmdLine<'static>,
 }
 
+/// The state of the kernel that is shared by all harts
+struct KernelState {
+    derivation_tree: Box<'static, 'static, DerivationTree<Capability>>,
+    init_caps: InitCaps<'static, 'static>,
+    ctx: KernelContext,
+}
+
+// Safety: the state is only accessed while holding the KERNEL lock
+unsafe impl Send for KernelState {}
+
+/// The big kernel lock which serializes all accesses to the derivation tree and thereby to all capabilities.
+///
+/// A hart holds it from the moment it traps into the kernel until it returns to userspace.
+/// It holds `None` until the boot hart has finished initializing the kernel.
+static KERNEL: SpinLock<Option<KernelState>> = SpinLock::new(None);
+
+/// The derivation tree which is inspected when the kernel panics.
+///
+/// It is kept outside of the [`KERNEL`] lock because that is usually held by the code that panics.
+static DERIVATION_TREE: AtomicPtr<DerivationTree<Capability>> = AtomicPtr::new(ptr::null_mut());
+
+/// Whether a panic is already being handled in which case no further diagnostics are printed because they might
+/// be what caused the panic
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
-    // print panic message
+    // print what happened recently, the state of the current hart and then the panic message so that it is the last
+    // thing on the console
+    if !PANICKING.swap(true, Ordering::AcqRel) {
+        LOGGER.dump_buffer();
+        print_hart_state();
+        backtrace::print_backtrace();
+    }
     println!("🚨 Kernel Panic! 😱  {}", info);
 
     // shutdown the device
+    #[cfg(feature = "kernel_tests")]
+    arch_specific::qemu_exit::exit(false);
+    #[cfg(not(feature = "kernel_tests"))]
     riscv::power::shutdown()
 }
 
 #[no_mangle]
-extern "C" fn _start(
-    _argc: u32,
-    _argv: *const *const core::ffi::c_char,
-    phys_fdt: PhysConstPtr<u8>,
-    phys_mem_start: PhysMutPtr<u8>,
-    phys_mem_end: PhysMutPtr<u8>,
-) {
+extern "C" fn _start(boot_info: PhysConstPtr<BootInfo>) {
     LOGGER.install().expect("Could not install logger");
     assert_start_expectations();
 
-    kernel_main(0, 0, phys_fdt, phys_mem_start, phys_mem_end);
+    let boot_info = unsafe { &*boot_info.as_mapped().raw() };
+    kernel_main(boot_info);
+    riscv::power::shutdown();
+}
+
+/// The entry point of secondary harts which are started by the boot hart
+extern "C" fn _start_secondary(hart_id: usize) -> ! {
+    unsafe { set_kernel_trap_handler() };
+    log::info!("hart {hart_id} entered the kernel");
+
+    // wait until the boot hart has finished initializing the kernel
+    while KERNEL.spin_lock().is_none() {
+        core::hint::spin_loop();
+    }
+
+    init::prepare_userspace_handoff();
+    kernel_loop(false);
     riscv::power::shutdown();
 }
 
-extern "C" fn kernel_main(
-    _hartid: usize,
-    _unused: usize,
-    dtb: PhysConstPtr<u8>,
-    phys_mem_start: PhysMutPtr<u8>,
-    phys_mem_end: PhysMutPtr<u8>,
-) {
+/// Print the task that is active on the current hart together with its registers as well as all capabilities that
+/// are currently accessed through cursors of the derivation tree.
+///
+/// Nothing is locked because the panicking code most likely holds the locks.
+fn print_hart_state() {
+    let Some(hart) = hart::try_current() else {
+        return;
+    };
+    let (task, frame) = hart.active_task();
+    match unsafe { frame.as_ref() } {
+        None => println!("hart {} has no active task", hart.hart_id),
+        Some(frame) => println!(
+            "hart {} was handling task {:p} with registers:\n{}",
+            hart.hart_id, task, frame
+        ),
+    }
+
+    let Some(derivation_tree) = (unsafe { DERIVATION_TREE.load(Ordering::Relaxed).as_ref() })
+    else {
+        return;
+    };
+    println!("active capability cursors:");
+    for cursor in derivation_tree.used_cursors() {
+        let (state, node) = match cursor {
+            CursorData::Inactive(node) => ("inactive", node),
+            CursorData::SharedRef(node) => ("shared", node),
+            CursorData::ExclusiveRef(node) => ("exclusive", node),
+            CursorData::Free | CursorData::Allocated => continue,
+        };
+        println!("  {state:<9} {node:p} {:?}", unsafe { (*node).get_tag() });
+    }
+}
+
+/// Get a slice to the physical memory that is described by the given region
+fn phys_slice(region: &MemoryRegion) -> &'static [u8] {
+    let start = PhysConstPtr::from(region.start as *const u8)
+        .as_mapped()
+        .raw();
+    unsafe { core::slice::from_raw_parts(start, region.len) }
+}
+
+fn kernel_main(boot_info: &BootInfo) {
     use crate::init::*;
 
-    let allocator: &KernelAlloc = init_kernel_allocator(phys_mem_start, phys_mem_end);
-    let dt = init_device_tree(dtb.as_mapped().into());
+    if !boot_info.symtab.is_empty() {
+        backtrace::init_symbols(backtrace::SymbolTable::new(
+            phys_slice(&boot_info.symtab),
+            phys_slice(&boot_info.strtab),
+        ));
+    }
+
+    // the loader has already validated the command line and stopped booting if it was invalid
+    let bootargs = match boot_info.cmdline.is_empty() {
+        true => "",
+        false => core::str::from_utf8(phys_slice(&boot_info.cmdline))
+            .expect("The kernel command line is not valid UTF-8"),
+    };
+    let cmdline = CmdLine::parse(bootargs).expect("Could not parse the kernel command line");
+    LOGGER.set_filter(cmdline.log_filter);
+    log::info!("kernel command line: {bootargs:?}");
+
+    for region in boot_info.memory.iter() {
+        log::info!("physical memory: {region}");
+    }
+    for region in boot_info.reserved.iter() {
+        log::info!("reserved memory: {region}");
+    }
+
+    for region in boot_info.usable.iter() {
+        log::info!("usable memory: {region}");
+    }
+
+    // the kernel allocates from the largest usable region which also backs the root memory capability while all
+    // other regions are handed to init as additional memory capabilities
+    let kernel_mem = *boot_info
+        .usable
+        .largest()
+        .expect("The boot info does not describe any usable memory");
+    let mut extra_mem = boot_info.usable;
+    extra_mem.subtract(&kernel_mem).unwrap();
+    let allocator: &KernelAlloc = init_kernel_allocator(
+        PhysMutPtr::from(kernel_mem.start as *mut u8),
+        PhysMutPtr::from(kernel_mem.end() as *mut u8),
+    );
+    let dt = init_device_tree(phys_slice(&boot_info.fdt).as_ptr());
+    let mut hart_ids = [0; hart::MAX_HARTS];
+    let hart_ids = devtree::get_hart_ids(&dt, &mut hart_ids);
+    let boot_hart = hart::init_boot_hart(hart_ids);
+    log::info!("booted on hart {} of harts {hart_ids:?}", boot_hart.hart_id);
     init_kernel_root_pt();
+    caps::asid::init_asid_pool();
+    hart::start_secondary_harts(allocator, hart_ids, _start_secondary);
 
     let plic = init_plic();
 
     let derivation_tree = init_derivation_tree(allocator);
+    DERIVATION_TREE.store(
+        &*derivation_tree as *const _ as *mut DerivationTree<Capability>,
+        Ordering::Relaxed,
+    );
+
+    // init is resolved before the init caps are created because those take all remaining memory of the allocator
+    let initrd = match boot_info.initrd.is_empty() {
+        true => None,
+        false => Some(phys_slice(&boot_info.initrd)),
+    };
+    let init_bin = match cmdline.init {
+        None => phys_slice(&boot_info.init),
+        Some(path) => find_init_in_initrd(allocator, initrd, path),
+    };
     let mut init_caps = create_init_caps(&allocator, &derivation_tree, &dt);
-    load_init_task(&derivation_tree, &mut init_caps, dtb);
-    map_device_tree(
-        init_caps
-            .init_task
-            .get_inner_task()
-            .unwrap()
-            .get_cspace()
-            .get_shared()
-            .unwrap()
-            .get_inner_cspace()
-            .unwrap()
-            .slots[1]
-            .borrow()
-            .get_inner_memory()
-            .unwrap(),
-        init_caps
-            .init_task
-            .get_inner_task()
-            .unwrap()
-            .get_vspace()
-            .get_shared()
-            .unwrap()
-            .get_inner_vspace()
-            .unwrap(),
+    load_init_task(
+        &derivation_tree,
+        &mut init_caps,
         &dt,
+        init_bin,
+        initrd,
+        extra_mem.as_slice(),
     );
 
+    *KERNEL.spin_lock() = Some(KernelState {
+        derivation_tree,
+        init_caps,
+        ctx: KernelContext { plic, cmdline },
+    });
+
     prepare_userspace_handoff();
 
-    kernel_loop(derivation_tree, init_caps, &mut KernelContext { plic });
+    log::info!("🚀 launching init");
+    kernel_loop(true);
 }
 
-fn maybe_task_wait(task: &Capability) {
-    let task = task.get_inner_task().unwrap();
-    let state = task.state.borrow().execution_state;
-    if state == TaskExecutionState::Waiting {
-        unsafe { asm!("wfi") };
+/// The alignment which the init binary needs for being parsed as ELF file
+const INIT_BIN_ALIGN: usize = 16;
+
+/// Look up the init binary at the given path in the initrd.
+///
+/// Its content is copied into memory that is suitably aligned for parsing it as ELF file if it is not already aligned
+/// inside the initrd.
+fn find_init_in_initrd(
+    allocator: &'static KernelAlloc,
+    initrd: Option<&'static [u8]>,
+    path: &str,
+) -> &'static [u8] {
+    log::info!("loading init from {path} in the initrd");
+    let initrd = initrd.expect("An init path was given but there is no initrd");
+    let entry = initrd::Archive::new(initrd)
+        .expect("The initrd is not a valid cpio archive")
+        .find(path)
+        .filter(|entry| entry.is_file())
+        .unwrap_or_else(|| panic!("There is no file {path} in the initrd"));
+
+    if entry.data.as_ptr() as usize % INIT_BIN_ALIGN == 0 {
+        return entry.data;
+    }
+    let mut init_bin =
+        Box::<[u8]>::new_uninit_slice_with_alignment(entry.data.len(), INIT_BIN_ALIGN, allocator)
+            .expect("Could not allocate memory for aligning the init binary");
+    for (dst, src) in init_bin.iter_mut().zip(entry.data) {
+        dst.write(*src);
     }
+    unsafe { init_bin.assume_init() }.leak()
 }
 
 fn task_set_pc(task: &mut Capability, pc: usize) {
@@ -131,61 +289,194 @@ fn task_set_pc(task: &mut Capability, pc: usize) {
     tf.start_pc = pc;
 }
 
-fn kernel_loop(
-    derivation_tree: Box<DerivationTree<Capability>>,
-    mut init_caps: InitCaps,
-    ctx: &mut KernelContext,
-) {
-    use crate::init::{prepare_task, yield_to_task};
-    log::info!("🚀 launching init");
-    let mut active_cursor = derivation_tree.get_node(&mut *init_caps.init_task).unwrap();
+/// Execute tasks on the current hart until a [`Schedule::Stop`] is requested.
+///
+/// Only the hart given by `runs_init` executes init whenever it is idle while all other harts execute tasks from the
+/// [`RUN_QUEUE`].
+fn kernel_loop(runs_init: bool) {
+    use crate::init::run_task;
     let mut schedule = Schedule::RunInit;
+    let mut trap_info = None;
     loop {
-        match schedule {
-            Schedule::RunInit => {
-                active_cursor = derivation_tree.get_node(&mut *init_caps.init_task).unwrap();
-                maybe_task_wait(&active_cursor.get_shared().unwrap());
-                prepare_task(&mut active_cursor.get_exclusive().unwrap());
+        let next = {
+            let mut kernel = KERNEL.spin_lock();
+            let kernel = kernel.as_mut().unwrap();
+            // the active task is read from the hart local data because it is updated there when the task capability
+            // through which the task was scheduled is destroyed
+            if let Some(trap_info) = trap_info.take() {
+                schedule = handle_trap(kernel, hart::current().active_task().0, &trap_info);
             }
-            Schedule::Keep => {}
-            Schedule::RunTask(task_cap) => {
-                active_cursor = derivation_tree.get_node(task_cap).unwrap();
-                prepare_task(&mut active_cursor.get_exclusive().unwrap());
+            if schedule == Schedule::Stop {
+                break;
             }
-            Schedule::Stop => break,
+            let active_task = hart::current().active_task().0;
+            let next = select_task(kernel, schedule, active_task, runs_init);
+            // this is done while holding the kernel lock so that a task which becomes runnable afterwards kicks this hart
+            hart::current().set_idle(next.is_none());
+            if next.is_none() {
+                // the address space of the previous task may be freed while this hart is idle
+                unsafe { arch_specific::mmu::use_kernel_pagetable() };
+            }
+            let (task, frame) = next.unwrap_or((ptr::null_mut(), ptr::null_mut()));
+            hart::current().set_active_task(task, frame);
+            next
         };
 
-        let mut active_task = active_cursor.get_exclusive().unwrap();
-        unsafe { set_user_trap_handler() };
-        let trap_info = yield_to_task(&mut active_task);
-        unsafe { set_kernel_trap_handler() };
-
-        match trap_info.cause {
-            TrapEvent::Exception(Exception::EnvCallFromUMode) => {
-                schedule = syscalls::handle_syscall(active_task, &trap_info, ctx);
+        match next {
+            Some((_, frame)) => {
+                trap_info = Some(unsafe { run_task(frame) });
             }
-            TrapEvent::Interrupt(Interrupt::SupervisorTimerInterrupt) => {
-                log::trace!("⏰");
-                const MILLI: u64 = 10_000; // 10_000 * time_base (100 nanos) ;
-                set_timeout(100 * MILLI).expect("Could not set new timer interrupt");
-                task_set_pc(&mut active_task, trap_info.epc);
+            None => {
+                idle();
                 schedule = Schedule::RunInit;
             }
-            TrapEvent::Interrupt(Interrupt::SupervisorExternalInterrupt) => {
-                let claim = ctx.plic.claim_next(1).expect("no claim available");
-                let irq_ctrl = init_caps.irq_control.get_inner_irq_control().unwrap();
-                if let Some(notification) = irq_ctrl.get_notification(claim) {
-                    log::debug!("triggering notification for irq 0x{:x}", claim);
-                    NotificationIface.notify(&notification.borrow());
-                }
-                task_set_pc(&mut active_task, trap_info.epc);
-                schedule = Schedule::Keep;
+        }
+    }
+}
+
+/// Select the task that executes next on the current hart and prepare its execution.
+///
+/// Returns the task together with its trap frame or `None` if there is no task that could be executed.
+fn select_task(
+    kernel: &mut KernelState,
+    schedule: Schedule,
+    active_task: *mut Capability,
+    runs_init: bool,
+) -> Option<(*mut Capability, *mut TrapFrame)> {
+    use crate::init::{enter_task, prepare_task};
+    let task = match schedule {
+        Schedule::RunInit => {
+            let init_task = &*kernel.init_caps.init_task;
+            let init_state = init_task
+                .get_inner_task()
+                .unwrap()
+                .state
+                .borrow()
+                .execution_state;
+            if runs_init && init_state == TaskExecutionState::Idle {
+                &mut *kernel.init_caps.init_task as *mut Capability
+            } else {
+                unsafe { RUN_QUEUE.spin_lock().pop_runnable() }?
+            }
+        }
+        Schedule::Keep => active_task,
+        Schedule::RunTask(task) => task,
+        Schedule::Stop => unreachable!(),
+    };
+
+    let mut cursor = kernel.derivation_tree.get_node(task).unwrap();
+    let mut task_cap = cursor.get_exclusive().unwrap();
+    prepare_task(&mut task_cap);
+    Some((task, enter_task(&mut task_cap)))
+}
+
+/// Handle a trap of the given task and decide what should be executed next on the current hart
+fn handle_trap(kernel: &mut KernelState, task: *mut Capability, trap_info: &TrapInfo) -> Schedule {
+    use crate::init::leave_task;
+    if kernel.ctx.cmdline.debug.contains(DebugFlags::TRAPS) {
+        log::info!(
+            "trap {:?} at {:#x} on hart {}",
+            trap_info.cause,
+            trap_info.epc,
+            hart::current().hart_id
+        );
+    }
+    if let TrapEvent::Interrupt(interrupt) = &trap_info.cause {
+        handle_interrupt(kernel, interrupt);
+    }
+
+    // the task capability no longer exists if the task was destroyed while this hart executed it
+    if caps::task::finish_deferred_destroy(hart::current().hart_id) {
+        return Schedule::RunInit;
+    }
+
+    let is_init = task == &mut *kernel.init_caps.init_task as *mut Capability;
+    let mut cursor = kernel.derivation_tree.get_node(task).unwrap();
+    let mut task_cap = cursor.get_exclusive().unwrap();
+    if *task_cap.get_tag() != Tag::Task {
+        log::debug!("task was destroyed while it was running");
+        return Schedule::RunInit;
+    }
+    leave_task(&mut task_cap);
+
+    match trap_info.cause {
+        TrapEvent::Exception(Exception::EnvCallFromUMode) => syscalls::handle_syscall(
+            task_cap,
+            trap_info,
+            &kernel.derivation_tree,
+            &mut kernel.ctx,
+        ),
+        TrapEvent::Interrupt(Interrupt::SupervisorTimerInterrupt) => {
+            task_set_pc(&mut task_cap, trap_info.epc);
+            // preempted tasks continue on whichever hart is free first while init is only run by its own hart
+            if !is_init {
+                sched::enqueue(task);
             }
-            _ => {
-                println!("Interrupt!: Cause: {:#x?}", trap_info);
-                panic!("interrupt type is not handled yet");
+            Schedule::RunInit
+        }
+        TrapEvent::Interrupt(Interrupt::SupervisorExternalInterrupt)
+        | TrapEvent::Interrupt(Interrupt::SupervisorSoftwareInterrupt) => {
+            task_set_pc(&mut task_cap, trap_info.epc);
+            Schedule::Keep
+        }
+        _ => {
+            // record where the trap happened so that the registers which are printed when panicking are complete
+            task_set_pc(&mut task_cap, trap_info.epc);
+            panic!("trap is not handled yet: {:#x?}", trap_info);
+        }
+    }
+}
+
+/// Handle an interrupt that is pending on the current hart
+fn handle_interrupt(kernel: &mut KernelState, interrupt: &Interrupt) {
+    match interrupt {
+        Interrupt::SupervisorTimerInterrupt => {
+            log::trace!("⏰");
+            const MILLI: u64 = 10_000; // 10_000 * time_base (100 nanos) ;
+            set_timeout(kernel.ctx.cmdline.tick_ms * MILLI)
+                .expect("Could not set new timer interrupt");
+        }
+        Interrupt::SupervisorExternalInterrupt => {
+            // the interrupt is delivered to all harts so another one might have claimed it already
+            let Some(claim) = kernel.ctx.plic.claim_next(hart::current().plic_context) else {
+                return;
+            };
+            let irq_ctrl = kernel
+                .init_caps
+                .irq_control
+                .get_inner_irq_control()
+                .unwrap();
+            if let Some(notification) = irq_ctrl.get_notification(claim) {
+                log::debug!("triggering notification for irq 0x{:x}", claim);
+                NotificationIface.notify(&notification.borrow());
             }
         }
+        Interrupt::SupervisorSoftwareInterrupt => {
+            // another hart kicked this one, either to wake it up or as part of a TLB shootdown that was already
+            // handled by the SBI implementation so there is nothing left to do
+            unsafe { Sip::clear(InterruptBits::SupervisorSoftwareInterrupt) };
+        }
+        _ => panic!("interrupt {:?} is not handled yet", interrupt),
+    }
+}
+
+/// Put the current hart to sleep until an interrupt is pending and handle it.
+///
+/// The kernel itself runs with interrupts disabled so they do not trap and need to be handled explicitly.
+fn idle() {
+    riscv::utils::wait_for_interrupt();
+    let pending = Sip::read();
+    let mut kernel = KERNEL.spin_lock();
+    let kernel = kernel.as_mut().unwrap();
+    hart::current().set_idle(false);
+    if pending.contains(InterruptBits::SupervisorTimerInterrupt) {
+        handle_interrupt(kernel, &Interrupt::SupervisorTimerInterrupt);
+    }
+    if pending.contains(InterruptBits::SupervisorExternalInterrupt) {
+        handle_interrupt(kernel, &Interrupt::SupervisorExternalInterrupt);
+    }
+    if pending.contains(InterruptBits::SupervisorSoftwareInterrupt) {
+        handle_interrupt(kernel, &Interrupt::SupervisorSoftwareInterrupt);
     }
 }
 
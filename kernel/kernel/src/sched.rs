@@ -1,11 +1,102 @@
 //! Scheduling related functionality and data structures.
 
-use crate::caps::Capability;
+use crate::caps::task::TaskExecutionState;
+use crate::caps::{Capability, Tag};
+use crate::hart;
+use ksync::SpinLock;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Schedule {
+    /// Run init if the hart is the one which runs init and otherwise the next task from the [`RUN_QUEUE`]
     RunInit,
     Keep,
     RunTask(*mut Capability),
     Stop,
 }
+
+/// How many tasks the [`RUN_QUEUE`] can hold
+const RUN_QUEUE_SIZE: usize = 64;
+
+/// Tasks that were preempted and should continue running on whichever hart becomes free first.
+///
+/// Harts that do not run init take their tasks from this queue while init may still yield to any of the tasks in it.
+pub static RUN_QUEUE: SpinLock<RunQueue> = SpinLock::new(RunQueue::new());
+
+/// Queue a task that has become runnable and kick idle harts so that one of them executes it
+pub fn enqueue(task: *mut Capability) {
+    RUN_QUEUE.spin_lock().push(task);
+    hart::kick_idle_harts();
+}
+
+/// A FIFO queue of task capabilities
+pub struct RunQueue {
+    tasks: [*mut Capability; RUN_QUEUE_SIZE],
+    len: usize,
+}
+
+// Safety: the queue only holds pointers to capabilities which are dereferenced while holding the kernel lock
+unsafe impl Send for RunQueue {}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            tasks: [core::ptr::null_mut(); RUN_QUEUE_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Append a task to the end of the queue if it is not already queued.
+    ///
+    /// If the queue is full, the task is dropped which is fine because init can still yield to it.
+    pub fn push(&mut self, task: *mut Capability) {
+        if self.tasks[..self.len].contains(&task) {
+            return;
+        }
+        if self.len == RUN_QUEUE_SIZE {
+            log::debug!("run queue is full, not queueing preempted task");
+            return;
+        }
+        self.tasks[self.len] = task;
+        self.len += 1;
+    }
+
+    /// Remove the given task from the queue, e.g. because its capability is being destroyed
+    pub fn remove(&mut self, task: *mut Capability) {
+        if let Some(i) = self.tasks[..self.len].iter().position(|&t| t == task) {
+            self.tasks.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+        }
+    }
+
+    /// Take the first task that can currently be executed out of the queue.
+    ///
+    /// The queue is not updated when a queued task is scheduled in some other way so tasks that are not idle anymore
+    /// are silently removed.
+    ///
+    /// # Safety
+    /// The kernel lock must be held because the queued capabilities are inspected.
+    pub unsafe fn pop_runnable(&mut self) -> Option<*mut Capability> {
+        while self.len > 0 {
+            let task = self.tasks[0];
+            self.tasks.copy_within(1..self.len, 0);
+            self.len -= 1;
+            if is_runnable(unsafe { &*task }) {
+                return Some(task);
+            }
+        }
+        None
+    }
+}
+
+/// Whether the given capability is a task that can be executed right now
+fn is_runnable(task: &Capability) -> bool {
+    let Some(task) = task.get_inner_task().ok() else {
+        return false;
+    };
+    let Ok(state) = task.state.try_borrow() else {
+        return false;
+    };
+    state.execution_state == TaskExecutionState::Idle
+        && *state.vspace.get_tag() == Tag::VSpace
+        && *state.cspace.get_tag() == Tag::CSpace
+}
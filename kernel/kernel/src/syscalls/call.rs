@@ -1,11 +1,14 @@
 use crate::caps::Tag;
 use crate::sched::Schedule;
 use crate::syscalls::handler_trait::SyscallHandler;
+use crate::syscalls::ipc::cspace::cspace_call;
+use crate::syscalls::ipc::endpoint::endpoint_call;
+use crate::syscalls::ipc::notification::notification_call;
 use crate::syscalls::ipc::page::page_call;
-use crate::syscalls::SyscallContext;
+use crate::syscalls::{utils, SyscallContext};
 use crate::KernelContext;
 use syscall_abi::call::Call;
-use syscall_abi::SyscallBinding;
+use syscall_abi::{SyscallBinding, SyscallError};
 
 pub(super) struct CallHandler;
 
@@ -26,27 +29,24 @@ impl SyscallHandler for CallHandler {
         let cspace = cspace.get_shared().unwrap();
         let cspace = cspace.get_inner_cspace().unwrap();
 
-        let cap = unsafe {
-            cspace
-                .resolve_caddr(args.target)
-                .unwrap() // TODO Handle error by returning InvalidCaddr
-                .as_mut()
-                .unwrap()
+        let cap = match unsafe { utils::resolve_cap_mut(cspace, args.target) } {
+            Ok(cap) => cap,
+            Err(e) => return (Schedule::Keep, Err(e)),
         };
         log::debug!("dispatching call to {:?} capability", cap.get_tag());
         let result = match cap.get_tag() {
-            Tag::Uninit => todo!("call for uninit unimplemented"),
-            Tag::Memory => todo!("call for memory unimplemented"),
-            Tag::CSpace => todo!("call for cspace unimplemented"),
-            Tag::VSpace => todo!("call for vspace unimplemented"),
-            Tag::Task => todo!("call to task unimplemented"),
             Tag::Page => page_call(cspace, cap.get_inner_page_mut().unwrap(), args),
-            Tag::IrqControl => todo!("call for irq-control unimplemented"),
-            Tag::Irq => todo!("call for irq unimplemented"),
-            Tag::Notification => todo!("call for notification unimplemented"),
-            Tag::Devmem => todo!("call for devmem unimplemented"),
-            Tag::AsidControl => todo!("call for asid-control unimplemented"),
-            Tag::Endpoint => todo!("call for endpoint unimplemented"),
+            Tag::CSpace => cspace_call(cspace, cap.get_inner_cspace().unwrap(), args),
+            Tag::Notification => notification_call(cspace, cap, args),
+            Tag::Endpoint => endpoint_call(cspace, cap.get_inner_endpoint().unwrap(), args),
+            Tag::Uninit => Err(SyscallError::InvalidCap),
+            Tag::Memory
+            | Tag::VSpace
+            | Tag::Task
+            | Tag::IrqControl
+            | Tag::Irq
+            | Tag::Devmem
+            | Tag::AsidControl => Err(SyscallError::Unsupported),
         };
         (Schedule::Keep, result)
     }
@@ -1,9 +1,10 @@
 use syscall_abi::copy::Copy;
-use syscall_abi::{NoValue, SyscallBinding};
+use syscall_abi::{NoValue, SyscallBinding, SyscallError};
 
+use crate::caps::Tag;
 use crate::sched::Schedule;
 use crate::syscalls::handler_trait::SyscallHandler;
-use crate::syscalls::SyscallContext;
+use crate::syscalls::{utils, SyscallContext};
 use crate::{caps, KernelContext};
 
 pub(super) struct CopyHandler;
@@ -25,20 +26,23 @@ impl SyscallHandler for CopyHandler {
         let cspace = cspace.get_shared().unwrap();
         let cspace = cspace.get_inner_cspace().unwrap();
 
-        let src = unsafe {
-            cspace
-                .resolve_caddr(args.src)
-                .unwrap() // TODO handle error by returning InvalidCAddr
-                .as_ref()
-                .unwrap()
+        let src = match unsafe { utils::resolve_cap_mut(cspace, args.src) } {
+            Ok(cap) => cap,
+            Err(e) => return (Schedule::Keep, Err(e)),
         };
-        let target = unsafe {
-            cspace
-                .resolve_caddr(args.dst)
-                .unwrap() // TODO handle error by returning InvalidCAddr
-                .as_mut()
-                .unwrap()
+        let target = match unsafe { utils::resolve_cap_mut(cspace, args.dst) } {
+            Ok(cap) => cap,
+            Err(e) => return (Schedule::Keep, Err(e)),
         };
+        if core::ptr::eq(src, target) {
+            return (Schedule::Keep, Err(SyscallError::AliasingCSlot));
+        }
+        if *src.get_tag() == Tag::Uninit {
+            return (Schedule::Keep, Err(SyscallError::InvalidCap));
+        }
+        if *target.get_tag() != Tag::Uninit {
+            return (Schedule::Keep, Err(SyscallError::OccupiedSlot));
+        }
 
         unsafe { caps::copy(src, target) };
 
@@ -1,11 +1,19 @@
+use crate::caps::{Capability, Tag};
 use crate::sched::Schedule;
 use crate::syscalls::handler_trait::SyscallHandler;
+use crate::syscalls::utils;
 use crate::syscalls::SyscallContext;
 use crate::KernelContext;
-use core::str;
+use allocators::bump_allocator::BumpAllocator;
+use core::{mem, slice, str};
+use derivation_tree::tree::TreeNodeOps;
 use klog::print;
-use syscall_abi::debug::{DebugLog, DebugPutc};
-use syscall_abi::{NoValue, SyscallBinding};
+use syscall_abi::debug::{
+    CapTreeEntry, DebugCapTree, DebugCapTreeReturn, DebugKstat, DebugLog, DebugPutc, DebugReadLog,
+    DebugReadLogReturn, KernelStats, CAP_TREE_ENTRIES_PER_PAGE, READ_LOG_CHUNK_SIZE,
+};
+use syscall_abi::identify::CapabilityVariant;
+use syscall_abi::{NoValue, SyscallBinding, SyscallError};
 
 pub(super) struct DebugPutcHandler;
 
@@ -40,8 +48,193 @@ impl SyscallHandler for DebugLogHandler {
         Schedule,
         <<Self as SyscallHandler>::Syscall as SyscallBinding>::Return,
     ) {
-        let str = str::from_utf8(&args.byte_slice[..args.len]).unwrap();
+        let Some(bytes) = args.byte_slice.get(..args.len) else {
+            return (Schedule::Keep, Err(SyscallError::InvalidArg));
+        };
+        let Ok(str) = str::from_utf8(bytes) else {
+            return (Schedule::Keep, Err(SyscallError::InvalidArg));
+        };
         print!("{}", str);
         (Schedule::Keep, Ok(NoValue))
     }
 }
+
+pub(super) struct DebugReadLogHandler;
+
+impl SyscallHandler for DebugReadLogHandler {
+    type Syscall = DebugReadLog;
+
+    fn handle(
+        &mut self,
+        _kernel_ctx: &mut KernelContext,
+        _syscall_ctx: &mut SyscallContext<'_, '_>,
+        args: <<Self as SyscallHandler>::Syscall as SyscallBinding>::CallArgs,
+    ) -> (
+        Schedule,
+        <<Self as SyscallHandler>::Syscall as SyscallBinding>::Return,
+    ) {
+        let mut bytes = [0; READ_LOG_CHUNK_SIZE];
+        let (pos, len, end) = crate::LOGGER.read_buffer(args.pos as u64, &mut bytes);
+        (
+            Schedule::Keep,
+            Ok(DebugReadLogReturn {
+                pos: pos as usize,
+                len,
+                end: end as usize,
+                bytes,
+            }),
+        )
+    }
+}
+
+pub(super) struct DebugKstatHandler;
+
+impl SyscallHandler for DebugKstatHandler {
+    type Syscall = DebugKstat;
+
+    fn handle(
+        &mut self,
+        _kernel_ctx: &mut KernelContext,
+        syscall_ctx: &mut SyscallContext<'_, '_>,
+        _args: <<Self as SyscallHandler>::Syscall as SyscallBinding>::CallArgs,
+    ) -> (
+        Schedule,
+        <<Self as SyscallHandler>::Syscall as SyscallBinding>::Return,
+    ) {
+        let derivation_tree = syscall_ctx.derivation_tree;
+        let kernel_alloc = unsafe { crate::KERNEL_ALLOCATOR.as_ref() };
+        let mut stats = KernelStats {
+            free_bytes: kernel_alloc.map_or(0, |alloc| alloc.get_free_bytes()),
+            cursors_used: derivation_tree.used_cursors().count(),
+            cursors_total: derivation_tree.cursor_capacity(),
+            ..KernelStats::default()
+        };
+
+        let mut nodes = 0;
+        for node in derivation_tree.iter() {
+            nodes += 1;
+            let Ok(mut cursor) = derivation_tree.get_node(node) else {
+                return (Schedule::Keep, Err(SyscallError::Busy));
+            };
+            let tag = match cursor.get_shared() {
+                Ok(cap) => {
+                    // copies of a memory capability share their allocator so it is only counted once
+                    if let Ok(memory) = cap.get_inner_memory() {
+                        if cap.is_first_copy() {
+                            stats.free_bytes += memory.allocator.get_free_bytes();
+                        }
+                    }
+                    *cap.get_tag()
+                }
+                // the only node that is already accessed exclusively is the calling task
+                Err(_) => *syscall_ctx.task.get_tag(),
+            };
+            let count = &mut stats.caps[CapabilityVariant::from(tag) as usize];
+            *count = count.saturating_add(1);
+        }
+        stats.tree_bytes = nodes * mem::size_of::<Capability>();
+
+        (Schedule::Keep, Ok(stats))
+    }
+}
+
+pub(super) struct DebugCapTreeHandler;
+
+impl SyscallHandler for DebugCapTreeHandler {
+    type Syscall = DebugCapTree;
+
+    fn handle(
+        &mut self,
+        _kernel_ctx: &mut KernelContext,
+        syscall_ctx: &mut SyscallContext<'_, '_>,
+        args: <<Self as SyscallHandler>::Syscall as SyscallBinding>::CallArgs,
+    ) -> (
+        Schedule,
+        <<Self as SyscallHandler>::Syscall as SyscallBinding>::Return,
+    ) {
+        let derivation_tree = syscall_ctx.derivation_tree;
+
+        // get the page into which the entries are written
+        let entries = {
+            let task = syscall_ctx.task.get_inner_task().unwrap();
+            let mut cspace = task.get_cspace();
+            let cspace = cspace.get_shared().unwrap();
+            let cspace = cspace.get_inner_cspace().unwrap();
+            let page = match unsafe { utils::lookup_cap(cspace, args.page, Tag::Page) } {
+                Ok(page) => page.get_inner_page().unwrap(),
+                Err(e) => return (Schedule::Keep, Err(e)),
+            };
+            unsafe {
+                slice::from_raw_parts_mut(
+                    page.kernel_addr.cast::<CapTreeEntry>(),
+                    CAP_TREE_ENTRIES_PER_PAGE,
+                )
+            }
+        };
+
+        // describe as many nodes as fit into the page but count all of them
+        let mut total = 0;
+        let mut written = 0;
+        for (i, node) in derivation_tree.iter().enumerate() {
+            total = i + 1;
+            if i < args.start || written == entries.len() {
+                continue;
+            }
+            let Ok(mut cursor) = derivation_tree.get_node(node) else {
+                return (Schedule::Keep, Err(SyscallError::Busy));
+            };
+            entries[written] = match cursor.get_shared() {
+                Ok(cap) => describe_node(node, &cap),
+                // the only node that is already accessed exclusively is the calling task
+                Err(_) => describe_node(node, &syscall_ctx.task),
+            };
+            written += 1;
+        }
+
+        // find the CSpace slots in which the described nodes are stored
+        let entries = &mut entries[..written];
+        for node in derivation_tree.iter() {
+            let Ok(mut cursor) = derivation_tree.get_node(node) else {
+                return (Schedule::Keep, Err(SyscallError::Busy));
+            };
+            let Ok(cap) = cursor.get_shared() else {
+                continue;
+            };
+            // copies of a CSpace share their slots so only the first one needs to be searched
+            let Ok(cspace) = cap.get_inner_cspace() else {
+                continue;
+            };
+            if !cap.is_first_copy() {
+                continue;
+            }
+            for (i, slot) in cspace.slots.iter().enumerate() {
+                let slot_ptr = slot.as_ptr() as usize;
+                if let Some(entry) = entries.iter_mut().find(|entry| entry.id == slot_ptr) {
+                    entry.cspace = node as usize;
+                    entry.slot = i;
+                }
+            }
+        }
+
+        (Schedule::Keep, Ok(DebugCapTreeReturn { written, total }))
+    }
+}
+
+/// Describe the position of a node in the derivation tree.
+///
+/// The CSpace slot in which the node is stored is left empty because it cannot be determined from the node itself.
+fn describe_node(node: *mut Capability, cap: &Capability) -> CapTreeEntry {
+    let copy_of = if cap.is_final_copy() {
+        0
+    } else {
+        unsafe { cap.get_first_copy() as usize }
+    };
+    CapTreeEntry {
+        id: node as usize,
+        parent: cap.get_parent() as usize,
+        depth: cap.get_tree_data().depth(),
+        variant: CapabilityVariant::from(*cap.get_tag()) as usize,
+        copy_of,
+        ..CapTreeEntry::default()
+    }
+}
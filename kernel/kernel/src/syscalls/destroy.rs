@@ -1,9 +1,11 @@
-use syscall_abi::destroy::Destroy;
-use syscall_abi::{NoValue, SyscallBinding};
+use derivation_tree::AsStaticMut;
+use syscall_abi::destroy::{Destroy, DestroyArgs};
+use syscall_abi::{IntoRawSysRepsonse, NoValue, SyscallError};
 
+use crate::caps::Capability;
 use crate::sched::Schedule;
-use crate::syscalls::handler_trait::SyscallHandler;
-use crate::syscalls::SyscallContext;
+use crate::syscalls::handler_trait::RawSyscallHandler;
+use crate::syscalls::{utils, SyscallContext};
 use crate::{
     caps::{self},
     KernelContext,
@@ -11,33 +13,50 @@ use crate::{
 
 pub(super) struct DestroyHandler;
 
-impl SyscallHandler for DestroyHandler {
+impl RawSyscallHandler for DestroyHandler {
     type Syscall = Destroy;
 
-    fn handle(
+    fn handle_raw(
         &mut self,
         _kernel_ctx: &mut KernelContext,
         syscall_ctx: &mut SyscallContext<'_, '_>,
-        args: <<Self as SyscallHandler>::Syscall as SyscallBinding>::CallArgs,
-    ) -> (
-        Schedule,
-        <<Self as SyscallHandler>::Syscall as SyscallBinding>::Return,
-    ) {
+    ) -> Schedule {
+        let raw_args = syscall_ctx.get_raw_args();
+        let args = DestroyArgs::from(raw_args);
+        let task_ptr = syscall_ctx.task.as_static_mut() as *mut Capability;
         let task = syscall_ctx.task.get_inner_task().unwrap();
-        let mut cspace = task.get_cspace();
-        let cspace = cspace.get_shared().unwrap();
-        let cspace = cspace.get_inner_cspace().unwrap();
-
-        let target = unsafe {
-            cspace
-                .resolve_caddr(args.caddr)
-                .unwrap() // TODO Handle error by returning InvalidCAddr
-                .as_mut()
-                .unwrap()
+
+        let target = {
+            let mut cspace = task.get_cspace();
+            let cspace = cspace.get_shared().unwrap();
+            let cspace = cspace.get_inner_cspace().unwrap();
+            unsafe { utils::resolve_cap_mut(cspace, args.caddr) }
+        };
+        let target = match target {
+            Ok(target) if caps::is_destroy_supported(target) => target,
+            Ok(_) => return syscall_ctx.return_error(SyscallError::Unsupported),
+            Err(e) => return syscall_ctx.return_error(e),
         };
 
+        // The result is written before the destruction because the target might be the calling task itself in
+        // which case its state is not accessible afterwards.
+        {
+            let mut task_state = task.state.borrow_mut();
+            task_state.frame.start_pc = syscall_ctx.trap_info.epc + 4;
+            task_state
+                .frame
+                .write_syscall_return(Ok(NoValue).into_response());
+        }
+
+        // the current task is also destroyed if it is stored in a cspace whose last copy is destroyed
+        let destroys_current_task = caps::destroys(target, task_ptr);
         unsafe { caps::destroy(target) };
 
-        (Schedule::Keep, Ok(NoValue))
+        if destroys_current_task {
+            log::debug!("current task was destroyed, switching to init");
+            Schedule::RunInit
+        } else {
+            Schedule::Keep
+        }
     }
 }
@@ -1,7 +1,7 @@
 use crate::sched::Schedule;
 use crate::syscalls::SyscallContext;
 use crate::KernelContext;
-use syscall_abi::{IntoRawSysRepsonse, SyscallBinding};
+use syscall_abi::{IntoRawSysRepsonse, NoValue, SyscallBinding, SyscallError};
 
 /// A trait for handling a specific syscall in the most bare-bones way possible.
 ///
@@ -19,7 +19,7 @@ pub(super) trait RawSyscallHandler {
 /// A trait for handling most syscalls.
 ///
 /// The `RawSyscallHandler` auto-implementation on top of this guarantees the following:
-/// 1. Decode syscall specific arguments from `RawSyscallArgs` and log them
+/// 1. Decode syscall specific arguments from `RawSyscallArgs` and log them or return `InvalidArg` if that fails
 /// 2. *Execute this handler*
 /// 3. Log the result and transform it into `RawSyscallReturn`
 /// 4. Write the result into the calling tasks registers
@@ -48,22 +48,36 @@ impl<Handler: SyscallHandler> RawSyscallHandler for Handler {
     ) -> Schedule {
         // parse syscall arguments
         let raw_args = syscall_ctx.get_raw_args();
-        let args = <Handler::Syscall as SyscallBinding>::CallArgs::try_from(raw_args)
-            .unwrap_or_else(|_| panic!("could not decode syscall args"));
-
-        // execute the handler
-        log::trace!(
-            "handling {} syscall with args {:x?}",
-            core::any::type_name::<Handler::Syscall>(),
-            args
-        );
-        let (schedule, result) = self.handle(kernel_ctx, syscall_ctx, args);
-        log::trace!(
-            "{} syscall result is {:x?} with new schedule {:?}",
-            core::any::type_name::<Handler::Syscall>(),
-            result,
-            schedule
-        );
+        let (schedule, response) =
+            match <Handler::Syscall as SyscallBinding>::CallArgs::try_from(raw_args) {
+                Err(_) => {
+                    log::debug!(
+                        "could not decode {} syscall args {:x?}",
+                        core::any::type_name::<Handler::Syscall>(),
+                        raw_args
+                    );
+                    (
+                        Schedule::Keep,
+                        Err::<NoValue, SyscallError>(SyscallError::InvalidArg).into_response(),
+                    )
+                }
+                Ok(args) => {
+                    // execute the handler
+                    log::trace!(
+                        "handling {} syscall with args {:x?}",
+                        core::any::type_name::<Handler::Syscall>(),
+                        args
+                    );
+                    let (schedule, result) = self.handle(kernel_ctx, syscall_ctx, args);
+                    log::trace!(
+                        "{} syscall result is {:x?} with new schedule {:?}",
+                        core::any::type_name::<Handler::Syscall>(),
+                        result,
+                        schedule
+                    );
+                    (schedule, result.into_response())
+                }
+            };
 
         // write the result back to userspace
         let mut task_state = syscall_ctx
@@ -72,9 +86,7 @@ impl<Handler: SyscallHandler> RawSyscallHandler for Handler {
             .unwrap()
             .state
             .borrow_mut();
-        task_state
-            .frame
-            .write_syscall_return(result.into_response());
+        task_state.frame.write_syscall_return(response);
 
         // increase the tasks program counter
         task_state.frame.start_pc = syscall_ctx.trap_info.epc + 4;
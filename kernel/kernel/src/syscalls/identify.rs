@@ -1,10 +1,9 @@
-use crate::caps::Tag;
 use crate::sched::Schedule;
 use crate::syscalls::handler_trait::SyscallHandler;
 use crate::syscalls::SyscallContext;
 use crate::KernelContext;
 use syscall_abi::identify::Identify;
-use syscall_abi::{identify::CapabilityVariant, SyscallBinding, SyscallError};
+use syscall_abi::{identify::CapabilityVariant, LookupError, SyscallBinding, SyscallError};
 
 pub(super) struct IdentifyHandler;
 
@@ -25,28 +24,21 @@ impl SyscallHandler for IdentifyHandler {
         let cspace = cspace.get_shared().unwrap();
         let cspace = cspace.get_inner_cspace().unwrap();
 
-        let cap_ptr = match unsafe { cspace.resolve_caddr(args.caddr) } {
-            Some(ptr) => ptr,
-            None => return (Schedule::Keep, Err(SyscallError::InvalidCAddr)),
+        let cap_ptr = match unsafe { cspace.resolve_caddr_depth(args.caddr, args.depth) } {
+            Ok(ptr) => ptr,
+            Err(e) => {
+                log::debug!("could not resolve caddr {:#x}: {:?}", args.caddr, e);
+                let error = LookupError {
+                    error: SyscallError::InvalidCAddr,
+                    depth: e.depth,
+                };
+                return (Schedule::Keep, Err(error));
+            }
         };
 
         // TODO Use a cursor to safely access the capability
         let cap = unsafe { &*cap_ptr };
-        let tag = cap.get_tag();
-        let variant = match tag {
-            Tag::Uninit => CapabilityVariant::Uninit,
-            Tag::Memory => CapabilityVariant::Memory,
-            Tag::CSpace => CapabilityVariant::CSpace,
-            Tag::VSpace => CapabilityVariant::VSpace,
-            Tag::Task => CapabilityVariant::Task,
-            Tag::Page => CapabilityVariant::Page,
-            Tag::IrqControl => CapabilityVariant::IrqControl,
-            Tag::Irq => CapabilityVariant::Irq,
-            Tag::Notification => CapabilityVariant::Notification,
-            Tag::Devmem => CapabilityVariant::Devmem,
-            Tag::AsidControl => CapabilityVariant::AsidControl,
-            Tag::Endpoint => CapabilityVariant::Endpoint,
-        };
+        let variant = CapabilityVariant::from(*cap.get_tag());
 
         (Schedule::Keep, Ok(variant))
     }
@@ -1,4 +1,5 @@
-use crate::caps::{asid::asid_control_assign, AsidControl, CSpace, SyscallError};
+use crate::caps::{asid::asid_control_assign, AsidControl, CSpace, SyscallError, Tag};
+use crate::syscalls::utils;
 use syscall_abi::send::SendArgs;
 
 pub fn asid_control_send(
@@ -8,15 +9,13 @@ pub fn asid_control_send(
 ) -> Result<(), SyscallError> {
     const ASSIGN: usize = 1234;
     match args.label() {
-        ASSIGN => asid_control_assign(asid_control, unsafe {
-            cspace
-                .resolve_caddr(args.cap_args()[0])
-                .ok_or(SyscallError::InvalidCAddr)?
-                .as_mut()
-                .unwrap()
-                .get_inner_vspace_mut()
-                .unwrap()
-        }),
+        ASSIGN => {
+            let [vspace] = args.cap_args() else {
+                return Err(SyscallError::InvalidArg);
+            };
+            let vspace = unsafe { utils::lookup_cap_mut(cspace, *vspace, Tag::VSpace) }?;
+            asid_control_assign(asid_control, vspace.get_inner_vspace_mut().unwrap())
+        }
         _ => Err(SyscallError::Unsupported),
     }
 }
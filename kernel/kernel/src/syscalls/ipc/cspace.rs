@@ -0,0 +1,65 @@
+use syscall_abi::call::CallArgs;
+use syscall_abi::send::SendArgs;
+use syscall_abi::{SyscallResult, SyscallReturnData};
+
+use derivation_tree::Correspondence;
+
+use crate::caps::{CSpace, Capability, SyscallError, Tag};
+use crate::syscalls::utils;
+
+pub fn cspace_send(
+    task: *const Capability,
+    cspace: &CSpace,
+    target_cspace: &mut CSpace,
+    args: &SendArgs,
+) -> Result<(), SyscallError> {
+    const SET_GUARD: usize = 0;
+    const COPY_SLOTS: usize = 1;
+    match args.label() {
+        SET_GUARD => {
+            let ([], [guard, guard_bits]) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            target_cspace.set_guard(*guard, *guard_bits)
+        }
+        COPY_SLOTS => {
+            let ([dst], []) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            let dst = unsafe { utils::lookup_cap(cspace, *dst, Tag::CSpace) }?;
+            let dst = dst.get_inner_cspace().unwrap();
+            // the calling task and its root CSpace are selected by cursors for the duration of the syscall
+            if target_cspace.corresponds_to(cspace)
+                || dst.corresponds_to(cspace)
+                || target_cspace.contains(task)
+            {
+                return Err(SyscallError::Busy);
+            }
+            unsafe { target_cspace.copy_slots_into(dst) }
+        }
+        _ => Err(SyscallError::Unsupported),
+    }
+}
+
+pub fn cspace_call(
+    _cspace: &CSpace,
+    target_cspace: &CSpace,
+    args: CallArgs,
+) -> SyscallResult<SyscallReturnData> {
+    const GET_INFO: usize = 0;
+    match args.label() {
+        GET_INFO => {
+            let (occupied, free_from) = target_cspace.occupancy();
+            Ok([
+                target_cspace.addr_bits(),
+                target_cspace.slots.len(),
+                occupied,
+                free_from,
+                target_cspace.guard,
+                target_cspace.guard_bits,
+                0,
+            ])
+        }
+        _ => Err(SyscallError::Unsupported),
+    }
+}
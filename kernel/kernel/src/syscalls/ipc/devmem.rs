@@ -1,24 +1,22 @@
-use core::arch::asm;
 use riscv::pt::{EntryFlags, PAGESIZE};
 use syscall_abi::send::SendArgs;
 use syscall_abi::CAddr;
 
 use crate::{
     caps::{CSpace, Devmem, SyscallError, Tag},
+    hart,
     syscalls::utils,
 };
 
 pub fn devmem_send(cspace: &CSpace, devmem: &Devmem, args: &SendArgs) -> Result<(), SyscallError> {
     const MAP: usize = 1;
     match args.label() {
-        MAP => devmem_map(
-            cspace,
-            devmem,
-            args.cap_args()[0],
-            args.cap_args()[1],
-            args.data_args()[0],
-            args.data_args()[1],
-        ),
+        MAP => {
+            let ([mem, vspace], [base, len]) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            devmem_map(cspace, devmem, *mem, *vspace, *base, *len)
+        }
         _ => Err(SyscallError::Unsupported),
     }
 }
@@ -46,17 +44,13 @@ fn devmem_map(
     log::info!("mapping devmem entry: {:x?} {:x?}", &entry.base, &entry.len);
 
     for offset in (0..len).step_by(PAGESIZE) {
-        vspace
-            .map_address(
-                mem.get_inner_memory().unwrap(),
-                entry.base + offset,
-                entry.base + offset,
-                EntryFlags::Read | EntryFlags::Write | EntryFlags::UserReadable,
-            )
-            .unwrap();
-    }
-    unsafe {
-        asm!("sfence.vma");
+        vspace.map_address(
+            mem.get_inner_memory().unwrap(),
+            entry.base + offset,
+            entry.base + offset,
+            EntryFlags::Read | EntryFlags::Write | EntryFlags::UserReadable,
+        )?;
     }
+    hart::flush_tlb(vspace.asid, entry.base, len);
     Ok(())
 }
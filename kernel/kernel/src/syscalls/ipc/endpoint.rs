@@ -1,22 +1,30 @@
 use crate::caps::endpoint::{Endpoint, EndpointIface};
-use crate::caps::task::TaskExecutionState;
-use crate::caps::{Capability, Task};
-use crate::sched::Schedule;
+use crate::caps::task::{TaskExecutionState, WaitingOn};
+use crate::caps::{CSpace, Capability, NotificationIface, Tag, Task, TaskIface};
+use crate::sched::{self, Schedule};
+use syscall_abi::call::CallArgs;
 use syscall_abi::receive::{Receive, ReceiveReturn};
 use syscall_abi::send::SendArgs;
-use syscall_abi::{IntoRawSysRepsonse, NoValue, RawSyscallArgs, SyscallBinding, SyscallResult};
+use syscall_abi::{
+    IntoRawSysRepsonse, NoValue, RawSyscallArgs, SyscallBinding, SyscallError, SyscallResult,
+    SyscallReturnData,
+};
 
 fn ipc_recieve_from(src_task: &Task) -> <Receive as SyscallBinding>::Return {
     let src_state = src_task.state.borrow();
     let args: &RawSyscallArgs = src_state.frame.get_syscall_args().try_into().unwrap();
     let SendArgs { tag, raw_args, .. } = SendArgs::from(*args);
-    Ok(ReceiveReturn { tag, raw_args })
+    Ok(ReceiveReturn {
+        tag,
+        raw_args,
+        notification: 0,
+    })
 }
 
 fn wake_endpoint_sender(sender: &Task, result: SyscallResult<NoValue>) {
     log::trace!("waking sender: {:?}", &result);
     let mut state = sender.state.borrow_mut();
-    assert!(state.waiting_on.take().is_some());
+    assert!(!state.waiting_on.take().is_nothing());
     state.execution_state = TaskExecutionState::Idle;
     state.frame.write_syscall_return(result.into_response());
 }
@@ -24,7 +32,7 @@ fn wake_endpoint_sender(sender: &Task, result: SyscallResult<NoValue>) {
 fn wake_endpoint_receiver(receiver: &Task, result: SyscallResult<ReceiveReturn>) {
     log::trace!("waking receiver: {:?}", &result);
     let mut state = receiver.state.borrow_mut();
-    assert!(state.waiting_on.take().is_some());
+    assert!(!state.waiting_on.take().is_nothing());
     state.execution_state = TaskExecutionState::Idle;
     state.frame.write_syscall_return(result.into_response());
 }
@@ -34,13 +42,14 @@ fn block_endpoint_sender(
     sender_ptr: *mut Capability,
     ep: &Endpoint,
     ep_ptr: *mut Capability,
-) {
+) -> Result<(), SyscallError> {
     log::trace!("blocking endpoint sender");
-    unsafe { EndpointIface.add_sender(ep, sender_ptr) };
+    unsafe { EndpointIface.add_sender(ep, sender_ptr) }?;
     let mut task_state = sender.state.borrow_mut();
-    assert!(task_state.waiting_on.is_none());
-    task_state.waiting_on = Some(ep_ptr);
+    assert!(task_state.waiting_on.is_nothing());
+    task_state.waiting_on = WaitingOn::One(ep_ptr);
     task_state.execution_state = TaskExecutionState::Waiting;
+    Ok(())
 }
 
 fn block_endpoint_receiver(
@@ -48,13 +57,14 @@ fn block_endpoint_receiver(
     receiver_ptr: *mut Capability,
     ep: &Endpoint,
     ep_ptr: *mut Capability,
-) {
+) -> Result<(), SyscallError> {
     log::trace!("blocking endpoint receiver");
-    unsafe { EndpointIface.add_receiver(ep, receiver_ptr) };
+    unsafe { EndpointIface.add_receiver(ep, receiver_ptr) }?;
     let mut task_state = receiver.state.borrow_mut();
-    assert!(task_state.waiting_on.is_none());
-    task_state.waiting_on = Some(ep_ptr);
+    assert!(task_state.waiting_on.is_nothing());
+    task_state.waiting_on = WaitingOn::One(ep_ptr);
     task_state.execution_state = TaskExecutionState::Waiting;
+    Ok(())
 }
 
 pub fn endpoint_send(
@@ -68,28 +78,86 @@ pub fn endpoint_send(
         let receiver = unsafe { x.as_mut().unwrap() }.get_inner_task().unwrap();
         let result = ipc_recieve_from(sender);
         wake_endpoint_receiver(receiver, result);
+        sched::enqueue(x);
         // TODO: return runTask::destination task
         return (Some(Ok(NoValue)), Schedule::Keep);
     }
 
-    block_endpoint_sender(sender, sender_ptr, ep, ep_ptr);
+    if let Err(e) = block_endpoint_sender(sender, sender_ptr, ep, ep_ptr) {
+        return (Some(Err(e)), Schedule::Keep);
+    }
+
+    // a task that waits for a sender in wait_any can now receive without blocking
+    let watcher = ep.state.borrow_mut().watch_set.take();
+    if let Some(watcher) = watcher {
+        log::trace!("waking task that watches the endpoint");
+        TaskIface.wake(unsafe { &*watcher });
+        sched::enqueue(watcher);
+    }
     (None, Schedule::RunInit)
 }
 
+/// Take the value of the notification that is bound to the receiver (if any).
+///
+/// Returns `None` if no notification is bound or if the bound notification is currently unset.
+fn take_bound_notification(receiver: &Task) -> Option<usize> {
+    let state = receiver.state.borrow();
+    if *state.bound_notification.get_tag() != Tag::Notification {
+        return None;
+    }
+    match NotificationIface.take_value(&state.bound_notification) {
+        0 => None,
+        value => Some(value),
+    }
+}
+
 pub fn endpoint_recv(
     receiver_ptr: *mut Capability,
     reciever: &Task,
     ep_ptr: *mut Capability,
     ep: &Endpoint,
 ) -> (Option<SyscallResult<ReceiveReturn>>, Schedule) {
+    if let Some(value) = take_bound_notification(reciever) {
+        log::trace!("bound notification is set, handling it instead of receiving");
+        return (
+            Some(Ok(ReceiveReturn::from_notification(value))),
+            Schedule::Keep,
+        );
+    }
+
     if let Some(x) = ep.state.borrow_mut().send_set.take() {
         log::trace!("endpoint syncronized, handling recev");
         let sender = unsafe { x.as_ref().unwrap() }.get_inner_task().unwrap();
         let result = ipc_recieve_from(sender);
         wake_endpoint_sender(sender, Ok(NoValue));
+        sched::enqueue(x);
         return (Some(result), Schedule::Keep);
     }
 
-    block_endpoint_receiver(reciever, receiver_ptr, ep, ep_ptr);
-    (None, Schedule::RunInit)
+    match block_endpoint_receiver(reciever, receiver_ptr, ep, ep_ptr) {
+        Ok(()) => (None, Schedule::RunInit),
+        Err(e) => (Some(Err(e)), Schedule::Keep),
+    }
+}
+
+/// Non-blocking operations on an endpoint.
+///
+/// `POLL` reports whether a sender is currently blocked on the endpoint so that a `receive` completes without
+/// blocking.
+pub fn endpoint_call(
+    _cspace: &CSpace,
+    ep: &Endpoint,
+    args: CallArgs,
+) -> SyscallResult<SyscallReturnData> {
+    const POLL: usize = 0;
+    match args.label() {
+        POLL => {
+            let ([], []) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            let has_sender = ep.state.borrow().send_set.is_some();
+            Ok([has_sender as usize, 0, 0, 0, 0, 0, 0])
+        }
+        _ => Err(SyscallError::Unsupported),
+    }
 }
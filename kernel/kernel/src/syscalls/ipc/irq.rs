@@ -4,7 +4,7 @@ use syscall_abi::send::SendArgs;
 use crate::{
     arch_specific::plic::PLIC,
     caps::{self, CSpace, Capability, Irq, IrqControlIface, IrqIface, NotificationIface, Tag},
-    KernelContext,
+    hart, KernelContext,
 };
 
 use super::super::utils;
@@ -32,8 +32,8 @@ pub(in crate::syscalls) fn sys_irq_complete(
     // mark the interrupt as completed
     log::debug!("marking interrupt 0x{:x} as complete", interrupt_line);
 
-    // TODO: figure out which context to complete
-    plic.complete(1, interrupt_line as u32);
+    // the interrupt is enabled for all harts so completing it in the context of the current one is sufficient
+    plic.complete(hart::current().plic_context, interrupt_line as u32);
 
     Ok(())
 }
@@ -58,18 +58,18 @@ fn irq_control_claim(
     plic: &mut PLIC,
     args: &SendArgs,
 ) -> Result<(), caps::SyscallError> {
-    let [notification_addr, irq_addr] = args.cap_args() else {
-        panic!("not enough cap args")
+    let ([notification_addr, irq_addr], [interrupt_line]) = (args.cap_args(), args.data_args())
+    else {
+        return Err(caps::SyscallError::InvalidArg);
     };
+    let interrupt_line = *interrupt_line;
 
-    let interrupt_line = args.data_args()[0];
     // get valid notification cap from task
     let notification_cap =
-        unsafe { utils::lookup_cap(cspace, (*notification_addr).into(), Tag::Notification) }
-            .unwrap();
+        unsafe { utils::lookup_cap(cspace, *notification_addr, Tag::Notification) }?;
 
     // get valid uninitialized target cap from task
-    let irq_cap = unsafe { utils::lookup_cap_mut(cspace, *irq_addr, Tag::Uninit) }.unwrap();
+    let irq_cap = unsafe { utils::lookup_cap_mut(cspace, *irq_addr, Tag::Uninit) }?;
 
     // try to claim the given interrupt line
     match IrqControlIface.try_get_unclaimed(irq_control, interrupt_line) {
@@ -85,16 +85,11 @@ fn irq_control_claim(
             let irq_control_slot = unsafe { &mut *irq_control_slot };
             NotificationIface.copy(notification_cap, irq_control_slot);
 
-            // activate the specified interrupt line in the PLIC
-            // we currently only run the first hart in supervisor mode, which corresponds to
-            // qemu_virt:  context 1
-            // qemu_sifive_u: context 2
-
-            // TODO: determine which context(s) whe should enable
-            plic.enable_interrupt(interrupt_line as u32, 0);
-            plic.enable_interrupt(interrupt_line as u32, 1);
-            plic.enable_interrupt(interrupt_line as u32, 2);
-            plic.enable_interrupt(interrupt_line as u32, 3);
+            // activate the specified interrupt line in the PLIC for all harts so that whichever hart is free first
+            // claims it
+            for hart in hart::online() {
+                plic.enable_interrupt(interrupt_line as u32, hart.plic_context);
+            }
 
             // we use priority 2 because we set the interrupt threshold to 1 in plic initialization
             plic.set_priority(interrupt_line as u32, 2);
@@ -12,14 +12,18 @@ use super::super::utils;
 pub fn mem_send(cspace: &CSpace, mem: &Capability, args: &SendArgs) -> Result<(), SyscallError> {
     const DERIVE: usize = 1;
     match args.label() {
-        DERIVE => mem_derive(
-            cspace,
-            mem,
-            args.cap_args()[0],
-            CapabilityVariant::try_from(args.data_args()[0])
-                .map_err(|_| SyscallError::InvalidArg)?,
-            args.data_args()[1],
-        ),
+        DERIVE => {
+            let ([target], [variant, size]) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            mem_derive(
+                cspace,
+                mem,
+                *target,
+                CapabilityVariant::try_from(*variant).map_err(|_| SyscallError::InvalidArg)?,
+                *size,
+            )
+        }
         _ => Err(SyscallError::Unsupported),
     }
 }
@@ -35,30 +39,18 @@ fn mem_derive(
 
     // derive the correct capability
     match variant {
-        CapabilityVariant::Uninit => return Err(SyscallError::InvalidArg),
-        CapabilityVariant::Memory => unimplemented!("memory cannot yet be derived"),
-        CapabilityVariant::CSpace => {
-            CSpaceIface.derive(mem, target_cap, size);
-        }
-        CapabilityVariant::VSpace => {
-            VSpaceIface.derive(mem, target_cap);
-        }
-        CapabilityVariant::Task => {
-            TaskIface.derive(mem, target_cap);
-        }
-        CapabilityVariant::Page => {
-            PageIface.derive(mem, target_cap);
-        }
-        CapabilityVariant::IrqControl => {
-            todo!("signal that deriving irq-control from mem is not supported")
-        }
-        CapabilityVariant::Irq => todo!("signal that deriving irq from mem is not supported"),
-        CapabilityVariant::Notification => {
-            NotificationIface.derive(mem, target_cap);
-        }
-        CapabilityVariant::Devmem => todo!("cant derive devmem"),
-        CapabilityVariant::AsidControl => todo!("cant derive asid_control"),
+        CapabilityVariant::Uninit => Err(SyscallError::InvalidArg),
+        CapabilityVariant::CSpace => CSpaceIface.derive(mem, target_cap, size),
+        CapabilityVariant::VSpace => VSpaceIface.derive(mem, target_cap),
+        CapabilityVariant::Task => TaskIface.derive(mem, target_cap),
+        CapabilityVariant::Page => PageIface.derive(mem, target_cap),
+        CapabilityVariant::Notification => NotificationIface.derive(mem, target_cap),
         CapabilityVariant::Endpoint => EndpointIface.derive(mem, target_cap),
+        // these are either created by the kernel during boot or not yet derivable from memory
+        CapabilityVariant::Memory
+        | CapabilityVariant::IrqControl
+        | CapabilityVariant::Irq
+        | CapabilityVariant::Devmem
+        | CapabilityVariant::AsidControl => Err(SyscallError::Unsupported),
     }
-    Ok(())
 }
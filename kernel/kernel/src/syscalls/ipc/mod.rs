@@ -1,7 +1,9 @@
 pub mod asid_control;
+pub mod cspace;
 pub mod devmem;
 pub mod endpoint;
 pub mod irq;
 pub mod mem;
+pub mod notification;
 pub mod page;
 pub mod task;
@@ -0,0 +1,43 @@
+use syscall_abi::call::CallArgs;
+use syscall_abi::send::SendArgs;
+use syscall_abi::{SyscallResult, SyscallReturnData};
+
+use crate::caps::{CSpace, Capability, NotificationIface, SyscallError};
+
+/// Non-blocking operations on a notification.
+///
+/// `POLL` takes the current value of the notification like `wait_on` does but returns `0` instead of blocking if the
+/// notification is unset.
+pub fn notification_call(
+    _cspace: &CSpace,
+    notification: &Capability,
+    args: CallArgs,
+) -> SyscallResult<SyscallReturnData> {
+    const POLL: usize = 0;
+    match args.label() {
+        POLL => {
+            let ([], []) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            Ok([NotificationIface.take_value(notification), 0, 0, 0, 0, 0, 0])
+        }
+        _ => Err(SyscallError::Unsupported),
+    }
+}
+
+/// Operations on a notification that are performed by sending to it.
+///
+/// `SIGNAL` sets the notification and wakes the task waiting on it, the same way an interrupt does.
+pub fn notification_send(notification: &Capability, args: &SendArgs) -> Result<(), SyscallError> {
+    const SIGNAL: usize = 0;
+    match args.label() {
+        SIGNAL => {
+            let ([], []) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
+            };
+            NotificationIface.notify(notification);
+            Ok(())
+        }
+        _ => Err(SyscallError::Unsupported),
+    }
+}
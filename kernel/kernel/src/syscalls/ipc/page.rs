@@ -14,12 +14,8 @@ pub fn page_send(cspace: &CSpace, page: &mut Page, args: &SendArgs) -> Result<()
 
     match args.label() {
         MAP => {
-            let [mem, vspace] = args.cap_args() else {
-                panic!("not enough cap arguments")
-            };
-
-            let [addr, flags] = args.data_args() else {
-                panic!("not enough data arguments")
+            let ([mem, vspace], [addr, flags]) = (args.cap_args(), args.data_args()) else {
+                return Err(SyscallError::InvalidArg);
             };
             let mem_cap = unsafe { utils::lookup_cap(cspace, *mem, Tag::Memory) }?;
             let vspace_cap = unsafe { utils::lookup_cap(cspace, *vspace, Tag::VSpace) }?;
@@ -1,24 +1,89 @@
 use derivation_tree::caps::CapabilityIface;
+use derivation_tree::Correspondence;
 use syscall_abi::send::SendArgs;
 use syscall_abi::CAddr;
 
+use crate::caps::task::TaskExecutionState;
 use crate::{
-    caps::{CSpace, CSpaceIface, SyscallError, Tag, Task, VSpaceIface},
+    caps::{
+        self, CSpace, CSpaceIface, Capability, NotificationIface, SyscallError, Tag, Task,
+        VSpaceIface,
+    },
+    sched,
     syscalls::utils,
 };
 
-pub fn task_send(cspace: &CSpace, task: &Task, args: &SendArgs) -> Result<(), SyscallError> {
+pub fn task_send(
+    cspace: &CSpace,
+    task_cap: &mut Capability,
+    args: &SendArgs,
+) -> Result<(), SyscallError> {
     const ASSIGN_REGS: usize = 1;
     const ASSIGN_VSPACE: usize = 2;
     const ASSIGN_CSPACE: usize = 3;
+    const BIND_NOTIFICATION: usize = 4;
+    const UNBIND_NOTIFICATION: usize = 5;
+    const START: usize = 6;
+    let task_ptr = task_cap as *mut Capability;
+    let task = task_cap.get_inner_task().unwrap();
     match args.label() {
         ASSIGN_REGS => task_assign_control_registers(task, args.data_args()),
-        ASSIGN_VSPACE => task_assign_vspace(cspace, task, args.cap_args()[0]),
-        ASSIGN_CSPACE => task_assign_cspace(cspace, task, args.cap_args()[0]),
+        ASSIGN_VSPACE => task_assign_vspace(cspace, task, first_cap_arg(args)?),
+        ASSIGN_CSPACE => task_assign_cspace(cspace, task, first_cap_arg(args)?),
+        BIND_NOTIFICATION => task_bind_notification(cspace, task, first_cap_arg(args)?),
+        UNBIND_NOTIFICATION => task_unbind_notification(task),
+        START => task_start(task_ptr, task),
         _ => Err(SyscallError::Unsupported),
     }
 }
 
+fn first_cap_arg(args: &SendArgs) -> Result<CAddr, SyscallError> {
+    args.cap_args()
+        .first()
+        .copied()
+        .ok_or(SyscallError::InvalidArg)
+}
+
+fn task_bind_notification(
+    cspace: &CSpace,
+    task: &Task,
+    notification_addr: CAddr,
+) -> Result<(), SyscallError> {
+    // get valid notification cap from current tasks cspace
+    let source = unsafe { utils::lookup_cap(cspace, notification_addr, Tag::Notification) }?;
+
+    // bind the notification first so that a notification which is bound to another task is rejected before
+    // anything about the task is changed
+    unsafe { NotificationIface.bind_task(source, &*task.state) }?;
+
+    // replace a previously bound notification with the new one
+    let mut state = task.state.borrow_mut();
+    if *state.bound_notification.get_tag() == Tag::Notification {
+        if state
+            .bound_notification
+            .get_inner_notification()
+            .unwrap()
+            .corresponds_to(source.get_inner_notification().unwrap())
+        {
+            log::debug!("notification is already bound to the task");
+            return Ok(());
+        }
+        NotificationIface.unbind_task(&state.bound_notification);
+    }
+    unsafe { caps::destroy(&mut state.bound_notification) };
+    NotificationIface.copy(&source, &mut state.bound_notification);
+    Ok(())
+}
+
+fn task_unbind_notification(task: &Task) -> Result<(), SyscallError> {
+    let mut state = task.state.borrow_mut();
+    if *state.bound_notification.get_tag() == Tag::Notification {
+        NotificationIface.unbind_task(&state.bound_notification);
+    }
+    unsafe { caps::destroy(&mut state.bound_notification) };
+    Ok(())
+}
+
 fn task_assign_cspace(
     cspace: &CSpace,
     task: &Task,
@@ -30,7 +95,9 @@ fn task_assign_cspace(
     // assign cspace to target task
     log::debug!("copy cspace: {:?}", cspace_addr);
     let mut task = task.state.borrow_mut();
-    log::debug!("{:?}", task.cspace.get_tag());
+    if *task.cspace.get_tag() != Tag::Uninit {
+        return Err(SyscallError::OccupiedSlot);
+    }
     CSpaceIface.copy(&source, &mut task.cspace);
     log::trace!("cspace copied");
     Ok(())
@@ -50,6 +117,9 @@ fn task_assign_vspace(
         source.get_inner_vspace().unwrap().asid
     );
     let mut task = task.state.borrow_mut();
+    if *task.vspace.get_tag() != Tag::Uninit {
+        return Err(SyscallError::OccupiedSlot);
+    }
     VSpaceIface.copy(&source, &mut task.vspace);
     log::trace!("vspace copied");
     Ok(())
@@ -60,15 +130,28 @@ fn task_assign_control_registers(task: &Task, args: &[usize]) -> Result<(), Sysc
 
     // assign control registers as specified by the syscall
     let mut task_state = task.state.borrow_mut();
-    let [pc, sp, gp, tp] = args[..4] else {
-        panic!("wrong count")
+    let [pc, sp, gp, tp] = *args else {
+        return Err(SyscallError::InvalidArg);
     };
     task_state.frame.start_pc = pc;
     task_state.frame.general_purpose_regs[2] = sp;
-
-    // NOTE: check that this order is correct
     task_state.frame.general_purpose_regs[3] = gp;
-    task_state.frame.general_purpose_regs[8] = tp;
+    task_state.frame.general_purpose_regs[4] = tp;
+
+    Ok(())
+}
 
+/// Queue the task so that it is executed by the scheduler instead of only running when it is yielded to
+fn task_start(task_ptr: *mut Capability, task: &Task) -> Result<(), SyscallError> {
+    {
+        let state = task.state.borrow();
+        if state.execution_state != TaskExecutionState::Idle
+            || *state.vspace.get_tag() != Tag::VSpace
+            || *state.cspace.get_tag() != Tag::CSpace
+        {
+            return Err(SyscallError::InvalidArg);
+        }
+    }
+    sched::enqueue(task_ptr);
     Ok(())
 }
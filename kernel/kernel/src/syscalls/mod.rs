@@ -14,21 +14,29 @@ mod receive;
 mod send;
 mod system_reset;
 mod utils;
+mod wait_any;
 mod wait_on;
 
 use crate::caps::Capability;
 use crate::sched::Schedule;
-use crate::syscalls::debug::{DebugLogHandler, DebugPutcHandler};
+use crate::syscalls::debug::{
+    DebugCapTreeHandler, DebugKstatHandler, DebugLogHandler, DebugPutcHandler, DebugReadLogHandler,
+};
 use crate::syscalls::identify::IdentifyHandler;
 use crate::syscalls::r#yield::YieldHandler;
 use crate::syscalls::system_reset::SystemResetHandler;
+use crate::syscalls::wait_any::WaitAnyHandler;
 use crate::syscalls::wait_on::WaitOnHandler;
 use crate::syscalls::yield_to::YieldToHandler;
 use crate::KernelContext;
-use derivation_tree::tree::CursorRefMut;
+use cmdline::DebugFlags;
+use derivation_tree::tree::{CursorRefMut, DerivationTree};
 use riscv::trap::TrapInfo;
+use syscall_abi::debug::DebugCapTree;
+use syscall_abi::debug::DebugKstat;
 use syscall_abi::debug::DebugLog;
 use syscall_abi::debug::DebugPutc;
+use syscall_abi::debug::DebugReadLog;
 use syscall_abi::identify::Identify;
 use syscall_abi::r#yield::Yield;
 use syscall_abi::receive::Receive;
@@ -43,6 +51,7 @@ use crate::syscalls::send::SendHandler;
 use syscall_abi::call::Call;
 use syscall_abi::destroy::Destroy;
 use syscall_abi::exit::Exit;
+use syscall_abi::wait_any::WaitAny;
 use syscall_abi::wait_on::WaitOn;
 use syscall_abi::yield_to::YieldTo;
 use syscall_abi::*;
@@ -52,6 +61,7 @@ use self::receive::ReceiveHandler;
 pub(self) struct SyscallContext<'l, 'c> {
     pub task: CursorRefMut<'l, 'c, Capability>,
     pub trap_info: &'l TrapInfo,
+    pub derivation_tree: &'l DerivationTree<Capability>,
 }
 
 impl SyscallContext<'_, '_> {
@@ -67,11 +77,29 @@ impl SyscallContext<'_, '_> {
         )
         .unwrap()
     }
+
+    /// Complete the current syscall by returning `error` to the calling task
+    fn return_error(&self, error: SyscallError) -> Schedule {
+        let mut task_state = self.task.get_inner_task().unwrap().state.borrow_mut();
+        task_state
+            .frame
+            .write_syscall_return(Err::<NoValue, SyscallError>(error).into_response());
+        task_state.frame.start_pc = self.trap_info.epc + 4;
+        Schedule::Keep
+    }
 }
 
 impl<'l, 'cursor> SyscallContext<'l, 'cursor> {
-    fn from(trap_info: &'l TrapInfo, task: CursorRefMut<'l, 'cursor, Capability>) -> Self {
-        Self { trap_info, task }
+    fn from(
+        trap_info: &'l TrapInfo,
+        task: CursorRefMut<'l, 'cursor, Capability>,
+        derivation_tree: &'l DerivationTree<Capability>,
+    ) -> Self {
+        Self {
+            trap_info,
+            task,
+            derivation_tree,
+        }
     }
 }
 
@@ -88,6 +116,7 @@ pub(self) type HandlerReturn<Syscall> = (Schedule, <Syscall as SyscallBinding>::
 pub fn handle_syscall(
     mut task: CursorRefMut<'_, '_, Capability>,
     trap_info: &TrapInfo,
+    derivation_tree: &DerivationTree<Capability>,
     kernel_ctx: &mut KernelContext,
 ) -> Schedule {
     // extract syscall number and raw arguments from calling tasks registers
@@ -99,12 +128,19 @@ pub fn handle_syscall(
         (syscall_no, args)
     };
 
-    let mut syscall_ctx = SyscallContext::from(trap_info, task);
+    if kernel_ctx.cmdline.debug.contains(DebugFlags::SYSCALLS) {
+        log::info!("syscall {} with args {:x?}", syscall_no, raw_args);
+    }
+
+    let mut syscall_ctx = SyscallContext::from(trap_info, task, derivation_tree);
 
     match syscall_no {
         // handle syscalls
         DebugPutc::SYSCALL_NO => DebugPutcHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         DebugLog::SYSCALL_NO => DebugLogHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
+        DebugReadLog::SYSCALL_NO => DebugReadLogHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
+        DebugKstat::SYSCALL_NO => DebugKstatHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
+        DebugCapTree::SYSCALL_NO => DebugCapTreeHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         Identify::SYSCALL_NO => IdentifyHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         YieldTo::SYSCALL_NO => YieldToHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         Yield::SYSCALL_NO => YieldHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
@@ -116,6 +152,7 @@ pub fn handle_syscall(
         Destroy::SYSCALL_NO => DestroyHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         syscall_abi::copy::Copy::SYSCALL_NO => CopyHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
         WaitOn::SYSCALL_NO => WaitOnHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
+        WaitAny::SYSCALL_NO => WaitAnyHandler.handle_raw(kernel_ctx, &mut syscall_ctx),
 
         // handle an unknown syscall
         _ => handle_unknown_syscall(&mut syscall_ctx, syscall_no, raw_args),
@@ -133,12 +170,5 @@ fn handle_unknown_syscall(
         raw_args
     );
 
-    // write error into task
-    let mut task_state = ctx.task.get_inner_task().unwrap().state.borrow_mut();
-    task_state.frame.write_syscall_return(
-        SyscallResult::<NoValue>::Err(SyscallError::UnknownSyscall).into_response(),
-    );
-    task_state.frame.start_pc = ctx.trap_info.epc + 4;
-
-    Schedule::Keep
+    ctx.return_error(SyscallError::UnknownSyscall)
 }
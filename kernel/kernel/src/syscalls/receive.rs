@@ -7,6 +7,7 @@ use syscall_abi::{
 use crate::{caps, sched::Schedule, syscalls::ipc, KernelContext};
 
 use super::handler_trait::RawSyscallHandler;
+use super::utils;
 
 pub(super) struct ReceiveHandler;
 
@@ -29,26 +30,23 @@ impl RawSyscallHandler for ReceiveHandler {
         let cspace = cspace.get_shared().unwrap();
         let cspace = cspace.get_inner_cspace().unwrap();
 
-        let cap = unsafe {
-            cspace
-                .resolve_caddr(args.target)
-                .unwrap() // TODO Handle error by returning InvalidCAddr
-                .as_mut()
-                .unwrap()
+        let cap = match unsafe { utils::resolve_cap_mut(cspace, args.target) } {
+            Ok(cap) => cap,
+            Err(e) => return syscall_ctx.return_error(e),
         };
-        log::debug!("dispatching send to {:?} capability", cap.get_tag());
+        log::debug!("dispatching receive to {:?} capability", cap.get_tag());
         let result: SyscallResult<ReceiveReturn> = match cap.get_tag() {
-            caps::Tag::Uninit => todo!(),
-            caps::Tag::Memory => todo!(),
-            caps::Tag::CSpace => todo!(),
-            caps::Tag::VSpace => todo!(),
-            caps::Tag::Task => todo!(),
-            caps::Tag::Page => todo!(),
-            caps::Tag::IrqControl => todo!(),
-            caps::Tag::Irq => todo!(),
-            caps::Tag::Notification => todo!(),
-            caps::Tag::Devmem => todo!(),
-            caps::Tag::AsidControl => todo!(),
+            caps::Tag::Uninit => Err(SyscallError::InvalidCap),
+            caps::Tag::Memory
+            | caps::Tag::CSpace
+            | caps::Tag::VSpace
+            | caps::Tag::Task
+            | caps::Tag::Page
+            | caps::Tag::IrqControl
+            | caps::Tag::Irq
+            | caps::Tag::Notification
+            | caps::Tag::Devmem
+            | caps::Tag::AsidControl => Err(SyscallError::Unsupported),
             caps::Tag::Endpoint => {
                 log::debug!("handling endpoint receive");
                 let (res, schedule) = ipc::endpoint::endpoint_recv(
@@ -66,7 +64,6 @@ impl RawSyscallHandler for ReceiveHandler {
                 return schedule;
             }
         };
-        #[allow(unreachable_code)]
         match result {
             Ok(r) => {
                 task.state
@@ -11,6 +11,7 @@ use crate::{
 
 use super::handler_trait::RawSyscallHandler;
 use super::ipc;
+use super::utils;
 
 pub(super) struct SendHandler;
 
@@ -34,20 +35,15 @@ impl RawSyscallHandler for SendHandler {
         let cspace = cspace.get_shared().unwrap();
         let cspace = cspace.get_inner_cspace().unwrap();
 
-        let cap = unsafe {
-            cspace
-                .resolve_caddr(args.target)
-                .unwrap() // TODO Handle error by returning InvalidCAddr
-                .as_mut()
-                .unwrap()
+        let cap = match unsafe { utils::resolve_cap_mut(cspace, args.target) } {
+            Ok(cap) => cap,
+            Err(e) => return syscall_ctx.return_error(e),
         };
         log::debug!("dispatching send to {:?} capability", cap.get_tag());
         let result = match cap.get_tag() {
-            caps::Tag::Uninit => todo!("send for uninit unimplemented"),
+            caps::Tag::Uninit => Err(SyscallError::InvalidCap),
             caps::Tag::Memory => ipc::mem::mem_send(cspace, cap, &args),
-            caps::Tag::CSpace => todo!("send for cspace unimplemented"),
-            caps::Tag::VSpace => todo!("send for vspace unimplemented"),
-            caps::Tag::Task => ipc::task::task_send(cspace, cap.get_inner_task().unwrap(), &args),
+            caps::Tag::Task => ipc::task::task_send(cspace, cap, &args),
             caps::Tag::Page => {
                 ipc::page::page_send(cspace, cap.get_inner_page_mut().unwrap(), &args)
             }
@@ -55,7 +51,14 @@ impl RawSyscallHandler for SendHandler {
             caps::Tag::Irq => {
                 ipc::irq::irq_send(kernel_ctx, cspace, cap.get_inner_irq().unwrap(), &args)
             }
-            caps::Tag::Notification => todo!("send for notification unimplemented"),
+            caps::Tag::CSpace => ipc::cspace::cspace_send(
+                task_ptr,
+                cspace,
+                cap.get_inner_cspace_mut().unwrap(),
+                &args,
+            ),
+            caps::Tag::Notification => ipc::notification::notification_send(cap, &args),
+            caps::Tag::VSpace => Err(SyscallError::Unsupported),
             caps::Tag::Devmem => {
                 ipc::devmem::devmem_send(cspace, cap.get_inner_devmem().unwrap(), &args)
             }
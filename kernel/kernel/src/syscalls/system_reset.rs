@@ -24,6 +24,13 @@ impl SyscallHandler for SystemResetHandler {
             args.typ,
             args.reason
         );
+
+        // test runs report their result through the exit status of QEMU
+        #[cfg(feature = "kernel_tests")]
+        if args.typ == ResetType::Shutdown {
+            crate::arch_specific::qemu_exit::exit(args.reason == ResetReason::NoReason);
+        }
+
         sbi::system_reset::system_reset(
             match args.typ {
                 ResetType::Shutdown => sbi::system_reset::ResetType::Shutdown,
@@ -1,14 +1,32 @@
 use crate::caps::{CSpace, Capability, SyscallError};
 use syscall_abi::CAddr;
 
+/// Resolve `caddr` to a pointer to the capability it refers to
+unsafe fn resolve(cspace: &CSpace, caddr: CAddr) -> Result<*mut Capability, SyscallError> {
+    cspace
+        .resolve_caddr_depth(caddr, CAddr::FULL_DEPTH)
+        .map_err(|e| {
+            log::debug!("could not resolve caddr {:#x}: {:?}", caddr, e);
+            SyscallError::InvalidCAddr
+        })
+}
+
+/// Resolve `caddr` to the capability it refers to, regardless of its type
+pub(crate) unsafe fn resolve_cap_mut(
+    cspace: &CSpace,
+    caddr: CAddr,
+) -> Result<&'static mut Capability, SyscallError> {
+    let cap_ptr = resolve(cspace, caddr)?;
+    // TODO Use a cursor to safely access the capability
+    Ok(cap_ptr.as_mut().unwrap())
+}
+
 pub(crate) unsafe fn lookup_cap(
     cspace: &CSpace,
     caddr: CAddr,
     expected_tag: crate::caps::Tag,
 ) -> Result<&'static Capability, SyscallError> {
-    let cap_ptr = cspace
-        .resolve_caddr(caddr)
-        .ok_or(SyscallError::InvalidCap)?;
+    let cap_ptr = resolve(cspace, caddr)?;
     // TODO Use a cursor to safely access the capability
     let cap = cap_ptr.as_ref().unwrap();
     if *cap.get_tag() != expected_tag {
@@ -22,9 +40,7 @@ pub(crate) unsafe fn lookup_cap_mut(
     caddr: CAddr,
     expected_tag: crate::caps::Tag,
 ) -> Result<&'static mut Capability, SyscallError> {
-    let cap_ptr = cspace
-        .resolve_caddr(caddr)
-        .ok_or(SyscallError::InvalidCap)?;
+    let cap_ptr = resolve(cspace, caddr)?;
     // TODO Use a cursor to safely access the capability
     let cap = cap_ptr.as_mut().unwrap();
     if *cap.get_tag() != expected_tag {
@@ -0,0 +1,91 @@
+use crate::caps::endpoint::EndpointIface;
+use crate::caps::task::{TaskExecutionState, WaitingOn};
+use crate::caps::{Capability, NotificationIface, SyscallError, Tag, TaskIface};
+use crate::sched::Schedule;
+use crate::syscalls::handler_trait::RawSyscallHandler;
+use crate::syscalls::{utils, SyscallContext};
+use crate::KernelContext;
+use derivation_tree::AsStaticMut;
+use syscall_abi::wait_any::{WaitAny, WaitAnyArgs, WaitAnyReturn, MAX_WAIT_OBJECTS};
+use syscall_abi::{IntoRawSysRepsonse, SyscallResult};
+
+pub(super) struct WaitAnyHandler;
+
+impl RawSyscallHandler for WaitAnyHandler {
+    type Syscall = WaitAny;
+
+    fn handle_raw(
+        &mut self,
+        _kernel_ctx: &mut KernelContext,
+        syscall_ctx: &mut SyscallContext<'_, '_>,
+    ) -> Schedule {
+        // parse arguments
+        let Ok(args) = WaitAnyArgs::try_from(syscall_ctx.get_raw_args()) else {
+            return syscall_ctx.return_error(SyscallError::InvalidArg);
+        };
+
+        // get basic caps from task
+        let task_cap_ptr = syscall_ctx.task.as_static_mut() as *mut Capability;
+        let task = syscall_ctx.task.get_inner_task().unwrap();
+        let mut cspace = task.get_cspace();
+        let cspace = cspace.get_shared().unwrap();
+        let cspace = cspace.get_inner_cspace().unwrap();
+
+        // look up all objects before touching any of them so that invalid arguments leave everything unchanged
+        let mut objects = [None; MAX_WAIT_OBJECTS];
+        for (object, caddr) in objects.iter_mut().zip(args.objects()) {
+            let cap = match unsafe { utils::resolve_cap_mut(cspace, *caddr) } {
+                Ok(cap) => cap,
+                Err(e) => return syscall_ctx.return_error(e),
+            };
+            if cap.tag != Tag::Notification && cap.tag != Tag::Endpoint {
+                return syscall_ctx.return_error(SyscallError::InvalidCap);
+            }
+            *object = Some(cap as *const Capability);
+        }
+
+        // complete the syscall if one of the objects is already ready
+        for (index, object) in objects.iter().flatten().enumerate() {
+            let object = unsafe { &**object };
+            let is_ready = match object.tag {
+                Tag::Notification => NotificationIface.take_value(object) != 0,
+                _ => {
+                    let endpoint = object.get_inner_endpoint().unwrap();
+                    endpoint.state.borrow().send_set.is_some()
+                }
+            };
+            if is_ready {
+                let mut task_state = task.state.borrow_mut();
+                task_state.frame.start_pc = syscall_ctx.trap_info.epc + 4;
+                task_state.frame.write_syscall_return(
+                    SyscallResult::Ok(WaitAnyReturn { index }).into_response(),
+                );
+                return Schedule::Keep;
+            }
+        }
+
+        // none of the objects is ready so the task needs to be blocked on all of them.
+        // once one of them wakes the task, it is removed from all wait sets and re-executes this syscall.
+        task.state.borrow_mut().waiting_on = WaitingOn::Any(objects);
+        for object in objects.iter().flatten() {
+            let object = unsafe { &**object };
+            let result = match object.tag {
+                Tag::Notification => unsafe {
+                    NotificationIface.add_to_wait_set(object, task_cap_ptr)
+                },
+                _ => unsafe {
+                    EndpointIface.add_watcher(object.get_inner_endpoint().unwrap(), task_cap_ptr)
+                },
+            };
+            if let Err(e) = result {
+                TaskIface.stop_waiting(task);
+                return syscall_ctx.return_error(e);
+            }
+        }
+        let mut task_state = task.state.borrow_mut();
+        task_state.execution_state = TaskExecutionState::Waiting;
+        task_state.frame.start_pc = syscall_ctx.trap_info.epc;
+
+        Schedule::RunInit
+    }
+}
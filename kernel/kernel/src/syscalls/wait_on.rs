@@ -1,4 +1,4 @@
-use crate::caps::task::TaskExecutionState;
+use crate::caps::task::{TaskExecutionState, WaitingOn};
 use crate::caps::{Capability, NotificationIface, Tag, TaskIface};
 use crate::sched::Schedule;
 use crate::syscalls::handler_trait::RawSyscallHandler;
@@ -20,7 +20,7 @@ impl RawSyscallHandler for WaitOnHandler {
     ) -> Schedule {
         // parse arguments
         let raw_args = syscall_ctx.get_raw_args();
-        let args = WaitOnArgs::try_from(raw_args).unwrap();
+        let args = WaitOnArgs::from(raw_args);
 
         // get basic caps from task
         let task_cap_ptr = syscall_ctx.task.as_static_mut() as *mut Capability;
@@ -31,28 +31,33 @@ impl RawSyscallHandler for WaitOnHandler {
 
         // get valid notification from cspace
         let notification_cap =
-            unsafe { utils::lookup_cap(cspace, args.notification, Tag::Notification) }.unwrap();
+            match unsafe { utils::lookup_cap(cspace, args.notification, Tag::Notification) } {
+                Ok(cap) => cap,
+                Err(e) => return syscall_ctx.return_error(e),
+            };
 
         let value = NotificationIface.take_value(notification_cap);
         if value == 0 {
             // notification did not contain anything so the task needs to be blocked
-            unsafe {
-                NotificationIface.add_to_wait_set(notification_cap, task_cap_ptr);
+            if let Err(e) =
+                unsafe { NotificationIface.add_to_wait_set(notification_cap, task_cap_ptr) }
+            {
+                return syscall_ctx.return_error(e);
             }
             let mut task_state = task.state.borrow_mut();
             task_state.execution_state = TaskExecutionState::Waiting;
-            task_state.waiting_on = Some(notification_cap as *const Capability);
+            task_state.waiting_on = WaitingOn::One(notification_cap as *const Capability);
             task_state.frame.start_pc = syscall_ctx.trap_info.epc;
 
             Schedule::RunInit
         } else {
             // notification already has a value so we ensure that the task is not blocked anymore and return that value
             unsafe {
-                NotificationIface.remove_from_wait_set(notification_cap, task_cap_ptr);
+                NotificationIface.remove_from_wait_set(notification_cap, &*task.state);
             }
             {
                 let mut task_state = task.state.borrow_mut();
-                task_state.waiting_on = None;
+                task_state.waiting_on = WaitingOn::Nothing;
                 task_state.frame.start_pc = syscall_ctx.trap_info.epc + 4;
                 task_state
                     .frame
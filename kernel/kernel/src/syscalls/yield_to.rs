@@ -5,7 +5,7 @@ use crate::syscalls::handler_trait::SyscallHandler;
 use crate::syscalls::SyscallContext;
 use crate::KernelContext;
 use syscall_abi::yield_to::{TaskStatus, YieldTo};
-use syscall_abi::SyscallBinding;
+use syscall_abi::{SyscallBinding, SyscallError};
 
 use super::utils;
 
@@ -32,7 +32,7 @@ impl SyscallHandler for YieldToHandler {
         // get valid memory cap from task
         let target_task_cap = match unsafe { utils::lookup_cap_mut(cspace, args.task, Tag::Task) } {
             Ok(c) => c,
-            Err(_e) => return (Schedule::Keep, Err(syscall_abi::SyscallError::InvalidCap)),
+            Err(_e) => return (Schedule::Keep, Err(SyscallError::InvalidCap)),
         };
         let target_task_ptr = target_task_cap as *mut Capability;
         let target_task = target_task_cap.get_inner_task().unwrap();
@@ -41,6 +41,13 @@ impl SyscallHandler for YieldToHandler {
         match target_task_state.execution_state {
             TaskExecutionState::Running => (Schedule::Keep, Ok(TaskStatus::AlreadyRunning)),
             TaskExecutionState::Waiting => (Schedule::Keep, Ok(TaskStatus::Blocked)),
+            // a task can only be executed once it has a vspace and cspace assigned
+            TaskExecutionState::Idle
+                if *target_task_state.vspace.get_tag() != Tag::VSpace
+                    || *target_task_state.cspace.get_tag() != Tag::CSpace =>
+            {
+                (Schedule::Keep, Err(SyscallError::InvalidArg))
+            }
             TaskExecutionState::Idle => (
                 Schedule::RunTask(target_task_ptr),
                 Ok(TaskStatus::DidExecute),
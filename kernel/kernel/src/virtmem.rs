@@ -1,4 +1,4 @@
-use allocators::{AllocInit, Allocator};
+use allocators::{AllocError, AllocInit, Allocator};
 use core::alloc::Layout;
 use riscv::mem::ptrs::{MappedConstPtr, MappedMutPtr, PhysConstPtr, PhysMutPtr};
 
@@ -33,15 +33,15 @@ pub fn map(
     vaddr: usize,
     paddr: usize,
     flags: EntryFlags,
-) {
+) -> Result<(), AllocError> {
     while let Err(e) = riscv::pt::map(KernelMapper, root, vaddr, paddr, flags) {
         let new_pt = alloc
-            .allocate(Layout::new::<MemoryPage>(), AllocInit::Zeroed)
-            .unwrap()
+            .allocate(Layout::new::<MemoryPage>(), AllocInit::Zeroed)?
             .as_mut_ptr()
             .cast();
         riscv::pt::map_pt(KernelMapper, root, e.level, e.target_vaddr, new_pt).unwrap();
     }
+    Ok(())
 }
 
 pub fn virt_to_phys(root: &PageTable, vaddr: usize) -> Option<usize> {
@@ -77,7 +77,8 @@ pub fn map_range_alloc(
             addr,
             MappedConstPtr::from(page_addr).as_direct().raw() as usize,
             flags,
-        );
+        )
+        .unwrap();
 
         offset += 1;
     }
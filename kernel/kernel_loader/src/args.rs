@@ -3,6 +3,7 @@
 //! Typically U-Boot passes through kernel parameters via an `argc`, `argv` pair.
 //! The code in this module parses relevant kernel-loader arguments from that iterator.
 
+use crate::elfloader::elf_file;
 use core::ffi::CStr;
 
 /// An iterator over an *argc*, *argv* pair.
@@ -61,8 +62,17 @@ pub struct LoaderArgs {
     /// This image is usually placed there by qemu or u-boot before jumping into the kernel_loader.
     pub image_addr: *const u8,
 
-    /// The size of the kernel image in bytes.
-    pub image_size: usize,
+    /// The address of the init binary image (in physical memory).
+    /// Like the kernel image, it is usually placed there by qemu or u-boot and passed on to the kernel which starts
+    /// it as the first userspace task.
+    pub init_addr: *const u8,
+
+    /// The address of the initial ram filesystem (in physical memory) or null if none was given.
+    /// The kernel hands it to init which reads boot-time programs from it.
+    pub initrd_addr: *const u8,
+
+    /// The size of the initial ram filesystem in bytes.
+    pub initrd_size: usize,
 }
 
 impl LoaderArgs {
@@ -72,7 +82,9 @@ impl LoaderArgs {
 
         let mut phys_fdt_addr = None;
         let mut image_addr = None;
-        let mut image_size = None;
+        let mut init_addr = None;
+        let mut initrd_addr = None;
+        let mut initrd_size = None;
         for arg in args {
             if let Some(addr_s) = arg.strip_prefix("fdt_addr=") {
                 let addr =
@@ -84,32 +96,57 @@ impl LoaderArgs {
                     usize::from_str_radix(addr_s, 16).expect("image_addr should be in base 16");
                 image_addr = Some(addr as *const u8);
             }
-            if let Some(size_s) = arg.strip_prefix("image_size=") {
+            if let Some(addr_s) = arg.strip_prefix("init_addr=") {
+                let addr =
+                    usize::from_str_radix(addr_s, 16).expect("init_addr should be in base 16");
+                init_addr = Some(addr as *const u8);
+            }
+            if let Some(addr_s) = arg.strip_prefix("initrd_addr=") {
+                let addr =
+                    usize::from_str_radix(addr_s, 16).expect("initrd_addr should be in base 16");
+                initrd_addr = Some(addr as *const u8);
+            }
+            if let Some(size_s) = arg.strip_prefix("initrd_size=") {
                 let size =
-                    usize::from_str_radix(size_s, 16).expect("image size should be in base 16");
-                image_size = Some(size);
+                    usize::from_str_radix(size_s, 16).expect("initrd_size should be in base 16");
+                initrd_size = Some(size);
             }
         }
 
-        // set sane argument defaults
-        if image_size.is_none() {
-            log::warn!("no image_size= (size of the actual kernel image in bytes) kernel argument given; assuming 2MB");
-            const MB: usize = 1024 * 1024;
-            image_size = Some(2 * MB);
-        }
-
         Self {
             phys_fdt_addr: phys_fdt_addr
                 .expect("no fdt_addr= (address of the device tree blob) kernel argument given"),
             image_addr: image_addr
                 .expect("no image_addr= (image of the actual kernel) kernel argument given"),
-            image_size: image_size.unwrap(),
+            init_addr: init_addr
+                .expect("no init_addr= (image of the init binary) kernel argument given"),
+            initrd_addr: initrd_addr.unwrap_or(core::ptr::null()),
+            initrd_size: initrd_addr.and(initrd_size).unwrap_or(0),
         }
     }
 
-    /// Get a slice to the in-memory kernel binary as indicated by the argument
+    /// Get a slice to the in-memory kernel binary as indicated by the argument.
+    ///
+    /// The size of the slice is determined from the ELF headers of the binary.
     pub fn get_kernel_bin(&self) -> &[u8] {
         // Safety: This is as safe as it gets because we receive those arguments from our bootloader which we trust
-        unsafe { core::slice::from_raw_parts(self.image_addr, self.image_size) }
+        unsafe { elf_file(self.image_addr) }
+    }
+
+    /// Get a slice to the in-memory init binary as indicated by the argument.
+    ///
+    /// The size of the slice is determined from the ELF headers of the binary.
+    pub fn get_init_bin(&self) -> &[u8] {
+        // Safety: This is as safe as it gets because we receive those arguments from our bootloader which we trust
+        unsafe { elf_file(self.init_addr) }
+    }
+
+    /// Get a slice to the in-memory initial ram filesystem if one was given
+    pub fn get_initrd(&self) -> Option<&[u8]> {
+        if self.initrd_addr.is_null() {
+            return None;
+        }
+        // Safety: This is as safe as it gets because we receive those arguments from our bootloader which we trust
+        Some(unsafe { core::slice::from_raw_parts(self.initrd_addr, self.initrd_size) })
     }
 }
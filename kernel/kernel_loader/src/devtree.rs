@@ -1,9 +1,9 @@
 //! Device-Tree interaction
 
-use core::cmp::{max, Ordering};
+use boot_info::{MemoryRegion, MemoryRegions};
 use core::fmt::Formatter;
 use core::{fmt, mem};
-use device_tree::fdt::{FdtError, FlattenedDeviceTree, MemoryReservationEntry};
+use device_tree::fdt::{FdtError, FlattenedDeviceTree, NodeProperty};
 use thiserror_no_std::Error;
 
 #[derive(Debug, Error)]
@@ -14,13 +14,16 @@ pub enum DeviceInfoError {
     NoPropOnNode(&'static str, &'static str),
     #[error("The device tree could not be parsed")]
     DeviceTreeError(#[from] FdtError),
+    #[error("The device tree describes more memory regions than can be passed to the kernel")]
+    TooManyMemoryRegions,
 }
 
 /// A data structure that contains all information relevant to the kernel loader
 pub struct DeviceInfo {
-    pub total_memory: (*mut u8, usize),
-    pub reserved_memory: MemoryReservationEntry,
-    pub usable_memory: (*mut u8, usize),
+    /// All banks of physical memory, including reserved regions
+    pub memory: MemoryRegions,
+    /// Regions of physical memory that are reserved by the device tree
+    pub reserved_memory: MemoryRegions,
     pub bootargs: Option<&'static str>,
     pub fdt: FlattenedDeviceTree<'static>,
 }
@@ -29,13 +32,10 @@ impl DeviceInfo {
     /// Extract device information from a flattened device tree located at the given pointer
     pub unsafe fn from_raw_ptr(ptr: *const u8) -> Result<Self, DeviceInfoError> {
         let fdt = FlattenedDeviceTree::from_ptr(ptr)?;
-        let total_mem = get_all_memory(&fdt)?;
-        let reserved_mem = get_reserved_memory(&fdt)?;
 
         Ok(Self {
-            total_memory: total_mem,
-            reserved_memory: reserved_mem,
-            usable_memory: calc_usable_memory(total_mem, reserved_mem)?,
+            memory: get_all_memory(&fdt)?,
+            reserved_memory: get_reserved_memory(&fdt)?,
             bootargs: get_bootargs(&fdt),
             fdt,
         })
@@ -45,134 +45,115 @@ impl DeviceInfo {
 impl fmt::Debug for DeviceInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("DeviceInfo")
-            .field("total_memory", &self.total_memory)
+            .field("memory", &self.memory)
             .field("reserved_memory", &self.reserved_memory)
-            .field("usable_memory", &self.usable_memory)
             .field("bootargs", &self.bootargs)
             .finish_non_exhaustive()
     }
 }
 
-/// Search for reserved memory in the device tree and return a new reservation that concatenates all areas found
-/// in the device tree.
+/// Iterate over the `(address, size)` pairs that are encoded in a *reg* property.
 ///
-/// **Note**: This only makes sense to do when the reserved memory lies completely at the beginning or end of the
-/// whole devices memory.
-/// Otherwise the area in the middle will be included through the concatenation and we are left with no usable
-/// memory at all.
+/// **Note**: This assumes that both `#address-cells` and `#size-cells` are 2 which is the case for all 64-bit
+/// platforms that we support.
+fn reg_entries<'buf>(reg_prop: &NodeProperty<'buf>) -> impl Iterator<Item = MemoryRegion> + 'buf {
+    reg_prop
+        .value
+        .chunks_exact(mem::size_of::<u64>() * 2)
+        .map(|entry| {
+            let (start, len) = entry.split_at(mem::size_of::<u64>());
+            MemoryRegion::new(
+                u64::from_be_bytes(start.try_into().unwrap()) as usize,
+                u64::from_be_bytes(len.try_into().unwrap()) as usize,
+            )
+        })
+}
+
+/// Collect all memory regions which are reserved by the device tree.
 ///
 /// # Device Tree Details
-/// The reserved memory regions are extracted from the device trees */reserved-memory* node.
+/// Reserved memory regions are described in two places which are both taken into account:
 ///
-/// For details about the node, see the [Device Tree Spec Section 3.5](https://devicetree-specification.readthedocs.io/en/latest/chapter3-devicenodes.html#reserved-memory-node) or [u-boot Reserved Memory Regions Doc](https://github.com/qemu/u-boot/blob/master/doc/device-tree-bindings/reserved-memory/reserved-memory.txt).
+/// - The memory reservation block of the flattened device tree ([Spec Section 5.3](https://devicetree-specification.readthedocs.io/en/latest/chapter5-flattened-format.html#memory-reservation-block)).
+/// - The children of the */reserved-memory* node.
+///   For details about the node, see the [Device Tree Spec Section 3.5](https://devicetree-specification.readthedocs.io/en/latest/chapter3-devicenodes.html#reserved-memory-node) or [u-boot Reserved Memory Regions Doc](https://github.com/qemu/u-boot/blob/master/doc/device-tree-bindings/reserved-memory/reserved-memory.txt).
 fn get_reserved_memory(
     device_tree: &FlattenedDeviceTree<'_>,
-) -> Result<MemoryReservationEntry, DeviceInfoError> {
-    // TODO Concatenating all reserved areas does not work if there are reservations at the top and bottom of physical memory. This should be improved
+) -> Result<MemoryRegions, DeviceInfoError> {
+    let mut reserved = MemoryRegions::new();
+
+    for entry in device_tree.memory_reservations {
+        let region = MemoryRegion::new(entry.address as usize, entry.size as usize);
+        log::trace!("found memory reservation block entry {region}");
+        reserved
+            .push(region)
+            .map_err(|_| DeviceInfoError::TooManyMemoryRegions)?;
+    }
 
-    // search for node in the tree
     log::trace!("looking for /reserved-memory node in device tree");
-    let node = device_tree
+    let Some(node) = device_tree
         .structure
         .children()
         .find(|node| node.name == "reserved-memory")
-        .ok_or(DeviceInfoError::NoNodeInDeviceTree)?;
+    else {
+        return Ok(reserved);
+    };
 
-    // the node describes reserved areas via child nodes so let's find them now and extract the reserved areas from the "reg" property
+    // the node describes reserved areas via child nodes so let's find them now and extract the reserved areas from
+    // their "reg" property
     log::trace!("inspecting found /reserved-memory nodes children for reserved memory areas");
-    let areas = node.children().map(|child_node| {
-        let reg_prop = child_node
-            .props()
-            .find(|prop| prop.name == "reg")
-            .expect("/reserved-memory nodes children did not have a reg property");
-
-        let mem_start = reg_prop.nth_u64(0).unwrap();
-        let mem_len = reg_prop.nth_u64(1).unwrap();
-        log::trace!(
-            "found reserved memory area {} at start=0x{:x} len=0x{:x} (end=0x{:x})",
-            child_node.name,
-            mem_start,
-            mem_len,
-            mem_start + mem_len
-        );
-
-        MemoryReservationEntry::new(mem_start, mem_len)
-    });
-
-    // concatenate areas together
-    let area = areas.reduce(|res_a, res_b| match res_a.address.cmp(&res_b.address) {
-        Ordering::Less => {
-            MemoryReservationEntry::new(res_a.address, (res_b.address - res_a.address) + res_b.size)
+    for child_node in node.children() {
+        let reg_prop = child_node.props().find(|prop| prop.name == "reg").ok_or(
+            DeviceInfoError::NoPropOnNode("reserved-memory child", "reg"),
+        )?;
+
+        for region in reg_entries(&reg_prop) {
+            log::trace!("found reserved memory area {} at {region}", child_node.name);
+            reserved
+                .push(region)
+                .map_err(|_| DeviceInfoError::TooManyMemoryRegions)?;
         }
-        Ordering::Equal => MemoryReservationEntry::new(res_a.address, max(res_a.size, res_b.size)),
-        Ordering::Greater => {
-            MemoryReservationEntry::new(res_b.address, (res_a.address - res_b.address) + res_a.size)
-        }
-    });
+    }
 
-    Ok(area.unwrap_or(MemoryReservationEntry::new(0, 0)))
+    Ok(reserved)
 }
 
-/// Search for memory description in the device tree and return the starting address and size of it.
+/// Collect all banks of physical memory that are described in the device tree.
 ///
 /// **Note**: The memory returned here includes **all** device memory, including reserved regions.
 /// It cannot be directly used and the reserved sections must be taken into account.
 ///
 /// # Device Tree Details
-/// The reserved memory regions are extracted from the device trees */memory* node.
+/// The memory banks are extracted from the *reg* properties of all */memory* nodes.
 ///
 /// For details about the node, see the [DeviceTree specs /memory node](https://devicetree-specification.readthedocs.io/en/v0.3/devicenodes.html#memory-node).
-fn get_all_memory(
-    device_tree: &FlattenedDeviceTree<'_>,
-) -> Result<(*mut u8, usize), DeviceInfoError> {
-    log::trace!("searching for memory node in device tree");
+fn get_all_memory(device_tree: &FlattenedDeviceTree<'_>) -> Result<MemoryRegions, DeviceInfoError> {
+    log::trace!("searching for memory nodes in device tree");
+    let mut memory = MemoryRegions::new();
 
-    let mem_node = device_tree
+    for mem_node in device_tree
         .structure
         .children()
-        .find(|node| node.name.starts_with("memory@"))
-        .ok_or(DeviceInfoError::NoNodeInDeviceTree)?;
-    log::trace!("found memory node {} in device tree", mem_node.name);
-
-    let reg_prop = mem_node
-        .props()
-        .find(|prop| prop.name == "reg")
-        .ok_or(DeviceInfoError::NoPropOnNode("memory", "reg"))?;
-
-    let mem_start = u64::from_be_bytes(
-        (&reg_prop.value[0..mem::size_of::<u64>()])
-            .try_into()
-            .unwrap(),
-    );
-    let mem_len = u64::from_be_bytes(
-        (&reg_prop.value[mem::size_of::<u64>()..mem::size_of::<u64>() * 2])
-            .try_into()
-            .unwrap(),
-    );
-
-    log::trace!(
-        "found reg property describing usable memory start = {:#x} len = {:#x} (end = {:#x})",
-        mem_start,
-        mem_len,
-        mem_start + mem_len
-    );
-
-    Ok((mem_start as *mut u8, mem_len as usize))
-}
+        .filter(|node| node.name.starts_with("memory@"))
+    {
+        log::trace!("found memory node {} in device tree", mem_node.name);
+        let reg_prop = mem_node
+            .props()
+            .find(|prop| prop.name == "reg")
+            .ok_or(DeviceInfoError::NoPropOnNode("memory", "reg"))?;
 
-/// Return the start address of general purpose memory and how much space is available
-pub fn calc_usable_memory(
-    total_memory: (*mut u8, usize),
-    resv_mem: MemoryReservationEntry,
-) -> Result<(*mut u8, usize), DeviceInfoError> {
-    let (total_mem_start, total_mem_len) = total_memory;
-
-    // TODO We currently assume that reserved memory starts at the bottom of physical memory. This is, of course, not always the case and should be properly handled
-    assert_eq!(total_mem_start as u64, resv_mem.address);
-    Ok((
-        (resv_mem.address + resv_mem.size) as *mut u8,
-        total_mem_len - resv_mem.size as usize,
-    ))
+        for region in reg_entries(&reg_prop) {
+            log::trace!("found reg property describing memory {region}");
+            memory
+                .push(region)
+                .map_err(|_| DeviceInfoError::TooManyMemoryRegions)?;
+        }
+    }
+
+    if memory.as_slice().is_empty() {
+        return Err(DeviceInfoError::NoNodeInDeviceTree);
+    }
+    Ok(memory)
 }
 
 /// Return the boot arguments that were passed
@@ -4,6 +4,7 @@ use crate::virtmem;
 
 use crate::virtmem::map_range_alloc;
 use allocators::bump_allocator::BumpAllocator;
+use core::ptr;
 use elfloader::arch::riscv::RelocationTypes;
 use elfloader::{
     ElfLoader, ElfLoaderErr, Flags, LoadableHeaders, RelocationEntry, RelocationType, VAddr,
@@ -11,6 +12,81 @@ use elfloader::{
 use riscv::mem::mapping::PhysMapping;
 use riscv::mem::{EntryFlags, PageTable, PAGESIZE};
 
+/// Get the ELF file which starts at the given address as a slice.
+///
+/// The size of the file is determined from its headers so that the bootloader doesn't need to know it and files of
+/// arbitrary size can be loaded.
+/// It is the largest end offset of the ELF header, the program and section header tables and all segments and
+/// sections that have content in the file.
+///
+/// # Safety
+/// `image` must point to a complete 64-bit little-endian ELF file.
+pub unsafe fn elf_file(image: *const u8) -> &'static [u8] {
+    let read_u16 = |offset: usize| ptr::read_unaligned(image.add(offset) as *const u16) as usize;
+    let read_u32 = |offset: usize| ptr::read_unaligned(image.add(offset) as *const u32) as usize;
+    let read_u64 = |offset: usize| ptr::read_unaligned(image.add(offset) as *const u64) as usize;
+
+    const SHT_NOBITS: usize = 8;
+    assert_eq!(
+        core::slice::from_raw_parts(image, 6),
+        b"\x7fELF\x02\x01",
+        "image at {image:p} is not a 64-bit little-endian elf file"
+    );
+
+    let ph_offset = read_u64(0x20);
+    let sh_offset = read_u64(0x28);
+    let header_size = read_u16(0x34);
+    let ph_entry_size = read_u16(0x36);
+    let ph_num = read_u16(0x38);
+    let sh_entry_size = read_u16(0x3a);
+    let sh_num = read_u16(0x3c);
+
+    let mut size = header_size
+        .max(ph_offset + ph_num * ph_entry_size)
+        .max(sh_offset + sh_num * sh_entry_size);
+    for i in 0..ph_num {
+        let header = ph_offset + i * ph_entry_size;
+        size = size.max(read_u64(header + 0x08) + read_u64(header + 0x20));
+    }
+    for i in 0..sh_num {
+        let header = sh_offset + i * sh_entry_size;
+        if read_u32(header + 0x04) != SHT_NOBITS {
+            size = size.max(read_u64(header + 0x18) + read_u64(header + 0x20));
+        }
+    }
+
+    core::slice::from_raw_parts(image, size)
+}
+
+/// Find the symbol table of the given ELF file and return it together with the string table that holds the
+/// symbol names.
+///
+/// Returns `None` if the file has been stripped.
+pub fn symbol_table(elf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let read_u16 =
+        |offset: usize| u16::from_le_bytes(elf[offset..offset + 2].try_into().unwrap()) as usize;
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(elf[offset..offset + 4].try_into().unwrap()) as usize;
+    let read_u64 =
+        |offset: usize| u64::from_le_bytes(elf[offset..offset + 8].try_into().unwrap()) as usize;
+
+    const SHT_SYMTAB: usize = 2;
+    let sh_offset = read_u64(0x28);
+    let sh_entry_size = read_u16(0x3a);
+    let sh_num = read_u16(0x3c);
+    let section = |index: usize| {
+        let header = sh_offset + index * sh_entry_size;
+        let offset = read_u64(header + 0x18);
+        &elf[offset..offset + read_u64(header + 0x20)]
+    };
+
+    let symtab =
+        (0..sh_num).find(|i| read_u32(sh_offset + i * sh_entry_size + 0x04) == SHT_SYMTAB)?;
+    // the sh_link field of a symbol table holds the index of its string table
+    let strtab = read_u32(sh_offset + symtab * sh_entry_size + 0x28);
+    Some((section(symtab), section(strtab)))
+}
+
 /// A simple [`ElfLoader`] implementation that is able to load the kernel binary given only an allocator
 pub struct KernelLoader<'alloc, A: BumpAllocator<'static>> {
     pub allocator: &'alloc A,
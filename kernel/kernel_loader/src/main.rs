@@ -13,22 +13,19 @@
 mod args;
 mod devtree;
 mod elfloader;
-mod user_args;
 mod virtmem;
 
 use crate::args::{CmdArgIter, LoaderArgs};
 use crate::devtree::DeviceInfo;
 use crate::elfloader::KernelLoader;
-use crate::user_args::UserArgs;
 use ::elfloader::ElfBinary;
 use allocators::bump_allocator::{BumpAllocator, ForwardBumpingAllocator};
-use allocators::{AllocInit, Allocator, Box};
-use core::alloc::Layout;
+use allocators::Box;
+use boot_info::{BootInfo, MemoryRegion};
+use cmdline::CmdLine;
 use core::arch::asm;
-use core::cmp::min;
 use core::panic::PanicInfo;
 use core::ptr;
-use device_tree::fdt::FlattenedDeviceTree;
 use klog::KernelLogger;
 use log::Level;
 use riscv::mem::mapping::PhysMapping;
@@ -44,6 +41,21 @@ fn panic_handler(info: &PanicInfo) -> ! {
     riscv::power::abort();
 }
 
+/// Copy the given data into memory that is allocated with the given alignment from the allocator and return where
+/// it has been placed
+fn copy_to_allocated(
+    data: &[u8],
+    align: usize,
+    allocator: &ForwardBumpingAllocator<'static>,
+) -> MemoryRegion {
+    let mut copy = Box::new_uninit_slice_with_alignment(data.len(), align, allocator).unwrap();
+    let copy = unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), copy.as_mut_ptr() as *mut u8, data.len());
+        copy.assume_init()
+    };
+    MemoryRegion::from_slice(copy.leak())
+}
+
 /// The entry point of the loader that is called by U-Boot
 #[no_mangle]
 pub extern "C" fn _start(argc: u32, argv: *const *const core::ffi::c_char) -> ! {
@@ -58,34 +70,50 @@ pub extern "C" fn _start(argc: u32, argv: *const *const core::ffi::c_char) -> !
     };
     log::debug!("device info = {:x?}", device_info);
 
-    // parse user specified arguments from device-tree and apply them
-    let user_args = match device_info.bootargs {
-        None => {
-            log::debug!("using default user arguments {:?}", UserArgs::default());
-            UserArgs::default()
-        }
-        Some(args) => {
-            let parsed_args = UserArgs::from_str(args);
-            log::debug!("got kernel arguments {args:?} -> {parsed_args:?}");
-            parsed_args
-        }
-    };
-    LOGGER.update_log_level(user_args.log_level);
-
-    // extract usable memory from device information
-    let (mem_start, mem_len) = device_info.usable_memory;
-    let mut mem_end = unsafe { mem_start.add(mem_len) };
+    // parse the kernel command line from the device tree and apply the parts that are relevant to the loader
+    let bootargs = device_info.bootargs.unwrap_or_default();
+    let cmdline = CmdLine::parse(bootargs)
+        .unwrap_or_else(|e| panic!("Could not parse the kernel command line {bootargs:?}: {e}"));
+    log::debug!("got kernel command line {bootargs:?} -> {cmdline:?}");
+    if cmdline.help {
+        klog::print!("{}", cmdline::HELP);
+        riscv::power::shutdown();
+    }
+    LOGGER.set_filter(cmdline.log_filter);
+
+    // u-boot places the device tree and kernel arguments at the very end of a memory bank and since we don't want
+    // to overwrite them, everything from the device tree up to the end of its bank is treated as reserved
+    let mut reserved = device_info.reserved_memory;
+    let fdt_page = args.phys_fdt_addr as usize & !(PAGESIZE - 1);
+    if let Some(bank) = device_info
+        .memory
+        .iter()
+        .find(|bank| bank.contains(fdt_page))
+    {
+        reserved
+            .push(MemoryRegion::new(fdt_page, bank.end() - fdt_page))
+            .expect("Could not reserve the device tree");
+    }
 
-    // u-boot places the device tree and kernel arguments at the very end of physical memory and since we don't want
-    // to overwrite it, we fake mem_end to end before it
-    mem_end = (min(mem_end, args.phys_fdt_addr.cast_mut()) as usize & !(4096 - 1)) as *mut u8;
+    // usable memory is all memory that is not reserved
+    let mut usable = device_info.memory;
+    for region in reserved.iter() {
+        usable
+            .subtract(region)
+            .expect("Physical memory is too fragmented to be described");
+    }
+    usable.shrink_to_alignment(PAGESIZE);
+    log::debug!("usable memory = {usable:x?}");
 
-    // create an allocator to allocate essential data structures from the end of usable memory
+    // create an allocator to allocate essential data structures from the largest usable region
+    let alloc_region = *usable.largest().expect("There is no usable memory");
+    let mem_start = alloc_region.start as *mut u8;
+    let mem_end = alloc_region.end() as *mut u8;
     log::debug!(
         "creating allocator for general purpose memory start = {:p} end = {:p} (len = {} bytes)",
         mem_start,
         mem_end,
-        mem_end as usize - mem_start as usize
+        alloc_region.len
     );
     let allocator = unsafe { ForwardBumpingAllocator::<'static>::new_raw(mem_start, mem_end) };
 
@@ -118,55 +146,87 @@ pub extern "C" fn _start(argc: u32, argv: *const *const core::ffi::c_char) -> !
         virtmem::use_pagetable(root_pagetable as *mut PageTable);
     }
 
-    // TODO Don't move device-tree since it is located in a memory area that is outside of our allocation pool and fine to be there. However not moving it currently panics the kernel :(
-    log::debug!("moving device tree");
-    let mut phys_dev_tree = Box::new_uninit_slice_with_alignment(
-        device_info.fdt.header.total_size as usize,
-        4096,
-        allocator,
-    )
-    .unwrap();
-    let phys_dev_tree = unsafe {
-        ptr::copy_nonoverlapping(
-            device_info.fdt.buf.as_ptr(),
-            phys_dev_tree.as_mut_ptr() as *mut u8,
-            device_info.fdt.header.total_size as usize,
-        );
-        phys_dev_tree.assume_init()
+    // the init binary is copied into allocated memory because the area where the bootloader placed it is handed to
+    // the kernel as free memory
+    log::debug!("moving init binary");
+    let phys_init = copy_to_allocated(args.get_init_bin(), 16, allocator);
+
+    // the initrd is moved for the same reason as the init binary but it is also mapped into init by the kernel which
+    // is why it needs to be page aligned
+    let phys_initrd = match args.get_initrd() {
+        None => MemoryRegion::empty(),
+        Some(initrd) => {
+            log::debug!("moving initrd");
+            copy_to_allocated(initrd, PAGESIZE, allocator)
+        }
     };
-    assert!(FlattenedDeviceTree::from_buffer(&phys_dev_tree).is_ok());
-
-    // waste a page or two so we get back to page alignment
-    // TODO: remove this when the kernel fixes alignment itself
-    let _ = allocator
-        .allocate(
-            Layout::from_size_align(PAGESIZE, PAGESIZE).unwrap(),
-            AllocInit::Uninitialized,
-        )
-        .unwrap();
 
-    // TODO: relocate and map argv
-    // TODO: add phys mem to argv
+    // the symbols of the kernel are copied as well so that the kernel can print symbolized backtraces
+    let (symtab, strtab) = match crate::elfloader::symbol_table(args.get_kernel_bin()) {
+        None => {
+            log::debug!("kernel binary has no symbol table");
+            (MemoryRegion::empty(), MemoryRegion::empty())
+        }
+        Some((symtab, strtab)) => {
+            log::debug!("moving kernel symbol table");
+            (
+                copy_to_allocated(symtab, 8, allocator),
+                copy_to_allocated(strtab, 1, allocator),
+            )
+        }
+    };
 
-    let phys_free_mem = allocator.steal_remaining_mem().as_mut_ptr_range();
+    // the device tree is left where the bootloader placed it because it lies outside of our allocation pool
+    let fdt = MemoryRegion::from_slice(device_info.fdt.buf);
+
+    // the boot info is allocated before handing all remaining memory to the kernel so that the kernel does not
+    // overwrite it
+    let boot_info = Box::new_uninit(allocator).unwrap().leak();
+    let remaining_mem = allocator.steal_remaining_mem().as_mut_ptr_range();
+    let free_start = (remaining_mem.start as usize).next_multiple_of(PAGESIZE);
+
+    // everything that the loader allocated (the kernels page tables and segments, init, the initrd and this boot info)
+    let loader_mem = MemoryRegion::new(mem_start as usize, free_start - mem_start as usize);
+    reserved
+        .push(loader_mem)
+        .expect("Could not reserve the memory used by the loader");
+    usable
+        .subtract(&loader_mem)
+        .expect("Physical memory is too fragmented to be described");
+
+    let boot_info = boot_info.write(BootInfo {
+        fdt,
+        memory: device_info.memory,
+        reserved,
+        usable,
+        init: phys_init,
+        initrd: phys_initrd,
+        cmdline: match device_info.bootargs {
+            None => MemoryRegion::empty(),
+            Some(bootargs) => MemoryRegion::from_slice(bootargs.as_bytes()),
+        },
+        symtab,
+        strtab,
+    });
+    log::debug!("boot info = {boot_info:x?}");
 
     log::info!("starting Kernel, entry point: {entry_point:#x}");
     unsafe {
         asm!(
             // set global-pointer to 0
             "mv gp, x0",
+            // clear the frame pointer so that backtraces in the kernel end at its entry point
+            "mv fp, x0",
+            // clear the thread pointer because the kernel sets it up once it knows on which hart it runs
+            "mv tp, x0",
             // setup stack pointer to show to kernel-stack
             "mv sp, {stack}",
             // jump to kernel entrypoint
             "jr {entry}",
             stack = in(reg) STACK_HIGH - 16,
             entry = in(reg) entry_point,
-            // explicitly set kernel arguments in known registers
-            in("a0") argc,
-            in("a1") argv,
-            in("a2") phys_dev_tree.leak().as_mut_ptr(),
-            in("a3") phys_free_mem.start,
-            in("a4") phys_free_mem.end,
+            // explicitly pass the boot info as kernel argument
+            in("a0") boot_info as *mut BootInfo,
             options(noreturn)
         )
     }
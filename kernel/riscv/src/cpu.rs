@@ -375,6 +375,23 @@ impl Sie {
     }
 }
 
+/// Disabling interrupts via sie masks all supervisor interrupts of the current hart regardless of whether they are
+/// globally enabled in [`SStatus`].
+unsafe impl ksync::InterruptControl for Sie {
+    type Saved = InterruptBits;
+
+    fn disable() -> Self::Saved {
+        // swap in an empty mask atomically so that no interrupt can fire between reading and clearing it
+        let saved: u64;
+        unsafe { asm!("csrrw {}, sie, zero", out(reg) saved) };
+        InterruptBits::from_bits_retain(saved)
+    }
+
+    fn restore(saved: Self::Saved) {
+        unsafe { Self::write(saved) };
+    }
+}
+
 bitflags! {
     /// When the `CY`, `TM`, `IR` or `HPMn` bit in the [`scounteren`](SCounterEn) register is clear, attempty to read
     /// the `cycle`, `time` `instrset` or `hpmcountern` register while executing in U-mode will cause an illegal
@@ -487,6 +504,28 @@ impl SScratch {
     }
 }
 
+/// Thread Pointer Register
+///
+/// This is not a control and status register but the general purpose register `tp` (`x4`) which the calling
+/// convention reserves for thread local storage.
+/// The kernel uses it to hold a pointer to the data of the hart that it is executing on.
+/// Its kernel value is saved and restored by `trap_frame_load` and the trap handler so it always holds the kernel
+/// value while kernel code is running, even if userspace modifies the register.
+#[allow(dead_code)]
+pub struct ThreadPointer {}
+
+impl ThreadPointer {
+    pub fn read() -> usize {
+        let res: usize;
+        unsafe { asm!("mv {}, tp", out(reg) res, options(nomem, nostack)) };
+        res
+    }
+
+    pub unsafe fn write(val: usize) {
+        unsafe { asm!("mv tp, {}", in(reg) val, options(nomem, nostack)) }
+    }
+}
+
 /// Supervisor Exception Program Counter
 ///
 /// A 64 bit read/write register.
@@ -796,4 +835,16 @@ impl Satp {
     pub unsafe fn write(val: SatpData) {
         Self::write_raw(val.into())
     }
+
+    /// Determine how many ASID bits (ASIDLEN) are implemented by the hardware.
+    ///
+    /// This writes all ones into the ASID field and reads back which bits stuck.
+    /// The previous value of the register is restored afterwards.
+    pub unsafe fn probe_asid_bits() -> u32 {
+        let old = Self::read_raw();
+        Self::write_raw(old | ((1 << 16) - 1) << 44);
+        let asid = Self::read().asid;
+        Self::write_raw(old);
+        asid.trailing_ones()
+    }
 }
@@ -0,0 +1,140 @@
+//! Bring-up of secondary harts via the SBI HSM (hart state management) extension and communication between harts
+
+use crate::pt::PAGESIZE;
+use core::arch::asm;
+use sbi::hsm::HartStatus;
+use sbi::{HartMask, SbiError};
+
+/// The information that a secondary hart needs to enter the kernel.
+///
+/// ## ABI
+/// The layout of this data structure is important because it is accessed via the assembly code in
+/// `./asm/hart_start.S`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct HartStartInfo {
+    /// The value that is written to the [`Satp`](crate::cpu::Satp) register to enable address translation
+    pub satp: u64,
+    /// The (virtual) address at which the harts kernel stack begins, i.e. its highest address
+    pub stack_top: usize,
+    /// The value that is put into the [`ThreadPointer`](crate::cpu::ThreadPointer) register
+    pub thread_pointer: usize,
+    /// The virtual address of `hart_start_virt`
+    trap_entry: usize,
+    /// The function that the hart calls once it runs with address translation enabled
+    pub entry: extern "C" fn(hart_id: usize) -> !,
+}
+
+extern "C" {
+    /// The code at which secondary harts start executing.
+    ///
+    /// This is implemented by `./asm/hart_start.S`.
+    fn hart_start_trampoline();
+
+    /// The code which secondary harts execute once address translation is enabled.
+    ///
+    /// This is implemented by `./asm/hart_start.S`.
+    fn hart_start_virt();
+}
+
+impl HartStartInfo {
+    pub fn new(
+        satp: u64,
+        stack_top: usize,
+        thread_pointer: usize,
+        entry: extern "C" fn(hart_id: usize) -> !,
+    ) -> Self {
+        Self {
+            satp,
+            stack_top,
+            thread_pointer,
+            trap_entry: hart_start_virt as usize,
+            entry,
+        }
+    }
+}
+
+/// The virtual address of the code at which secondary harts start executing.
+///
+/// Since harts are started with address translation disabled, its physical address needs to be passed to
+/// [`start_hart`].
+pub fn trampoline_addr() -> usize {
+    hart_start_trampoline as usize
+}
+
+/// Start the given hart so that it enters the kernel as described by a [`HartStartInfo`].
+///
+/// # Safety
+/// `phys_trampoline` must be the physical address of the code at [`trampoline_addr`] and `phys_start_info` must be
+/// the physical address of a valid [`HartStartInfo`] which stays valid until the hart has called its entry point.
+pub unsafe fn start_hart(
+    hart_id: usize,
+    phys_trampoline: usize,
+    phys_start_info: usize,
+) -> Result<(), SbiError> {
+    log::debug!("starting hart {hart_id}");
+    sbi::hsm::hart_start(hart_id, phys_trampoline, phys_start_info)
+}
+
+/// Whether the given hart is currently started, i.e. executing code
+pub fn is_started(hart_id: usize) -> Result<bool, SbiError> {
+    Ok(matches!(
+        sbi::hsm::hart_status(hart_id)?,
+        HartStatus::Started
+    ))
+}
+
+/// Build a hart mask which includes all of the given harts or `None` if there are none.
+///
+/// The hart ids must be given in ascending order because the mask is relative to the first one.
+fn hart_mask(hart_ids: impl IntoIterator<Item = usize>) -> Option<HartMask> {
+    let mut hart_ids = hart_ids.into_iter();
+    let first = hart_ids.next()?;
+    Some(
+        hart_ids.fold(HartMask::new(first).with(first), |mask, hart_id| {
+            mask.with(hart_id)
+        }),
+    )
+}
+
+/// Send an inter-processor interrupt to the given harts.
+///
+/// The interrupt is delivered to them as a supervisor software interrupt.
+/// The hart ids must be given in ascending order.
+pub fn send_ipi(hart_ids: impl IntoIterator<Item = usize>) -> Result<(), SbiError> {
+    match hart_mask(hart_ids) {
+        Some(mask) => sbi::ipi::send_ipi(mask),
+        None => Ok(()),
+    }
+}
+
+/// Flush the TLB entries of the virtual address range `start..start + size` that belong to the address space `asid`
+/// on the current hart.
+///
+/// A `start` and `size` of 0 flushes all entries of the address space.
+pub fn sfence_vma_asid(start: usize, size: usize, asid: usize) {
+    if start == 0 && size == 0 {
+        unsafe { asm!("sfence.vma x0, {}", in(reg) asid) };
+        return;
+    }
+    for vaddr in (start & !(PAGESIZE - 1)..start + size).step_by(PAGESIZE) {
+        unsafe { asm!("sfence.vma {}, {}", in(reg) vaddr, in(reg) asid) };
+    }
+}
+
+/// Instruct the given harts to flush the TLB entries of the virtual address range `start..start + size` that belong
+/// to the address space `asid`.
+///
+/// A `start` and `size` of 0 flushes all entries of the address space.
+/// The hart ids must be given in ascending order.
+pub fn remote_sfence_vma_asid(
+    hart_ids: impl IntoIterator<Item = usize>,
+    start: usize,
+    size: usize,
+    asid: usize,
+) -> Result<(), SbiError> {
+    match hart_mask(hart_ids) {
+        Some(mask) => sbi::rfence::remote_sfence_vma_asid(mask, start, size, asid),
+        None => Ok(()),
+    }
+}
@@ -8,6 +8,8 @@ pub mod pt;
 #[cfg(target_arch = "riscv64")]
 pub mod cpu;
 #[cfg(target_arch = "riscv64")]
+pub mod hart;
+#[cfg(target_arch = "riscv64")]
 pub mod power;
 #[cfg(target_arch = "riscv64")]
 pub mod timer;
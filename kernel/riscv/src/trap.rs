@@ -1,4 +1,5 @@
 use crate::cpu;
+use core::fmt;
 use cpu::{InterruptBits, SStatusFlags, StVecData, TrapEvent};
 use syscall_abi::RawSyscallReturn;
 
@@ -83,6 +84,33 @@ impl TrapFrame {
     }
 }
 
+/// The ABI names of the general purpose registers `x0`-`x31`
+const GP_REG_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Print the program counter and all general purpose registers by their ABI names, four per line
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pc   {:#018x}", self.start_pc)?;
+        for (i, (name, value)) in GP_REG_NAMES
+            .iter()
+            .zip(self.general_purpose_regs.iter())
+            .enumerate()
+        {
+            write!(f, "{name:<4} {value:#018x}")?;
+            match i % 4 {
+                3 if i + 1 < GP_REG_NAMES.len() => writeln!(f)?,
+                3 => {}
+                _ => write!(f, "  ")?,
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Context information about the last triggered trap of a [`TrapFrame`]
 #[repr(C)]
 #[derive(Debug)]
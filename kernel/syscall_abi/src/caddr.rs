@@ -1,3 +1,4 @@
+use crate::SyscallError;
 use core::fmt;
 use core::fmt::{Debug, Formatter};
 
@@ -78,11 +79,37 @@ use core::fmt::{Debug, Formatter};
 ///part 2   part 1
 /// ```
 ///
+/// # Guards
+///
+/// Every CSpace capability can additionally be given a guard which is a fixed value of a fixed number of bits.
+/// A part that selects a slot in a guarded CSpace must then consist of the guard followed by the slot number, i.e.
+/// `(guard << radix) | slot` where the radix is the number of bits that are needed to index all slots of the CSpace.
+/// Resolving an address fails if its guard bits don't match the guard of the CSpace.
+///
+/// For example, a CSpace with 4 slots and a guard of `0b11` that uses 2 bits requires the slot at index 1 to be
+/// addressed with the part `0b11_01`:
+///
+/// ```rust
+/// # use syscall_abi::CAddr;
+/// let addr = CAddr::new(0b11_01, 2 + 2);
+/// ```
+///
+/// # Resolution Depth
+///
+/// Syscalls which support it can be told to only resolve a certain number of bits of an address.
+/// Only the bits of the parts are counted for this, not the continuation bits between them.
+/// Resolution then stops after the part that ends at that depth even if the address has more parts, which allows
+/// addressing a CSpace capability itself with an address that points into it.
+/// [`CAddr::FULL_DEPTH`] resolves all parts of an address.
+///
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 #[repr(transparent)]
 pub struct CAddr(usize);
 
 impl CAddr {
+    /// A resolution depth which resolves all parts of an address
+    pub const FULL_DEPTH: usize = usize::MAX;
+
     /// Create a new CAddr with a single part that points to `value`.
     ///
     /// To construct more complex, hierarchical CAddrs use the builder via [`CAddr::builder()`](Self::builder).
@@ -184,6 +211,26 @@ impl fmt::Binary for CAddr {
     }
 }
 
+/// The error of a syscall that reports how far a [`CAddr`] could be resolved.
+///
+/// This is returned in place of a [`SyscallError`] by syscalls whose main purpose is resolving CAddrs.
+#[derive(Debug, Eq, PartialEq)]
+pub struct LookupError {
+    /// The reason why the syscall failed
+    pub error: SyscallError,
+    /// How many bits of the CAddr were resolved successfully before resolution failed.
+    ///
+    /// This is only meaningful if `error` is [`SyscallError::InvalidCAddr`] and counts the bits in the same way as
+    /// the [resolution depth](CAddr#resolution-depth).
+    pub depth: usize,
+}
+
+impl From<SyscallError> for LookupError {
+    fn from(error: SyscallError) -> Self {
+        Self { error, depth: 0 }
+    }
+}
+
 /// A helper struct to construct a [`CAddr`] from multiple parts
 pub struct CAddrBuilder<const MAX_PARTS: usize> {
     parts: [Option<(usize, usize)>; MAX_PARTS],
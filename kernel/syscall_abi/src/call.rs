@@ -7,7 +7,7 @@
 use crate::ipc_tag::IpcTag;
 use crate::{CAddr, NoValue, RawSyscallArgs, SyscallBinding, SyscallResult, SyscallReturnData};
 use core::fmt::{Debug, Formatter};
-use core::mem;
+use core::{cmp, mem};
 
 pub const NUM_DATA_REGS: usize = 5;
 
@@ -31,14 +31,21 @@ pub struct CallArgs {
 
 impl CallArgs {
     /// Return the capabilities that are included as arguments to this send call
+    ///
+    /// If the tag specifies more arguments than there are registers, only the ones that fit are returned.
     pub fn cap_args(&self) -> &[CAddr] {
-        let slice = &self.raw_args[..self.tag.ncaps() as usize];
+        let ncaps = cmp::min(self.tag.ncaps() as usize, NUM_DATA_REGS);
+        let slice = &self.raw_args[..ncaps];
         unsafe { mem::transmute::<&[usize], &[CAddr]>(slice) }
     }
 
     /// Return the inline data that is included as argument to this send call
+    ///
+    /// If the tag specifies more arguments than there are registers, only the ones that fit are returned.
     pub fn data_args(&self) -> &[usize] {
-        &self.raw_args[self.tag.ncaps() as usize..(self.tag.ncaps() + self.tag.nparams()) as usize]
+        let ncaps = cmp::min(self.tag.ncaps() as usize, NUM_DATA_REGS);
+        let nparams = self.tag.nparams() as usize;
+        &self.raw_args[ncaps..cmp::min(ncaps + nparams, NUM_DATA_REGS)]
     }
 
     /// The label of this IPC operation.
@@ -1,6 +1,7 @@
 //! Definitions for the `debug_log` syscall.
 
-use crate::{NoValue, RawSyscallArgs, SyscallBinding, SyscallResult};
+use crate::identify::CapabilityVariant;
+use crate::{CAddr, NoValue, RawSyscallArgs, SyscallBinding, SyscallResult, SyscallReturnData};
 use core::convert::Infallible;
 use core::{mem, slice};
 
@@ -150,3 +151,403 @@ impl SyscallBinding for DebugPutc {
     type CallArgs = DebugPutcArgs;
     type Return = SyscallResult<NoValue>;
 }
+
+// Definitions for the `debug_read_log` syscall
+
+/// How many bytes of the kernel log a single `debug_read_log` syscall returns at most
+pub const READ_LOG_CHUNK_SIZE: usize = 4 * USIZE2U8;
+
+/// Read recently emitted records from the kernels log buffer.
+///
+/// All records that the kernel ever logged form a stream of bytes in which each byte has a fixed position.
+/// The kernel only retains the most recent part of that stream so reading can start at a position which was already
+/// overwritten, in which case the returned position is larger than the requested one.
+pub struct DebugReadLog;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DebugReadLogArgs {
+    /// The position in the log stream from which to start reading
+    pub pos: usize,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DebugReadLogReturn {
+    /// The position in the log stream of the first returned byte
+    pub pos: usize,
+    /// How many bytes of `bytes` contain log data.
+    ///
+    /// A length of 0 means that there are no more records after `pos` yet.
+    pub len: usize,
+    /// The position after the last byte that is currently in the log.
+    ///
+    /// Since the log keeps growing while it is read, readers can use this to decide when to stop.
+    pub end: usize,
+    /// UTF-8 encoded log data which may begin or end in the middle of a character
+    pub bytes: [u8; READ_LOG_CHUNK_SIZE],
+}
+
+impl DebugReadLogReturn {
+    /// The log data that was returned
+    pub fn data(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl SyscallBinding for DebugReadLog {
+    const SYSCALL_NO: usize = 25;
+    type CallArgs = DebugReadLogArgs;
+    type Return = SyscallResult<DebugReadLogReturn>;
+}
+
+impl TryFrom<RawSyscallArgs> for DebugReadLogArgs {
+    type Error = Infallible;
+
+    fn try_from(args: RawSyscallArgs) -> Result<Self, Self::Error> {
+        Ok(Self { pos: args[0] })
+    }
+}
+
+impl Into<RawSyscallArgs> for DebugReadLogArgs {
+    fn into(self) -> RawSyscallArgs {
+        [self.pos, 0, 0, 0, 0, 0, 0]
+    }
+}
+
+impl TryFrom<SyscallReturnData> for DebugReadLogReturn {
+    type Error = ();
+
+    fn try_from(value: SyscallReturnData) -> Result<Self, Self::Error> {
+        let [pos, len, end, data @ ..] = value;
+        if len > READ_LOG_CHUNK_SIZE {
+            return Err(());
+        }
+        let mut bytes = [0; READ_LOG_CHUNK_SIZE];
+        for (chunk, reg) in bytes.chunks_exact_mut(USIZE2U8).zip(data) {
+            chunk.copy_from_slice(&reg.to_ne_bytes());
+        }
+        Ok(Self {
+            pos,
+            len,
+            end,
+            bytes,
+        })
+    }
+}
+
+impl Into<SyscallReturnData> for DebugReadLogReturn {
+    fn into(self) -> SyscallReturnData {
+        let mut result: SyscallReturnData = [0; 7];
+        result[0] = self.pos;
+        result[1] = self.len;
+        result[2] = self.end;
+        for (reg, chunk) in result[3..]
+            .iter_mut()
+            .zip(self.bytes.chunks_exact(USIZE2U8))
+        {
+            *reg = usize::from_ne_bytes(chunk.try_into().unwrap());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod read_log_test {
+    use crate::debug::{DebugReadLogReturn, READ_LOG_CHUNK_SIZE};
+    use crate::SyscallReturnData;
+
+    #[test]
+    fn test_return_roundtrip() {
+        // arrange
+        let mut bytes = [0; READ_LOG_CHUNK_SIZE];
+        bytes[..5].copy_from_slice(b"hello");
+        let ret = DebugReadLogReturn {
+            pos: 1234,
+            len: 5,
+            end: 2000,
+            bytes,
+        };
+
+        // act
+        let raw: SyscallReturnData = ret.into();
+        let ret = DebugReadLogReturn::try_from(raw).unwrap();
+
+        // assert
+        assert_eq!(ret.pos, 1234);
+        assert_eq!(ret.end, 2000);
+        assert_eq!(ret.data(), b"hello");
+    }
+
+    #[test]
+    fn test_invalid_length_is_rejected() {
+        let raw: SyscallReturnData = [0, READ_LOG_CHUNK_SIZE + 1, 0, 0, 0, 0, 0];
+        assert!(DebugReadLogReturn::try_from(raw).is_err());
+    }
+}
+
+// Definitions for the `debug_kstat` syscall
+
+/// The number of capability variants for which [`KernelStats`] holds counts
+pub const KSTAT_VARIANTS: usize = CapabilityVariant::Endpoint as usize + 1;
+
+/// How many capability counts of [`KernelStats`] are packed into a single register
+const COUNTS_PER_REG: usize = mem::size_of::<usize>() / mem::size_of::<u16>();
+
+/// Get statistics about the capabilities and the memory that the kernel manages
+pub struct DebugKstat;
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct KernelStats {
+    /// How many capabilities of each variant exist in the derivation tree, indexed by [`CapabilityVariant`].
+    ///
+    /// The counts are packed into registers which is why they saturate at `u16::MAX`.
+    pub caps: [u16; KSTAT_VARIANTS],
+    /// How many bytes can still be allocated from the kernels own allocator and from all memory capabilities
+    pub free_bytes: usize,
+    /// How many bytes the nodes of the derivation tree occupy
+    pub tree_bytes: usize,
+    /// How many cursors of the derivation tree are in use.
+    ///
+    /// This includes the cursor through which the kernel accesses the calling task while handling the syscall.
+    pub cursors_used: usize,
+    /// How many cursors the derivation tree has in total
+    pub cursors_total: usize,
+}
+
+impl KernelStats {
+    /// How many capabilities of the given variant exist
+    pub fn count(&self, variant: CapabilityVariant) -> u16 {
+        self.caps[variant as usize]
+    }
+}
+
+impl SyscallBinding for DebugKstat {
+    const SYSCALL_NO: usize = 26;
+    type CallArgs = NoValue;
+    type Return = SyscallResult<KernelStats>;
+}
+
+impl TryFrom<SyscallReturnData> for KernelStats {
+    type Error = Infallible;
+
+    fn try_from(value: SyscallReturnData) -> Result<Self, Self::Error> {
+        const COUNT_REGS: usize = KSTAT_VARIANTS.div_ceil(COUNTS_PER_REG);
+        let (counts, [free_bytes, tree_bytes, cursors_used, cursors_total]) =
+            value.split_at(COUNT_REGS)
+        else {
+            unreachable!()
+        };
+        let mut caps = [0; KSTAT_VARIANTS];
+        for (i, count) in caps.iter_mut().enumerate() {
+            *count = (counts[i / COUNTS_PER_REG] >> (16 * (i % COUNTS_PER_REG))) as u16;
+        }
+        Ok(Self {
+            caps,
+            free_bytes: *free_bytes,
+            tree_bytes: *tree_bytes,
+            cursors_used: *cursors_used,
+            cursors_total: *cursors_total,
+        })
+    }
+}
+
+impl Into<SyscallReturnData> for KernelStats {
+    fn into(self) -> SyscallReturnData {
+        const COUNT_REGS: usize = KSTAT_VARIANTS.div_ceil(COUNTS_PER_REG);
+        let mut result: SyscallReturnData = [0; 7];
+        for (i, count) in self.caps.iter().enumerate() {
+            result[i / COUNTS_PER_REG] |= (*count as usize) << (16 * (i % COUNTS_PER_REG));
+        }
+        result[COUNT_REGS] = self.free_bytes;
+        result[COUNT_REGS + 1] = self.tree_bytes;
+        result[COUNT_REGS + 2] = self.cursors_used;
+        result[COUNT_REGS + 3] = self.cursors_total;
+        result
+    }
+}
+
+#[cfg(test)]
+mod kstat_test {
+    use crate::debug::{KernelStats, KSTAT_VARIANTS};
+    use crate::identify::CapabilityVariant;
+    use crate::SyscallReturnData;
+
+    #[test]
+    fn test_return_roundtrip() {
+        // arrange
+        let mut caps = [0; KSTAT_VARIANTS];
+        for (i, count) in caps.iter_mut().enumerate() {
+            *count = i as u16 * 1000 + 1;
+        }
+        caps[CapabilityVariant::Endpoint as usize] = u16::MAX;
+        let stats = KernelStats {
+            caps,
+            free_bytes: 0x1234_5678,
+            tree_bytes: 4096,
+            cursors_used: 2,
+            cursors_total: 8,
+        };
+
+        // act
+        let raw: SyscallReturnData = stats.into();
+        let decoded = KernelStats::try_from(raw).unwrap();
+
+        // assert
+        assert_eq!(decoded, stats);
+        assert_eq!(decoded.count(CapabilityVariant::Memory), 1001);
+        assert_eq!(decoded.count(CapabilityVariant::Endpoint), u16::MAX);
+    }
+}
+
+// Definitions for the `debug_cap_tree` syscall
+
+/// The size of the page into which the `debug_cap_tree` syscall writes its entries
+const CAP_TREE_PAGE_SIZE: usize = 4096;
+
+/// How many entries the `debug_cap_tree` syscall writes into the page at most
+pub const CAP_TREE_ENTRIES_PER_PAGE: usize = CAP_TREE_PAGE_SIZE / mem::size_of::<CapTreeEntry>();
+
+/// Dump the kernels derivation tree into a page so that it can be inspected from userspace.
+///
+/// The nodes are written as an array of [`CapTreeEntry`] in the order in which they are stored in the tree, i.e.
+/// every node is followed by its copies and then by its derivations.
+/// Since the tree is usually larger than a single page, the syscall can be repeated with an increasing `start` index
+/// until all nodes have been written.
+/// The tree may change between two calls so the result of multiple calls is not necessarily consistent.
+pub struct DebugCapTree;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DebugCapTreeArgs {
+    /// The page capability into which the entries are written
+    pub page: CAddr,
+    /// The index of the first node in the tree that should be written
+    pub start: usize,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DebugCapTreeReturn {
+    /// How many entries were written into the page
+    pub written: usize,
+    /// How many nodes the derivation tree holds in total
+    pub total: usize,
+}
+
+/// A single node of the derivation tree as written by the `debug_cap_tree` syscall.
+///
+/// Nodes are identified by an opaque id which stays the same for as long as the node exists.
+/// An id of `0` never refers to a node.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct CapTreeEntry {
+    /// The id of this node
+    pub id: usize,
+    /// The id of the node from which this one is derived or `0` for the root of the tree
+    pub parent: usize,
+    /// How deep the node is located in the tree, starting with `1` for the root
+    pub depth: usize,
+    /// The [`CapabilityVariant`] of the node
+    pub variant: usize,
+    /// The id of the first node of the set of copies that this node belongs to or `0` if the node has no copies
+    pub copy_of: usize,
+    /// The id of the CSpace in which the node is stored or `0` if it is not stored in a CSpace but e.g. held by a task
+    pub cspace: usize,
+    /// The slot of [`cspace`](Self::cspace) in which the node is stored
+    pub slot: usize,
+}
+
+impl CapTreeEntry {
+    /// The [`CapabilityVariant`] of the node or `None` if the kernel reported an unknown one
+    pub fn variant(&self) -> Option<CapabilityVariant> {
+        CapabilityVariant::try_from(self.variant).ok()
+    }
+}
+
+impl SyscallBinding for DebugCapTree {
+    const SYSCALL_NO: usize = 27;
+    type CallArgs = DebugCapTreeArgs;
+    type Return = SyscallResult<DebugCapTreeReturn>;
+}
+
+impl TryFrom<RawSyscallArgs> for DebugCapTreeArgs {
+    type Error = Infallible;
+
+    fn try_from(args: RawSyscallArgs) -> Result<Self, Self::Error> {
+        Ok(Self {
+            page: args[0].into(),
+            start: args[1],
+        })
+    }
+}
+
+impl Into<RawSyscallArgs> for DebugCapTreeArgs {
+    fn into(self) -> RawSyscallArgs {
+        [self.page.into(), self.start, 0, 0, 0, 0, 0]
+    }
+}
+
+impl TryFrom<SyscallReturnData> for DebugCapTreeReturn {
+    type Error = ();
+
+    fn try_from(value: SyscallReturnData) -> Result<Self, Self::Error> {
+        let [written, total, ..] = value;
+        if written > CAP_TREE_ENTRIES_PER_PAGE || written > total {
+            return Err(());
+        }
+        Ok(Self { written, total })
+    }
+}
+
+impl Into<SyscallReturnData> for DebugCapTreeReturn {
+    fn into(self) -> SyscallReturnData {
+        [self.written, self.total, 0, 0, 0, 0, 0]
+    }
+}
+
+#[cfg(test)]
+mod cap_tree_test {
+    use crate::debug::{DebugCapTreeArgs, DebugCapTreeReturn, CAP_TREE_ENTRIES_PER_PAGE};
+    use crate::{CAddr, RawSyscallArgs, SyscallReturnData};
+
+    #[test]
+    fn test_args_roundtrip() {
+        // arrange
+        let args = DebugCapTreeArgs {
+            page: CAddr::new(5, 7),
+            start: 73,
+        };
+
+        // act
+        let raw: RawSyscallArgs = args.into();
+        let args = DebugCapTreeArgs::try_from(raw).unwrap();
+
+        // assert
+        assert_eq!(
+            args,
+            DebugCapTreeArgs {
+                page: CAddr::new(5, 7),
+                start: 73
+            }
+        );
+    }
+
+    #[test]
+    fn test_return_roundtrip() {
+        let ret = DebugCapTreeReturn {
+            written: 12,
+            total: 85,
+        };
+        let raw: SyscallReturnData = ret.into();
+        assert_eq!(
+            DebugCapTreeReturn::try_from(raw).unwrap(),
+            DebugCapTreeReturn {
+                written: 12,
+                total: 85
+            }
+        );
+    }
+
+    #[test]
+    fn test_more_entries_than_fit_into_page_are_rejected() {
+        let raw: SyscallReturnData = [CAP_TREE_ENTRIES_PER_PAGE + 1, 1000, 0, 0, 0, 0, 0];
+        assert!(DebugCapTreeReturn::try_from(raw).is_err());
+    }
+}
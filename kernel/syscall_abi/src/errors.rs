@@ -16,6 +16,8 @@ back_to_enum! {
         AlreadyMapped = 10,
         NoAsid = 11,
         NotFound = 12,
+        ObjectDestroyed = 13,
+        Busy = 14,
         ValueInvalid = usize::MAX - 2,
         UnknownError = usize::MAX - 1,
         UnknownSyscall = usize::MAX,
@@ -1,12 +1,10 @@
 //! Definitions for the `identify` syscall.
 
-use crate::{
-    back_to_enum, CAddr, RawSyscallArgs, SyscallBinding, SyscallResult, SyscallReturnData,
-};
+use crate::{back_to_enum, CAddr, LookupError, RawSyscallArgs, SyscallBinding, SyscallReturnData};
 use core::convert::Infallible;
 
 back_to_enum! {
-    #[derive(Debug, PartialEq, Eq)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
     #[repr(usize)]
     pub enum CapabilityVariant {
         Uninit = 0,
@@ -35,17 +33,19 @@ pub struct Identify;
 #[derive(Debug, Eq, PartialEq)]
 pub struct IdentifyArgs {
     pub caddr: CAddr,
+    /// How many bits of `caddr` are resolved, see [resolution depth](CAddr#resolution-depth)
+    pub depth: usize,
 }
 
 impl SyscallBinding for Identify {
     const SYSCALL_NO: usize = 3;
     type CallArgs = IdentifyArgs;
-    type Return = SyscallResult<CapabilityVariant>;
+    type Return = Result<CapabilityVariant, LookupError>;
 }
 
 impl From<IdentifyArgs> for RawSyscallArgs {
     fn from(args: IdentifyArgs) -> Self {
-        [args.caddr.into(), 0, 0, 0, 0, 0, 0]
+        [args.caddr.into(), args.depth, 0, 0, 0, 0, 0]
     }
 }
 
@@ -55,6 +55,7 @@ impl TryFrom<RawSyscallArgs> for IdentifyArgs {
     fn try_from(args: RawSyscallArgs) -> Result<Self, Self::Error> {
         Ok(Self {
             caddr: args[0].into(),
+            depth: args[1],
         })
     }
 }
@@ -72,3 +73,41 @@ impl TryFrom<SyscallReturnData> for CapabilityVariant {
         Self::try_from(value[0])
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::identify::{CapabilityVariant, Identify};
+    use crate::{
+        FromRawSysResponse, IntoRawSysRepsonse, LookupError, SyscallBinding, SyscallError,
+    };
+
+    type Return = <Identify as SyscallBinding>::Return;
+
+    #[test]
+    fn test_variant_roundtrip() {
+        let raw = Return::Ok(CapabilityVariant::Page).into_response();
+        assert_eq!(Return::from_response(raw), Ok(CapabilityVariant::Page));
+    }
+
+    #[test]
+    fn test_error_keeps_depth() {
+        // arrange
+        let error = LookupError {
+            error: SyscallError::InvalidCAddr,
+            depth: 9,
+        };
+
+        // act
+        let raw = Return::Err(error).into_response();
+
+        // assert
+        assert_eq!(raw[0], SyscallError::InvalidCAddr as usize);
+        assert_eq!(
+            Return::from_response(raw),
+            Err(LookupError {
+                error: SyscallError::InvalidCAddr,
+                depth: 9
+            })
+        );
+    }
+}
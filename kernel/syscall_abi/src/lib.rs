@@ -29,6 +29,10 @@
 //! | [exit] | *22* |
 //! | [call] | *23* |
 //! | [receive] | *24* |
+//! | [debug_read_log](debug::DebugReadLog) | *25* | [DebugReadLogArgs](debug::DebugReadLogArgs) | [DebugReadLogReturn](debug::DebugReadLogReturn) | Read recently emitted records from the kernel log buffer |
+//! | [debug_kstat](debug::DebugKstat) | *26* | [NoValue](NoValue) | [KernelStats](debug::KernelStats) | Get statistics about capabilities and kernel memory |
+//! | [debug_cap_tree](debug::DebugCapTree) | *27* | [DebugCapTreeArgs](debug::DebugCapTreeArgs) | [DebugCapTreeReturn](debug::DebugCapTreeReturn) | Dump the derivation tree into a page |
+//! | [wait_any](wait_any::WaitAny) | *28* | [WaitAnyArgs](wait_any::WaitAnyArgs) | [WaitAnyReturn](wait_any::WaitAnyReturn) | Wait until one of several notifications or endpoints is ready |
 //!
 //! # Calling Conventions
 //!
@@ -66,12 +70,13 @@ pub mod send;
 pub mod system_reset;
 mod traits;
 mod utils;
+pub mod wait_any;
 pub mod wait_on;
 pub mod r#yield;
 pub mod yield_to;
 
 use bitflags::bitflags;
-pub use caddr::CAddr;
+pub use caddr::{CAddr, LookupError};
 pub use errors::SyscallError;
 pub use ipc_tag::IpcTag;
 pub use traits::*;
@@ -35,14 +35,38 @@ impl From<ReceiveArgs> for RawSyscallArgs {
 pub struct ReceiveReturn {
     pub tag: IpcTag,
     pub raw_args: [usize; NUM_DATA_REGS],
+
+    /// The value of the notification that is bound to the receiving task.
+    ///
+    /// A value of `0` indicates that a message was received from a sender.
+    /// Any other value indicates that the receive was completed by a signal of the bound notification instead, in
+    /// which case `tag` and `raw_args` carry no meaning.
+    pub notification: usize,
+}
+
+impl ReceiveReturn {
+    /// Construct a return value which reports a signal of the receivers bound notification instead of a message
+    pub fn from_notification(value: usize) -> Self {
+        Self {
+            tag: IpcTag::from_raw(0),
+            raw_args: [0; NUM_DATA_REGS],
+            notification: value,
+        }
+    }
+
+    /// Whether the receive was completed by a signal of the bound notification instead of a message from a sender
+    pub fn is_notification(&self) -> bool {
+        self.notification != 0
+    }
 }
 
 impl From<RawSyscallArgs> for ReceiveReturn {
     fn from(value: RawSyscallArgs) -> Self {
-        let [tag, a0, a1, a2, a3, a4, _] = value;
+        let [tag, a0, a1, a2, a3, a4, notification] = value;
         Self {
             tag: IpcTag::from_raw(tag),
             raw_args: [a0, a1, a2, a3, a4],
+            notification,
         }
     }
 }
@@ -50,6 +74,6 @@ impl From<RawSyscallArgs> for ReceiveReturn {
 impl Into<RawSyscallArgs> for ReceiveReturn {
     fn into(self) -> RawSyscallArgs {
         let [a0, a1, a2, a3, a4] = self.raw_args;
-        [self.tag.as_raw(), a0, a1, a2, a3, a4, 0]
+        [self.tag.as_raw(), a0, a1, a2, a3, a4, self.notification]
     }
 }
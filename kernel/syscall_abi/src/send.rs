@@ -7,7 +7,7 @@
 use crate::ipc_tag::IpcTag;
 use crate::{CAddr, NoValue, RawSyscallArgs, SyscallBinding, SyscallResult};
 use core::fmt::{Debug, Formatter};
-use core::mem;
+use core::{cmp, mem};
 
 pub const NUM_DATA_REGS: usize = 5;
 
@@ -31,14 +31,21 @@ pub struct SendArgs {
 
 impl SendArgs {
     /// Return the capabilities that are included as arguments to this send call
+    ///
+    /// If the tag specifies more arguments than there are registers, only the ones that fit are returned.
     pub fn cap_args(&self) -> &[CAddr] {
-        let slice = &self.raw_args[..self.tag.ncaps() as usize];
+        let ncaps = cmp::min(self.tag.ncaps() as usize, NUM_DATA_REGS);
+        let slice = &self.raw_args[..ncaps];
         unsafe { mem::transmute::<&[usize], &[CAddr]>(slice) }
     }
 
     /// Return the inline data that is included as argument to this send call
+    ///
+    /// If the tag specifies more arguments than there are registers, only the ones that fit are returned.
     pub fn data_args(&self) -> &[usize] {
-        &self.raw_args[self.tag.ncaps() as usize..(self.tag.ncaps() + self.tag.nparams()) as usize]
+        let ncaps = cmp::min(self.tag.ncaps() as usize, NUM_DATA_REGS);
+        let nparams = self.tag.nparams() as usize;
+        &self.raw_args[ncaps..cmp::min(ncaps + nparams, NUM_DATA_REGS)]
     }
 
     /// The label of this IPC operation.
@@ -89,3 +96,36 @@ impl From<SendArgs> for RawSyscallArgs {
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::ipc_tag::IpcTag;
+    use crate::send::SendArgs;
+    use crate::RawSyscallArgs;
+
+    #[test]
+    fn test_args_within_registers() {
+        // arrange
+        let raw_args: RawSyscallArgs = [0, IpcTag::from_parts(1, 2, 3).as_raw(), 1, 2, 3, 4, 5];
+
+        // act
+        let args = SendArgs::from(raw_args);
+
+        // assert
+        assert_eq!(args.cap_args(), &[1.into(), 2.into()]);
+        assert_eq!(args.data_args(), &[3, 4, 5]);
+    }
+
+    #[test]
+    fn test_args_exceeding_registers_are_clamped() {
+        // arrange
+        let raw_args: RawSyscallArgs = [0, IpcTag::from_parts(1, 7, 7).as_raw(), 1, 2, 3, 4, 5];
+
+        // act
+        let args = SendArgs::from(raw_args);
+
+        // assert
+        assert_eq!(args.cap_args().len(), 5);
+        assert!(args.data_args().is_empty());
+    }
+}
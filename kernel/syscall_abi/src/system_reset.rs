@@ -46,20 +46,22 @@ impl From<SystemResetArgs> for RawSyscallArgs {
     }
 }
 
-impl From<RawSyscallArgs> for SystemResetArgs {
-    fn from(value: RawSyscallArgs) -> Self {
-        Self {
+impl TryFrom<RawSyscallArgs> for SystemResetArgs {
+    type Error = ();
+
+    fn try_from(value: RawSyscallArgs) -> Result<Self, Self::Error> {
+        Ok(Self {
             typ: match value[0] {
                 0 => ResetType::Shutdown,
                 1 => ResetType::ColdReboot,
                 2 => ResetType::WarmReboot,
-                _ => panic!(),
+                _ => return Err(()),
             },
             reason: match value[1] {
                 0 => ResetReason::NoReason,
                 1 => ResetReason::SystemFailure,
-                _ => panic!(),
+                _ => return Err(()),
             },
-        }
+        })
     }
 }
@@ -1,4 +1,5 @@
 use crate::errors::SyscallError;
+use crate::LookupError;
 use core::fmt::Debug;
 
 /// A trait for binding a syscall number to its specific argument and return type.
@@ -31,7 +32,7 @@ pub type RawSyscallReturn = [usize; 8];
 pub type SyscallReturnData = [usize; 7];
 
 /// A type that is used when a syscall requires no arguments or returns nothing.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct NoValue;
 
 impl From<SyscallReturnData> for NoValue {
@@ -102,3 +103,26 @@ where
         }
     }
 }
+
+impl<T> IntoRawSysRepsonse for Result<T, LookupError>
+where
+    T: Into<SyscallReturnData>,
+{
+    fn into_response(self) -> RawSyscallReturn {
+        match self {
+            Ok(v) => Ok::<T, SyscallError>(v).into_response(),
+            Err(e) => [e.error as usize, e.depth, 0, 0, 0, 0, 0, 0],
+        }
+    }
+}
+
+impl<T> FromRawSysResponse for Result<T, LookupError>
+where
+    T: TryFrom<SyscallReturnData>,
+{
+    fn from_response(raw: RawSyscallReturn) -> Self {
+        // the depth is only part of error responses
+        let depth = if raw[0] == 0 { 0 } else { raw[1] };
+        SyscallResult::<T>::from_response(raw).map_err(|error| LookupError { error, depth })
+    }
+}
@@ -0,0 +1,126 @@
+//! Definitions for the `wait_any` syscall.
+//!
+//! `wait_any` blocks until one of several notifications or endpoints becomes ready and reports which one it was.
+//! A notification is ready once it is signaled, in which case its value is taken like `wait_on` does.
+//! An endpoint is ready once a sender is blocked on it so that a following `receive` completes immediately.
+
+use crate::{CAddr, RawSyscallArgs, SyscallBinding, SyscallResult, SyscallReturnData};
+
+/// How many objects can be waited on at once
+pub const MAX_WAIT_OBJECTS: usize = 6;
+
+pub struct WaitAny;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct WaitAnyArgs {
+    objects: [CAddr; MAX_WAIT_OBJECTS],
+    len: usize,
+}
+
+impl WaitAnyArgs {
+    /// Wait on the given notifications and endpoints.
+    ///
+    /// Returns `None` if no objects or more than [`MAX_WAIT_OBJECTS`] are given.
+    pub fn new(objects: &[CAddr]) -> Option<Self> {
+        if objects.is_empty() || objects.len() > MAX_WAIT_OBJECTS {
+            return None;
+        }
+        let mut args = Self {
+            objects: [CAddr::from_raw(0); MAX_WAIT_OBJECTS],
+            len: objects.len(),
+        };
+        args.objects[..objects.len()].copy_from_slice(objects);
+        Some(args)
+    }
+
+    /// The notifications and endpoints that are waited on
+    pub fn objects(&self) -> &[CAddr] {
+        &self.objects[..self.len]
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WaitAnyReturn {
+    /// The index of the object that became ready in the [`objects`](WaitAnyArgs::objects) that were waited on
+    pub index: usize,
+}
+
+impl SyscallBinding for WaitAny {
+    const SYSCALL_NO: usize = 28;
+    type CallArgs = WaitAnyArgs;
+    type Return = SyscallResult<WaitAnyReturn>;
+}
+
+impl TryFrom<RawSyscallArgs> for WaitAnyArgs {
+    type Error = ();
+
+    fn try_from(value: RawSyscallArgs) -> Result<Self, Self::Error> {
+        let [len, objects @ ..] = value;
+        if len == 0 || len > MAX_WAIT_OBJECTS {
+            return Err(());
+        }
+        Ok(Self {
+            objects: objects.map(CAddr::from),
+            len,
+        })
+    }
+}
+
+impl From<WaitAnyArgs> for RawSyscallArgs {
+    fn from(value: WaitAnyArgs) -> Self {
+        let [o0, o1, o2, o3, o4, o5] = value.objects.map(|caddr| caddr.raw());
+        [value.len, o0, o1, o2, o3, o4, o5]
+    }
+}
+
+impl Into<SyscallReturnData> for WaitAnyReturn {
+    fn into(self) -> SyscallReturnData {
+        [self.index, 0, 0, 0, 0, 0, 0]
+    }
+}
+
+impl From<SyscallReturnData> for WaitAnyReturn {
+    fn from(value: SyscallReturnData) -> Self {
+        Self { index: value[0] }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wait_any::{WaitAnyArgs, WaitAnyReturn, MAX_WAIT_OBJECTS};
+    use crate::{CAddr, FromRawSysResponse, IntoRawSysRepsonse, RawSyscallArgs, SyscallResult};
+
+    #[test]
+    fn test_args_roundtrip() {
+        // arrange
+        let objects = [CAddr::from_raw(3), CAddr::from_raw(7)];
+
+        // act
+        let raw: RawSyscallArgs = WaitAnyArgs::new(&objects).unwrap().into();
+        let args = WaitAnyArgs::try_from(raw).unwrap();
+
+        // assert
+        assert_eq!(raw[0], 2);
+        assert_eq!(args.objects(), &objects);
+    }
+
+    #[test]
+    fn test_invalid_object_count() {
+        assert_eq!(WaitAnyArgs::new(&[]), None);
+        assert_eq!(
+            WaitAnyArgs::new(&[CAddr::from_raw(1); MAX_WAIT_OBJECTS + 1]),
+            None
+        );
+        assert!(WaitAnyArgs::try_from([0, 1, 2, 3, 4, 5, 6]).is_err());
+        assert!(WaitAnyArgs::try_from([MAX_WAIT_OBJECTS + 1, 1, 2, 3, 4, 5, 6]).is_err());
+    }
+
+    #[test]
+    fn test_return_roundtrip() {
+        let raw = SyscallResult::Ok(WaitAnyReturn { index: 4 }).into_response();
+        assert_eq!(
+            SyscallResult::<WaitAnyReturn>::from_response(raw),
+            Ok(WaitAnyReturn { index: 4 })
+        );
+    }
+}
@@ -1,27 +1,89 @@
 #!/bin/bash
+#
+# Usage: run_kernel.sh [--test <success-pattern>] [--init <init>] [--initrd <initrd>] [--memory-banks <n>] <kernel-loader>
+#                      <kernel> [bootargs]
+#
+# The init program defaults to the release build of the init crate and the initial ram filesystem defaults to the
+# initrd.cpio archive that is built by `make initrd.cpio`. If the initrd does not exist, the system is booted without it.
+#
+# With --memory-banks, the 1G of memory is split into <n> equally sized banks which qemu describes as separate numa
+# nodes.
+#
+# With --test, qemu is run headless and the script exits successfully only if qemu exits with status 0 before the
+# timeout of $TEST_TIMEOUT seconds (default 120) is reached and its serial output contains <success-pattern>.
 
 D=$(realpath $(dirname $0))
 
+TEST_PATTERN=""
+INIT=$D/target/riscv64imac-unknown-none-elf/release/init
+INITRD=$D/initrd.cpio
+MEMORY_BANKS=1
+while [[ "$1" == --* ]]; do
+  case "$1" in
+    --test) TEST_PATTERN=$2 ;;
+    --init) INIT=$2 ;;
+    --initrd) INITRD=$2 ;;
+    --memory-banks) MEMORY_BANKS=$2 ;;
+    *) echo "unknown option $1"; exit 1 ;;
+  esac
+  shift 2
+done
+
 KERNEL_LOADER=$1
 TARGET=$2
 shift 2
 
-if [[ ! -z "$@" ]]; then
-  BOOTARGS="-append $@"
-else
-  BOOTARGS=""
+# all remaining arguments form the kernel command line, see `cargo run -- help`
+BOOTARGS=()
+if [[ $# -gt 0 ]]; then
+  BOOTARGS=(-append "$*")
+fi
+
+QEMU_ARGS=(
+    -m 1G
+    -smp 4
+    -machine virt
+    -bios default
+    -serial stdio
+    -kernel u-boot/u-boot.bin
+    -fsdev local,security_model=mapped-xattr,id=guest_root,readonly=on,path=$D/guest_root
+    -device virtio-9p-device,fsdev=guest_root,mount_tag=/
+    -device loader,addr=0x84000000,force-raw=on,file="$KERNEL_LOADER"
+    -device loader,addr=0x84800000,force-raw=on,file="$TARGET"
+    -device loader,addr=0x8d000000,force-raw=on,file="$INIT"
+)
+
+if [[ $MEMORY_BANKS -gt 1 ]]; then
+  for ((i = 0; i < MEMORY_BANKS; i++)); do
+    QEMU_ARGS+=(-object memory-backend-ram,id=mem$i,size=$((1024 / MEMORY_BANKS))M -numa node,memdev=mem$i)
+  done
+fi
+
+if [[ -f "$INITRD" ]]; then
+  QEMU_ARGS+=(-device loader,addr=0x8e000000,force-raw=on,file="$INITRD")
+fi
+
+if [[ ! -z "$TEST_PATTERN" ]]; then
+  LOG=$(mktemp)
+  trap 'rm -f "$LOG"' EXIT
+  timeout ${TEST_TIMEOUT:-120} qemu-system-riscv64 "${QEMU_ARGS[@]}" -display none -monitor none "${BOOTARGS[@]}" < /dev/null | tee $LOG
+  STATUS=${PIPESTATUS[0]}
+  if [[ $STATUS != 0 ]]; then
+    echo "test failed: qemu exited with status $STATUS"
+    exit 1
+  elif grep -q "Kernel Panic" $LOG; then
+    echo "test failed: the kernel panicked"
+    exit 1
+  elif ! grep -q "$TEST_PATTERN" $LOG; then
+    echo "test failed: serial output did not contain \"$TEST_PATTERN\""
+    exit 1
+  fi
+  echo "test succeeded"
+  exit 0
 fi
 
-qemu-system-riscv64 -s -m 1G \
-    -machine virt \
-    -bios default \
-    -serial stdio \
-    -kernel u-boot/u-boot.bin \
-    -fsdev local,security_model=mapped-xattr,id=guest_root,readonly=on,path=$D/guest_root \
-    -device virtio-9p-device,fsdev=guest_root,mount_tag=/ \
+qemu-system-riscv64 -s "${QEMU_ARGS[@]}" \
     -device virtio-gpu-device,xres=640,yres=480 \
     -device virtio-keyboard-device \
-    -device loader,addr=0x84000000,force-raw=on,file="$KERNEL_LOADER" \
-    -device loader,addr=0x84800000,force-raw=on,file="$TARGET" \
-    $BOOTARGS
+    "${BOOTARGS[@]}"
 #    -d guest_errors,trace:cpu_halt,trace:cpu_unhalt,trace:virtio_irq,trace:virtio_set_status,trace:virtio_notify,trace:virtio_queue_notify,trace:virtio_gpu_cmd_res_back_attach,trace:virtio_gpu_cmd_get_display_info,trace:virtio_gpu_cmd_res_create_2d \
@@ -4,14 +4,18 @@ set -euo pipefail
 
 # Test that the project builds using the default target
 
-cargo build --release -p init # TODO: remove this once the kernel is configured to load init dynamically
+cargo build --release -p init
 cargo build --release -p kernel_loader # TODO: remove once kernel modules are configured dynamically
 cargo build
 
 
 # Test support crates using host architecture
 cargo test --target x86_64-unknown-linux-gnu -p allocators
+cargo test --target x86_64-unknown-linux-gnu -p boot_info
+cargo test --target x86_64-unknown-linux-gnu -p cmdline
 cargo test --target x86_64-unknown-linux-gnu -p derivation_tree
+cargo test --target x86_64-unknown-linux-gnu -p initrd
+cargo test --target x86_64-unknown-linux-gnu -p klog
 cargo test --target x86_64-unknown-linux-gnu -p ksync
 cargo test --target x86_64-unknown-linux-gnu -p regs
 
@@ -1,5 +1,5 @@
-use crate::tree::cursors::CursorSet;
-use crate::tree::{CursorHandle, NextNodeIterator, OutOfCursorsError, TreeNodeOps};
+use crate::tree::cursors::{CursorSet, SET_SIZE};
+use crate::tree::{CursorData, CursorHandle, NextNodeIterator, OutOfCursorsError, TreeNodeOps};
 use core::mem::MaybeUninit;
 use core::ptr::{addr_of, addr_of_mut};
 
@@ -62,6 +62,21 @@ impl<T: TreeNodeOps> DerivationTree<T> {
         // TODO Fix const2mut cast
         NextNodeIterator::from_starting_node(addr_of!(self.root_node) as *mut _)
     }
+
+    /// Get the state of all cursors which are currently in use.
+    ///
+    /// This is meant for diagnostics like finding out which nodes were accessed when something went wrong.
+    pub fn used_cursors(&self) -> impl Iterator<Item = CursorData<T>> + '_ {
+        self.cursors
+            .cursor_iter()
+            .map(|cursor| cursor.get())
+            .filter(|cursor| !matches!(cursor, CursorData::Free))
+    }
+
+    /// How many cursors the tree has which limits how many nodes can be accessed at the same time
+    pub fn cursor_capacity(&self) -> usize {
+        SET_SIZE
+    }
 }
 
 #[cfg(test)]
@@ -226,6 +241,33 @@ mod test {
         assert!(node2.is_err());
     }
 
+    #[test]
+    fn test_used_cursors() {
+        // arrange
+        let mut loc = Box::new(MaybeUninit::uninit());
+        let tree = unsafe {
+            DerivationTree::init_with_root_value(&mut loc, TestNode::new(42));
+            assume_init_box(loc)
+        };
+        let root = &tree.root_node as *const _ as *mut TestNode;
+
+        // act
+        let mut cursor1 = tree.get_root_cursor().unwrap();
+        let cursor2 = tree.get_root_cursor().unwrap();
+        let node1 = cursor1.get_shared().unwrap();
+
+        // assert
+        let used: std::vec::Vec<_> = tree.used_cursors().collect();
+        assert_eq!(used.len(), 2);
+        assert!(tree.cursor_capacity() >= used.len());
+        assert!(matches!(used[0], CursorData::SharedRef(node) if node == root));
+        assert!(matches!(used[1], CursorData::Inactive(node) if node == root));
+        drop(node1);
+        drop(cursor1);
+        drop(cursor2);
+        assert_eq!(tree.used_cursors().count(), 0);
+    }
+
     #[test]
     fn test_cursor_duplication() {
         // arrange
@@ -265,6 +307,10 @@ mod test {
         assert!(!new_node.tree_data.prev.get().is_null());
         assert!(new_node.tree_data.next.get().is_null());
         assert!(new_node.is_final_copy());
+        assert!(new_node.is_first_copy());
+        assert!(tree.root_node.is_first_copy());
+        assert_eq!(unsafe { new_node.get_first_copy() }, &new_node as *const _);
+        assert_eq!(unsafe { new_node.get_next_copy() }, None);
         assert!(!tree.root_node.has_derivations());
     }
 
@@ -285,7 +331,8 @@ mod test {
 
         // assert
         assert!(!new_node.tree_data.is_not_in_tree());
-        assert_eq!(new_node.tree_data.depth.get(), 2);
+        assert_eq!(new_node.tree_data.depth(), 2);
+        assert_eq!(new_node.get_parent(), &tree.root_node as *const _);
         assert!(!new_node.tree_data.prev.get().is_null());
         assert!(new_node.tree_data.next.get().is_null());
         assert!(tree.root_node.has_derivations());
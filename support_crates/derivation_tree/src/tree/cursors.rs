@@ -7,7 +7,7 @@ use core::ops::{Deref, DerefMut};
 use core::ptr::addr_of_mut;
 use core::{mem, ptr};
 
-const SET_SIZE: usize = 8;
+pub(crate) const SET_SIZE: usize = 8;
 
 pub struct CursorSet<T: TreeNodeOps> {
     cursors: [Cursor<T>; SET_SIZE],
@@ -86,6 +86,7 @@ impl<T: TreeNodeOps> CursorSet<T> {
 
 pub type Cursor<T> = Cell<CursorData<T>>;
 
+/// The state of a cursor and the node it points to
 #[derive(Debug)]
 pub enum CursorData<T: TreeNodeOps> {
     /// The cursor is currently unused and can be given out to consumers.
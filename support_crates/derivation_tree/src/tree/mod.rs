@@ -10,6 +10,8 @@ mod iterator;
 mod node;
 
 pub use collection::DerivationTree;
-pub use cursors::{AliasingError, CursorHandle, CursorRef, CursorRefMut, OutOfCursorsError};
+pub use cursors::{
+    AliasingError, CursorData, CursorHandle, CursorRef, CursorRefMut, OutOfCursorsError,
+};
 pub use iterator::NextNodeIterator;
 pub use node::{TreeNodeData, TreeNodeOps};
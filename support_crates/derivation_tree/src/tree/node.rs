@@ -127,6 +127,56 @@ pub trait TreeNodeOps: Sized + Correspondence {
         current_ptr
     }
 
+    /// Get a pointer to another copy of `self` or `None` if `self` is the final copy
+    ///
+    /// # Safety
+    /// You are not allowed to drop any node while holding the returned pointer.
+    unsafe fn get_other_copy(&self) -> Option<*mut Self> {
+        let tree_data = self.get_tree_data();
+
+        if let Some(prev_node) = unsafe { tree_data.prev.get().as_ref() } {
+            if prev_node.corresponds_to(self) {
+                return Some(tree_data.prev.get());
+            }
+        }
+
+        if let Some(next_node) = unsafe { tree_data.next.get().as_ref() } {
+            if next_node.corresponds_to(self) {
+                return Some(tree_data.next.get());
+            }
+        }
+
+        None
+    }
+
+    /// Get a pointer to the copy of `self` that directly follows it in the tree or `None` if `self` is the last copy
+    ///
+    /// # Safety
+    /// You are not allowed to drop any node while holding the returned pointer.
+    unsafe fn get_next_copy(&self) -> Option<*mut Self> {
+        match unsafe { self.get_tree_data().next.get().as_ref() } {
+            Some(next_node) if next_node.corresponds_to(self) => {
+                Some(self.get_tree_data().next.get())
+            }
+            _ => None,
+        }
+    }
+
+    /// Get a pointer to the first copy of `self` which is `self` if no other copy precedes it in the tree
+    ///
+    /// # Safety
+    /// You are not allowed to drop any node while holding the returned pointer.
+    unsafe fn get_first_copy(&self) -> *const Self {
+        let mut current: &Self = self;
+        while let Some(prev_node) = unsafe { current.get_tree_data().prev.get().as_ref() } {
+            if !prev_node.corresponds_to(self) {
+                break;
+            }
+            current = prev_node;
+        }
+        current
+    }
+
     /// Whether this node is the last copy of the contained value
     fn is_final_copy(&self) -> bool {
         let tree_data = self.get_tree_data();
@@ -146,6 +196,16 @@ pub trait TreeNodeOps: Sized + Correspondence {
         true
     }
 
+    /// Whether no other copy of the contained value precedes this node in the tree.
+    ///
+    /// Exactly one node of each set of copies is the first copy which can be used to visit every value only once.
+    fn is_first_copy(&self) -> bool {
+        match unsafe { self.get_tree_data().prev.get().as_ref() } {
+            Some(prev_node) => !prev_node.corresponds_to(self),
+            None => true,
+        }
+    }
+
     /// Insert a new node with *copy* ordering.
     ///
     /// Essentially, the new node will be inserted directly after `self` and on the same depth but see the struct
@@ -288,6 +348,11 @@ impl<T: TreeNodeOps> TreeNodeData<T> {
         self.depth.set(0);
     }
 
+    /// How deep the node is located in the tree with the root node having a depth of 1
+    pub fn depth(&self) -> usize {
+        self.depth.get()
+    }
+
     /// Whether this node is currently part of a derivation tree
     pub fn is_not_in_tree(&self) -> bool {
         self.cursors.get().is_null()
@@ -0,0 +1,85 @@
+//! Filtering of log records based on their target
+
+use log::LevelFilter;
+
+/// How many target specific levels a [`LogFilter`] can hold
+pub const MAX_TARGET_FILTERS: usize = 16;
+
+/// A description of which log records should be emitted.
+///
+/// The filter consists of a default level and a list of target specific levels which override it.
+/// A target level applies to all records whose target is either equal to it or one of its submodules, so a level for
+/// `kernel::caps` also applies to records from `kernel::caps::memory` but not to `kernel::capsule`.
+/// If several target levels apply, the one with the longest target wins.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LogFilter<'a> {
+    default: LevelFilter,
+    targets: [(&'a str, LevelFilter); MAX_TARGET_FILTERS],
+    len: usize,
+}
+
+impl<'a> LogFilter<'a> {
+    /// Create a filter that emits all records up to the given level
+    pub const fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            targets: [("", LevelFilter::Off); MAX_TARGET_FILTERS],
+            len: 0,
+        }
+    }
+
+    /// The level of all records whose target has no specific level
+    pub fn default_level(&self) -> LevelFilter {
+        self.default
+    }
+
+    /// Change the level of all records whose target has no specific level
+    pub fn set_default_level(&mut self, level: LevelFilter) {
+        self.default = level;
+    }
+
+    /// Add a level for a specific target.
+    ///
+    /// If the target already has a level, it is replaced.
+    /// Fails with the given target as error if the filter already holds [`MAX_TARGET_FILTERS`] targets.
+    pub fn set_target_level(&mut self, target: &'a str, level: LevelFilter) -> Result<(), &'a str> {
+        if let Some(existing) = self.targets[..self.len]
+            .iter_mut()
+            .find(|(existing, _)| *existing == target)
+        {
+            existing.1 = level;
+            return Ok(());
+        }
+        if self.len == MAX_TARGET_FILTERS {
+            return Err(target);
+        }
+        self.targets[self.len] = (target, level);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The target specific levels of this filter
+    pub fn target_levels(&self) -> &[(&'a str, LevelFilter)] {
+        &self.targets[..self.len]
+    }
+
+    /// The maximum level that records of the given target are emitted with
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.target_levels()
+            .iter()
+            .filter(|(prefix, _)| match target.strip_prefix(prefix) {
+                Some(rest) => rest.is_empty() || rest.starts_with("::"),
+                None => false,
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level that any record could be emitted with
+    pub fn max_level(&self) -> LevelFilter {
+        self.target_levels()
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
@@ -1,36 +1,82 @@
 //! A logging implementation which uses an OpenSBI syscall to print characters
 use core::fmt::Write;
 
+use crate::filter::LogFilter;
+use crate::log_buffer::{LogBuffer, LOG_BUFFER_SIZE};
 use crate::print::KernelWriter;
-use log::{Level, Log, Metadata, Record, SetLoggerError};
-
-// TODO Improve updating the maximum log level
-// Currently, only the global log:: filter is updated because that's easier that worrying about internal mutability of
-// the KernelLogger struct which would be required when wanting to update internal state too
+use ksync::SpinLock;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
 pub struct KernelLogger {
-    pub initial_log_level: Level,
+    filter: SpinLock<LogFilter<'static>>,
+    /// The most recently emitted records so that they can be inspected after the fact
+    buffer: SpinLock<LogBuffer<LOG_BUFFER_SIZE>>,
 }
 
 impl KernelLogger {
     pub const fn new(max_log_level: Level) -> KernelLogger {
+        // Level::to_level_filter() is not const
+        let max_log_level = match max_log_level {
+            Level::Error => LevelFilter::Error,
+            Level::Warn => LevelFilter::Warn,
+            Level::Info => LevelFilter::Info,
+            Level::Debug => LevelFilter::Debug,
+            Level::Trace => LevelFilter::Trace,
+        };
         KernelLogger {
-            initial_log_level: max_log_level,
+            filter: SpinLock::new(LogFilter::new(max_log_level)),
+            buffer: SpinLock::new(LogBuffer::new()),
         }
     }
 
     pub fn install(&'static self) -> Result<(), SetLoggerError> {
-        log::set_logger(self).map(|_| log::set_max_level(self.initial_log_level.to_level_filter()))
+        let max_level = self.filter.spin_lock().max_level();
+        log::set_logger(self).map(|_| log::set_max_level(max_level))
     }
 
+    /// Change the level of all records whose target has no specific level in the current filter
     pub fn update_log_level(&'static self, level: Level) {
-        log::set_max_level(level.to_level_filter());
+        let mut filter = self.filter.spin_lock();
+        filter.set_default_level(level.to_level_filter());
+        log::set_max_level(filter.max_level());
+    }
+
+    /// Replace the filter which decides which records are emitted
+    pub fn set_filter(&'static self, filter: LogFilter<'static>) {
+        *self.filter.spin_lock() = filter;
+        log::set_max_level(filter.max_level());
+    }
+
+    /// Copy recently emitted records starting at position `pos` of the log buffer into `out`.
+    ///
+    /// If `pos` has already been overwritten, copying starts at the oldest retained byte instead.
+    /// Returns the position of the first copied byte, how many bytes were copied and the position after the last
+    /// byte in the buffer.
+    pub fn read_buffer(&self, pos: u64, out: &mut [u8]) -> (u64, usize, u64) {
+        let buffer = self.buffer.spin_lock();
+        let (pos, len) = buffer.read(pos, out);
+        (pos, len, buffer.written())
+    }
+
+    /// Print all retained records, e.g. when the kernel panics.
+    ///
+    /// Since this is meant to be called when something already went wrong, the buffer is not printed if it is
+    /// currently locked instead of risking a deadlock.
+    pub fn dump_buffer(&self) {
+        let mut writer = KernelWriter {};
+        let Ok(buffer) = self.buffer.try_lock() else {
+            let _ = writer.write_str("the log buffer is locked and cannot be dumped\n");
+            return;
+        };
+        let _ = writer.write_str("---- recent log records ----\n");
+        let _ = buffer.write_to(&mut writer);
+        let _ = writer.write_str("---- end of log records ----\n");
     }
 }
 
 impl Log for KernelLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.spin_lock().level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
@@ -49,7 +95,13 @@ impl Log for KernelLogger {
                     record.target(),
                     record.args(),
                 ))
-                .expect("Could not write log message to Sbi")
+                .expect("Could not write log message to Sbi");
+            let _ = self.buffer.spin_lock().write_fmt(format_args!(
+                "{:<5} {}: {}\n",
+                record.level(),
+                record.target(),
+                record.args(),
+            ));
         }
     }
 
@@ -2,7 +2,11 @@
 
 #[macro_use]
 pub mod print;
+mod filter;
 mod kernel_logger;
+mod log_buffer;
 
+pub use filter::{LogFilter, MAX_TARGET_FILTERS};
 pub use kernel_logger::KernelLogger;
+pub use log_buffer::{LogBuffer, LOG_BUFFER_SIZE};
 pub use print::KernelWriter;
@@ -0,0 +1,196 @@
+//! An in-memory ring buffer which retains the most recently emitted log records
+
+use core::fmt;
+use core::str;
+
+/// How many bytes of log records the [`KernelLogger`](crate::KernelLogger) retains
+pub const LOG_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A ring buffer of bytes which retains the last `N` bytes that were written into it.
+///
+/// All bytes that were ever written form a stream in which every byte has a fixed position.
+/// Readers address the buffer by these positions so that they can continue reading where they left off and notice
+/// when bytes were overwritten before they got to read them.
+#[derive(Debug)]
+pub struct LogBuffer<const N: usize> {
+    bytes: [u8; N],
+    /// How many bytes have been written in total which is also the position of the next byte
+    written: u64,
+}
+
+impl<const N: usize> LogBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            written: 0,
+        }
+    }
+
+    /// The position of the next byte that will be written
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// The position of the oldest byte that is still retained
+    pub fn oldest(&self) -> u64 {
+        self.written.saturating_sub(N as u64)
+    }
+
+    /// Append the given bytes, overwriting the oldest ones if the buffer is full
+    pub fn push(&mut self, data: &[u8]) {
+        let total = data.len() as u64;
+        // only the last N bytes would survive anyway
+        let data = &data[data.len().saturating_sub(N)..];
+        let start = (self.written + total - data.len() as u64) as usize % N;
+        let first = core::cmp::min(N - start, data.len());
+        self.bytes[start..start + first].copy_from_slice(&data[..first]);
+        self.bytes[..data.len() - first].copy_from_slice(&data[first..]);
+        self.written += total;
+    }
+
+    /// Copy the retained bytes starting at position `pos` into `out`.
+    ///
+    /// If `pos` has already been overwritten, copying starts at the oldest retained byte instead.
+    /// Returns the position of the first copied byte and how many bytes were copied.
+    pub fn read(&self, pos: u64, out: &mut [u8]) -> (u64, usize) {
+        let pos = pos.clamp(self.oldest(), self.written);
+        let len = core::cmp::min(out.len() as u64, self.written - pos) as usize;
+        for (i, byte) in out[..len].iter_mut().enumerate() {
+            *byte = self.bytes[((pos + i as u64) % N as u64) as usize];
+        }
+        (pos, len)
+    }
+
+    /// Write all retained records to `writer`.
+    ///
+    /// If older records have already been overwritten, the partially retained record at the start is skipped.
+    pub fn write_to(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        let mut pos = self.oldest();
+        if pos > 0 {
+            // skip until after the first line break
+            let mut byte = [0];
+            while self.read(pos, &mut byte).1 == 1 {
+                pos += 1;
+                if byte[0] == b'\n' {
+                    break;
+                }
+            }
+        }
+
+        let mut chunk = [0; 64];
+        let mut len = 0;
+        while pos < self.written {
+            let (_, read) = self.read(pos, &mut chunk[len..]);
+            pos += read as u64;
+            len += read;
+            // the chunk may end in the middle of a character which is then kept for the next iteration
+            let valid = match str::from_utf8(&chunk[..len]) {
+                Ok(s) => {
+                    writer.write_str(s)?;
+                    len
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    writer.write_str(str::from_utf8(&chunk[..valid]).unwrap())?;
+                    match e.error_len() {
+                        Some(error_len) => {
+                            writer.write_char(char::REPLACEMENT_CHARACTER)?;
+                            valid + error_len
+                        }
+                        None => valid,
+                    }
+                }
+            };
+            chunk.copy_within(valid..len, 0);
+            len -= valid;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for LogBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for LogBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::LogBuffer;
+    use core::fmt::Write;
+    use std::string::String;
+
+    fn contents<const N: usize>(buffer: &LogBuffer<N>) -> String {
+        let mut s = String::new();
+        buffer.write_to(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn retains_everything_until_full() {
+        let mut buffer = LogBuffer::<16>::new();
+        write!(buffer, "a\nbc\n").unwrap();
+        assert_eq!(buffer.oldest(), 0);
+        assert_eq!(buffer.written(), 5);
+        assert_eq!(contents(&buffer), "a\nbc\n");
+    }
+
+    #[test]
+    fn overwrites_oldest_bytes() {
+        let mut buffer = LogBuffer::<8>::new();
+        buffer.push(b"first\n");
+        buffer.push(b"second\n");
+        assert_eq!(buffer.oldest(), 5);
+        let mut out = [0; 8];
+        assert_eq!(buffer.read(0, &mut out), (5, 8));
+        assert_eq!(&out, b"\nsecond\n");
+    }
+
+    #[test]
+    fn pushing_more_than_capacity_keeps_the_end() {
+        let mut buffer = LogBuffer::<4>::new();
+        buffer.push(b"x");
+        buffer.push(b"abcdefg");
+        assert_eq!(buffer.written(), 8);
+        let mut out = [0; 4];
+        assert_eq!(buffer.read(0, &mut out), (4, 4));
+        assert_eq!(&out, b"defg");
+    }
+
+    #[test]
+    fn read_continues_at_position() {
+        let mut buffer = LogBuffer::<16>::new();
+        buffer.push(b"hello world");
+        let mut out = [0; 5];
+        assert_eq!(buffer.read(6, &mut out), (6, 5));
+        assert_eq!(&out, b"world");
+        assert_eq!(buffer.read(11, &mut out), (11, 0));
+        assert_eq!(buffer.read(100, &mut out), (11, 0));
+    }
+
+    #[test]
+    fn dump_skips_partially_overwritten_record() {
+        let mut buffer = LogBuffer::<12>::new();
+        buffer.push(b"one\ntwo\nthree\n");
+        assert_eq!(contents(&buffer), "two\nthree\n");
+    }
+
+    #[test]
+    fn dump_handles_characters_split_by_wrap_around() {
+        let mut buffer = LogBuffer::<74>::new();
+        buffer.push(b"0123456789\n");
+        // the second emoji of the last record straddles the end of the backing array
+        let record = "🚀 launching init ".repeat(2) + "\n";
+        buffer.push(record.as_bytes());
+        buffer.push(record.as_bytes());
+        assert_eq!(contents(&buffer), record);
+    }
+}
@@ -0,0 +1,179 @@
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use crate::{Guard, SpinLock, TicketGuard, TicketLock};
+
+/// Control over the interrupts of the current hart.
+///
+/// This is implemented by architecture specific code, e.g. for `riscv::cpu::Sie`, so that this crate does not need to
+/// know how interrupts are enabled or disabled.
+///
+/// # Safety
+/// After [`disable()`](InterruptControl::disable) returns, no interrupt handler must run on the current hart until
+/// [`restore()`](InterruptControl::restore) is called.
+pub unsafe trait InterruptControl {
+    /// The interrupt configuration that was active before interrupts were disabled
+    type Saved;
+
+    /// Disable all interrupts of the current hart and return the previous configuration
+    fn disable() -> Self::Saved;
+
+    /// Restore an interrupt configuration that was returned by [`disable()`](InterruptControl::disable)
+    fn restore(saved: Self::Saved);
+}
+
+/// A Guard that keeps the interrupts of the current hart disabled for its lifetime.
+///
+/// Once it is dropped, the interrupt configuration that was active when it was created is restored so guards can be
+/// nested.
+/// The guard is neither [`Send`] nor [`Sync`] because it belongs to the hart on which it was created.
+pub struct InterruptGuard<I: InterruptControl> {
+    saved: Option<I::Saved>,
+    _hart_local: PhantomData<*const ()>,
+}
+
+impl<I: InterruptControl> InterruptGuard<I> {
+    /// Disable interrupts until the returned guard is dropped
+    pub fn new() -> Self {
+        Self {
+            saved: Some(I::disable()),
+            _hart_local: PhantomData,
+        }
+    }
+}
+
+impl<I: InterruptControl> Default for InterruptGuard<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: InterruptControl> Drop for InterruptGuard<I> {
+    fn drop(&mut self) {
+        if let Some(saved) = self.saved.take() {
+            I::restore(saved);
+        }
+    }
+}
+
+/// A lock guard which additionally keeps interrupts disabled for as long as the lock is held.
+///
+/// This is needed for data that is shared with interrupt handlers because an interrupt handler trying to lock a lock
+/// which is already held on the same hart would spin forever.
+///
+/// Use it via the implemented [`Deref`] and [`DerefMut`] traits.
+pub struct IrqSafeGuard<G, I: InterruptControl> {
+    // fields are dropped in declaration order so the lock is released before interrupts are enabled again
+    guard: G,
+    _interrupts: InterruptGuard<I>,
+}
+
+impl<G: Deref, I: InterruptControl> Deref for IrqSafeGuard<G, I> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut, I: InterruptControl> DerefMut for IrqSafeGuard<G, I> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T> SpinLock<T> {
+    /// Disable interrupts and lock the lock, returning the protected data via a guard that enables them again once
+    /// it is dropped
+    pub fn spin_lock_irqsave<I: InterruptControl>(&self) -> IrqSafeGuard<Guard<T>, I> {
+        let interrupts = InterruptGuard::new();
+        IrqSafeGuard {
+            guard: self.spin_lock(),
+            _interrupts: interrupts,
+        }
+    }
+}
+
+impl<T> TicketLock<T> {
+    /// Disable interrupts and lock the lock, returning the protected data via a guard that enables them again once
+    /// it is dropped
+    pub fn spin_lock_irqsave<I: InterruptControl>(&self) -> IrqSafeGuard<TicketGuard<T>, I> {
+        let interrupts = InterruptGuard::new();
+        IrqSafeGuard {
+            guard: self.spin_lock(),
+            _interrupts: interrupts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::{InterruptControl, InterruptGuard};
+    use crate::{SpinLock, TicketLock};
+    use std::cell::Cell;
+
+    std::thread_local! {
+        static ENABLED: Cell<bool> = const { Cell::new(true) };
+    }
+
+    /// Simulates the interrupt enable bit of a hart with one flag per test thread
+    struct FakeInterrupts;
+
+    unsafe impl InterruptControl for FakeInterrupts {
+        type Saved = bool;
+
+        fn disable() -> Self::Saved {
+            ENABLED.with(|enabled| enabled.replace(false))
+        }
+
+        fn restore(saved: Self::Saved) {
+            ENABLED.with(|enabled| enabled.set(saved))
+        }
+    }
+
+    fn enabled() -> bool {
+        ENABLED.with(|enabled| enabled.get())
+    }
+
+    #[test]
+    fn guard_disables_interrupts_for_its_lifetime() {
+        let guard = InterruptGuard::<FakeInterrupts>::new();
+        assert!(!enabled());
+        drop(guard);
+        assert!(enabled());
+    }
+
+    #[test]
+    fn nested_guards_restore_the_outer_state() {
+        let outer = InterruptGuard::<FakeInterrupts>::new();
+        let inner = InterruptGuard::<FakeInterrupts>::new();
+        drop(inner);
+        assert!(!enabled());
+        drop(outer);
+        assert!(enabled());
+    }
+
+    #[test]
+    fn spin_lock_irqsave_holds_lock_with_interrupts_disabled() {
+        let lock = SpinLock::new(0);
+        let mut guard = lock.spin_lock_irqsave::<FakeInterrupts>();
+        *guard += 1;
+        assert!(!enabled());
+        assert!(lock.try_lock().is_err());
+        drop(guard);
+        assert!(enabled());
+        assert_eq!(*lock.try_lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn ticket_lock_irqsave_holds_lock_with_interrupts_disabled() {
+        let lock = TicketLock::new(0);
+        let guard = lock.spin_lock_irqsave::<FakeInterrupts>();
+        assert!(!enabled());
+        assert!(lock.is_locked());
+        drop(guard);
+        assert!(enabled());
+        assert!(!lock.is_locked());
+    }
+}
@@ -1,6 +1,12 @@
 //! Kernel Synchronisation Primitives
 #![no_std]
 
+mod interrupt_guard;
+mod rw_lock;
 mod spin_lock;
+mod ticket_lock;
 
+pub use interrupt_guard::{InterruptControl, InterruptGuard, IrqSafeGuard};
+pub use rw_lock::{ReadGuard, RwSpinLock, WriteGuard};
 pub use spin_lock::{Guard, SpinLock};
+pub use ticket_lock::{TicketGuard, TicketLock};
@@ -0,0 +1,237 @@
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Set while a writer holds the lock
+const WRITER: usize = 1;
+/// Set while a writer waits for the lock so that no new readers are admitted
+const WRITER_WAITING: usize = 1 << 1;
+/// The amount by which the state is incremented for each reader
+const READER: usize = 1 << 2;
+
+/// A reader-writer spin lock implementation.
+///
+/// The lock can either be held by any number of readers or by a single writer.
+/// Waiting writers are preferred over new readers so that a steady stream of readers cannot starve them.
+#[derive(Debug)]
+pub struct RwSpinLock<T> {
+    /// The number of readers in multiples of [`READER`] combined with the [`WRITER`] and [`WRITER_WAITING`] flags
+    state: AtomicUsize,
+    value: UnsafeCell<T>,
+}
+
+/// A Guard giving shared access to some data locked through a [`RwSpinLock`].
+///
+/// Use it via the implemented [`Deref`] trait.
+pub struct ReadGuard<'a, T> {
+    lock: &'a RwSpinLock<T>,
+}
+
+/// A Guard giving exclusive access to some data locked through a [`RwSpinLock`].
+///
+/// Use it via the implemented [`Deref`] and [`DerefMut`] traits.
+pub struct WriteGuard<'a, T> {
+    lock: &'a RwSpinLock<T>,
+}
+
+impl<T> RwSpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Try to lock the lock for reading and return a [`ReadGuard`] if successful.
+    ///
+    /// This fails if a writer holds the lock or waits for it.
+    pub fn try_read(&self) -> Result<ReadGuard<T>, ()> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITER | WRITER_WAITING) != 0 {
+                return Err(());
+            }
+            let readers = state.checked_add(READER).expect("too many readers");
+            match self.state.compare_exchange_weak(
+                state,
+                readers,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Ok(ReadGuard { lock: self }),
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Try to lock the lock for writing and return a [`WriteGuard`] if successful.
+    ///
+    /// This fails if the lock is held by anyone else.
+    pub fn try_write(&self) -> Result<WriteGuard<T>, ()> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & !WRITER_WAITING != 0 {
+            return Err(());
+        }
+        // clearing WRITER_WAITING is fine because other waiting writers set it again while they spin
+        match self
+            .state
+            .compare_exchange(state, WRITER, Ordering::Acquire, Ordering::Relaxed)
+        {
+            Ok(_) => Ok(WriteGuard { lock: self }),
+            Err(_) => Err(()),
+        }
+    }
+
+    /// Repeatedly try to lock the lock for reading until it succeeds, returning the protected data via a
+    /// [`ReadGuard`]
+    pub fn spin_read(&self) -> ReadGuard<T> {
+        loop {
+            match self.try_read() {
+                Ok(guard) => return guard,
+                Err(()) => spin_loop(),
+            }
+        }
+    }
+
+    /// Repeatedly try to lock the lock for writing until it succeeds, returning the protected data via a
+    /// [`WriteGuard`]
+    pub fn spin_write(&self) -> WriteGuard<T> {
+        loop {
+            match self.try_write() {
+                Ok(guard) => return guard,
+                Err(()) => {
+                    self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+                    spin_loop();
+                }
+            }
+        }
+    }
+
+    fn read_unlock(&self) {
+        self.state.fetch_sub(READER, Ordering::Release);
+    }
+
+    fn write_unlock(&self) {
+        // keep WRITER_WAITING so that writers which are still waiting get their turn before new readers
+        self.state.fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+unsafe impl<T> Sync for RwSpinLock<T> where T: Send + Sync {}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The very existence of this Guard guarantees that no writer holds the lock
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.read_unlock();
+    }
+}
+
+impl<T> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The very existence of this Guard guarantees we've exclusively locked the lock
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The very existence of this Guard guarantees we've exclusively locked the lock
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.write_unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::{RwSpinLock, WRITER_WAITING};
+    use core::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[cfg(not(miri))]
+    const ITERATIONS: usize = 10_000;
+    #[cfg(miri)]
+    const ITERATIONS: usize = 50;
+
+    #[test]
+    fn multiple_readers_can_hold_the_lock() {
+        let lock = RwSpinLock::new(42);
+        let a = lock.try_read().unwrap();
+        let b = lock.try_read().unwrap();
+        assert_eq!(*a + *b, 84);
+        assert!(lock.try_write().is_err());
+        drop(a);
+        assert!(lock.try_write().is_err());
+        drop(b);
+        assert!(lock.try_write().is_ok());
+    }
+
+    #[test]
+    fn writer_excludes_everyone_else() {
+        let lock = RwSpinLock::new(0);
+        let mut guard = lock.try_write().unwrap();
+        *guard = 1;
+        assert!(lock.try_read().is_err());
+        assert!(lock.try_write().is_err());
+        drop(guard);
+        assert_eq!(*lock.try_read().unwrap(), 1);
+    }
+
+    #[test]
+    fn waiting_writer_blocks_new_readers() {
+        let lock = RwSpinLock::new(());
+        let reader = lock.try_read().unwrap();
+        lock.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+        assert!(lock.try_read().is_err());
+        drop(reader);
+        drop(lock.try_write().unwrap());
+        assert!(lock.try_read().is_ok());
+    }
+
+    #[test]
+    fn concurrent_readers_and_writers() {
+        const THREADS: usize = 4;
+        // both fields are always written together so readers must never observe them differing
+        let lock = Arc::new(RwSpinLock::new((0, 0)));
+        let threads: Vec<_> = (0..THREADS)
+            .map(|i| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for _ in 0..ITERATIONS {
+                        if i % 2 == 0 {
+                            let mut guard = lock.spin_write();
+                            guard.0 += 1;
+                            guard.1 += 1;
+                        } else {
+                            let guard = lock.spin_read();
+                            assert_eq!(guard.0, guard.1);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let guard = lock.spin_read();
+        assert_eq!(*guard, (THREADS / 2 * ITERATIONS, THREADS / 2 * ITERATIONS));
+    }
+}
@@ -70,3 +70,46 @@ impl<T> Drop for Guard<'_, T> {
         self.lock.unlock();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::SpinLock;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[cfg(not(miri))]
+    const ITERATIONS: usize = 10_000;
+    #[cfg(miri)]
+    const ITERATIONS: usize = 50;
+
+    #[test]
+    fn try_lock_fails_while_locked() {
+        let lock = SpinLock::new(0);
+        let guard = lock.try_lock().unwrap();
+        assert!(lock.try_lock().is_err());
+        drop(guard);
+        assert!(lock.try_lock().is_ok());
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        const THREADS: usize = 4;
+        let lock = Arc::new(SpinLock::new(0));
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for _ in 0..ITERATIONS {
+                        *lock.spin_lock() += 1;
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*lock.spin_lock(), THREADS * ITERATIONS);
+    }
+}
@@ -0,0 +1,156 @@
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A fair spinning Mutex implementation.
+///
+/// Every locking attempt draws a ticket and the lock is handed to the waiting tickets in the order in which they
+/// were drawn so that no hart can be starved by others that repeatedly lock and unlock it.
+#[derive(Debug)]
+pub struct TicketLock<T> {
+    next_ticket: AtomicUsize,
+    now_serving: AtomicUsize,
+    value: UnsafeCell<T>,
+}
+
+/// A Guard protecting some data locked through a [`TicketLock`].
+///
+/// Use it via the implemented [`Deref`] and [`DerefMut`] traits.
+pub struct TicketGuard<'a, T> {
+    lock: &'a TicketLock<T>,
+}
+
+impl<T> TicketLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            next_ticket: AtomicUsize::new(0),
+            now_serving: AtomicUsize::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Try to lock the lock without waiting and return a [`TicketGuard`] if successful
+    pub fn try_lock(&self) -> Result<TicketGuard<T>, ()> {
+        // only draw a ticket if it would be served immediately
+        let ticket = self.now_serving.load(Ordering::Relaxed);
+        match self.next_ticket.compare_exchange(
+            ticket,
+            ticket.wrapping_add(1),
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => Ok(TicketGuard { lock: self }),
+            Err(_) => Err(()),
+        }
+    }
+
+    /// Draw a ticket and wait until it is served, returning the protected data via a [`TicketGuard`]
+    pub fn spin_lock(&self) -> TicketGuard<T> {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            spin_loop();
+        }
+        TicketGuard { lock: self }
+    }
+
+    /// Whether the lock is currently held by someone
+    pub fn is_locked(&self) -> bool {
+        self.next_ticket.load(Ordering::Relaxed) != self.now_serving.load(Ordering::Relaxed)
+    }
+
+    fn unlock(&self) {
+        // only the holder of the lock modifies now_serving so a plain store is enough
+        let ticket = self.now_serving.load(Ordering::Relaxed);
+        self.now_serving
+            .store(ticket.wrapping_add(1), Ordering::Release);
+    }
+}
+
+unsafe impl<T> Sync for TicketLock<T> where T: Send {}
+
+impl<T> Deref for TicketGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The very existence of this Guard guarantees we've exclusively locked the lock
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for TicketGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: The very existence of this Guard guarantees we've exclusively locked the lock
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for TicketGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::TicketLock;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[cfg(not(miri))]
+    const ITERATIONS: usize = 10_000;
+    #[cfg(miri)]
+    const ITERATIONS: usize = 50;
+
+    #[test]
+    fn try_lock_fails_while_locked() {
+        let lock = TicketLock::new(0);
+        let guard = lock.try_lock().unwrap();
+        assert!(lock.is_locked());
+        assert!(lock.try_lock().is_err());
+        drop(guard);
+        assert!(!lock.is_locked());
+        assert!(lock.try_lock().is_ok());
+    }
+
+    #[test]
+    fn guard_gives_access_to_value() {
+        let lock = TicketLock::new(1);
+        *lock.spin_lock() += 1;
+        assert_eq!(*lock.spin_lock(), 2);
+    }
+
+    #[test]
+    fn tickets_wrap_around() {
+        let lock = TicketLock::new(());
+        lock.next_ticket
+            .store(usize::MAX, core::sync::atomic::Ordering::Relaxed);
+        lock.now_serving
+            .store(usize::MAX, core::sync::atomic::Ordering::Relaxed);
+        drop(lock.spin_lock());
+        drop(lock.try_lock().unwrap());
+        assert!(!lock.is_locked());
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        const THREADS: usize = 4;
+        let lock = Arc::new(TicketLock::new(0));
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for _ in 0..ITERATIONS {
+                        *lock.spin_lock() += 1;
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*lock.spin_lock(), THREADS * ITERATIONS);
+    }
+}
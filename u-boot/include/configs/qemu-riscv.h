@@ -33,10 +33,18 @@
 		"fi;\0"
 
 
+/*
+ * The initrd is only passed to the kernel loader if a cpio archive has been loaded to initrd_addr which is detected by
+ * the first four bytes of its "070701" magic.
+ */
 #define BOOTENV_DEV_ELF(devtypeu, devtypel, instance) \
 	"bootcmd_elf=" \
 		"setenv autostart yes; " \
-		"bootelf fdt_addr=${fdt_addr} image_addr=${image_addr} image_size=${image_size};\0"
+		"if itest.l *${initrd_addr} == 37303730; then " \
+			"bootelf fdt_addr=${fdt_addr} image_addr=${image_addr} init_addr=${init_addr} initrd_addr=${initrd_addr} initrd_size=${initrd_size}; " \
+		"else " \
+			"bootelf fdt_addr=${fdt_addr} image_addr=${image_addr} init_addr=${init_addr}; " \
+		"fi;\0"
 
 #define BOOTENV_DEV_NAME_ELF(devtypeu, devtypel, instance) \
 	"elf "
@@ -49,7 +57,9 @@
 	"initrd_high=0xffffffffffffffff\0" \
 	"kernel_addr_r=0x84000000\0" \
 	"image_addr=84800000\0" \
-	"image_size=900000\0" \
+	"init_addr=8d000000\0" \
+	"initrd_addr=8e000000\0" \
+	"initrd_size=800000\0" \
 	"kernel_comp_addr_r=0x88000000\0" \
 	"kernel_comp_size=0x4000000\0" \
 	"fdt_addr_r=0x8c000000\0" \
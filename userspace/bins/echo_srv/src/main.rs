@@ -18,14 +18,17 @@ fn panic_handler(info: &PanicInfo) -> ! {
 
 fn main() {
     println!("echo server started");
-    const ENDPOINT_CADDR: CAddr = CAddr::new(1, 1);
+    // init places a copy of our cspace into slot 0 and the endpoint into slot 1
+    const CSPACE_CADDR: CAddr = CAddr::from_raw(0);
+    let cspace = liblunatix::ipc::cspace::info(CSPACE_CADDR).expect("could not query our cspace");
+    let endpoint_caddr = CAddr::new(cspace.part(1), cspace.part_bits());
     assert_eq!(
-        liblunatix::syscalls::identify(ENDPOINT_CADDR),
+        liblunatix::syscalls::identify(endpoint_caddr),
         Ok(CapabilityVariant::Endpoint)
     );
 
     for i in 0..10_000 {
-        let recv = liblunatix::syscalls::receive(ENDPOINT_CADDR, 0, &[])
+        let recv = liblunatix::syscalls::receive(endpoint_caddr, 0, &[])
             .expect("did not receive successfull receive");
         //println!("received: {:?}", &recv);
         assert_eq!(i, recv.raw_args[1]);
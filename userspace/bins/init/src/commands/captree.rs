@@ -0,0 +1,83 @@
+use super::Command;
+use crate::{CADDR_MEM, CADDR_VSPACE};
+use alloc::vec::Vec;
+use caddr_alloc::OwnedSlot;
+use liblunatix::caps::PageCap;
+use liblunatix::prelude::syscall_abi::debug::{CapTreeEntry, CAP_TREE_ENTRIES_PER_PAGE};
+use liblunatix::prelude::syscall_abi::MapFlags;
+use liblunatix::prelude::*;
+
+pub struct CapTree;
+
+/// The address at which the page is mapped into which the kernel dumps the derivation tree
+const CAP_TREE_PAGE_ADDR: usize = 0x20_0000_0000;
+
+impl Command for CapTree {
+    fn get_name(&self) -> &'static str {
+        "captree"
+    }
+
+    fn get_summary(&self) -> &'static str {
+        "print the kernels capability derivation tree"
+    }
+
+    fn execute(&self, _args: &str) -> Result<(), &'static str> {
+        let slot = OwnedSlot::alloc();
+        let page: PageCap = CADDR_MEM
+            .derive(slot.caddr())
+            .map_err(|_| "could not allocate a page for the derivation tree")?;
+        page.map(CADDR_VSPACE, CADDR_MEM, CAP_TREE_PAGE_ADDR, MapFlags::READ)
+            .map_err(|_| "could not map the page for the derivation tree")?;
+        let nodes = read_tree(page)?;
+
+        // nodes are referred to by their index because the ids that the kernel hands out are long and meaningless
+        let index_of = |id: usize| nodes.iter().position(|node| node.id == id);
+        for (i, node) in nodes.iter().enumerate() {
+            print!(
+                "{: >4} {:indent$}",
+                i,
+                "",
+                indent = 2 * node.depth.saturating_sub(1)
+            );
+            match node.variant() {
+                Some(variant) => print!("{:?}", variant),
+                None => print!("<unknown variant {}>", node.variant),
+            }
+            if node.copy_of != 0 && node.copy_of != node.id {
+                match index_of(node.copy_of) {
+                    Some(original) => print!(" (copy of #{})", original),
+                    None => print!(" (copy)"),
+                }
+            }
+            if node.cspace != 0 {
+                match index_of(node.cspace) {
+                    Some(cspace) => print!(" in #{} slot {}", cspace, node.slot),
+                    None => print!(" in slot {}", node.slot),
+                }
+            }
+            println!();
+        }
+        Ok(())
+    }
+}
+
+/// Let the kernel dump all nodes of the derivation tree into the page that is mapped at [`CAP_TREE_PAGE_ADDR`]
+fn read_tree(page: PageCap) -> Result<Vec<CapTreeEntry>, &'static str> {
+    let entries = unsafe {
+        core::slice::from_raw_parts(
+            CAP_TREE_PAGE_ADDR as *const CapTreeEntry,
+            CAP_TREE_ENTRIES_PER_PAGE,
+        )
+    };
+
+    let mut nodes = Vec::new();
+    loop {
+        let ret = liblunatix::syscalls::cap_tree(page.caddr(), nodes.len())
+            .map_err(|_| "could not dump the derivation tree")?;
+        nodes.extend_from_slice(&entries[..ret.written]);
+        // the tree might shrink while it is read so stop once no more nodes are returned
+        if ret.written == 0 || nodes.len() >= ret.total {
+            return Ok(nodes);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::Command;
+use core::str;
+use liblunatix::prelude::*;
+
+pub struct Dmesg;
+
+impl Command for Dmesg {
+    fn get_name(&self) -> &'static str {
+        "dmesg"
+    }
+
+    fn get_summary(&self) -> &'static str {
+        "print the recent kernel log"
+    }
+
+    fn execute(&self, _args: &str) -> Result<(), &'static str> {
+        let mut pos = 0;
+        // the log grows while it is printed, e.g. when syscalls are logged, so only what exists now is printed
+        let mut end = None;
+        // chunks can end in the middle of a character so incomplete characters are kept for the next chunk
+        let mut pending = [0u8; 64];
+        let mut pending_len = 0;
+        loop {
+            let chunk = liblunatix::syscalls::read_kernel_log(pos)
+                .map_err(|_| "could not read the kernel log")?;
+            let end = *end.get_or_insert(chunk.end);
+            if chunk.len == 0 || chunk.pos >= end {
+                break;
+            }
+            if chunk.pos != pos && pos != 0 {
+                println!("[... {} bytes were overwritten ...]", chunk.pos - pos);
+                pending_len = 0;
+            }
+            pos = chunk.pos + chunk.len;
+
+            pending[pending_len..pending_len + chunk.len].copy_from_slice(chunk.data());
+            pending_len += chunk.len;
+            let valid = match str::from_utf8(&pending[..pending_len]) {
+                Ok(s) => {
+                    print!("{}", s);
+                    pending_len
+                }
+                Err(e) => {
+                    print!("{}", str::from_utf8(&pending[..e.valid_up_to()]).unwrap());
+                    match e.error_len() {
+                        Some(error_len) => {
+                            print!("\u{FFFD}");
+                            e.valid_up_to() + error_len
+                        }
+                        None => e.valid_up_to(),
+                    }
+                }
+            };
+            pending.copy_within(valid..pending_len, 0);
+            pending_len -= valid;
+        }
+        Ok(())
+    }
+}
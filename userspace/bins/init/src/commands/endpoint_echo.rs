@@ -1,21 +1,40 @@
 use crate::commands::Command;
 use crate::elfloader::LunatixElfLoader;
 use crate::sched::Scheduler;
-use crate::{CADDR_ASID_CONTROL, CADDR_MEM, CADDR_VSPACE, CSPACE_BITS, FS};
-use caddr_alloc::alloc_caddr;
+use crate::{CADDR_ALLOC, CADDR_ASID_CONTROL, CADDR_MEM, CADDR_VSPACE, FS};
+use caddr_alloc::OwnedSlot;
 use elfloader::ElfBinary;
 use io::read::Reader;
-use liblunatix::prelude::syscall_abi::identify::CapabilityVariant;
+use liblunatix::caps::{CSpaceCap, EndpointCap, PageCap, TaskCap, VSpaceCap};
 use liblunatix::prelude::syscall_abi::MapFlags;
-use liblunatix::prelude::CAddr;
+use liblunatix::prelude::{CAddr, Capability};
 
 pub struct EndpointEcho;
 
+/// The capabilities of a task which are destroyed when they are dropped
 struct TaskCaps {
-    task: CAddr,
-    cspace: CAddr,
-    vspace: CAddr,
-    stack_page: CAddr,
+    task: TaskCap,
+    cspace: CSpaceCap,
+    vspace: VSpaceCap,
+    stack_page: PageCap,
+    /// The slots of the above capabilities.
+    ///
+    /// The task is destroyed first so that it releases its copies of the cspace and vspace.
+    _slots: [OwnedSlot; 4],
+}
+
+impl TaskCaps {
+    /// The address of the slot at `index` of the tasks cspace
+    fn slot(&self, index: usize) -> CAddr {
+        CADDR_ALLOC.nested_caddr(self.cspace.caddr(), index, 1)
+    }
+}
+
+impl Drop for TaskCaps {
+    fn drop(&mut self) {
+        // the copy of the cspace in its own slot 0 would keep the cspace alive after all other copies are destroyed
+        liblunatix::syscalls::destroy(self.slot(0)).unwrap();
+    }
 }
 
 impl EndpointEcho {
@@ -30,19 +49,20 @@ impl EndpointEcho {
 
         // load the elf content into the task
         log::debug!("preparing capabilities for the new task");
-        liblunatix::ipc::asid::asid_assign(CADDR_ASID_CONTROL, task_caps.vspace).unwrap();
+        CADDR_ASID_CONTROL.assign(task_caps.vspace).unwrap();
 
         // load a stack for the child task
         log::debug!("mapping stack space for the new task");
         const TASK_STACK_LOW: usize = 0x5_0000_0000;
-        liblunatix::ipc::page::map_page(
-            task_caps.stack_page,
-            task_caps.vspace,
-            CADDR_MEM,
-            TASK_STACK_LOW,
-            MapFlags::READ | MapFlags::WRITE,
-        )
-        .unwrap();
+        task_caps
+            .stack_page
+            .map(
+                task_caps.vspace,
+                CADDR_MEM,
+                TASK_STACK_LOW,
+                MapFlags::READ | MapFlags::WRITE,
+            )
+            .unwrap();
 
         // load the elf content
         log::debug!("loading {path:?} elf code");
@@ -53,48 +73,46 @@ impl EndpointEcho {
         elf_loader.remap_to_target_vspace();
 
         // setting task start params
-        liblunatix::ipc::task::task_assign_control_registers(
-            task_caps.task,
-            elf_binary.entry_point() as usize,
-            TASK_STACK_LOW + 4096,
-            0x0,
-            0x0,
-        )
-        .unwrap();
+        task_caps
+            .task
+            .assign_control_registers(
+                elf_binary.entry_point() as usize,
+                TASK_STACK_LOW + 4096,
+                0x0,
+                0x0,
+            )
+            .unwrap();
 
         task_caps
     }
 
     fn make_task_caps(&self) -> TaskCaps {
-        let task = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, task, CapabilityVariant::Task, None).unwrap();
-
-        let cspace = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, cspace, CapabilityVariant::CSpace, Some(2))
+        let task_slot = OwnedSlot::alloc();
+        let task: TaskCap = CADDR_MEM.derive(task_slot.caddr()).unwrap();
+
+        let cspace_slot = OwnedSlot::alloc();
+        let cspace = CADDR_MEM.derive_cspace(cspace_slot.caddr(), 2).unwrap();
+        task.assign_cspace(cspace).unwrap();
+        // the task discovers the layout of its cspace through a copy of it in slot 0
+        cspace
+            .copy_to(CADDR_ALLOC.nested_caddr(cspace.caddr(), 0, 1))
             .unwrap();
-        liblunatix::ipc::task::task_assign_cspace(cspace, task).unwrap();
 
-        let vspace = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, vspace, CapabilityVariant::VSpace, None).unwrap();
-        liblunatix::ipc::task::task_assign_vspace(vspace, task).unwrap();
+        let vspace_slot = OwnedSlot::alloc();
+        let vspace = CADDR_MEM.derive(vspace_slot.caddr()).unwrap();
+        task.assign_vspace(vspace).unwrap();
 
-        let stack_page = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, stack_page, CapabilityVariant::Page, None).unwrap();
+        let stack_page_slot = OwnedSlot::alloc();
+        let stack_page = CADDR_MEM.derive(stack_page_slot.caddr()).unwrap();
 
         TaskCaps {
             task,
             cspace,
             vspace,
             stack_page,
+            _slots: [task_slot, stack_page_slot, vspace_slot, cspace_slot],
         }
     }
-
-    fn destroy_task_caps(&self, caps: TaskCaps) {
-        liblunatix::syscalls::destroy(caps.stack_page).unwrap();
-        liblunatix::syscalls::destroy(caps.cspace).unwrap();
-        liblunatix::syscalls::destroy(caps.task).unwrap();
-        liblunatix::syscalls::destroy(caps.vspace).unwrap();
-    }
 }
 
 impl Command for EndpointEcho {
@@ -111,33 +129,19 @@ impl Command for EndpointEcho {
         let client = self.load_binary("echo_client");
 
         log::info!("creating endpoint");
-        let endpoint_addr = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, endpoint_addr, CapabilityVariant::Endpoint, None)
-            .unwrap();
+        let endpoint_slot = OwnedSlot::alloc();
+        let endpoint: EndpointCap = CADDR_MEM.derive(endpoint_slot.caddr()).unwrap();
 
         log::info!("copying endpoint copies into tasks");
-        liblunatix::syscalls::copy(
-            endpoint_addr,
-            CAddr::builder()
-                .part(server.cspace.raw(), CSPACE_BITS)
-                .part(1, 1)
-                .finish(),
-        )
-        .unwrap();
-        liblunatix::syscalls::copy(
-            endpoint_addr,
-            CAddr::builder()
-                .part(client.cspace.raw(), CSPACE_BITS)
-                .part(1, 1)
-                .finish(),
-        )
-        .unwrap();
+        endpoint.copy_to(server.slot(1)).unwrap();
+        endpoint.copy_to(client.slot(1)).unwrap();
 
         log::info!("executing server and client tasks");
         let mut sched = Scheduler::new([server.task, client.task].into_iter());
         sched.run_schedule();
 
-        // TODO Cleanup the task objects
+        // dropping the task caps destroys the tasks together with their cspaces and vspaces
+        // TODO Cleanup the loaded pages of the tasks
 
         Ok(())
     }
@@ -1,13 +1,13 @@
+use aligned_vec::AVec;
 use alloc::vec::Vec;
 use elfloader::ElfBinary;
 use io::read::Reader;
-use liblunatix::prelude::CAddr;
 
 use crate::elfloader::LunatixElfLoader;
 use crate::sched::Scheduler;
-use crate::{CADDR_ASID_CONTROL, CADDR_MEM, CADDR_VSPACE, FS};
-use caddr_alloc::alloc_caddr;
-use liblunatix::prelude::syscall_abi::identify::CapabilityVariant;
+use crate::{CADDR_ASID_CONTROL, CADDR_MEM, CADDR_VSPACE, FS, INITRD};
+use caddr_alloc::OwnedSlot;
+use liblunatix::caps::{PageCap, TaskCap, VSpaceCap};
 use liblunatix::prelude::syscall_abi::MapFlags;
 use xmas_elf::sections::SectionData;
 
@@ -15,11 +15,15 @@ use super::Command;
 
 pub struct Exec;
 
+/// The capabilities of a task which are destroyed when they are dropped
 struct TaskCaps {
-    task: CAddr,
-    cspace: CAddr,
-    vspace: CAddr,
-    stack_page: CAddr,
+    task: TaskCap,
+    vspace: VSpaceCap,
+    stack_page: PageCap,
+    /// The slots of the above capabilities.
+    ///
+    /// The task is destroyed first so that it releases its copies of the cspace and vspace.
+    _slots: [OwnedSlot; 4],
 }
 
 impl Command for Exec {
@@ -34,28 +38,25 @@ impl Command for Exec {
     fn execute(&self, args: &str) -> Result<(), &'static str> {
         let mut tasks = Vec::new();
         for path in args.split(" ") {
-            log::debug!("reading binary {path:?} from filesystem");
-            let mut p9 = FS.0.borrow_mut();
-            let p9 = p9.as_mut().unwrap();
-            let mut reader = p9.read_file(&[path]).unwrap();
-            let file_bin = reader.read_to_vec(16).unwrap();
+            let file_bin = self.read_binary(path)?;
 
             // load the elf content into the task
             log::debug!("preparing capabilities for the new task");
             let task_caps = self.make_task_caps();
-            liblunatix::ipc::asid::asid_assign(CADDR_ASID_CONTROL, task_caps.vspace).unwrap();
+            CADDR_ASID_CONTROL.assign(task_caps.vspace).unwrap();
 
             // load a stack for the child task
             log::debug!("mapping stack space for the new task");
             const TASK_STACK_LOW: usize = 0x5_0000_0000;
-            liblunatix::ipc::page::map_page(
-                task_caps.stack_page,
-                task_caps.vspace,
-                CADDR_MEM,
-                TASK_STACK_LOW,
-                MapFlags::READ | MapFlags::WRITE,
-            )
-            .unwrap();
+            task_caps
+                .stack_page
+                .map(
+                    task_caps.vspace,
+                    CADDR_MEM,
+                    TASK_STACK_LOW,
+                    MapFlags::READ | MapFlags::WRITE,
+                )
+                .unwrap();
 
             // load the elf content
             log::debug!("loading {} elf code", path);
@@ -84,14 +85,15 @@ impl Command for Exec {
             }
 
             // setting task start params
-            liblunatix::ipc::task::task_assign_control_registers(
-                task_caps.task,
-                elf_binary.entry_point() as usize,
-                TASK_STACK_LOW + 4096,
-                0x0,
-                0x0,
-            )
-            .unwrap();
+            task_caps
+                .task
+                .assign_control_registers(
+                    elf_binary.entry_point() as usize,
+                    TASK_STACK_LOW + 4096,
+                    0x0,
+                    0x0,
+                )
+                .unwrap();
 
             tasks.push(task_caps);
         }
@@ -100,41 +102,53 @@ impl Command for Exec {
         let mut sched = Scheduler::new(tasks.iter().map(|caps| caps.task));
         sched.run_schedule();
 
-        // TODO Cleanup the task objects
+        // dropping the task caps destroys the tasks together with their cspaces and vspaces
+        // TODO Cleanup the loaded pages of the tasks
 
         Ok(())
     }
 }
 
 impl Exec {
+    /// Read a binary from the initrd or, if it is not contained there, from the 9p filesystem
+    fn read_binary(&self, path: &str) -> Result<AVec<u8>, &'static str> {
+        if let Some(entry) = INITRD.get().and_then(|initrd| initrd.find(path)) {
+            log::debug!("reading binary {path:?} from initrd");
+            return entry
+                .reader()
+                .read_to_vec(16)
+                .map_err(|_| "could not read binary");
+        }
+
+        log::debug!("reading binary {path:?} from filesystem");
+        let mut p9 = FS.0.borrow_mut();
+        let p9 = p9
+            .as_mut()
+            .ok_or("binary not found in initrd and no filesystem available")?;
+        let mut reader = p9.read_file(&[path]).unwrap();
+        reader.read_to_vec(16).map_err(|_| "could not read binary")
+    }
+
     fn make_task_caps(&self) -> TaskCaps {
-        let task = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, task, CapabilityVariant::Task, None).unwrap();
+        let task_slot = OwnedSlot::alloc();
+        let task: TaskCap = CADDR_MEM.derive(task_slot.caddr()).unwrap();
 
-        let cspace = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, cspace, CapabilityVariant::CSpace, Some(8))
-            .unwrap();
-        liblunatix::ipc::task::task_assign_cspace(cspace, task).unwrap();
+        let cspace_slot = OwnedSlot::alloc();
+        let cspace = CADDR_MEM.derive_cspace(cspace_slot.caddr(), 8).unwrap();
+        task.assign_cspace(cspace).unwrap();
 
-        let vspace = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, vspace, CapabilityVariant::VSpace, None).unwrap();
-        liblunatix::ipc::task::task_assign_vspace(vspace, task).unwrap();
+        let vspace_slot = OwnedSlot::alloc();
+        let vspace = CADDR_MEM.derive(vspace_slot.caddr()).unwrap();
+        task.assign_vspace(vspace).unwrap();
 
-        let stack_page = alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, stack_page, CapabilityVariant::Page, None).unwrap();
+        let stack_page_slot = OwnedSlot::alloc();
+        let stack_page = CADDR_MEM.derive(stack_page_slot.caddr()).unwrap();
 
         TaskCaps {
             task,
-            cspace,
             vspace,
             stack_page,
+            _slots: [task_slot, stack_page_slot, vspace_slot, cspace_slot],
         }
     }
-
-    fn destroy_task_caps(&self, caps: TaskCaps) {
-        liblunatix::syscalls::destroy(caps.stack_page).unwrap();
-        liblunatix::syscalls::destroy(caps.cspace).unwrap();
-        liblunatix::syscalls::destroy(caps.task).unwrap();
-        liblunatix::syscalls::destroy(caps.vspace).unwrap();
-    }
 }
@@ -1,3 +1,4 @@
+use liblunatix::prelude::CAddr;
 use liblunatix::println;
 
 use super::Command;
@@ -10,20 +11,26 @@ impl Command for Identify {
     }
 
     fn get_summary(&self) -> &'static str {
-        "identify syscall"
+        "identify syscall, optionally resolving only the given number of bits"
     }
 
     fn execute(&self, args: &str) -> Result<(), &'static str> {
-        let caddr = args
-            .trim()
-            .split(" ")
-            .next()
-            .ok_or("failed to read caddr")?;
+        let mut args = args.trim().split(" ");
+        let caddr = args.next().ok_or("failed to read caddr")?;
         let caddr = caddr
             .parse::<usize>()
             .map_err(|_| "failed to parse caddr")?
             .into();
-        println!("{:?}", liblunatix::syscalls::identify(caddr));
+        let depth = match args.next() {
+            Some(depth) => depth
+                .parse::<usize>()
+                .map_err(|_| "failed to parse depth")?,
+            None => CAddr::FULL_DEPTH,
+        };
+        match liblunatix::syscalls::identify_depth(caddr, depth) {
+            Ok(variant) => println!("{:?}", variant),
+            Err(e) => println!("{:?} after resolving {} bits", e.error, e.depth),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,29 @@
+use liblunatix::caps::TaskCap;
+use liblunatix::prelude::{Capability, SyscallError};
+
+use super::{CAddrArg, Command, ToValue};
+
+pub struct Kill;
+
+impl Command for Kill {
+    fn get_name(&self) -> &'static str {
+        "kill"
+    }
+
+    fn get_summary(&self) -> &'static str {
+        "destroy a task, even if it is blocked"
+    }
+
+    fn execute(&self, args: &str) -> Result<(), &'static str> {
+        let CAddrArg { addr } = args.to_value()?;
+        let task = match TaskCap::from_caddr(addr) {
+            Ok(task) => task,
+            Err(SyscallError::InvalidCap) => return Err("capability is not a task"),
+            Err(_) => return Err("could not identify capability"),
+        };
+        let Ok(_) = task.destroy() else {
+            return Err("syscall failed");
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use super::Command;
+use liblunatix::prelude::*;
+
+pub struct Kstat;
+
+impl Command for Kstat {
+    fn get_name(&self) -> &'static str {
+        "kstat"
+    }
+
+    fn get_summary(&self) -> &'static str {
+        "print capability and kernel memory statistics"
+    }
+
+    fn execute(&self, _args: &str) -> Result<(), &'static str> {
+        let stats =
+            liblunatix::syscalls::kstat().map_err(|_| "could not get the kernel statistics")?;
+
+        println!("capabilities:");
+        for (variant, &count) in stats.caps.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let variant = CapabilityVariant::try_from(variant).unwrap();
+            println!("\t {: >6} {:?}", count, variant);
+        }
+        println!(
+            "derivation tree: {} bytes, {} of {} cursors in use",
+            stats.tree_bytes, stats.cursors_used, stats.cursors_total
+        );
+        println!("free memory:     {} KiB", stats.free_bytes / 1024);
+        Ok(())
+    }
+}
@@ -1,20 +1,28 @@
+mod captree;
 mod cat;
 mod copy;
 mod destroy;
+mod dmesg;
 mod echo;
 mod endpoint_echo;
 mod exec;
 mod identify;
+mod kill;
+mod kstat;
 mod ls;
 mod shutdown;
 
+pub use captree::CapTree;
 pub use cat::Cat;
 pub use copy::Copy;
 pub use destroy::Destroy;
+pub use dmesg::Dmesg;
 pub use echo::Echo;
 pub use endpoint_echo::EndpointEcho;
 pub use exec::Exec;
 pub use identify::Identify;
+pub use kill::Kill;
+pub use kstat::Kstat;
 use liblunatix::prelude::CAddr;
 pub use ls::Ls;
 pub use shutdown::Shutdown;
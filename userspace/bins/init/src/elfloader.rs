@@ -2,9 +2,8 @@ use alloc::vec::Vec;
 use elfloader::{ElfLoader, ElfLoaderErr, Flags, LoadableHeaders, RelocationEntry, VAddr};
 
 use caddr_alloc;
-use liblunatix::prelude::syscall_abi::identify::CapabilityVariant;
+use liblunatix::caps::{MemoryCap, PageCap, VSpaceCap};
 use liblunatix::prelude::syscall_abi::MapFlags;
-use liblunatix::prelude::CAddr;
 
 const PAGESIZE: usize = 4096;
 
@@ -12,7 +11,7 @@ const PAGESIZE: usize = 4096;
 /// is used for it.
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 struct Mapping {
-    page: CAddr,
+    page: PageCap,
     local_addr: usize,
     target_addr: usize,
     flags: MapFlags,
@@ -22,13 +21,13 @@ struct Mapping {
 /// and then load content into them from the elf binary.
 pub struct LunatixElfLoader {
     /// The memory capability from which pages are allocated
-    mem: CAddr,
+    mem: MemoryCap,
     /// The vspace capability that is mapped to the currently active task.
     /// Content of the elf binary is loaded by mapping pages into this vspace and then storing the elf content
     /// inside it.
-    own_vspace: CAddr,
+    own_vspace: VSpaceCap,
     /// The vspace capability which is used by the task that will execute the elf binary.
-    target_vspace: CAddr,
+    target_vspace: VSpaceCap,
     /// Address at which pages are mapped while content is loaded into them.
     interim_addr: usize,
 
@@ -36,7 +35,12 @@ pub struct LunatixElfLoader {
 }
 
 impl LunatixElfLoader {
-    pub fn new(mem: CAddr, own_vspace: CAddr, target_vspace: CAddr, interim_addr: usize) -> Self {
+    pub fn new(
+        mem: MemoryCap,
+        own_vspace: VSpaceCap,
+        target_vspace: VSpaceCap,
+        interim_addr: usize,
+    ) -> Self {
         Self {
             mem,
             own_vspace,
@@ -52,7 +56,7 @@ impl LunatixElfLoader {
         target_addr: usize,
         target_flags: MapFlags,
     ) -> Option<&Mapping> {
-        let page = caddr_alloc::alloc_caddr();
+        let page = self.mem.derive(caddr_alloc::alloc_caddr()).unwrap();
         let mapping = Mapping {
             page,
             local_addr,
@@ -73,15 +77,16 @@ impl LunatixElfLoader {
     pub fn remap_to_target_vspace(&mut self) {
         for mapping in self.used_pages.iter() {
             log::trace!("remapping {mapping:x?} to target vspace");
-            liblunatix::ipc::page::unmap_page(mapping.page).unwrap();
-            liblunatix::ipc::page::map_page(
-                mapping.page,
-                self.target_vspace,
-                self.mem,
-                mapping.target_addr,
-                mapping.flags,
-            )
-            .unwrap();
+            mapping.page.unmap().unwrap();
+            mapping
+                .page
+                .map(
+                    self.target_vspace,
+                    self.mem,
+                    mapping.target_addr,
+                    mapping.flags,
+                )
+                .unwrap();
         }
     }
 }
@@ -116,18 +121,17 @@ impl ElfLoader for LunatixElfLoader {
                     load_header.offset()
                 );
                 self.interim_addr += PAGESIZE;
-                liblunatix::ipc::mem::derive(self.mem, mapping.page, CapabilityVariant::Page, None)
-                    .unwrap();
                 // map page for us so we can load content into it later
                 log::trace!("mapping page {:?} {:x}", mapping.page, mapping.local_addr);
-                liblunatix::ipc::page::map_page(
-                    mapping.page,
-                    self.own_vspace,
-                    self.mem,
-                    mapping.local_addr,
-                    MapFlags::READ | MapFlags::WRITE,
-                )
-                .unwrap();
+                mapping
+                    .page
+                    .map(
+                        self.own_vspace,
+                        self.mem,
+                        mapping.local_addr,
+                        MapFlags::READ | MapFlags::WRITE,
+                    )
+                    .unwrap();
             }
         }
 
@@ -19,41 +19,57 @@ use alloc::rc::Rc;
 use allocators::boundary_tag_alloc::{BoundaryTagAllocator, TagsU32};
 use caddr_alloc::CAddrAlloc;
 use core::fmt::Write;
-use core::{cell::RefCell, panic::PanicInfo, sync::atomic::AtomicUsize};
+use core::{cell::RefCell, panic::PanicInfo};
 use fdt::{node::FdtNode, Fdt};
+use initrd::Archive;
 use io::read::{ByteReader, EchoingByteReader};
-use liblunatix::prelude::syscall_abi::identify::CapabilityVariant;
+use liblunatix::caps::{
+    AsidControlCap, CSpaceCap, DevmemCap, IrqCap, IrqControlCap, MemoryCap, NotificationCap,
+    PageCap, VSpaceCap,
+};
 use liblunatix::prelude::syscall_abi::system_reset::{ResetReason, ResetType};
 use liblunatix::prelude::syscall_abi::MapFlags;
 use liblunatix::prelude::CAddr;
 use liblunatix::println;
+use liblunatix::rt::{select, Either, Runtime};
 use liblunatix::syscalls::print::SyscallWriter;
 use log::Level;
 use logger::Logger;
 use sifive_uart::SifiveUart;
 use static_once_cell::StaticOnceCell;
 use uart_driver::{MmUart, Uart};
+use virtio_input::{Keyboard, VirtioByteReader};
 use virtio_p9::{init_9p_driver, P9Driver};
 
 static LOGGER: Logger = Logger::new(Level::Info);
 
+/// The entry point of init which is called by the kernel.
+///
+/// The kernel passes the address and size of the initial ram filesystem which it has mapped into our vspace (or a size
+/// of 0 if no initrd was given at boot) as well as the address at which it has mapped the device tree.
 #[no_mangle]
-fn _start() {
+extern "C" fn _start(initrd_addr: *const u8, initrd_size: usize, dev_tree: *const u8) {
     LOGGER.install().expect("could not install logger");
-    main();
+    init_initrd(initrd_addr, initrd_size);
+    main(dev_tree);
 }
 
-/// How many bits the init tasks cspace uses to address its capabilities
-const CSPACE_BITS: usize = 7; // capacity = 128
-
-const CADDR_MEM: CAddr = CAddr::new(1, CSPACE_BITS);
-const _CADDR_CSPACE: CAddr = CAddr::new(2, CSPACE_BITS);
-const CADDR_VSPACE: CAddr = CAddr::new(3, CSPACE_BITS);
-const CADDR_IRQ_CONTROL: CAddr = CAddr::new(4, CSPACE_BITS);
-const CADDR_DEVMEM: CAddr = CAddr::new(5, CSPACE_BITS);
-const CADDR_ASID_CONTROL: CAddr = CAddr::new(6, CSPACE_BITS);
-const CADDR_UART_IRQ: CAddr = CAddr::new(7, CSPACE_BITS);
-const CADDR_UART_NOTIFICATION: CAddr = CAddr::new(8, CSPACE_BITS);
+// The kernel places these capabilities into the init tasks cspace.
+// Addresses which consist of a single part select the same slot regardless of how large the cspace is.
+const CADDR_MEM: MemoryCap = unsafe { MemoryCap::from_caddr_unchecked(CAddr::from_raw(1)) };
+const CADDR_CSPACE: CSpaceCap = unsafe { CSpaceCap::from_caddr_unchecked(CAddr::from_raw(2)) };
+const CADDR_VSPACE: VSpaceCap = unsafe { VSpaceCap::from_caddr_unchecked(CAddr::from_raw(3)) };
+const CADDR_IRQ_CONTROL: IrqControlCap =
+    unsafe { IrqControlCap::from_caddr_unchecked(CAddr::from_raw(4)) };
+const CADDR_DEVMEM: DevmemCap = unsafe { DevmemCap::from_caddr_unchecked(CAddr::from_raw(5)) };
+const CADDR_ASID_CONTROL: AsidControlCap =
+    unsafe { AsidControlCap::from_caddr_unchecked(CAddr::from_raw(6)) };
+// The kernel leaves these slots empty so that we can claim the uart interrupt into them
+const CADDR_UART_IRQ: CAddr = CAddr::from_raw(7);
+const CADDR_UART_NOTIFICATION: CAddr = CAddr::from_raw(8);
+/// The kernel places memory capabilities for additional usable memory regions in up to 7 consecutive slots starting
+/// here
+const _CADDR_EXTRA_MEM: CAddr = CAddr::from_raw(9);
 
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
@@ -61,7 +77,24 @@ fn panic_handler(info: &PanicInfo) -> ! {
     liblunatix::syscalls::system_reset(ResetType::Shutdown, ResetReason::SystemFailure);
 }
 
-fn init_uart<'a, 'dt>(node: &FdtNode<'a, 'dt>) -> Result<Uart<'static>, &'static str> {
+/// The capabilities through which the uart interrupt is received
+#[derive(Copy, Clone)]
+struct UartInterrupt {
+    irq: IrqCap,
+    notification: NotificationCap,
+}
+
+fn claim_uart_interrupt(interrupt_line: usize) -> UartInterrupt {
+    let notification = CADDR_MEM.derive(CADDR_UART_NOTIFICATION).unwrap();
+    let irq = CADDR_IRQ_CONTROL
+        .claim(interrupt_line, CADDR_UART_IRQ, notification)
+        .unwrap();
+    UartInterrupt { irq, notification }
+}
+
+fn init_uart<'a, 'dt>(
+    node: &FdtNode<'a, 'dt>,
+) -> Result<(Uart<'static>, UartInterrupt), &'static str> {
     if let None = node
         .compatible()
         .expect("no comptible")
@@ -83,39 +116,24 @@ fn init_uart<'a, 'dt>(node: &FdtNode<'a, 'dt>) -> Result<Uart<'static>, &'static
         return Err("no interrupt");
     };
 
-    liblunatix::ipc::mem::derive(
-        CADDR_MEM,
-        CADDR_UART_NOTIFICATION,
-        CapabilityVariant::Notification,
-        None,
-    )
-    .unwrap();
-    liblunatix::ipc::irq_control::irq_control_claim(
-        CADDR_IRQ_CONTROL,
-        interrupt as usize,
-        CADDR_UART_IRQ,
-        CADDR_UART_NOTIFICATION,
-    )
-    .unwrap();
-    assert_eq!(
-        liblunatix::syscalls::identify(CADDR_UART_IRQ).unwrap(),
-        CapabilityVariant::Irq
-    );
+    let interrupt = claim_uart_interrupt(interrupt as usize);
 
-    liblunatix::ipc::devmem::devmem_map(
-        CADDR_DEVMEM,
-        CADDR_MEM,
-        CADDR_VSPACE,
-        region.starting_address as usize,
-        region.size.unwrap() as usize,
-    )
-    .unwrap();
+    CADDR_DEVMEM
+        .map(
+            CADDR_MEM,
+            CADDR_VSPACE,
+            region.starting_address as usize,
+            region.size.unwrap() as usize,
+        )
+        .unwrap();
     let mut uart = unsafe { Uart::from_ptr(region.starting_address as *mut MmUart) };
     uart.enable_rx_interrupts();
-    Ok(uart)
+    Ok((uart, interrupt))
 }
 
-fn init_sifive_uart(node: &FdtNode<'_, '_>) -> Result<SifiveUart<'static>, &'static str> {
+fn init_sifive_uart(
+    node: &FdtNode<'_, '_>,
+) -> Result<(SifiveUart<'static>, UartInterrupt), &'static str> {
     let compatible = node.compatible().expect("no comptible").first();
     if compatible != "sifive,uart0" {
         return Err("not compatible");
@@ -133,70 +151,84 @@ fn init_sifive_uart(node: &FdtNode<'_, '_>) -> Result<SifiveUart<'static>, &'sta
         return Err("no interrupt");
     };
 
-    liblunatix::ipc::mem::derive(
-        CADDR_MEM,
-        CADDR_UART_NOTIFICATION,
-        CapabilityVariant::Notification,
-        None,
-    )
-    .unwrap();
-    liblunatix::ipc::irq_control::irq_control_claim(
-        CADDR_IRQ_CONTROL,
-        interrupt as usize,
-        CADDR_UART_IRQ,
-        CADDR_UART_NOTIFICATION,
-    )
-    .unwrap();
-    assert_eq!(
-        liblunatix::syscalls::identify(CADDR_UART_IRQ).unwrap(),
-        CapabilityVariant::Irq
-    );
+    let interrupt = claim_uart_interrupt(interrupt as usize);
 
-    liblunatix::ipc::devmem::devmem_map(
-        CADDR_DEVMEM,
-        CADDR_MEM,
-        CADDR_VSPACE,
-        region.starting_address as usize,
-        region.size.unwrap() as usize,
-    )
-    .unwrap();
+    CADDR_DEVMEM
+        .map(
+            CADDR_MEM,
+            CADDR_VSPACE,
+            region.starting_address as usize,
+            region.size.unwrap() as usize,
+        )
+        .unwrap();
     let mut uart = unsafe { SifiveUart::from_ptr(region.starting_address as *mut SifiveUartMM) };
     uart.enable_rx_interrupts();
-    Ok(uart)
+    Ok((uart, interrupt))
 }
 
-fn init_stdin(stdio: &FdtNode) -> Result<impl ByteReader, &'static str> {
-    enum Reader<'a> {
-        Uart(Uart<'a>),
-        Sifive(SifiveUart<'a>),
-    }
+/// The uart from which the chosen stdin of the device tree is read
+enum UartReader<'a> {
+    Uart(Uart<'a>, UartInterrupt),
+    Sifive(SifiveUart<'a>, UartInterrupt),
+}
 
-    impl ByteReader for Reader<'_> {
-        fn read_byte(&mut self) -> Result<u8, ()> {
-            match self {
-                Reader::Uart(uart) => {
-                    let _ = liblunatix::syscalls::wait_on(CADDR_UART_NOTIFICATION).unwrap();
-                    let c = unsafe { uart.read_data() };
-                    liblunatix::ipc::irq::irq_complete(CADDR_UART_IRQ).unwrap();
-                    return Ok(c);
-                }
-                Reader::Sifive(uart) => {
-                    let _ = liblunatix::syscalls::wait_on(CADDR_UART_NOTIFICATION).unwrap();
-                    let c = uart.read_data();
-                    liblunatix::ipc::irq::irq_complete(CADDR_UART_IRQ).unwrap();
-                    return Ok(c);
-                }
-            }
+impl UartReader<'_> {
+    fn interrupt(&self) -> UartInterrupt {
+        match self {
+            UartReader::Uart(_, interrupt) | UartReader::Sifive(_, interrupt) => *interrupt,
         }
     }
-    if let Ok(uart) = init_uart(stdio) {
-        return Ok(Reader::Uart(uart));
+
+    /// Read the byte whose arrival was signaled through the uart interrupt and complete the interrupt
+    fn take_byte(&mut self) -> u8 {
+        let c = match self {
+            UartReader::Uart(uart, _) => unsafe { uart.read_data() },
+            UartReader::Sifive(uart, _) => uart.read_data(),
+        };
+        self.interrupt().irq.complete().unwrap();
+        c
+    }
+
+    /// Wait through `rt` until a byte arrives
+    async fn next_byte(&mut self, rt: &Runtime) -> u8 {
+        let notification = rt.notification(self.interrupt().notification);
+        notification.wait().await.unwrap();
+        self.take_byte()
     }
+}
 
-    if let Ok(uart) = init_sifive_uart(&stdio) {
-        return Ok(Reader::Sifive(uart));
+impl ByteReader for UartReader<'_> {
+    fn read_byte(&mut self) -> Result<u8, ()> {
+        self.interrupt().notification.wait().unwrap();
+        Ok(self.take_byte())
+    }
+}
+
+fn init_stdin(stdio: &FdtNode) -> Result<UartReader<'static>, &'static str> {
+    if let Ok((uart, interrupt)) = init_uart(stdio) {
+        return Ok(UartReader::Uart(uart, interrupt));
+    }
+
+    if let Ok((uart, interrupt)) = init_sifive_uart(stdio) {
+        return Ok(UartReader::Sifive(uart, interrupt));
+    }
+    Err("could not init uart")
+}
+
+/// Reads bytes from whichever of the uart and the virtio keyboard delivers one first
+struct Stdin<K: Keyboard> {
+    rt: Runtime,
+    uart: UartReader<'static>,
+    keyboard: VirtioByteReader<K>,
+}
+
+impl<K: Keyboard> ByteReader for Stdin<K> {
+    fn read_byte(&mut self) -> Result<u8, ()> {
+        let Stdin { rt, uart, keyboard } = self;
+        match rt.block_on(select(uart.next_byte(rt), keyboard.next_byte(rt))) {
+            Either::Left(c) | Either::Right(c) => Ok(c),
+        }
     }
-    return Err("could not init uart");
 }
 
 unsafe impl Send for FileSystem {}
@@ -204,13 +236,28 @@ unsafe impl Sync for FileSystem {}
 pub struct FileSystem(RefCell<Option<P9Driver<'static>>>);
 pub static FS: FileSystem = FileSystem(RefCell::new(None));
 
+/// The initial ram filesystem which contains boot-time programs, if the kernel passed a valid one to us
+pub static INITRD: StaticOnceCell<Archive<'static>> = StaticOnceCell::new();
+
+fn init_initrd(initrd_addr: *const u8, initrd_size: usize) {
+    if initrd_size == 0 {
+        log::info!("no initrd was given at boot");
+        return;
+    }
+    let initrd = unsafe { core::slice::from_raw_parts(initrd_addr, initrd_size) };
+    match Archive::new(initrd) {
+        Ok(archive) => {
+            INITRD.get_or_init(|| archive);
+        }
+        Err(e) => log::warn!("the initrd is not a valid cpio archive: {e}"),
+    }
+}
+
 pub unsafe fn alloc_init(pages: usize, addr: *mut u8) -> BoundaryTagAllocator<'static, TagsU32> {
     const PAGESIZE: usize = 4096;
     for i in 0..pages {
-        let page = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(CADDR_MEM, page, CapabilityVariant::Page, None).unwrap();
-        liblunatix::ipc::page::map_page(
-            page,
+        let page: PageCap = CADDR_MEM.derive(caddr_alloc::alloc_caddr()).unwrap();
+        page.map(
             CADDR_VSPACE,
             CADDR_MEM,
             addr as usize + i * PAGESIZE,
@@ -227,10 +274,7 @@ pub unsafe fn alloc_init(pages: usize, addr: *mut u8) -> BoundaryTagAllocator<'s
 #[global_allocator]
 pub static ALLOC: StaticOnceCell<BoundaryTagAllocator<'static, TagsU32>> = StaticOnceCell::new();
 
-pub static CADDR_ALLOC: CAddrAlloc = CAddrAlloc {
-    cspace_bits: AtomicUsize::new(CSPACE_BITS),
-    cur: AtomicUsize::new(10),
-};
+pub static CADDR_ALLOC: CAddrAlloc = CAddrAlloc::undiscovered();
 
 pub struct Tee<A, B> {
     first: A,
@@ -257,12 +301,14 @@ impl<A: Write, B: Write> Write for Tee<A, B> {
     }
 }
 
-fn main() {
+fn main(dev_tree: *const u8) {
+    CADDR_ALLOC
+        .discover(CADDR_CSPACE, Some(CADDR_MEM))
+        .expect("could not query the layout of the init tasks cspace");
     unsafe { caddr_alloc::set_global_caddr_allocator(&CADDR_ALLOC) };
     ALLOC.get_or_init(|| unsafe { alloc_init(32, 0x10_0000 as *mut u8) });
-    let dev_tree_address: usize = 0x20_0000_0000;
-    let dt = unsafe { Fdt::from_ptr(dev_tree_address as *const u8).unwrap() };
-    let _stdin = init_stdin(&dt.chosen().stdout().expect("no stdout found")).unwrap();
+    let dt = unsafe { Fdt::from_ptr(dev_tree).unwrap() };
+    let stdin = init_stdin(&dt.chosen().stdout().expect("no stdout found")).unwrap();
 
     let p9 = init_9p_driver(CADDR_MEM, CADDR_VSPACE, CADDR_DEVMEM, CADDR_IRQ_CONTROL);
     let _ = FS.0.borrow_mut().insert(p9);
@@ -271,8 +317,12 @@ fn main() {
     let gpu_driver =
         virtio_gpu::gpu::init_gpu_driver(CADDR_MEM, CADDR_VSPACE, CADDR_DEVMEM, CADDR_IRQ_CONTROL);
     let gpu_driver = Rc::new(RefCell::new(gpu_driver));
-    let gpu_writer =
-        virtio_gpu::create_gpu_writer(gpu_driver.clone(), CADDR_MEM, CADDR_VSPACE, CSPACE_BITS);
+    let gpu_writer = virtio_gpu::create_gpu_writer(
+        gpu_driver.clone(),
+        CADDR_MEM,
+        CADDR_VSPACE,
+        CADDR_ALLOC.cspace_bits(),
+    );
 
     unsafe {
         let both = Tee {
@@ -287,11 +337,15 @@ fn main() {
     let input_driver =
         virtio_input::init_input_driver(CADDR_MEM, CADDR_VSPACE, CADDR_DEVMEM, CADDR_IRQ_CONTROL);
 
-    let byte_reader = virtio_input::VirtioByteReader {
-        input: input_driver,
-        keyboard: virtio_input::keyboards::QwertzKeyboard::new(),
+    let stdin = Stdin {
+        rt: Runtime::new(),
+        uart: stdin,
+        keyboard: VirtioByteReader {
+            input: input_driver,
+            keyboard: virtio_input::keyboards::QwertzKeyboard::new(),
+        },
     };
 
-    shell::shell(&mut EchoingByteReader(byte_reader));
+    shell::shell(&mut EchoingByteReader(stdin));
     println!("Init task says good bye 👋");
 }
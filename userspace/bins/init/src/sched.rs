@@ -1,14 +1,14 @@
 use alloc::collections::VecDeque;
+use liblunatix::caps::TaskCap;
 use liblunatix::prelude::syscall_abi::yield_to::TaskStatus;
-use liblunatix::prelude::CAddr;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Scheduler {
-    tasks: VecDeque<CAddr>,
+    tasks: VecDeque<TaskCap>,
 }
 
 impl Scheduler {
-    pub fn new(tasks: impl Iterator<Item = CAddr>) -> Self {
+    pub fn new(tasks: impl Iterator<Item = TaskCap>) -> Self {
         Self {
             tasks: VecDeque::from_iter(tasks),
         }
@@ -20,7 +20,7 @@ impl Scheduler {
                 "running schedule with {} tasks in round-robin until all are exited",
                 self.tasks.len()
             );
-            match liblunatix::syscalls::yield_to(task).unwrap() {
+            match task.yield_to().unwrap() {
                 TaskStatus::DidExecute => {
                     self.tasks.push_back(task);
                 }
@@ -74,6 +74,10 @@ const KNOWN_COMMANDS: &[&'static dyn Command] = &[
     &Help,
     &commands::Identify,
     &commands::Destroy,
+    &commands::Dmesg,
+    &commands::Kstat,
+    &commands::CapTree,
+    &commands::Kill,
     &commands::Copy,
     &commands::Cat,
     &commands::Ls,
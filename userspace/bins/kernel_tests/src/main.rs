@@ -0,0 +1,126 @@
+//! A program that exercises the kernel through all capability types.
+//!
+//! It is meant to be started as init (see `run_kernel.sh --init`) by a kernel that is built with the `kernel_tests`
+//! feature.
+//! The result of each test is reported through the serial console and the system is shut down with a reason that
+//! reflects the overall result, which the kernel turns into the exit status of QEMU.
+
+#![no_std]
+#![no_main]
+
+use caddr_alloc::CAddrAlloc;
+use core::panic::PanicInfo;
+use liblunatix::caps::{CSpaceCap, MemoryCap, VSpaceCap};
+use liblunatix::prelude::syscall_abi::system_reset::{ResetReason, ResetType};
+use liblunatix::prelude::{CAddr, Capability, SyscallError};
+use liblunatix::println;
+use liblunatix::thread::ThreadEnv;
+
+/// Fail the current test if the condition does not hold
+macro_rules! ensure {
+    ($cond:expr) => {
+        if !$cond {
+            return Err($crate::TestError::Assertion(stringify!($cond)));
+        }
+    };
+}
+
+/// Fail the current test if the two values are not equal
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    liblunatix::println!("left:  {:?}\nright: {:?}", left, right);
+                    return Err($crate::TestError::Assertion(concat!(
+                        stringify!($left),
+                        " == ",
+                        stringify!($right)
+                    )));
+                }
+            }
+        }
+    };
+}
+
+mod tests;
+
+const CADDR_MEM: CAddr = CAddr::from_raw(1);
+const CADDR_CSPACE: CAddr = CAddr::from_raw(2);
+const CADDR_VSPACE: CAddr = CAddr::from_raw(3);
+
+static CADDR_ALLOC: CAddrAlloc = CAddrAlloc::undiscovered();
+
+/// How many bits a CAddr part that selects a slot of our cspace has
+fn cspace_bits() -> usize {
+    CADDR_ALLOC.cspace_bits()
+}
+
+/// The reason for a test failure
+#[derive(Debug)]
+pub enum TestError {
+    /// A syscall failed unexpectedly
+    Syscall(SyscallError),
+    /// A condition which the test checks did not hold
+    Assertion(&'static str),
+}
+
+impl From<SyscallError> for TestError {
+    fn from(value: SyscallError) -> Self {
+        Self::Syscall(value)
+    }
+}
+
+pub type TestResult = Result<(), TestError>;
+
+#[no_mangle]
+fn _start() {
+    let cspace = CSpaceCap::from_caddr(CADDR_CSPACE).expect("slot 2 does not contain our cspace");
+    let mem = MemoryCap::from_caddr(CADDR_MEM).expect("slot 1 does not contain memory");
+    CADDR_ALLOC
+        .discover(cspace, Some(mem))
+        .expect("could not query the layout of our cspace");
+    unsafe { caddr_alloc::set_global_caddr_allocator(&CADDR_ALLOC) };
+    let vspace = VSpaceCap::from_caddr(CADDR_VSPACE).expect("slot 3 does not contain our vspace");
+    unsafe {
+        liblunatix::thread::set_thread_env(ThreadEnv {
+            mem,
+            cspace,
+            vspace,
+            alloc_caddr: caddr_alloc::alloc_caddr,
+            free_caddr: caddr_alloc::free_caddr,
+        })
+    };
+    let reason = match main() {
+        true => ResetReason::NoReason,
+        false => ResetReason::SystemFailure,
+    };
+    liblunatix::syscalls::system_reset(ResetType::Shutdown, reason);
+}
+
+#[panic_handler]
+fn panic_handler(info: &PanicInfo) -> ! {
+    println!("kernel_tests panicked {}", info);
+    liblunatix::syscalls::system_reset(ResetType::Shutdown, ResetReason::SystemFailure);
+}
+
+/// Run all tests and return whether they passed
+fn main() -> bool {
+    let mut failed = 0;
+    for (name, test) in tests::TESTS {
+        match test() {
+            Ok(()) => println!("test {} ... ok", name),
+            Err(e) => {
+                println!("test {} ... FAILED: {:?}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "kernel_tests: {} passed, {} failed",
+        tests::TESTS.len() - failed,
+        failed
+    );
+    failed == 0
+}
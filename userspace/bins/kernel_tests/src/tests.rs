@@ -0,0 +1,754 @@
+use caddr_alloc::{alloc_caddr, free_caddr, OwnedSlot};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use liblunatix::caps::{
+    Capability, EndpointCap, IrqControlCap, MemoryCap, NotificationCap, PageCap,
+};
+use liblunatix::ipc::cspace::{copy_slots, grow, info, CSpaceInfo};
+use liblunatix::ipc::page::{get_paddr, map_page};
+use liblunatix::ipc::task::{
+    task_assign_control_registers, task_assign_cspace, task_assign_vspace, task_bind_notification,
+    task_unbind_notification,
+};
+use liblunatix::prelude::syscall_abi::yield_to::TaskStatus;
+use liblunatix::prelude::syscall_abi::LookupError;
+use liblunatix::prelude::syscall_abi::MapFlags;
+use liblunatix::prelude::{CAddr, CapabilityVariant, SyscallError};
+use liblunatix::rt::{select, Either, Runtime};
+use liblunatix::syscalls::{copy, destroy, identify, identify_depth, receive, wait_any, yield_to};
+use liblunatix::thread;
+
+use crate::{
+    cspace_bits, TestError, TestResult, CADDR_ALLOC, CADDR_CSPACE, CADDR_MEM, CADDR_VSPACE,
+};
+
+/// All tests that are run, in order
+pub const TESTS: &[(&str, fn() -> TestResult)] = &[
+    ("derive_and_identify", derive_and_identify),
+    ("extra_memory", extra_memory),
+    ("destroy", destroy_caps),
+    ("destroy_final_copies", destroy_final_copies),
+    ("destroy_irq_copies", destroy_irq_copies),
+    ("copy", copy_caps),
+    ("copy_into_invalid_slot", copy_into_invalid_slot),
+    ("nested_cspace", nested_cspace),
+    ("invalid_caddr", invalid_caddr),
+    ("guarded_cspace", guarded_cspace),
+    ("resolve_depth", resolve_depth),
+    ("cspace_info", cspace_info),
+    ("grow_cspace", grow_cspace),
+    ("map_page", map_pages),
+    ("endpoint_ipc", endpoint_ipc),
+    ("destroy_endpoint_copy", destroy_endpoint_copy),
+    ("notification_binding", notification_binding),
+    ("typed_caps", typed_caps),
+    ("async_runtime", async_runtime),
+    ("wait_any", wait_any_objects),
+    ("wait_any_blocking", wait_any_blocking),
+    ("threads", threads),
+    ("caddr_reuse", caddr_reuse),
+    ("owned_slot", owned_slot),
+    // this fills up our cspace so that all following caddrs consist of multiple parts
+    ("caddr_alloc_expansion", caddr_alloc_expansion),
+];
+
+/// The slot in which the kernel places the IrqControl capability
+const CADDR_IRQ_CONTROL: CAddr = CAddr::from_raw(4);
+
+/// An interrupt line which is claimed by the `destroy_irq_copies` test because no device that is attached to qemu
+/// uses it
+const UNUSED_INTERRUPT_LINE: usize = 1;
+
+/// The address at which the page of the `map_page` test is mapped
+const TEST_PAGE_ADDR: usize = 0x6_0000_0000;
+
+/// The address at which the page of the `extra_memory` test is mapped
+const EXTRA_MEM_PAGE_ADDR: usize = 0x6_0007_0000;
+
+/// The slot in which the kernel places a memory capability for the first additional usable memory region
+const CADDR_EXTRA_MEM: CAddr = CAddr::from_raw(9);
+
+/// The address at which the stack of the sending task of the `endpoint_ipc` test is mapped
+const SENDER_STACK_ADDR: usize = 0x6_0001_0000;
+
+/// The address at which the stack of the sending task of the `async_runtime` test is mapped
+const ASYNC_SENDER_STACK_ADDR: usize = 0x6_0002_0000;
+
+/// The address at which the stack of the receiving task of the `notification_binding` test is mapped
+const RECEIVER_STACK_ADDR: usize = 0x6_0003_0000;
+
+/// The address at which the stack of the sending task of the `wait_any` test is mapped
+const WAIT_ANY_SENDER_STACK_ADDR: usize = 0x6_0004_0000;
+
+/// The addresses at which the stacks of the waiting and the sending task of the `wait_any_blocking` test are mapped
+const WAIT_ANY_BLOCKING_STACK_ADDRS: [usize; 2] = [0x6_0008_0000, 0x6_0009_0000];
+
+/// The addresses at which the stacks of the sending tasks of the `destroy_endpoint_copy` test are mapped
+const DESTROY_SENDER_STACK_ADDRS: [usize; 2] = [0x6_0005_0000, 0x6_0006_0000];
+
+const IPC_LABEL: usize = 42;
+const IPC_DATA: [usize; 2] = [0x55, 0xaa];
+
+/// Derive a new capability from the memory capability into a freshly allocated slot
+fn derive(variant: CapabilityVariant, size: Option<usize>) -> Result<CAddr, SyscallError> {
+    let caddr = alloc_caddr();
+    liblunatix::ipc::mem::derive(CADDR_MEM, caddr, variant, size)?;
+    Ok(caddr)
+}
+
+fn derive_and_identify() -> TestResult {
+    for (variant, size) in [
+        (CapabilityVariant::CSpace, Some(4)),
+        (CapabilityVariant::VSpace, None),
+        (CapabilityVariant::Task, None),
+        (CapabilityVariant::Page, None),
+        (CapabilityVariant::Notification, None),
+        (CapabilityVariant::Endpoint, None),
+    ] {
+        let caddr = derive(variant, size)?;
+        ensure_eq!(identify(caddr), Ok(variant));
+    }
+    Ok(())
+}
+
+fn extra_memory() -> TestResult {
+    // the kernel only creates additional memory capabilities if the machine has more than one memory bank
+    if identify(CADDR_EXTRA_MEM)? != CapabilityVariant::Memory {
+        return Ok(());
+    }
+
+    let page = alloc_caddr();
+    liblunatix::ipc::mem::derive(CADDR_EXTRA_MEM, page, CapabilityVariant::Page, None)?;
+    map_page(
+        page,
+        CADDR_VSPACE,
+        CADDR_MEM,
+        EXTRA_MEM_PAGE_ADDR,
+        MapFlags::READ | MapFlags::WRITE,
+    )?;
+    let ptr = EXTRA_MEM_PAGE_ADDR as *mut usize;
+    unsafe { ptr.write_volatile(0x1234_5678) };
+    ensure_eq!(unsafe { ptr.read_volatile() }, 0x1234_5678);
+    Ok(())
+}
+
+fn destroy_caps() -> TestResult {
+    for variant in [
+        CapabilityVariant::Task,
+        CapabilityVariant::Page,
+        CapabilityVariant::Notification,
+        CapabilityVariant::Endpoint,
+    ] {
+        let caddr = derive(variant, None)?;
+        destroy(caddr)?;
+        ensure_eq!(identify(caddr), Ok(CapabilityVariant::Uninit));
+    }
+    Ok(())
+}
+
+fn destroy_final_copies() -> TestResult {
+    // the capabilities in the slots of a cspace are destroyed together with its last copy
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let slot_1 = CAddr::builder()
+        .part(cspace.raw(), cspace_bits())
+        .part(1, 2)
+        .finish();
+    let notification = derive(CapabilityVariant::Notification, None)?;
+    copy(notification, slot_1)?;
+    destroy(cspace)?;
+    ensure_eq!(identify(cspace), Ok(CapabilityVariant::Uninit));
+    ensure_eq!(identify(notification), Ok(CapabilityVariant::Notification));
+
+    let vspace = derive(CapabilityVariant::VSpace, None)?;
+    destroy(vspace)?;
+    ensure_eq!(identify(vspace), Ok(CapabilityVariant::Uninit));
+
+    // a task holds the last copies of its cspace and vspace once our copies are destroyed
+    let task = derive(CapabilityVariant::Task, None)?;
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let vspace = derive(CapabilityVariant::VSpace, None)?;
+    task_assign_cspace(cspace, task)?;
+    task_assign_vspace(vspace, task)?;
+    destroy(cspace)?;
+    destroy(vspace)?;
+    destroy(task)?;
+    ensure_eq!(identify(task), Ok(CapabilityVariant::Uninit));
+    Ok(())
+}
+
+fn destroy_irq_copies() -> TestResult {
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let slot = |index| {
+        CAddr::builder()
+            .part(cspace.raw(), cspace_bits())
+            .part(index, 2)
+            .finish()
+    };
+    let notification: NotificationCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let irq = IrqControlCap::from_caddr(CADDR_IRQ_CONTROL)?.claim(
+        UNUSED_INTERRUPT_LINE,
+        slot(0),
+        notification,
+    )?;
+    copy(irq.caddr(), slot(1))?;
+
+    // neither copy is the last one but both would be destroyed together with the cspace which is not supported yet
+    ensure_eq!(destroy(cspace), Err(SyscallError::Unsupported));
+    ensure_eq!(identify(slot(0)), Ok(CapabilityVariant::Irq));
+    ensure_eq!(identify(slot(1)), Ok(CapabilityVariant::Irq));
+
+    // a copy can still be destroyed on its own while the last copy cannot
+    destroy(slot(1))?;
+    ensure_eq!(identify(slot(1)), Ok(CapabilityVariant::Uninit));
+    ensure_eq!(destroy(slot(0)), Err(SyscallError::Unsupported));
+    ensure_eq!(destroy(cspace), Err(SyscallError::Unsupported));
+    Ok(())
+}
+
+fn copy_caps() -> TestResult {
+    let original = derive(CapabilityVariant::Notification, None)?;
+    let copied = alloc_caddr();
+    copy(original, copied)?;
+    ensure_eq!(identify(copied), Ok(CapabilityVariant::Notification));
+
+    // the copy outlives the original
+    destroy(original)?;
+    ensure_eq!(identify(original), Ok(CapabilityVariant::Uninit));
+    ensure_eq!(identify(copied), Ok(CapabilityVariant::Notification));
+    destroy(copied)?;
+    ensure_eq!(identify(copied), Ok(CapabilityVariant::Uninit));
+    Ok(())
+}
+
+fn copy_into_invalid_slot() -> TestResult {
+    let src = derive(CapabilityVariant::Endpoint, None)?;
+    let occupied = derive(CapabilityVariant::Endpoint, None)?;
+    ensure_eq!(copy(src, occupied), Err(SyscallError::OccupiedSlot));
+    ensure_eq!(copy(src, src), Err(SyscallError::AliasingCSlot));
+    ensure_eq!(
+        copy(alloc_caddr(), alloc_caddr()),
+        Err(SyscallError::InvalidCap)
+    );
+    Ok(())
+}
+
+fn nested_cspace() -> TestResult {
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let nested = CAddr::builder()
+        .part(cspace.raw(), cspace_bits())
+        .part(2, 2)
+        .finish();
+    ensure_eq!(identify(nested), Ok(CapabilityVariant::Uninit));
+    liblunatix::ipc::mem::derive(CADDR_MEM, nested, CapabilityVariant::Notification, None)?;
+    ensure_eq!(identify(nested), Ok(CapabilityVariant::Notification));
+    Ok(())
+}
+
+fn invalid_caddr() -> TestResult {
+    // only cspaces can be used to address further capabilities
+    let notification = derive(CapabilityVariant::Notification, None)?;
+    let below_notification = CAddr::builder()
+        .part(notification.raw(), cspace_bits())
+        .part(0, 1)
+        .finish();
+    ensure_eq!(
+        identify(below_notification),
+        Err(SyscallError::InvalidCAddr)
+    );
+    ensure_eq!(destroy(below_notification), Err(SyscallError::InvalidCAddr));
+    Ok(())
+}
+
+fn guarded_cspace() -> TestResult {
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    liblunatix::ipc::cspace::set_guard(cspace, 0b101, 3)?;
+
+    // parts that select a slot of the guarded cspace consist of the guard followed by 2 bits for the slot
+    let slot_1 = |guard: usize| {
+        CAddr::builder()
+            .part(cspace.raw(), cspace_bits())
+            .part(guard << 2 | 1, 3 + 2)
+            .finish()
+    };
+    ensure_eq!(identify(slot_1(0b101)), Ok(CapabilityVariant::Uninit));
+    liblunatix::ipc::mem::derive(
+        CADDR_MEM,
+        slot_1(0b101),
+        CapabilityVariant::Notification,
+        None,
+    )?;
+    ensure_eq!(identify(slot_1(0b101)), Ok(CapabilityVariant::Notification));
+    ensure_eq!(
+        identify_depth(slot_1(0b100), CAddr::FULL_DEPTH),
+        Err(LookupError {
+            error: SyscallError::InvalidCAddr,
+            depth: cspace_bits()
+        })
+    );
+
+    // the guard must fit into its bits
+    ensure_eq!(
+        liblunatix::ipc::cspace::set_guard(cspace, 0b1000, 3),
+        Err(SyscallError::InvalidArg)
+    );
+    Ok(())
+}
+
+fn resolve_depth() -> TestResult {
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let nested = CAddr::builder()
+        .part(cspace.raw(), cspace_bits())
+        .part(3, 2)
+        .finish();
+
+    // stopping after the first part addresses the cspace itself
+    ensure_eq!(
+        identify_depth(nested, cspace_bits()),
+        Ok(CapabilityVariant::CSpace)
+    );
+    ensure_eq!(
+        identify_depth(nested, cspace_bits() + 2),
+        Ok(CapabilityVariant::Uninit)
+    );
+    ensure_eq!(
+        identify_depth(nested, cspace_bits() + 1),
+        Err(LookupError {
+            error: SyscallError::InvalidCAddr,
+            depth: cspace_bits()
+        })
+    );
+
+    // descending below a capability that is not a cspace reports how far resolution got
+    let notification = derive(CapabilityVariant::Notification, None)?;
+    let below_notification = CAddr::builder()
+        .part(notification.raw(), cspace_bits())
+        .part(0, 1)
+        .finish();
+    ensure_eq!(
+        identify_depth(below_notification, CAddr::FULL_DEPTH),
+        Err(LookupError {
+            error: SyscallError::InvalidCAddr,
+            depth: cspace_bits()
+        })
+    );
+    Ok(())
+}
+
+fn cspace_info() -> TestResult {
+    let cspace = derive(CapabilityVariant::CSpace, Some(4))?;
+    let slot_1 = CAddr::builder()
+        .part(cspace.raw(), cspace_bits())
+        .part(1, 2)
+        .finish();
+    liblunatix::ipc::mem::derive(CADDR_MEM, slot_1, CapabilityVariant::Notification, None)?;
+    ensure_eq!(
+        info(cspace),
+        Ok(CSpaceInfo {
+            radix: 2,
+            num_slots: 4,
+            occupied: 1,
+            free_from: 2,
+            guard: 0,
+            guard_bits: 0,
+        })
+    );
+
+    // a cspace with a single slot does not need any bits to address it
+    let single = derive(CapabilityVariant::CSpace, Some(1))?;
+    ensure_eq!(info(single)?.radix, 0);
+
+    // our own cspace contains at least the capabilities that the kernel placed into it
+    let own = info(CADDR_CSPACE)?;
+    ensure_eq!(own.part_bits(), cspace_bits());
+    ensure!(own.free_from > CADDR_VSPACE.raw());
+    Ok(())
+}
+
+fn grow_cspace() -> TestResult {
+    let slot_1 = |cspace: CAddr| {
+        CAddr::builder()
+            .part(cspace.raw(), cspace_bits())
+            .part(1, 1)
+            .finish()
+    };
+    let small = derive(CapabilityVariant::CSpace, Some(2))?;
+    liblunatix::ipc::mem::derive(
+        CADDR_MEM,
+        slot_1(small),
+        CapabilityVariant::Notification,
+        None,
+    )?;
+
+    let large = alloc_caddr();
+    grow(small, CADDR_MEM, large, 8)?;
+    ensure_eq!(info(large)?.occupied, 1);
+    // the bits of the last part are only used for validation so the same address selects the copy
+    ensure_eq!(identify(slot_1(large)), Ok(CapabilityVariant::Notification));
+    ensure_eq!(identify(slot_1(small)), Ok(CapabilityVariant::Notification));
+
+    // slots are only copied into distinct cspaces which are at least as large and free where they are copied into
+    ensure_eq!(copy_slots(large, small), Err(SyscallError::InvalidArg));
+    ensure_eq!(copy_slots(small, large), Err(SyscallError::OccupiedSlot));
+    ensure_eq!(copy_slots(small, small), Err(SyscallError::InvalidArg));
+
+    // our own cspace is in use while we make a syscall so it can neither be copied nor copied into
+    let own_size = derive(
+        CapabilityVariant::CSpace,
+        Some(info(CADDR_CSPACE)?.num_slots),
+    )?;
+    ensure_eq!(copy_slots(CADDR_CSPACE, own_size), Err(SyscallError::Busy));
+    ensure_eq!(copy_slots(small, CADDR_CSPACE), Err(SyscallError::Busy));
+    Ok(())
+}
+
+fn map_pages() -> TestResult {
+    let page = derive(CapabilityVariant::Page, None)?;
+    let rw = MapFlags::READ | MapFlags::WRITE;
+    map_page(page, CADDR_VSPACE, CADDR_MEM, TEST_PAGE_ADDR, rw)?;
+    ensure!(get_paddr(page)? != 0);
+
+    // the page is usable through the address at which it is mapped
+    let ptr = TEST_PAGE_ADDR as *mut usize;
+    unsafe { ptr.write_volatile(0x1234_5678) };
+    ensure_eq!(unsafe { ptr.read_volatile() }, 0x1234_5678);
+
+    let other = derive(CapabilityVariant::Page, None)?;
+    ensure_eq!(
+        map_page(other, CADDR_VSPACE, CADDR_MEM, TEST_PAGE_ADDR, rw),
+        Err(SyscallError::AlreadyMapped)
+    );
+    ensure_eq!(
+        map_page(other, CADDR_VSPACE, CADDR_MEM, TEST_PAGE_ADDR + 8, rw),
+        Err(SyscallError::InvalidArg)
+    );
+    Ok(())
+}
+
+/// Entry point of the task which sends a message to this one in the `endpoint_ipc` test
+extern "C" fn ipc_sender() -> ! {
+    // the endpoint is placed in the first slot of the tasks own cspace
+    const ENDPOINT: CAddr = CAddr::new(1, 1);
+    let _ = liblunatix::syscalls::send(ENDPOINT, IPC_LABEL, &[], &IPC_DATA);
+    liblunatix::syscalls::exit();
+}
+
+/// The number of times the task of the `wait_any_blocking` test was woken by its notification and its endpoint
+static WAIT_ANY_WAKEUPS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// Entry point of the task which waits on a notification and an endpoint in the `wait_any_blocking` test
+extern "C" fn wait_any_waiter() -> ! {
+    // the notification is placed in the zeroth and the endpoint in the first slot of the tasks own cspace
+    const NOTIFICATION: CAddr = CAddr::new(0, 1);
+    const ENDPOINT: CAddr = CAddr::new(1, 1);
+    while let Ok(index) = wait_any(&[NOTIFICATION, ENDPOINT]) {
+        WAIT_ANY_WAKEUPS[index].fetch_add(1, Ordering::SeqCst);
+        if index == 1 {
+            let _ = receive(ENDPOINT, 0, &[]);
+            break;
+        }
+    }
+    liblunatix::syscalls::exit();
+}
+
+/// The notification value with which the receive of [`ipc_receiver()`] was interrupted
+static RECEIVED_NOTIFICATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Entry point of the task which receives from an endpoint in the `notification_binding` test
+extern "C" fn ipc_receiver() -> ! {
+    const ENDPOINT: CAddr = CAddr::new(1, 1);
+    if let Ok(message) = receive(ENDPOINT, 0, &[]) {
+        if message.is_notification() {
+            RECEIVED_NOTIFICATION.store(message.notification, Ordering::SeqCst);
+        }
+    }
+    liblunatix::syscalls::exit();
+}
+
+/// Prepare a task which sends a message to `endpoint` and then exits
+fn spawn_ipc_sender(endpoint: CAddr, stack_addr: usize) -> Result<(CAddr, CAddr), TestError> {
+    spawn_ipc_task(ipc_sender, endpoint, stack_addr)
+}
+
+/// Prepare a task which starts at `entry` and has a copy of `endpoint` in the first slot of its cspace.
+///
+/// The task shares this tasks address space but has its own cspace and a stack which is mapped at `stack_addr`.
+/// Returns the task and the address of its endpoint copy from the view of this task.
+fn spawn_ipc_task(
+    entry: extern "C" fn() -> !,
+    endpoint: CAddr,
+    stack_addr: usize,
+) -> Result<(CAddr, CAddr), TestError> {
+    let task = derive(CapabilityVariant::Task, None)?;
+    let cspace = derive(CapabilityVariant::CSpace, Some(2))?;
+    task_assign_cspace(cspace, task)?;
+    task_assign_vspace(CADDR_VSPACE, task)?;
+    let stack = derive(CapabilityVariant::Page, None)?;
+    map_page(
+        stack,
+        CADDR_VSPACE,
+        CADDR_MEM,
+        stack_addr,
+        MapFlags::READ | MapFlags::WRITE,
+    )?;
+    task_assign_control_registers(task, entry as usize, stack_addr + 4096, 0, 0)?;
+
+    let task_endpoint = CAddr::builder()
+        .part(cspace.raw(), cspace_bits())
+        .part(1, 1)
+        .finish();
+    copy(endpoint, task_endpoint)?;
+    Ok((task, task_endpoint))
+}
+
+fn endpoint_ipc() -> TestResult {
+    let endpoint = derive(CapabilityVariant::Endpoint, None)?;
+    let (task, _) = spawn_ipc_sender(endpoint, SENDER_STACK_ADDR)?;
+
+    // the sender blocks because nobody is receiving yet which lets the receive below complete immediately
+    run_until(task, TaskStatus::Blocked)?;
+    let message = receive(endpoint, 0, &[])?;
+    ensure_eq!(message.tag.label(), IPC_LABEL);
+    ensure_eq!(message.raw_args[..IPC_DATA.len()], IPC_DATA);
+
+    run_until(task, TaskStatus::Exited)
+}
+
+fn destroy_endpoint_copy() -> TestResult {
+    // destroying the copy through which the sender waits leaves it blocked on the endpoint which still exists
+    let endpoint = derive(CapabilityVariant::Endpoint, None)?;
+    let (task, task_endpoint) = spawn_ipc_sender(endpoint, DESTROY_SENDER_STACK_ADDRS[0])?;
+    run_until(task, TaskStatus::Blocked)?;
+    destroy(task_endpoint)?;
+    ensure_eq!(yield_to(task), Ok(TaskStatus::Blocked));
+    let message = receive(endpoint, 0, &[])?;
+    ensure_eq!(message.tag.label(), IPC_LABEL);
+    run_until(task, TaskStatus::Exited)?;
+
+    // destroying the final copy fails the send so that the sender exits
+    let (task, task_endpoint) = spawn_ipc_sender(endpoint, DESTROY_SENDER_STACK_ADDRS[1])?;
+    run_until(task, TaskStatus::Blocked)?;
+    destroy(endpoint)?;
+    ensure_eq!(yield_to(task), Ok(TaskStatus::Blocked));
+    destroy(task_endpoint)?;
+    run_until(task, TaskStatus::Exited)
+}
+
+fn notification_binding() -> TestResult {
+    let notification = derive(CapabilityVariant::Notification, None)?;
+    let endpoint = derive(CapabilityVariant::Endpoint, None)?;
+    let (task, _) = spawn_ipc_task(ipc_receiver, endpoint, RECEIVER_STACK_ADDR)?;
+    let other = derive(CapabilityVariant::Task, None)?;
+
+    // a notification can only be bound to one task at a time
+    task_bind_notification(other, notification)?;
+    ensure_eq!(
+        task_bind_notification(task, notification),
+        Err(SyscallError::InvalidArg)
+    );
+    task_unbind_notification(other)?;
+    task_bind_notification(task, notification)?;
+
+    // signaling the notification interrupts the receive of the bound task with the notification value
+    run_until(task, TaskStatus::Blocked)?;
+    NotificationCap::from_caddr(notification)?.signal()?;
+    run_until(task, TaskStatus::Exited)?;
+    ensure_eq!(RECEIVED_NOTIFICATION.load(Ordering::SeqCst), 1);
+
+    // the notification is consumed by the receive
+    ensure_eq!(NotificationCap::from_caddr(notification)?.poll(), Ok(0));
+    task_unbind_notification(task)?;
+    task_bind_notification(other, notification)?;
+    task_unbind_notification(other)?;
+    Ok(())
+}
+
+fn typed_caps() -> TestResult {
+    // handles are only created for capabilities of the matching variant
+    ensure_eq!(
+        PageCap::from_caddr(CADDR_MEM),
+        Err(SyscallError::InvalidCap)
+    );
+    let mem = MemoryCap::from_caddr(CADDR_MEM)?;
+    ensure_eq!(mem.caddr(), CADDR_MEM);
+
+    let page: PageCap = mem.derive(alloc_caddr())?;
+    ensure_eq!(PageCap::try_from(page.caddr()), Ok(page));
+    let copy = page.copy_to(alloc_caddr())?;
+    ensure_eq!(copy.paddr(), page.paddr());
+    copy.destroy()?;
+    ensure_eq!(
+        PageCap::from_caddr(copy.caddr()),
+        Err(SyscallError::InvalidCap)
+    );
+    Ok(())
+}
+
+fn async_runtime() -> TestResult {
+    let notification: NotificationCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let endpoint: EndpointCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+
+    // polling does not block on objects which are not ready
+    ensure_eq!(notification.poll(), Ok(0));
+    ensure_eq!(endpoint.has_sender(), Ok(false));
+
+    let (task, _) = spawn_ipc_sender(endpoint.caddr(), ASYNC_SENDER_STACK_ADDR)?;
+    run_until(task, TaskStatus::Blocked)?;
+    ensure_eq!(endpoint.has_sender(), Ok(true));
+
+    // only the endpoint is ready so the runtime completes the receive and drops the notification future
+    let rt = Runtime::new();
+    let result = rt.block_on(select(
+        rt.notification(notification).wait(),
+        rt.endpoint(endpoint).recv(0, &[]),
+    ));
+    let Either::Right(message) = result else {
+        return Err(TestError::Assertion("the notification became ready"));
+    };
+    let message = message?;
+    ensure_eq!(message.tag.label(), IPC_LABEL);
+    ensure_eq!(message.raw_args[..IPC_DATA.len()], IPC_DATA);
+    ensure_eq!(endpoint.has_sender(), Ok(false));
+
+    run_until(task, TaskStatus::Exited)
+}
+
+fn wait_any_objects() -> TestResult {
+    let notification: NotificationCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let endpoint: EndpointCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let page: PageCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+
+    // only notifications and endpoints can be waited on and at least one of them is required
+    ensure_eq!(wait_any(&[]), Err(SyscallError::InvalidArg));
+    ensure_eq!(
+        wait_any(&[notification.caddr(), page.caddr()]),
+        Err(SyscallError::InvalidCap)
+    );
+
+    // the index of the endpoint is returned once a sender is blocked on it
+    let (task, _) = spawn_ipc_sender(endpoint.caddr(), WAIT_ANY_SENDER_STACK_ADDR)?;
+    run_until(task, TaskStatus::Blocked)?;
+    ensure_eq!(wait_any(&[notification.caddr(), endpoint.caddr()]), Ok(1));
+
+    // waiting does not consume the message
+    let message = endpoint.receive(0, &[])?;
+    ensure_eq!(message.tag.label(), IPC_LABEL);
+    ensure_eq!(message.raw_args[..IPC_DATA.len()], IPC_DATA);
+
+    run_until(task, TaskStatus::Exited)
+}
+
+fn wait_any_blocking() -> TestResult {
+    let notification: NotificationCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let endpoint: EndpointCap = MemoryCap::from_caddr(CADDR_MEM)?.derive(alloc_caddr())?;
+    let (waiter, task_endpoint) = spawn_ipc_task(
+        wait_any_waiter,
+        endpoint.caddr(),
+        WAIT_ANY_BLOCKING_STACK_ADDRS[0],
+    )?;
+    let (task_cspace, _) = task_endpoint.take_bits(cspace_bits());
+    let task_notification = CAddr::builder()
+        .part(task_cspace, cspace_bits())
+        .part(0, 1)
+        .finish();
+    copy(notification.caddr(), task_notification)?;
+
+    // the waiter blocks because neither object is ready and is woken by signaling the notification
+    run_until(waiter, TaskStatus::Blocked)?;
+    ensure_eq!(WAIT_ANY_WAKEUPS[0].load(Ordering::SeqCst), 0);
+    notification.signal()?;
+    run_until(waiter, TaskStatus::Blocked)?;
+    ensure_eq!(WAIT_ANY_WAKEUPS[0].load(Ordering::SeqCst), 1);
+    ensure_eq!(WAIT_ANY_WAKEUPS[1].load(Ordering::SeqCst), 0);
+
+    // the waiter is woken again by a sender arriving at the endpoint and then receives its message
+    let (sender, _) = spawn_ipc_sender(endpoint.caddr(), WAIT_ANY_BLOCKING_STACK_ADDRS[1])?;
+    run_until(sender, TaskStatus::Blocked)?;
+    run_until(waiter, TaskStatus::Exited)?;
+    ensure_eq!(WAIT_ANY_WAKEUPS[0].load(Ordering::SeqCst), 1);
+    ensure_eq!(WAIT_ANY_WAKEUPS[1].load(Ordering::SeqCst), 1);
+    run_until(sender, TaskStatus::Exited)
+}
+
+fn threads() -> TestResult {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    ensure_eq!(thread::current(), None);
+
+    // threads share our address space and can thus use our statics
+    let handle = thread::spawn(|| {
+        COUNTER.fetch_add(1, Ordering::SeqCst);
+        (thread::current(), IPC_LABEL)
+    })?;
+    let spawned = handle.thread();
+    let (current, result) = handle.join()?;
+    ensure_eq!(current, Some(spawned));
+    ensure_eq!(result, IPC_LABEL);
+    ensure_eq!(COUNTER.load(Ordering::SeqCst), 1);
+
+    // the task of a joined thread is destroyed
+    ensure_eq!(
+        identify(spawned.task().caddr()),
+        Ok(CapabilityVariant::Uninit)
+    );
+    Ok(())
+}
+
+fn caddr_reuse() -> TestResult {
+    let caddr = derive(CapabilityVariant::Notification, None)?;
+    destroy(caddr)?;
+    free_caddr(caddr);
+    ensure_eq!(alloc_caddr(), caddr);
+    free_caddr(caddr);
+    Ok(())
+}
+
+fn owned_slot() -> TestResult {
+    let slot = OwnedSlot::alloc();
+    let caddr = slot.caddr();
+    liblunatix::ipc::mem::derive(CADDR_MEM, caddr, CapabilityVariant::Notification, None)?;
+
+    // dropping the slot destroys the capability and allows the slot to be allocated again
+    drop(slot);
+    ensure_eq!(identify(caddr), Ok(CapabilityVariant::Uninit));
+    ensure_eq!(alloc_caddr(), caddr);
+    free_caddr(caddr);
+    Ok(())
+}
+
+fn caddr_alloc_expansion() -> TestResult {
+    // allocate until our cspace is full and the allocator continues in an additional cspace
+    let num_slots = info(CADDR_CSPACE)?.num_slots;
+    let caddr = loop {
+        let caddr = alloc_caddr();
+        if caddr.raw() >= num_slots {
+            break caddr;
+        }
+    };
+    ensure_eq!(identify(caddr), Ok(CapabilityVariant::Uninit));
+    liblunatix::ipc::mem::derive(CADDR_MEM, caddr, CapabilityVariant::Notification, None)?;
+    ensure_eq!(identify(caddr), Ok(CapabilityVariant::Notification));
+
+    // the additional cspace is placed into the last slot of ours
+    ensure_eq!(
+        identify(CAddr::from_raw(num_slots - 1)),
+        Ok(CapabilityVariant::CSpace)
+    );
+
+    // the slots of a cspace which is stored in the additional cspace are addressed behind its multi-part address
+    let nested = MemoryCap::from_caddr(CADDR_MEM)?.derive_cspace(alloc_caddr(), 2)?;
+    let nested_slot = CADDR_ALLOC.nested_caddr(nested.caddr(), 1, 1);
+    copy(caddr, nested_slot)?;
+    ensure_eq!(identify(nested_slot), Ok(CapabilityVariant::Notification));
+    Ok(())
+}
+
+/// Yield to the task until it reports the given status
+fn run_until(task: CAddr, status: TaskStatus) -> TestResult {
+    const MAX_YIELDS: usize = 100;
+    for _ in 0..MAX_YIELDS {
+        if yield_to(task)? == status {
+            return Ok(());
+        }
+    }
+    Err(TestError::Assertion(
+        "task did not reach the expected status",
+    ))
+}
@@ -0,0 +1,197 @@
+use liblunatix::prelude::syscall_abi::call::{Call, CallArgs};
+use liblunatix::prelude::syscall_abi::copy::CopyArgs;
+use liblunatix::prelude::syscall_abi::debug::{DebugLog, DebugLogArgs};
+use liblunatix::prelude::syscall_abi::destroy::DestroyArgs;
+use liblunatix::prelude::syscall_abi::identify::{Identify, IdentifyArgs};
+use liblunatix::prelude::syscall_abi::receive::{Receive, ReceiveArgs};
+use liblunatix::prelude::syscall_abi::send::SendArgs;
+use liblunatix::prelude::syscall_abi::{self, IpcTag, RawSyscallReturn, SyscallBinding};
+use liblunatix::prelude::{CAddr, CapabilityVariant};
+use liblunatix::println;
+use liblunatix::syscalls::raw_syscall;
+
+use crate::CADDR_MEM;
+
+/// How many capability slots the fuzzer uses as targets and arguments of its syscalls
+pub const NUM_SCRATCH_SLOTS: usize = 8;
+
+/// A xorshift pseudo random number generator
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
+    }
+
+    /// Return a random number that is smaller than `n`
+    fn below(&mut self, n: usize) -> usize {
+        self.next() % n
+    }
+}
+
+pub struct Fuzzer {
+    rng: XorShift,
+    scratch: [CAddr; NUM_SCRATCH_SLOTS],
+    /// How many bits a CAddr part that selects a slot of our root CSpace has
+    cspace_bits: usize,
+}
+
+impl Fuzzer {
+    /// Create a fuzzer which only addresses the given slots of our root CSpace and the capabilities contained below
+    /// them
+    pub fn new(scratch: [CAddr; NUM_SCRATCH_SLOTS], cspace_bits: usize) -> Self {
+        // some slots contain objects so that the syscalls reach deeper into the kernel
+        let variants = [
+            CapabilityVariant::Notification,
+            CapabilityVariant::Endpoint,
+            CapabilityVariant::Task,
+            CapabilityVariant::Page,
+            CapabilityVariant::CSpace,
+            CapabilityVariant::VSpace,
+        ];
+        for (&slot, variant) in scratch.iter().zip(variants) {
+            let size = match variant {
+                CapabilityVariant::CSpace => Some(4),
+                _ => None,
+            };
+            if let Err(e) = liblunatix::ipc::mem::derive(CADDR_MEM, slot, variant, size) {
+                println!(
+                    "could not derive capability into {:?} for fuzzing: {:?}",
+                    slot, e
+                );
+            }
+        }
+
+        Self {
+            rng: XorShift(0x2545_f491_4f6c_dd1d),
+            scratch,
+            cspace_bits,
+        }
+    }
+
+    /// Return a CAddr that points to one of the scratch slots or somewhere below one of them.
+    ///
+    /// Other capabilities of the task are never addressed so that the fuzzer cannot destroy them.
+    fn random_caddr(&mut self) -> CAddr {
+        let slot = self.scratch[self.rng.below(NUM_SCRATCH_SLOTS)];
+        match self.rng.below(4) {
+            // set the continuation bit so that random parts follow the scratch slot
+            0 => CAddr::from_raw(
+                slot.raw() | 1 << self.cspace_bits | self.rng.next() << (self.cspace_bits + 1),
+            ),
+            _ => slot,
+        }
+    }
+
+    /// Return a tag with a small label so that existing operations are hit but with random argument counts
+    fn random_tag(&mut self) -> IpcTag {
+        let label = match self.rng.below(8) {
+            0 => self.rng.next() >> 8,
+            _ => self.rng.below(8),
+        };
+        IpcTag::from_parts(label, self.rng.below(8) as u8, self.rng.below(8) as u8)
+    }
+
+    fn random_ipc_args(&mut self) -> [usize; 5] {
+        core::array::from_fn(|_| match self.rng.below(4) {
+            0 => self.random_caddr().raw(),
+            1 => self.rng.next(),
+            _ => self.rng.below(16),
+        })
+    }
+
+    /// Whether `caddr` points to an endpoint on which sending or receiving would block forever
+    fn is_endpoint(caddr: CAddr) -> bool {
+        liblunatix::syscalls::identify(caddr) == Ok(CapabilityVariant::Endpoint)
+    }
+
+    /// Issue the given number of random syscalls and return how many of them succeeded
+    pub fn run(&mut self, iterations: usize) -> usize {
+        let mut succeeded = 0;
+        for _ in 0..iterations {
+            if self.issue_random_syscall()[0] == 0 {
+                succeeded += 1;
+            }
+        }
+        succeeded
+    }
+
+    fn issue_random_syscall(&mut self) -> RawSyscallReturn {
+        match self.rng.below(9) {
+            0 => syscall::<Identify>(IdentifyArgs {
+                caddr: self.random_caddr(),
+                depth: match self.rng.below(2) {
+                    0 => CAddr::FULL_DEPTH,
+                    _ => self.rng.below(64),
+                },
+            }),
+            1 => syscall::<syscall_abi::copy::Copy>(CopyArgs {
+                src: self.random_caddr(),
+                dst: self.random_caddr(),
+            }),
+            2 => syscall::<syscall_abi::destroy::Destroy>(DestroyArgs {
+                caddr: self.random_caddr(),
+            }),
+            3 => {
+                let target = self.random_caddr();
+                if Self::is_endpoint(target) {
+                    return self.issue_random_syscall();
+                }
+                syscall::<syscall_abi::send::Send>(SendArgs {
+                    target,
+                    tag: self.random_tag(),
+                    raw_args: self.random_ipc_args(),
+                })
+            }
+            4 => syscall::<Call>(CallArgs {
+                target: self.random_caddr(),
+                tag: self.random_tag(),
+                raw_args: self.random_ipc_args(),
+            }),
+            5 => {
+                let target = self.random_caddr();
+                if Self::is_endpoint(target) {
+                    return self.issue_random_syscall();
+                }
+                syscall::<Receive>(ReceiveArgs {
+                    target,
+                    tag: self.random_tag(),
+                })
+            }
+            6 => {
+                // derive random objects from memory into random slots
+                let mut raw_args = self.random_ipc_args();
+                raw_args[0] = self.random_caddr().raw();
+                syscall::<syscall_abi::send::Send>(SendArgs {
+                    target: CADDR_MEM,
+                    tag: IpcTag::from_parts(self.rng.below(3), 1, 2),
+                    raw_args,
+                })
+            }
+            7 => syscall::<DebugLog>(DebugLogArgs {
+                len: self.rng.below(64),
+                // not valid UTF-8
+                byte_slice: [0xff; 48],
+            }),
+            _ => {
+                // no syscall is assigned to numbers this large
+                let no = self.rng.next() | 1 << 63;
+                let [a1, a2, a3, a4, a5, a6, a7] = self.random_raw_args();
+                raw_syscall(no, a1, a2, a3, a4, a5, a6, a7)
+            }
+        }
+    }
+
+    fn random_raw_args(&mut self) -> [usize; 7] {
+        core::array::from_fn(|_| self.rng.next())
+    }
+}
+
+/// Issue the syscall `T` but return its raw result because the fuzzer only cares about success or failure
+fn syscall<T: SyscallBinding>(args: T::CallArgs) -> RawSyscallReturn {
+    let [a1, a2, a3, a4, a5, a6, a7] = args.into();
+    raw_syscall(T::SYSCALL_NO, a1, a2, a3, a4, a5, a6, a7)
+}
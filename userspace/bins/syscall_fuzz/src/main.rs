@@ -0,0 +1,58 @@
+//! A program that issues random and malformed syscalls to check that the kernel rejects them instead of crashing.
+//!
+//! It is meant to be started as init (see `run_kernel.sh --init`) and reports the result through the serial console.
+
+#![no_std]
+#![no_main]
+
+mod fuzzer;
+
+use crate::fuzzer::{Fuzzer, NUM_SCRATCH_SLOTS};
+use core::panic::PanicInfo;
+use liblunatix::caps::{CSpaceCap, Capability};
+use liblunatix::prelude::syscall_abi::system_reset::{ResetReason, ResetType};
+use liblunatix::prelude::CAddr;
+use liblunatix::println;
+
+const CADDR_MEM: CAddr = CAddr::from_raw(1);
+const CADDR_CSPACE: CAddr = CAddr::from_raw(2);
+
+/// The first slot that is used by the fuzzer.
+///
+/// Slots before it contain capabilities that the kernel gives to init and are not touched.
+const FIRST_SCRATCH_SLOT: usize = 16;
+
+/// How many random syscalls are issued
+const ITERATIONS: usize = 10_000;
+
+#[no_mangle]
+fn _start() {
+    main();
+    liblunatix::syscalls::system_reset(ResetType::Shutdown, ResetReason::NoReason);
+}
+
+#[panic_handler]
+fn panic_handler(info: &PanicInfo) -> ! {
+    println!("syscall_fuzz panicked {}", info);
+    liblunatix::syscalls::system_reset(ResetType::Shutdown, ResetReason::SystemFailure);
+}
+
+fn main() {
+    let info = CSpaceCap::from_caddr(CADDR_CSPACE)
+        .and_then(|cspace| cspace.info())
+        .expect("could not query the layout of our cspace");
+    assert!(
+        FIRST_SCRATCH_SLOT + NUM_SCRATCH_SLOTS <= info.num_slots,
+        "our cspace is too small for the scratch slots"
+    );
+    let scratch = core::array::from_fn(|i| CAddr::new(FIRST_SCRATCH_SLOT + i, info.radix));
+    let mut fuzzer = Fuzzer::new(scratch, info.radix);
+    println!("syscall_fuzz: issuing {} random syscalls", ITERATIONS);
+    let succeeded = fuzzer.run(ITERATIONS);
+    println!(
+        "syscall_fuzz: {} syscalls succeeded and {} failed",
+        succeeded,
+        ITERATIONS - succeeded
+    );
+    println!("syscall_fuzz: kernel survived");
+}
@@ -1,11 +1,21 @@
 #![no_std]
 
-use core::sync::atomic::Ordering::SeqCst;
-use core::{cell::UnsafeCell, sync::atomic::AtomicUsize};
-use liblunatix::prelude::CAddr;
+use core::cell::UnsafeCell;
+use core::mem;
+use ksync::SpinLock;
+use liblunatix::caps::{CSpaceCap, MemoryCap};
+use liblunatix::ipc::cspace::CSpaceInfo;
+use liblunatix::prelude::syscall_abi::SyscallResult;
+use liblunatix::prelude::{CAddr, Capability};
 
 pub trait CAddressAllocator {
     fn alloc_caddr(&self) -> CAddr;
+
+    /// Return a slot that was previously handed out by [`alloc_caddr()`](CAddressAllocator::alloc_caddr) so that it
+    /// can be allocated again.
+    ///
+    /// The slot must not contain a capability anymore.
+    fn free_caddr(&self, caddr: CAddr);
 }
 
 unsafe impl Send for GlobalCaddrAllocator {}
@@ -20,6 +30,10 @@ impl GlobalCaddrAllocator {
             cell: UnsafeCell::new(None),
         }
     }
+
+    fn get(&self) -> &'static dyn CAddressAllocator {
+        unsafe { self.cell.get().as_ref().unwrap().unwrap() }
+    }
 }
 
 pub static CADDR_ALLOC: GlobalCaddrAllocator = GlobalCaddrAllocator::new();
@@ -31,20 +45,322 @@ pub unsafe fn set_global_caddr_allocator(alloc: &'static dyn CAddressAllocator)
 }
 
 pub fn alloc_caddr() -> CAddr {
-    let inner = unsafe { CADDR_ALLOC.cell.get().as_ref().unwrap().unwrap() };
-    inner.alloc_caddr()
+    CADDR_ALLOC.get().alloc_caddr()
+}
+
+/// Return a slot to the global allocator, see [`CAddressAllocator::free_caddr()`]
+pub fn free_caddr(caddr: CAddr) {
+    CADDR_ALLOC.get().free_caddr(caddr)
+}
+
+/// A slot that was allocated from the global allocator and which owns the capability that is stored in it.
+///
+/// Dropping the handle destroys the capability and frees the slot.
+#[derive(Debug)]
+pub struct OwnedSlot {
+    caddr: CAddr,
+}
+
+impl OwnedSlot {
+    /// Allocate a free slot from the global allocator
+    pub fn alloc() -> Self {
+        Self {
+            caddr: alloc_caddr(),
+        }
+    }
+
+    /// The address of the slot
+    pub fn caddr(&self) -> CAddr {
+        self.caddr
+    }
+
+    /// Give up ownership so that neither the capability is destroyed nor the slot is freed
+    pub fn into_caddr(self) -> CAddr {
+        let caddr = self.caddr;
+        mem::forget(self);
+        caddr
+    }
+}
+
+impl Drop for OwnedSlot {
+    fn drop(&mut self) {
+        // a capability that cannot be destroyed (e.g. the last copy of a memory capability with derivations) keeps
+        // the slot occupied, so the slot must not be handed out again
+        if liblunatix::syscalls::destroy(self.caddr).is_ok() {
+            free_caddr(self.caddr);
+        }
+    }
 }
 
+/// How many slots of a single CSpace the allocator manages at most
+const MAX_SLOTS: usize = 1024;
+/// How many CSpaces the allocator manages at most, including the root CSpace
+const MAX_LEVELS: usize = 8;
+const WORD_BITS: usize = usize::BITS as usize;
+
+/// An allocator which keeps track of the free slots of a tasks CSpace in a bitmap so that freed slots are reused.
+///
+/// The layout of the CSpace is either given statically via [`CAddrAlloc::new()`] or queried from the kernel via
+/// [`CAddrAlloc::discover()`].
+/// In the latter case, the allocator can also be given memory from which it derives additional CSpaces once the
+/// CSpace is full:
+/// The last slot of each CSpace is then reserved for a CSpace that has twice as many slots and into which allocation
+/// spills.
+/// CAddrs that point into these additional CSpaces consist of multiple parts.
+///
+/// Slots are always allocated from the first CSpace that has a free one so that the root CSpace is preferred.
 pub struct CAddrAlloc {
-    pub cspace_bits: AtomicUsize,
-    pub cur: AtomicUsize,
+    state: SpinLock<AllocState>,
+}
+
+struct AllocState {
+    /// How many bits a CAddr part that selects a slot of the tasks root CSpace has
+    root_bits: usize,
+    /// The root CSpace followed by the CSpaces that allocation has spilled into
+    levels: [Option<Level>; MAX_LEVELS],
+    /// Memory from which additional CSpaces are derived or `None` if the allocator cannot expand
+    mem: Option<MemoryCap>,
+}
+
+/// A CSpace from which slots are allocated
+#[derive(Copy, Clone)]
+struct Level {
+    /// The address of the CSpace together with how many bits that address uses or `None` for the root CSpace
+    prefix: Option<(CAddr, usize)>,
+    info: CSpaceInfo,
+    /// One bit per slot which is set if the slot is in use
+    used: [usize; MAX_SLOTS / WORD_BITS],
+}
+
+/// A mask of the lowest `bits` bits
+fn mask(bits: usize) -> usize {
+    1usize
+        .checked_shl(bits as u32)
+        .map_or(usize::MAX, |bit| bit - 1)
+}
+
+/// Append a part with `value` that uses `nbits` to `prefix` which uses `prefix_bits`
+fn append_part(prefix: CAddr, prefix_bits: usize, value: usize, nbits: usize) -> CAddr {
+    assert!(value <= mask(nbits));
+    assert!(
+        prefix_bits + 1 + nbits <= WORD_BITS,
+        "the cspace hierarchy is too deep to be addressed"
+    );
+    // the part is appended behind a continuation bit
+    CAddr::from_raw(prefix.raw() | (value << 1 | 1) << prefix_bits)
+}
+
+impl Level {
+    /// Create a level for a CSpace whose first `used` slots are already in use
+    const fn new(prefix: Option<(CAddr, usize)>, info: CSpaceInfo, used: usize) -> Self {
+        let mut level = Self {
+            prefix,
+            info,
+            used: [0; MAX_SLOTS / WORD_BITS],
+        };
+        let mut i = 0;
+        while i < used && i < MAX_SLOTS {
+            level.used[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+            i += 1;
+        }
+        level
+    }
+
+    /// How many slots of the CSpace are managed
+    fn num_slots(&self) -> usize {
+        usize::min(self.info.num_slots, MAX_SLOTS)
+    }
+
+    fn set_used(&mut self, index: usize, used: bool) {
+        let bit = 1 << (index % WORD_BITS);
+        match used {
+            true => self.used[index / WORD_BITS] |= bit,
+            false => self.used[index / WORD_BITS] &= !bit,
+        }
+    }
+
+    fn is_used(&self, index: usize) -> bool {
+        self.used[index / WORD_BITS] & 1 << (index % WORD_BITS) != 0
+    }
+
+    /// Find the first free slot
+    fn find_free(&self) -> Option<usize> {
+        self.used
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != usize::MAX)
+            .map(|(i, word)| i * WORD_BITS + word.trailing_ones() as usize)
+            .filter(|&index| index < self.num_slots())
+    }
+
+    /// How many bits an address that selects a slot of this CSpace uses
+    fn addr_bits(&self) -> usize {
+        match self.prefix {
+            None => self.info.part_bits(),
+            Some((_, prefix_bits)) => prefix_bits + 1 + self.info.part_bits(),
+        }
+    }
+
+    /// Construct the address that selects the slot at `index`
+    fn caddr(&self, index: usize) -> CAddr {
+        let part = self.info.part(index);
+        match self.prefix {
+            None => CAddr::new(part, self.info.part_bits()),
+            Some((prefix, prefix_bits)) => {
+                append_part(prefix, prefix_bits, part, self.info.part_bits())
+            }
+        }
+    }
+
+    /// The index of the slot that `caddr` selects if it points into this CSpace.
+    ///
+    /// This is the inverse of [`caddr()`](Self::caddr).
+    fn index_of(&self, caddr: CAddr) -> Option<usize> {
+        let part = match self.prefix {
+            None => caddr.raw(),
+            Some((prefix, prefix_bits)) => {
+                let continues = (caddr.raw() >> prefix_bits) & 1 == 1;
+                if caddr.raw() & mask(prefix_bits) != prefix.raw() || !continues {
+                    return None;
+                }
+                caddr.raw() >> (prefix_bits + 1)
+            }
+        };
+        let radix = self.info.radix;
+        if part & !mask(self.info.part_bits()) != 0 || part >> radix != self.info.guard {
+            return None;
+        }
+        Some(part & mask(radix)).filter(|&index| index < self.num_slots())
+    }
+}
+
+impl AllocState {
+    const fn new(root_bits: usize, root: Level, mem: Option<MemoryCap>) -> Self {
+        let mut levels = [None; MAX_LEVELS];
+        levels[0] = Some(root);
+        Self {
+            root_bits,
+            levels,
+            mem,
+        }
+    }
+
+    /// Derive a CSpace that is twice as large into the reserved last slot of the last CSpace and spill into it
+    fn expand(&mut self) -> &mut Level {
+        let mem = self
+            .mem
+            .expect("the cspace is full and the allocator has no memory to expand it");
+        let depth = self
+            .levels
+            .iter()
+            .take_while(|level| level.is_some())
+            .count();
+        assert!(
+            depth < MAX_LEVELS,
+            "the allocator cannot spill into more cspaces"
+        );
+        let last = self.levels[depth - 1].as_ref().unwrap();
+
+        let num_slots = usize::min(last.info.num_slots * 2, MAX_SLOTS);
+        let next = mem
+            .derive_cspace(last.caddr(last.num_slots() - 1), num_slots)
+            .expect("could not derive an additional cspace");
+        let info = next.info().unwrap();
+        let mut level = Level::new(Some((next.caddr(), last.addr_bits())), info, 0);
+        // the last slot is reserved for the next cspace
+        level.set_used(level.num_slots() - 1, true);
+        self.levels[depth].insert(level)
+    }
+}
+
+impl CAddrAlloc {
+    /// Create an allocator for a root CSpace which uses `cspace_bits` to address its slots and whose first `cur`
+    /// slots are already in use
+    pub const fn new(cspace_bits: usize, cur: usize) -> Self {
+        let info = CSpaceInfo {
+            radix: cspace_bits,
+            num_slots: 1 << cspace_bits,
+            occupied: cur,
+            free_from: cur,
+            guard: 0,
+            guard_bits: 0,
+        };
+        Self {
+            state: SpinLock::new(AllocState::new(
+                cspace_bits,
+                Level::new(None, info, cur),
+                None,
+            )),
+        }
+    }
+
+    /// Create an allocator which cannot hand out any slots until [`discover()`](Self::discover) is called
+    pub const fn undiscovered() -> Self {
+        Self::new(0, 1)
+    }
+
+    /// Query the layout of the tasks root CSpace from the kernel and allocate the slots after the last occupied one.
+    ///
+    /// `cspace` must be a copy of the root CSpace of the calling task.
+    /// If `mem` is given, additional CSpaces are derived from it once the root CSpace is full.
+    pub fn discover(&self, cspace: CSpaceCap, mem: Option<MemoryCap>) -> SyscallResult<()> {
+        let info = cspace.info()?;
+        let mut root = Level::new(None, info, info.free_from);
+        if mem.is_some() {
+            // the last slot is reserved for the first additional cspace
+            root.set_used(root.num_slots() - 1, true);
+        }
+        *self.state.spin_lock() = AllocState::new(info.part_bits(), root, mem);
+        Ok(())
+    }
+
+    /// How many bits a CAddr part that selects a slot of the tasks root CSpace has
+    pub fn cspace_bits(&self) -> usize {
+        self.state.spin_lock().root_bits
+    }
+
+    /// Construct the address of a slot inside the CSpace that is stored in `cspace`.
+    ///
+    /// `cspace` must be a slot that was handed out by this allocator and may thus consist of multiple parts once
+    /// allocation has spilled into additional CSpaces.
+    /// The slot is selected by a part with `value` that uses `nbits`.
+    pub fn nested_caddr(&self, cspace: CAddr, value: usize, nbits: usize) -> CAddr {
+        let state = self.state.spin_lock();
+        let level = state
+            .levels
+            .iter()
+            .flatten()
+            .find(|level| level.index_of(cspace).is_some())
+            .expect("the cspace was not allocated by this allocator");
+        append_part(cspace, level.addr_bits(), value, nbits)
+    }
 }
 
 impl CAddressAllocator for CAddrAlloc {
     fn alloc_caddr(&self) -> CAddr {
-        let addr = self.cur.fetch_add(1, SeqCst);
-        let cspace_bits = self.cspace_bits.load(SeqCst);
-        assert!(addr < 2usize.pow(cspace_bits as u32));
-        CAddr::new(addr, cspace_bits)
+        let mut state = self.state.spin_lock();
+        let free = state
+            .levels
+            .iter_mut()
+            .flatten()
+            .find_map(|level| Some((level.find_free()?, level)));
+        let (index, level) = match free {
+            Some(free) => free,
+            None => (0, state.expand()),
+        };
+        level.set_used(index, true);
+        level.caddr(index)
+    }
+
+    fn free_caddr(&self, caddr: CAddr) {
+        let mut state = self.state.spin_lock();
+        let (index, level) = state
+            .levels
+            .iter_mut()
+            .flatten()
+            .find_map(|level| Some((level.index_of(caddr)?, level)))
+            .expect("the caddr was not handed out by this allocator");
+        assert!(level.is_used(index), "the caddr is already free");
+        level.set_used(index, false);
     }
 }
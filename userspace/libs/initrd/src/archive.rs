@@ -0,0 +1,200 @@
+use crate::reader::FileReader;
+use thiserror_no_std::Error;
+
+/// The magic bytes that start every header in the newc format
+const MAGIC: &[u8] = b"070701";
+
+/// The magic bytes that start every header in the newc format with checksums (`cpio -H crc`)
+const MAGIC_CRC: &[u8] = b"070702";
+
+/// The length of an entry header in bytes
+const HEADER_LEN: usize = 110;
+
+/// The name of the entry which marks the end of the archive
+const TRAILER_NAME: &str = "TRAILER!!!";
+
+/// The bits of an entries mode which describe the file type
+const MODE_TYPE_MASK: u32 = 0o170000;
+
+/// The file type of regular files
+const MODE_TYPE_FILE: u32 = 0o100000;
+
+/// The file type of directories
+const MODE_TYPE_DIR: u32 = 0o040000;
+
+/// The error which indicates that an archive is not a valid cpio archive
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum ArchiveError {
+    /// An entry header does not start with the newc magic bytes
+    #[error("The archive entry does not start with the newc magic bytes")]
+    InvalidMagic,
+    /// A header field is not a valid hexadecimal number
+    #[error("The archive entry header contains an invalid field")]
+    InvalidHeader,
+    /// The archive ends in the middle of an entry
+    #[error("The archive ends in the middle of an entry")]
+    Truncated,
+    /// The name of an entry is not a valid NUL-terminated UTF-8 string
+    #[error("The archive entry has an invalid name")]
+    InvalidName,
+}
+
+/// A read-only view into a cpio archive in newc format
+#[derive(Debug, Copy, Clone)]
+pub struct Archive<'buf> {
+    buf: &'buf [u8],
+}
+
+impl<'buf> Archive<'buf> {
+    /// Create an archive from an underlying buffer.
+    ///
+    /// Only the first entry header is validated here.
+    /// Errors in later entries are reported while iterating over the archive.
+    pub fn new(buf: &'buf [u8]) -> Result<Self, ArchiveError> {
+        let magic = buf.get(0..MAGIC.len()).ok_or(ArchiveError::Truncated)?;
+        if magic != MAGIC && magic != MAGIC_CRC {
+            return Err(ArchiveError::InvalidMagic);
+        }
+        Ok(Self { buf })
+    }
+
+    /// Iterate over all entries of the archive
+    pub fn entries(&self) -> Entries<'buf> {
+        Entries {
+            buf: self.buf,
+            offset: 0,
+        }
+    }
+
+    /// Find the entry with the given path.
+    ///
+    /// Leading `/` and `./` are ignored both in the given path and in the names of the archives entries.
+    pub fn find(&self, path: &str) -> Option<Entry<'buf>> {
+        let path = normalize(path);
+        self.entries()
+            .map_while(Result::ok)
+            .find(|entry| normalize(entry.name) == path)
+    }
+}
+
+/// Strip the prefixes from a path that don't change which file it refers to
+fn normalize(mut path: &str) -> &str {
+    loop {
+        if let Some(stripped) = path.strip_prefix("./") {
+            path = stripped;
+        } else if let Some(stripped) = path.strip_prefix('/') {
+            path = stripped;
+        } else {
+            return path;
+        }
+    }
+}
+
+/// A single file, directory or other filesystem object that is contained in an archive
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Entry<'buf> {
+    /// The path of the entry as it is stored in the archive
+    pub name: &'buf str,
+    /// The file type and permission bits of the entry
+    pub mode: u32,
+    /// The content of the entry
+    pub data: &'buf [u8],
+}
+
+impl<'buf> Entry<'buf> {
+    /// Whether this entry is a regular file
+    pub fn is_file(&self) -> bool {
+        self.mode & MODE_TYPE_MASK == MODE_TYPE_FILE
+    }
+
+    /// Whether this entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.mode & MODE_TYPE_MASK == MODE_TYPE_DIR
+    }
+
+    /// Get a reader over the entries content
+    pub fn reader(&self) -> FileReader<'buf> {
+        FileReader::new(self.data)
+    }
+}
+
+/// An iterator over the entries of an archive
+#[derive(Debug, Clone)]
+pub struct Entries<'buf> {
+    buf: &'buf [u8],
+    offset: usize,
+}
+
+impl<'buf> Entries<'buf> {
+    /// Parse the entry at the current offset and return it together with the offset of the following entry
+    fn parse_entry(&self) -> Result<(Entry<'buf>, usize), ArchiveError> {
+        let header = self
+            .buf
+            .get(self.offset..self.offset + HEADER_LEN)
+            .ok_or(ArchiveError::Truncated)?;
+        let magic = &header[0..MAGIC.len()];
+        if magic != MAGIC && magic != MAGIC_CRC {
+            return Err(ArchiveError::InvalidMagic);
+        }
+
+        // all header fields are 8 hex digits that follow the magic bytes
+        let field = |i: usize| -> Result<usize, ArchiveError> {
+            let start = MAGIC.len() + i * 8;
+            let digits = core::str::from_utf8(&header[start..start + 8])
+                .map_err(|_| ArchiveError::InvalidHeader)?;
+            usize::from_str_radix(digits, 16).map_err(|_| ArchiveError::InvalidHeader)
+        };
+        let mode = field(1)? as u32;
+        let file_size = field(6)?;
+        let name_size = field(11)?;
+
+        let name_start = self.offset + HEADER_LEN;
+        let name = self
+            .buf
+            .get(name_start..name_start + name_size)
+            .ok_or(ArchiveError::Truncated)?;
+        let name = match name.split_last() {
+            Some((0, name)) => core::str::from_utf8(name).map_err(|_| ArchiveError::InvalidName)?,
+            _ => return Err(ArchiveError::InvalidName),
+        };
+
+        let data_start = align4(name_start + name_size);
+        let data = self
+            .buf
+            .get(data_start..data_start + file_size)
+            .ok_or(ArchiveError::Truncated)?;
+
+        Ok((Entry { name, mode, data }, align4(data_start + file_size)))
+    }
+}
+
+impl<'buf> Iterator for Entries<'buf> {
+    type Item = Result<Entry<'buf>, ArchiveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.buf.len() {
+            return None;
+        }
+
+        match self.parse_entry() {
+            Ok((entry, _)) if entry.name == TRAILER_NAME => {
+                self.offset = self.buf.len();
+                None
+            }
+            Ok((entry, next_offset)) => {
+                self.offset = next_offset;
+                Some(Ok(entry))
+            }
+            Err(e) => {
+                // an invalid entry also makes all following entries unreachable
+                self.offset = self.buf.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Round the given offset up to the next multiple of 4
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
@@ -0,0 +1,24 @@
+//! A library for reading the initial ram filesystem (initrd) that the kernel hands to init at boot.
+//!
+//! ## Format
+//! The initrd is a *cpio* archive in the portable ASCII format (also called *newc*) as it is produced by
+//! `cpio -o -H newc`.
+//! Each file in the archive is described by a 110 byte ASCII header which is followed by the NUL-terminated file
+//! name and the file content.
+//! Both the name and the content are padded to a 4 byte boundary.
+//! The archive is terminated by an entry named `TRAILER!!!`.
+//!
+//! ## Usage
+//! Files are looked up by their path with [`Archive::find`] and can then be accessed either directly through
+//! [`Entry::data`] or via a [`FileReader`] which implements `io::read::Reader` (with the default `io` feature) so that
+//! it can be used interchangeably with files from other sources.
+#![no_std]
+
+mod archive;
+mod reader;
+
+#[cfg(test)]
+mod tests;
+
+pub use archive::{Archive, ArchiveError, Entries, Entry};
+pub use reader::FileReader;
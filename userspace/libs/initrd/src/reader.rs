@@ -0,0 +1,31 @@
+/// A reader over the content of a single file in an archive
+#[derive(Debug, Clone)]
+pub struct FileReader<'buf> {
+    data: &'buf [u8],
+    pos: usize,
+}
+
+impl<'buf> FileReader<'buf> {
+    /// Create a reader that starts at the beginning of the given file content
+    pub fn new(data: &'buf [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Copy as many bytes as possible into `buf` and return how many were copied.
+    ///
+    /// Returns 0 once the end of the file is reached.
+    pub fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let remaining = &self.data[self.pos..];
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.pos += len;
+        len
+    }
+}
+
+#[cfg(all(target_arch = "riscv64", feature = "io"))]
+impl io::read::Reader for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        Ok(self.read_into(buf))
+    }
+}
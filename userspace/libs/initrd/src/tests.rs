@@ -0,0 +1,94 @@
+extern crate std;
+
+use crate::{Archive, ArchiveError};
+use std::format;
+use std::vec::Vec;
+
+/// Append a newc entry with the given name, mode and content to an archive buffer
+fn push_entry(archive: &mut Vec<u8>, name: &str, mode: u32, data: &[u8]) {
+    let header = format!(
+        "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+        0,
+        mode,
+        0,
+        0,
+        1,
+        0,
+        data.len(),
+        0,
+        0,
+        0,
+        0,
+        name.len() + 1,
+        0
+    );
+    archive.extend_from_slice(header.as_bytes());
+    archive.extend_from_slice(name.as_bytes());
+    archive.push(0);
+    archive.resize((archive.len() + 3) & !3, 0);
+    archive.extend_from_slice(data);
+    archive.resize((archive.len() + 3) & !3, 0);
+}
+
+fn test_archive() -> Vec<u8> {
+    let mut archive = Vec::new();
+    push_entry(&mut archive, ".", 0o040755, &[]);
+    push_entry(&mut archive, "hello_world", 0o100755, b"hello");
+    push_entry(&mut archive, "./bin/echo_srv", 0o100644, b"echo server");
+    push_entry(&mut archive, "TRAILER!!!", 0, &[]);
+    archive
+}
+
+#[test]
+fn test_entries() {
+    let archive = test_archive();
+    let archive = Archive::new(&archive).unwrap();
+    let names = archive
+        .entries()
+        .map(|entry| entry.unwrap().name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, [".", "hello_world", "./bin/echo_srv"]);
+}
+
+#[test]
+fn test_find() {
+    let archive = test_archive();
+    let archive = Archive::new(&archive).unwrap();
+    let entry = archive.find("hello_world").unwrap();
+    assert!(entry.is_file());
+    assert_eq!(entry.data, b"hello");
+    assert_eq!(archive.find("/bin/echo_srv").unwrap().data, b"echo server");
+    assert!(archive.find(".").unwrap().is_dir());
+    assert_eq!(archive.find("walk_cspace"), None);
+}
+
+#[test]
+fn test_reader() {
+    let archive = test_archive();
+    let archive = Archive::new(&archive).unwrap();
+    let mut reader = archive.find("bin/echo_srv").unwrap().reader();
+    let mut buf = [0u8; 8];
+    assert_eq!(reader.read_into(&mut buf), 8);
+    assert_eq!(&buf, b"echo ser");
+    assert_eq!(reader.read_into(&mut buf), 3);
+    assert_eq!(&buf[..3], b"ver");
+    assert_eq!(reader.read_into(&mut buf), 0);
+}
+
+#[test]
+fn test_invalid_magic() {
+    assert_eq!(
+        Archive::new(&[0u8; 128]).unwrap_err(),
+        ArchiveError::InvalidMagic
+    );
+}
+
+#[test]
+fn test_truncated() {
+    let archive = test_archive();
+    let archive = Archive::new(&archive[..140]).unwrap();
+    let mut entries = archive.entries();
+    assert!(entries.next().unwrap().is_ok());
+    assert_eq!(entries.next().unwrap(), Err(ArchiveError::Truncated));
+    assert_eq!(entries.next(), None);
+}
@@ -0,0 +1,325 @@
+//! Typed handles for capabilities.
+//!
+//! The handles are zero-cost wrappers around the [`CAddr`] of a capability which only expose the operations that are
+//! valid for the respective capability variant.
+//! They are either obtained by checking a CAddr via [`Capability::from_caddr()`] or as the result of an operation that
+//! creates a capability, e.g. [`MemoryCap::derive()`].
+
+use crate::ipc;
+use crate::syscalls;
+use syscall_abi::identify::CapabilityVariant;
+use syscall_abi::receive::ReceiveReturn;
+use syscall_abi::yield_to::TaskStatus;
+use syscall_abi::{CAddr, MapFlags, NoValue, SyscallError, SyscallResult};
+
+/// Operations which are shared by all capability variants
+pub trait Capability: Copy {
+    /// The variant that the kernel reports for capabilities of this type
+    const VARIANT: CapabilityVariant;
+
+    /// The address of the capability
+    fn caddr(self) -> CAddr;
+
+    /// Create a handle without checking that `caddr` actually points to a capability of this type.
+    ///
+    /// # Safety
+    /// If `caddr` does not point to such a capability, operations on the handle fail or, if another capability
+    /// is placed into the slot, act on the wrong capability.
+    unsafe fn from_caddr_unchecked(caddr: CAddr) -> Self;
+
+    /// Create a handle after checking with the kernel that `caddr` points to a capability of this type
+    fn from_caddr(caddr: CAddr) -> SyscallResult<Self> {
+        match syscalls::identify(caddr)? {
+            variant if variant == Self::VARIANT => Ok(unsafe { Self::from_caddr_unchecked(caddr) }),
+            _ => Err(SyscallError::InvalidCap),
+        }
+    }
+
+    /// Copy the capability into the empty slot at `target`
+    fn copy_to(self, target: CAddr) -> SyscallResult<Self> {
+        syscalls::copy(self.caddr(), target)?;
+        Ok(unsafe { Self::from_caddr_unchecked(target) })
+    }
+
+    /// Destroy the capability which leaves its slot empty
+    fn destroy(self) -> SyscallResult<NoValue> {
+        syscalls::destroy(self.caddr())
+    }
+}
+
+/// Capabilities which can be derived from a [`MemoryCap`] without further parameters
+pub trait Derive: Capability {}
+
+macro_rules! capability {
+    ($(#[$meta:meta])* $name:ident => $variant:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        #[repr(transparent)]
+        pub struct $name(CAddr);
+
+        impl $name {
+            /// Create a handle in a const context, see [`Capability::from_caddr_unchecked()`]
+            ///
+            /// # Safety
+            /// `caddr` must point to a capability of this type.
+            pub const unsafe fn from_caddr_unchecked(caddr: CAddr) -> Self {
+                Self(caddr)
+            }
+        }
+
+        impl Capability for $name {
+            const VARIANT: CapabilityVariant = CapabilityVariant::$variant;
+
+            fn caddr(self) -> CAddr {
+                self.0
+            }
+
+            unsafe fn from_caddr_unchecked(caddr: CAddr) -> Self {
+                Self(caddr)
+            }
+        }
+
+        impl TryFrom<CAddr> for $name {
+            type Error = SyscallError;
+
+            fn try_from(caddr: CAddr) -> Result<Self, Self::Error> {
+                <Self as Capability>::from_caddr(caddr)
+            }
+        }
+
+        impl From<$name> for CAddr {
+            fn from(cap: $name) -> Self {
+                cap.0
+            }
+        }
+    };
+}
+
+capability!(
+    /// A region of physical memory from which other capabilities are derived
+    MemoryCap => Memory
+);
+capability!(
+    /// A table of capability slots
+    CSpaceCap => CSpace
+);
+capability!(
+    /// An address space into which pages are mapped
+    VSpaceCap => VSpace
+);
+capability!(
+    /// A thread of execution
+    TaskCap => Task
+);
+capability!(
+    /// A single page of physical memory
+    PageCap => Page
+);
+capability!(
+    /// The authority to claim interrupt lines
+    IrqControlCap => IrqControl
+);
+capability!(
+    /// A claimed interrupt line
+    IrqCap => Irq
+);
+capability!(
+    /// A word of bits which tasks can signal and wait on
+    NotificationCap => Notification
+);
+capability!(
+    /// Device memory which can be mapped into an address space
+    DevmemCap => Devmem
+);
+capability!(
+    /// The authority to assign address space ids to vspaces
+    AsidControlCap => AsidControl
+);
+capability!(
+    /// A synchronous message channel between tasks
+    EndpointCap => Endpoint
+);
+
+impl Derive for VSpaceCap {}
+impl Derive for TaskCap {}
+impl Derive for PageCap {}
+impl Derive for NotificationCap {}
+impl Derive for EndpointCap {}
+
+impl MemoryCap {
+    /// Derive a new capability of type `T` into the empty slot at `target`
+    pub fn derive<T: Derive>(self, target: CAddr) -> SyscallResult<T> {
+        ipc::mem::derive(self.0, target, T::VARIANT, None)?;
+        Ok(unsafe { T::from_caddr_unchecked(target) })
+    }
+
+    /// Derive a new CSpace with `num_slots` slots into the empty slot at `target`
+    pub fn derive_cspace(self, target: CAddr, num_slots: usize) -> SyscallResult<CSpaceCap> {
+        ipc::mem::derive(self.0, target, CapabilityVariant::CSpace, Some(num_slots))?;
+        Ok(CSpaceCap(target))
+    }
+}
+
+impl CSpaceCap {
+    /// See [`ipc::cspace::info()`]
+    pub fn info(self) -> SyscallResult<ipc::cspace::CSpaceInfo> {
+        ipc::cspace::info(self.0)
+    }
+
+    /// See [`ipc::cspace::set_guard()`]
+    pub fn set_guard(self, guard: usize, guard_bits: usize) -> SyscallResult<NoValue> {
+        ipc::cspace::set_guard(self.0, guard, guard_bits)
+    }
+
+    /// See [`ipc::cspace::copy_slots()`]
+    pub fn copy_slots(self, target: CSpaceCap) -> SyscallResult<NoValue> {
+        ipc::cspace::copy_slots(self.0, target.0)
+    }
+
+    /// See [`ipc::cspace::grow()`]
+    pub fn grow(self, mem: MemoryCap, target: CAddr, num_slots: usize) -> SyscallResult<CSpaceCap> {
+        ipc::cspace::grow(self.0, mem.0, target, num_slots)?;
+        Ok(CSpaceCap(target))
+    }
+}
+
+impl TaskCap {
+    /// Use `cspace` as the root CSpace of the task
+    pub fn assign_cspace(self, cspace: CSpaceCap) -> SyscallResult<NoValue> {
+        ipc::task::task_assign_cspace(cspace.0, self.0)
+    }
+
+    /// Use `vspace` as the address space of the task
+    pub fn assign_vspace(self, vspace: VSpaceCap) -> SyscallResult<NoValue> {
+        ipc::task::task_assign_vspace(vspace.0, self.0)
+    }
+
+    /// Set the program counter, stack pointer, global pointer and thread pointer with which the task starts
+    pub fn assign_control_registers(
+        self,
+        pc: usize,
+        sp: usize,
+        gp: usize,
+        tp: usize,
+    ) -> SyscallResult<NoValue> {
+        ipc::task::task_assign_control_registers(self.0, pc, sp, gp, tp)
+    }
+
+    /// See [`ipc::task::task_bind_notification()`]
+    pub fn bind_notification(self, notification: NotificationCap) -> SyscallResult<NoValue> {
+        ipc::task::task_bind_notification(self.0, notification.0)
+    }
+
+    /// See [`ipc::task::task_unbind_notification()`]
+    pub fn unbind_notification(self) -> SyscallResult<NoValue> {
+        ipc::task::task_unbind_notification(self.0)
+    }
+
+    /// See [`ipc::task::task_start()`]
+    pub fn start(self) -> SyscallResult<NoValue> {
+        ipc::task::task_start(self.0)
+    }
+
+    /// Run the task until it yields back to us
+    pub fn yield_to(self) -> SyscallResult<TaskStatus> {
+        syscalls::yield_to(self.0)
+    }
+}
+
+impl PageCap {
+    /// Map the page into `vspace` at `addr`, using `mem` to allocate intermediate page tables
+    pub fn map(
+        self,
+        vspace: VSpaceCap,
+        mem: MemoryCap,
+        addr: usize,
+        flags: MapFlags,
+    ) -> SyscallResult<NoValue> {
+        ipc::page::map_page(self.0, vspace.0, mem.0, addr, flags)
+    }
+
+    /// Remove the page from the vspace it is mapped into
+    pub fn unmap(self) -> SyscallResult<NoValue> {
+        ipc::page::unmap_page(self.0)
+    }
+
+    /// The physical address of the page
+    pub fn paddr(self) -> SyscallResult<usize> {
+        ipc::page::get_paddr(self.0)
+    }
+}
+
+impl IrqControlCap {
+    /// Claim `interrupt_line` into the empty slot at `target` and signal `notification` when it is triggered
+    pub fn claim(
+        self,
+        interrupt_line: usize,
+        target: CAddr,
+        notification: NotificationCap,
+    ) -> SyscallResult<IrqCap> {
+        ipc::irq_control::irq_control_claim(self.0, interrupt_line, target, notification.0)?;
+        Ok(IrqCap(target))
+    }
+}
+
+impl IrqCap {
+    /// Mark the interrupt as handled so that it can be triggered again
+    pub fn complete(self) -> SyscallResult<NoValue> {
+        ipc::irq::irq_complete(self.0)
+    }
+}
+
+impl NotificationCap {
+    /// Block until the notification is signaled
+    pub fn wait(self) -> SyscallResult<NoValue> {
+        syscalls::wait_on(self.0)
+    }
+
+    /// See [`ipc::notification::poll()`]
+    pub fn poll(self) -> SyscallResult<usize> {
+        ipc::notification::poll(self.0)
+    }
+
+    /// See [`ipc::notification::signal()`]
+    pub fn signal(self) -> SyscallResult<NoValue> {
+        ipc::notification::signal(self.0)
+    }
+}
+
+impl DevmemCap {
+    /// Map the device memory region starting at `base` into `vspace`, using `mem` to allocate intermediate page
+    /// tables
+    pub fn map(
+        self,
+        mem: MemoryCap,
+        vspace: VSpaceCap,
+        base: usize,
+        len: usize,
+    ) -> SyscallResult<NoValue> {
+        ipc::devmem::devmem_map(self.0, mem.0, vspace.0, base, len)
+    }
+}
+
+impl AsidControlCap {
+    /// Assign an address space id to `vspace` so that it can be used by tasks
+    pub fn assign(self, vspace: VSpaceCap) -> SyscallResult<NoValue> {
+        ipc::asid::asid_assign(self.0, vspace.0)
+    }
+}
+
+impl EndpointCap {
+    /// Send a message and block until it is received
+    pub fn send(self, label: usize, caps: &[CAddr], data: &[usize]) -> SyscallResult<NoValue> {
+        syscalls::send(self.0, label, caps, data)
+    }
+
+    /// Block until a message is received
+    pub fn receive(self, label: usize, caps: &[CAddr]) -> SyscallResult<ReceiveReturn> {
+        syscalls::receive(self.0, label, caps)
+    }
+
+    /// See [`ipc::endpoint::has_sender()`]
+    pub fn has_sender(self) -> SyscallResult<bool> {
+        ipc::endpoint::has_sender(self.0)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::syscalls::{call, send};
+use syscall_abi::identify::CapabilityVariant;
+use syscall_abi::{CAddr, NoValue, SyscallError, SyscallResult};
+
+/// Information about the layout and occupancy of a CSpace
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CSpaceInfo {
+    /// How many bits are needed to index all slots of the CSpace
+    pub radix: usize,
+    /// How many slots the CSpace has in total
+    pub num_slots: usize,
+    /// How many slots are currently occupied
+    pub occupied: usize,
+    /// The index after the last occupied slot, i.e. all slots starting at this index are free
+    pub free_from: usize,
+    /// The guard that CAddr parts selecting a slot of the CSpace must start with
+    pub guard: usize,
+    /// How many bits the guard uses
+    pub guard_bits: usize,
+}
+
+impl CSpaceInfo {
+    /// How many bits a CAddr part that selects a slot of the CSpace has, including the guard
+    pub fn part_bits(&self) -> usize {
+        self.guard_bits + self.radix
+    }
+
+    /// Construct the CAddr part which selects the slot at `index`
+    pub fn part(&self, index: usize) -> usize {
+        self.guard << self.radix | index
+    }
+}
+
+/// Require all CAddr parts that select a slot of the CSpace to start with `guard`, stored in `guard_bits` bits
+pub fn set_guard(cspace: CAddr, guard: usize, guard_bits: usize) -> SyscallResult<NoValue> {
+    const SET_GUARD: usize = 0;
+    send(cspace, SET_GUARD, &[], &[guard, guard_bits])
+}
+
+/// Copy the capabilities of all occupied slots of `cspace` into the slots with the same index of `target`.
+///
+/// `target` must have at least as many slots as `cspace` and the slots into which capabilities are copied must be
+/// free.
+pub fn copy_slots(cspace: CAddr, target: CAddr) -> SyscallResult<NoValue> {
+    const COPY_SLOTS: usize = 1;
+    send(cspace, COPY_SLOTS, &[target], &[])
+}
+
+/// Query the radix and occupancy of a CSpace
+pub fn info(cspace: CAddr) -> SyscallResult<CSpaceInfo> {
+    const GET_INFO: usize = 0;
+    call(cspace, GET_INFO, &[], &[]).map(|data| CSpaceInfo {
+        radix: data[0],
+        num_slots: data[1],
+        occupied: data[2],
+        free_from: data[3],
+        guard: data[4],
+        guard_bits: data[5],
+    })
+}
+
+/// Derive a CSpace with `num_slots` slots from `mem` into `target` and copy all capabilities of `cspace` into it.
+///
+/// Addresses consisting of a single part that select a slot of `cspace` select a copy of the same capability in the
+/// grown CSpace.
+pub fn grow(cspace: CAddr, mem: CAddr, target: CAddr, num_slots: usize) -> SyscallResult<NoValue> {
+    // check the size up front so that no CSpace is derived which could then not be filled
+    if num_slots < info(cspace)?.num_slots {
+        return Err(SyscallError::InvalidArg);
+    }
+    crate::ipc::mem::derive(mem, target, CapabilityVariant::CSpace, Some(num_slots))?;
+    copy_slots(cspace, target)
+}
@@ -0,0 +1,8 @@
+use crate::syscalls::call;
+use syscall_abi::{CAddr, SyscallResult};
+
+/// Whether a sender is currently blocked on the endpoint so that a `receive` completes without blocking
+pub fn has_sender(endpoint: CAddr) -> SyscallResult<bool> {
+    const POLL: usize = 0;
+    call(endpoint, POLL, &[], &[]).map(|data| data[0] != 0)
+}
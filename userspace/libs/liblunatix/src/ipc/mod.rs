@@ -1,7 +1,10 @@
 pub mod asid;
+pub mod cspace;
 pub mod devmem;
+pub mod endpoint;
 pub mod irq;
 pub mod irq_control;
 pub mod mem;
+pub mod notification;
 pub mod page;
 pub mod task;
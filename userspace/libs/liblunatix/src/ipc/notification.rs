@@ -0,0 +1,16 @@
+use crate::syscalls::{call, send};
+use syscall_abi::{CAddr, NoValue, SyscallResult};
+
+/// Take the value of the notification without blocking.
+///
+/// Like `wait_on`, this clears the notification but returns `0` instead of blocking if it is currently unset.
+pub fn poll(notification: CAddr) -> SyscallResult<usize> {
+    const POLL: usize = 0;
+    call(notification, POLL, &[], &[]).map(|data| data[0])
+}
+
+/// Set the notification and wake the task that waits on it, like an interrupt does
+pub fn signal(notification: CAddr) -> SyscallResult<NoValue> {
+    const SIGNAL: usize = 0;
+    send(notification, SIGNAL, &[], &[])
+}
@@ -11,13 +11,38 @@ pub fn task_assign_vspace(vspace: CAddr, task: CAddr) -> SyscallResult<NoValue>
     send(task, ASSIGN_VSPACE, &[vspace], &[])
 }
 
+/// Set the program counter, stack pointer, global pointer and thread pointer with which the task starts
 pub fn task_assign_control_registers(
     task: CAddr,
     pc: usize,
     sp: usize,
-    fp: usize,
     gp: usize,
+    tp: usize,
 ) -> SyscallResult<NoValue> {
     const ASSIGN_REGS: usize = 1;
-    send(task, ASSIGN_REGS, &[], &[pc, sp, fp, gp])
+    send(task, ASSIGN_REGS, &[], &[pc, sp, gp, tp])
+}
+
+/// Bind a notification to the task.
+///
+/// If the notification is signaled while the task is blocked in an endpoint
+/// [`receive`](crate::syscalls::receive), the receive returns early and reports the notification value via
+/// [`ReceiveReturn::notification`](syscall_abi::receive::ReceiveReturn::notification).
+pub fn task_bind_notification(task: CAddr, notification: CAddr) -> SyscallResult<NoValue> {
+    const BIND_NOTIFICATION: usize = 4;
+    send(task, BIND_NOTIFICATION, &[notification], &[])
+}
+
+/// Remove the binding between the task and its currently bound notification
+pub fn task_unbind_notification(task: CAddr) -> SyscallResult<NoValue> {
+    const UNBIND_NOTIFICATION: usize = 5;
+    send(task, UNBIND_NOTIFICATION, &[], &[])
+}
+
+/// Hand the task to the kernel scheduler so that it runs alongside the calling task.
+///
+/// The task needs a vspace and cspace assigned and must not have been executed yet.
+pub fn task_start(task: CAddr) -> SyscallResult<NoValue> {
+    const START: usize = 6;
+    send(task, START, &[], &[])
 }
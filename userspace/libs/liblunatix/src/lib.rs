@@ -2,10 +2,14 @@
 
 #[macro_use]
 pub mod syscalls;
+pub mod caps;
 pub mod ipc;
 pub mod print;
+pub mod rt;
+pub mod thread;
 
 pub mod prelude {
+    pub use crate::caps::Capability;
     pub use crate::print;
     pub use crate::println;
     pub use print::SYS_WRITER;
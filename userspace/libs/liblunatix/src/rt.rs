@@ -0,0 +1,387 @@
+//! A small single-threaded async runtime.
+//!
+//! A [`Runtime`] drives one future to completion while that future waits for any number of notifications and
+//! endpoints, e.g. by combining [`Notification::wait()`] and [`Endpoint::recv()`] futures with [`select()`] or
+//! [`join()`].
+//! This allows a single task to serve several devices or clients instead of blocking on one of them.
+//!
+//! While the driven future is pending, the runtime blocks on all objects it waits for with the `wait_any` syscall.
+//! Only if the future waits for more than [`MAX_WAIT_OBJECTS`] objects, the runtime falls back to polling them and
+//! yields the rest of its timeslice between polls.
+//! If `wait_any` takes the value of a notification whose future is not polled anymore, the signal is kept by the
+//! runtime until a future waits for that notification again.
+
+use crate::caps::{Capability, EndpointCap, NotificationCap};
+use crate::syscalls;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use syscall_abi::receive::ReceiveReturn;
+use syscall_abi::wait_any::MAX_WAIT_OBJECTS;
+use syscall_abi::{CAddr, NoValue, SyscallResult};
+
+/// An object that a pending future waits for
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Source {
+    Notification(NotificationCap),
+    Endpoint(EndpointCap),
+}
+
+impl Source {
+    fn caddr(self) -> CAddr {
+        match self {
+            Source::Notification(notification) => notification.caddr(),
+            Source::Endpoint(endpoint) => endpoint.caddr(),
+        }
+    }
+}
+
+/// The objects that the driven future waited for during the last poll
+#[derive(Debug, Default)]
+struct Interest {
+    sources: [Option<Source>; MAX_WAIT_OBJECTS],
+    /// Whether the future waited for more objects than fit into `sources`
+    overflow: bool,
+    /// Notifications whose value the runtime took while blocking on them but which no future has consumed yet.
+    ///
+    /// These are kept across [`Runtime::block_on()`] calls because the future that waited for such a notification
+    /// might have been dropped before it was polled again, e.g. by [`select()`].
+    signaled: [Option<NotificationCap>; MAX_WAIT_OBJECTS],
+}
+
+impl Interest {
+    fn register(&mut self, source: Source) {
+        if self.sources.contains(&Some(source)) {
+            return;
+        }
+        match self.sources.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(source),
+            None => self.overflow = true,
+        }
+    }
+
+    /// Remember that the runtime took the value of `notification` on behalf of the next future that waits for it
+    fn signal(&mut self, notification: NotificationCap) {
+        match self.signaled.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(notification),
+            // handing the signal back to the kernel is better than losing it
+            None => {
+                let _ = notification.signal();
+            }
+        }
+    }
+
+    /// Consume a signal of `notification` that the runtime took on its behalf and return whether there was one
+    fn take_signal(&mut self, notification: NotificationCap) -> bool {
+        match self
+            .signaled
+            .iter_mut()
+            .find(|slot| **slot == Some(notification))
+        {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// An executor which drives a single future, see the [module documentation](self)
+#[derive(Debug, Default)]
+pub struct Runtime {
+    interest: RefCell<Interest>,
+}
+
+impl Runtime {
+    pub const fn new() -> Self {
+        Self {
+            interest: RefCell::new(Interest {
+                sources: [None; MAX_WAIT_OBJECTS],
+                overflow: false,
+                signaled: [None; MAX_WAIT_OBJECTS],
+            }),
+        }
+    }
+
+    /// Drive `future` to completion and return its output
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        // all futures are polled again after every event so wakers are not needed
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            {
+                let mut interest = self.interest.borrow_mut();
+                interest.sources = [None; MAX_WAIT_OBJECTS];
+                interest.overflow = false;
+            }
+
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            self.wait_for_event();
+        }
+    }
+
+    /// Wait until one of the objects from the last poll is probably ready
+    fn wait_for_event(&self) {
+        let mut interest = self.interest.borrow_mut();
+        if interest.overflow {
+            let _ = syscalls::r#yield();
+            return;
+        }
+
+        let sources = interest.sources;
+        let mut caddrs = [CAddr::from_raw(0); MAX_WAIT_OBJECTS];
+        let mut len = 0;
+        for (caddr, source) in caddrs.iter_mut().zip(sources.iter().flatten()) {
+            *caddr = source.caddr();
+            len += 1;
+        }
+        if len == 0 {
+            // the future is pending for some other reason so it is simply polled again
+            let _ = syscalls::r#yield();
+            return;
+        }
+
+        // errors are reported by the futures when they poll their objects again
+        if let Ok(index) = syscalls::wait_any(&caddrs[..len]) {
+            // the kernel took the value of a ready notification on our behalf
+            if let Some(Some(Source::Notification(notification))) = sources.get(index) {
+                interest.signal(*notification);
+            }
+        }
+    }
+
+    /// Wrap `notification` so that it can be waited on asynchronously
+    pub fn notification(&self, notification: NotificationCap) -> Notification<'_> {
+        Notification {
+            rt: self,
+            cap: notification,
+        }
+    }
+
+    /// Wrap `endpoint` so that messages can be received from it asynchronously
+    pub fn endpoint(&self, endpoint: EndpointCap) -> Endpoint<'_> {
+        Endpoint {
+            rt: self,
+            cap: endpoint,
+        }
+    }
+}
+
+/// A notification which is waited on by the futures of a [`Runtime`]
+#[derive(Debug, Copy, Clone)]
+pub struct Notification<'rt> {
+    rt: &'rt Runtime,
+    cap: NotificationCap,
+}
+
+impl<'rt> Notification<'rt> {
+    /// The notification capability
+    pub fn cap(&self) -> NotificationCap {
+        self.cap
+    }
+
+    /// Wait until the notification is signaled
+    pub fn wait(&self) -> Wait<'rt> {
+        Wait {
+            rt: self.rt,
+            cap: self.cap,
+        }
+    }
+}
+
+/// The future returned by [`Notification::wait()`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Wait<'rt> {
+    rt: &'rt Runtime,
+    cap: NotificationCap,
+}
+
+impl Future for Wait<'_> {
+    type Output = SyscallResult<NoValue>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut interest = self.rt.interest.borrow_mut();
+        if interest.take_signal(self.cap) {
+            return Poll::Ready(Ok(NoValue));
+        }
+
+        match self.cap.poll() {
+            Err(e) => Poll::Ready(Err(e)),
+            Ok(0) => {
+                interest.register(Source::Notification(self.cap));
+                Poll::Pending
+            }
+            Ok(_) => Poll::Ready(Ok(NoValue)),
+        }
+    }
+}
+
+/// An endpoint from which the futures of a [`Runtime`] receive messages
+#[derive(Debug, Copy, Clone)]
+pub struct Endpoint<'rt> {
+    rt: &'rt Runtime,
+    cap: EndpointCap,
+}
+
+impl<'rt> Endpoint<'rt> {
+    /// The endpoint capability
+    pub fn cap(&self) -> EndpointCap {
+        self.cap
+    }
+
+    /// Receive the next message that is sent to the endpoint, see [`EndpointCap::receive()`]
+    pub fn recv<'a>(&self, label: usize, caps: &'a [CAddr]) -> Recv<'a>
+    where
+        'rt: 'a,
+    {
+        Recv {
+            rt: self.rt,
+            cap: self.cap,
+            label,
+            caps,
+        }
+    }
+}
+
+/// The future returned by [`Endpoint::recv()`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Recv<'a> {
+    rt: &'a Runtime,
+    cap: EndpointCap,
+    label: usize,
+    caps: &'a [CAddr],
+}
+
+impl Future for Recv<'_> {
+    type Output = SyscallResult<ReceiveReturn>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // only receive once a sender is blocked so that the receive completes immediately
+        match self.cap.has_sender() {
+            Err(e) => Poll::Ready(Err(e)),
+            Ok(true) => Poll::Ready(self.cap.receive(self.label, self.caps)),
+            Ok(false) => {
+                self.rt
+                    .interest
+                    .borrow_mut()
+                    .register(Source::Endpoint(self.cap));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The output of [`select()`], indicating which of the two futures completed first
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// Wait for whichever of the two futures completes first and drop the other one
+pub fn select<A: Future, B: Future>(a: A, b: B) -> Select<A, B> {
+    Select { a, b }
+}
+
+/// The future returned by [`select()`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Select<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: Future, B: Future> Future for Select<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: the fields are never moved out of the pinned struct
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(a) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+            return Poll::Ready(Either::Left(a));
+        }
+        if let Poll::Ready(b) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+            return Poll::Ready(Either::Right(b));
+        }
+        Poll::Pending
+    }
+}
+
+/// Wait for both futures to complete
+pub fn join<A: Future, B: Future>(a: A, b: B) -> Join<A, B> {
+    Join {
+        a: MaybeDone::Pending(a),
+        b: MaybeDone::Pending(b),
+    }
+}
+
+/// The future returned by [`join()`]
+#[must_use = "futures do nothing unless polled"]
+pub struct Join<A: Future, B: Future> {
+    a: MaybeDone<A>,
+    b: MaybeDone<B>,
+}
+
+/// A future of a [`Join`] together with its output once it completed
+enum MaybeDone<F: Future> {
+    Pending(F),
+    Done(F::Output),
+    Taken,
+}
+
+impl<F: Future> MaybeDone<F> {
+    /// Poll the future if it has not completed yet and return whether it has
+    ///
+    /// # Safety
+    /// `self` must be pinned.
+    unsafe fn poll(&mut self, cx: &mut Context<'_>) -> bool {
+        if let MaybeDone::Pending(future) = self {
+            match Pin::new_unchecked(future).poll(cx) {
+                Poll::Ready(output) => *self = MaybeDone::Done(output),
+                Poll::Pending => return false,
+            }
+        }
+        true
+    }
+
+    fn take(&mut self) -> F::Output {
+        match core::mem::replace(self, MaybeDone::Taken) {
+            MaybeDone::Done(output) => output,
+            _ => panic!("output of a joined future was taken before it completed"),
+        }
+    }
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: the futures are only dropped in place and never moved out of the pinned struct
+        let this = unsafe { self.get_unchecked_mut() };
+        let a_done = unsafe { this.a.poll(cx) };
+        let b_done = unsafe { this.b.poll(cx) };
+        if a_done && b_done {
+            Poll::Ready((this.a.take(), this.b.take()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(core::ptr::null(), &VTABLE),
+        |_| {},
+        |_| {},
+        |_| {},
+    );
+    unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) }
+}
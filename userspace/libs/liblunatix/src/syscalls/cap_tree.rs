@@ -0,0 +1,8 @@
+use crate::syscalls::syscall;
+use syscall_abi::debug::{DebugCapTree, DebugCapTreeArgs, DebugCapTreeReturn};
+use syscall_abi::{CAddr, SyscallResult};
+
+/// Write the nodes of the kernels derivation tree, beginning with the node at index `start`, into the given page
+pub fn cap_tree(page: CAddr, start: usize) -> SyscallResult<DebugCapTreeReturn> {
+    syscall::<DebugCapTree>(DebugCapTreeArgs { page, start })
+}
@@ -1,11 +1,16 @@
 use crate::syscalls::syscall;
 use syscall_abi::{
     identify::{CapabilityVariant, Identify, IdentifyArgs},
-    CAddr, SyscallResult,
+    CAddr, LookupError, SyscallResult,
 };
 
 pub fn identify(caddr: CAddr) -> SyscallResult<CapabilityVariant> {
-    syscall::<Identify>(IdentifyArgs {
-        caddr: caddr.into(),
-    })
+    identify_depth(caddr, CAddr::FULL_DEPTH).map_err(|e| e.error)
+}
+
+/// Identify the capability that the first `depth` bits of `caddr` point to.
+///
+/// On failure, the returned error also reports how many bits could be resolved.
+pub fn identify_depth(caddr: CAddr, depth: usize) -> Result<CapabilityVariant, LookupError> {
+    syscall::<Identify>(IdentifyArgs { caddr, depth })
 }
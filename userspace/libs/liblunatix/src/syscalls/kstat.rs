@@ -0,0 +1,8 @@
+use crate::syscalls::syscall;
+use syscall_abi::debug::{DebugKstat, KernelStats};
+use syscall_abi::{NoValue, SyscallResult};
+
+/// Get statistics about the capabilities and the memory that the kernel manages
+pub fn kstat() -> SyscallResult<KernelStats> {
+    syscall::<DebugKstat>(NoValue)
+}
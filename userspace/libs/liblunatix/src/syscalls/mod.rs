@@ -1,13 +1,16 @@
+mod cap_tree;
 mod copy;
 mod destroy;
 mod exit;
 mod identify;
+mod kstat;
 mod receive;
 mod send;
 #[macro_use]
 pub mod print;
 mod call;
 mod system_reset;
+mod wait_any;
 mod wait_on;
 mod r#yield;
 mod yield_to;
@@ -16,15 +19,18 @@ use core::arch::asm;
 use syscall_abi::{FromRawSysResponse, RawSyscallReturn, SyscallBinding};
 
 pub use call::call;
+pub use cap_tree::cap_tree;
 pub use copy::copy;
 pub use destroy::destroy;
 pub use exit::exit;
-pub use identify::identify;
-pub use print::{print, put_c};
+pub use identify::{identify, identify_depth};
+pub use kstat::kstat;
+pub use print::{print, put_c, read_kernel_log};
 pub use r#yield::r#yield;
 pub use receive::receive;
 pub use send::send;
 pub use system_reset::system_reset;
+pub use wait_any::wait_any;
 pub use wait_on::wait_on;
 pub use yield_to::yield_to;
 
@@ -2,12 +2,22 @@ use crate::syscalls::syscall;
 use core::fmt::{self, Write};
 use syscall_abi::debug::{DebugLog, DebugLogArgs};
 use syscall_abi::debug::{DebugPutc, DebugPutcArgs};
+use syscall_abi::debug::{DebugReadLog, DebugReadLogArgs, DebugReadLogReturn};
+use syscall_abi::SyscallResult;
 
 pub fn print(s: &str) {
     const REG_SIZE: usize = core::mem::size_of::<usize>();
     const BUF_SIZE: usize = REG_SIZE * 6;
-    for chunk in s.as_bytes().chunks(BUF_SIZE) {
-        syscall_writeslice(chunk);
+    // chunks are split at character boundaries because the kernel only accepts valid UTF-8
+    let mut remainder = s;
+    while !remainder.is_empty() {
+        let mut len = core::cmp::min(BUF_SIZE, remainder.len());
+        while !remainder.is_char_boundary(len) {
+            len -= 1;
+        }
+        let (chunk, rest) = remainder.split_at(len);
+        syscall_writeslice(chunk.as_bytes());
+        remainder = rest;
     }
 }
 
@@ -15,6 +25,13 @@ pub fn put_c(c: char) {
     syscall_putc(c)
 }
 
+/// Read recently emitted records of the kernel log, starting at the position `pos` of the log stream.
+///
+/// If `pos` was already overwritten, the returned data starts at the oldest position that the kernel still retains.
+pub fn read_kernel_log(pos: usize) -> SyscallResult<DebugReadLogReturn> {
+    syscall::<DebugReadLog>(DebugReadLogArgs { pos })
+}
+
 /// Dummy struct that makes converting [`fmt::Arguments`] easier to convert to strings
 /// by offloading that to the [`Write`] trait.
 pub struct SyscallWriter {}
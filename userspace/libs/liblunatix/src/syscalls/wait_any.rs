@@ -0,0 +1,11 @@
+use crate::syscalls::syscall;
+use syscall_abi::wait_any::{WaitAny, WaitAnyArgs};
+use syscall_abi::{CAddr, SyscallError, SyscallResult};
+
+/// Block until one of the given notifications or endpoints is ready and return its index in `objects`.
+///
+/// At most [`MAX_WAIT_OBJECTS`](syscall_abi::wait_any::MAX_WAIT_OBJECTS) objects can be waited on at once.
+pub fn wait_any(objects: &[CAddr]) -> SyscallResult<usize> {
+    let args = WaitAnyArgs::new(objects).ok_or(SyscallError::InvalidArg)?;
+    syscall::<WaitAny>(args).map(|ready| ready.index)
+}
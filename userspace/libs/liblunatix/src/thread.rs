@@ -0,0 +1,305 @@
+//! Threads which share the CSpace and VSpace of the task that spawns them.
+//!
+//! A thread is a separate task which is scheduled by the kernel alongside its spawner.
+//! Its stack is mapped into a fresh region that is reserved via the `mmap` crate and the top of that stack holds a
+//! control block to which the thread pointer (`tp`) of the thread points.
+//! This is what [`current()`] uses to find out which thread is executing.
+//! Thread-local storage is not supported: no `.tdata`/`.tbss` blocks are set up for a thread, so `tp` only points to
+//! that control block and `#[thread_local]` statics must not be used.
+//!
+//! liblunatix does not manage CSpace slots itself so the capabilities and slot allocator from which threads are
+//! created have to be configured once via [`set_thread_env()`] before [`spawn()`] is used.
+
+use crate::caps::{
+    CSpaceCap, Capability, Derive, MemoryCap, NotificationCap, PageCap, TaskCap, VSpaceCap,
+};
+use crate::syscalls;
+use core::alloc::Layout;
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{fence, Ordering};
+use syscall_abi::yield_to::TaskStatus;
+use syscall_abi::{CAddr, MapFlags, SyscallResult};
+
+const PAGESIZE: usize = 4096;
+
+/// How many pages the stack of a thread has
+pub const STACK_PAGES: usize = 4;
+
+/// The capabilities from which threads are created
+#[derive(Debug, Copy, Clone)]
+pub struct ThreadEnv {
+    /// The memory from which the tasks, stacks and notifications of threads are derived
+    pub mem: MemoryCap,
+    /// The CSpace of the calling task which is shared with its threads
+    pub cspace: CSpaceCap,
+    /// The VSpace of the calling task which is shared with its threads
+    pub vspace: VSpaceCap,
+    /// Allocate an empty slot in `cspace`
+    pub alloc_caddr: fn() -> CAddr,
+    /// Return a slot that was allocated via `alloc_caddr` and is empty again
+    pub free_caddr: fn(CAddr),
+}
+
+struct GlobalThreadEnv {
+    cell: UnsafeCell<Option<ThreadEnv>>,
+}
+
+unsafe impl Sync for GlobalThreadEnv {}
+
+static THREAD_ENV: GlobalThreadEnv = GlobalThreadEnv {
+    cell: UnsafeCell::new(None),
+};
+
+/// Configure from which capabilities threads are created.
+///
+/// # Safety
+/// This must be called once, before any thread is spawned.
+pub unsafe fn set_thread_env(env: ThreadEnv) {
+    let inner = THREAD_ENV.cell.get().as_mut().unwrap();
+    assert!(inner.is_none());
+    let _ = inner.insert(env);
+}
+
+fn thread_env() -> ThreadEnv {
+    unsafe { *THREAD_ENV.cell.get() }
+        .expect("set_thread_env() was not called before spawning a thread")
+}
+
+/// A thread that was started by [`spawn()`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Thread {
+    task: TaskCap,
+}
+
+impl Thread {
+    /// The task which executes the thread
+    pub fn task(&self) -> TaskCap {
+        self.task
+    }
+}
+
+/// The thread that is currently executing.
+///
+/// Returns `None` when called outside of a thread that was started by [`spawn()`], e.g. in the main thread of a task.
+pub fn current() -> Option<Thread> {
+    // Safety: the thread pointer is either 0 or points to the control block at the top of the threads stack
+    unsafe { (thread_pointer() as *const ThreadControlBlock).as_ref() }
+        .map(|tcb| Thread { task: tcb.task })
+}
+
+/// The data to which the thread pointer of a thread points
+#[derive(Debug)]
+#[repr(C)]
+struct ThreadControlBlock {
+    task: TaskCap,
+    /// Signaled once the thread has stored its result
+    exited: NotificationCap,
+}
+
+/// Everything that is passed to a thread at the top of its stack
+#[repr(C)]
+struct Packet<F, T> {
+    /// The control block needs to come first so that [`current()`] can find it without knowing `F` and `T`
+    tcb: ThreadControlBlock,
+    result: Option<T>,
+    closure: Option<F>,
+}
+
+/// Run `f` in a new thread.
+///
+/// The thread starts running right away and its result can be retrieved by [`JoinHandle::join()`].
+/// Dropping the handle detaches the thread which then keeps its capabilities and stack forever.
+pub fn spawn<F, T>(f: F) -> SyscallResult<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    assert!(
+        mem::size_of::<Packet<F, T>>() <= STACK_PAGES * PAGESIZE / 2,
+        "the closure of a thread must not take up more than half of its stack"
+    );
+    let env = thread_env();
+    let mut resources = Resources::default();
+    match resources.start(env, f) {
+        Ok((thread, result)) => Ok(JoinHandle {
+            thread,
+            resources,
+            result,
+        }),
+        Err(e) => {
+            resources.release(env);
+            Err(e)
+        }
+    }
+}
+
+/// An owned permission to wait for a thread to finish, see [`spawn()`]
+#[derive(Debug)]
+#[must_use = "dropping a JoinHandle detaches the thread"]
+pub struct JoinHandle<T> {
+    thread: Thread,
+    resources: Resources,
+    result: *mut Option<T>,
+}
+
+impl<T> JoinHandle<T> {
+    /// The thread that is being waited on
+    pub fn thread(&self) -> Thread {
+        self.thread
+    }
+
+    /// Block until the thread has finished, release its stack and capabilities and return its result
+    pub fn join(self) -> SyscallResult<T> {
+        let env = thread_env();
+        self.resources.exited.unwrap().wait()?;
+        fence(Ordering::Acquire);
+
+        // Safety: the thread does not touch its packet anymore after signaling that it exited
+        let result =
+            unsafe { (*self.result).take() }.expect("thread signaled its exit without a result");
+
+        // the thread signals before its exit syscall and may still be running on another hart, so its stack is only
+        // released once the kernel reports the task as exited
+        loop {
+            match self.thread.task.yield_to()? {
+                TaskStatus::Exited => break,
+                TaskStatus::AlreadyRunning => {
+                    syscalls::r#yield()?;
+                }
+                TaskStatus::DidExecute | TaskStatus::Blocked => {}
+            }
+        }
+
+        let mut resources = self.resources;
+        resources.release(env);
+        Ok(result)
+    }
+}
+
+/// The capabilities which belong to a thread
+#[derive(Debug, Default)]
+struct Resources {
+    task: Option<TaskCap>,
+    exited: Option<NotificationCap>,
+    stack: [Option<PageCap>; STACK_PAGES],
+}
+
+impl Resources {
+    /// Create and start the thread while recording everything it owns in `self`
+    fn start<F, T>(&mut self, env: ThreadEnv, f: F) -> SyscallResult<(Thread, *mut Option<T>)>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let task: TaskCap = derive(env)?;
+        self.task = Some(task);
+        let exited: NotificationCap = derive(env)?;
+        self.exited = Some(exited);
+
+        // map the stack
+        let layout = Layout::from_size_align(STACK_PAGES * PAGESIZE, PAGESIZE).unwrap();
+        let region =
+            mmap::allocate_raw(layout).expect("could not reserve address space for a thread stack");
+        for (i, slot) in self.stack.iter_mut().enumerate() {
+            let page: PageCap = derive(env)?;
+            *slot = Some(page);
+            page.map(
+                env.vspace,
+                env.mem,
+                region.start as usize + i * PAGESIZE,
+                MapFlags::READ | MapFlags::WRITE,
+            )?;
+        }
+
+        // place the packet at the top of the stack and let the stack grow down from below it
+        let stack_top = region.start as usize + region.bytes;
+        let packet_addr = (stack_top - mem::size_of::<Packet<F, T>>())
+            & !(mem::align_of::<Packet<F, T>>().max(16) - 1);
+        let packet = packet_addr as *mut Packet<F, T>;
+
+        task.assign_cspace(env.cspace)?;
+        task.assign_vspace(env.vspace)?;
+        task.assign_control_registers(
+            thread_start::<F, T> as extern "C" fn() -> ! as usize,
+            packet_addr,
+            global_pointer(),
+            packet_addr,
+        )?;
+        unsafe {
+            packet.write(Packet {
+                tcb: ThreadControlBlock { task, exited },
+                result: None,
+                closure: Some(f),
+            })
+        };
+        fence(Ordering::Release);
+        if let Err(e) = task.start() {
+            // the thread never ran so the closure is still in the packet
+            unsafe { ptr::drop_in_place(packet) };
+            return Err(e);
+        }
+
+        Ok((Thread { task }, unsafe {
+            ptr::addr_of_mut!((*packet).result)
+        }))
+    }
+
+    /// Destroy all capabilities of the thread and free their slots
+    fn release(&mut self, env: ThreadEnv) {
+        // the task goes first so that it cannot be started anymore once its stack is unmapped
+        if let Some(task) = self.task.take() {
+            release(env, task);
+        }
+        if let Some(exited) = self.exited.take() {
+            release(env, exited);
+        }
+        for page in self.stack.iter_mut().filter_map(Option::take) {
+            release(env, page);
+        }
+    }
+}
+
+/// Derive a capability into a newly allocated slot
+fn derive<T: Derive>(env: ThreadEnv) -> SyscallResult<T> {
+    let slot = (env.alloc_caddr)();
+    env.mem.derive(slot).map_err(|e| {
+        (env.free_caddr)(slot);
+        e
+    })
+}
+
+/// Destroy a capability that was created by [`derive()`] and free its slot
+fn release<T: Capability>(env: ThreadEnv, cap: T) {
+    // a slot whose capability could not be destroyed must not be handed out again
+    if syscalls::destroy(cap.caddr()).is_ok() {
+        (env.free_caddr)(cap.caddr());
+    }
+}
+
+/// Entry point of every thread.
+///
+/// The thread pointer points to the [`Packet`] which contains the closure that is executed.
+extern "C" fn thread_start<F: FnOnce() -> T, T>() -> ! {
+    let packet = unsafe { &mut *(thread_pointer() as *mut Packet<F, T>) };
+    let f = packet.closure.take().unwrap();
+    packet.result = Some(f());
+
+    fence(Ordering::Release);
+    let _ = packet.tcb.exited.signal();
+    syscalls::exit();
+}
+
+fn thread_pointer() -> usize {
+    let tp: usize;
+    unsafe { asm!("mv {}, tp", out(reg) tp) };
+    tp
+}
+
+fn global_pointer() -> usize {
+    let gp: usize;
+    unsafe { asm!("mv {}, gp", out(reg) gp) };
+    gp
+}
@@ -1,8 +1,12 @@
 #![no_std]
 
 use core::alloc::Layout;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-static mut MMAP_ALLOC: *mut u8 = 0x2a_0000_0000 as *mut u8;
+/// The start of the address space that has not been handed out yet.
+///
+/// This is atomic because threads on several harts may reserve regions at the same time.
+static MMAP_ALLOC: AtomicUsize = AtomicUsize::new(0x2a_0000_0000);
 
 #[derive(Debug)]
 pub struct RawRegion {
@@ -11,27 +15,28 @@ pub struct RawRegion {
 }
 
 pub fn allocate_raw(layout: Layout) -> Result<RawRegion, ()> {
-    log::debug!("mmap alloc: {:?}, start: {:p}", &layout, unsafe {
-        MMAP_ALLOC
-    });
+    log::debug!(
+        "mmap alloc: {:?}, start: {:#x}",
+        &layout,
+        MMAP_ALLOC.load(Ordering::Relaxed)
+    );
     assert!(layout.size() > 0);
     // only alloc with page alignment
     let layout = layout.align_to(4096).unwrap();
 
-    // get aligned start pointer
-    let start_unaligned = unsafe { MMAP_ALLOC };
-    let align_offset = start_unaligned.align_offset(layout.align());
-    assert!(align_offset != usize::MAX);
-    let start = start_unaligned.wrapping_add(align_offset);
-    assert!(start.align_offset(layout.align()) == 0);
-
-    // update end pointer
-    // hack because some code allocs outside of region
-    let end = start.wrapping_add(layout.size() + 4096);
-    unsafe { MMAP_ALLOC = end };
+    // get aligned start address and update the end address in one step so that no region is handed out twice
+    let mut start = 0;
+    MMAP_ALLOC
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |start_unaligned| {
+            start = (start_unaligned + layout.align() - 1) & !(layout.align() - 1);
+            // hack because some code allocs outside of region
+            Some(start + layout.size() + 4096)
+        })
+        .unwrap();
+    assert!(start % layout.align() == 0);
 
     let res = RawRegion {
-        start,
+        start: start as *mut u8,
         bytes: layout.size(),
     };
     log::debug!("result: {:0x?}", &res);
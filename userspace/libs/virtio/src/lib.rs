@@ -4,9 +4,9 @@ use core::alloc::Layout;
 
 use bitflags::bitflags;
 use caddr_alloc;
+use liblunatix::caps::{MemoryCap, PageCap, VSpaceCap};
 use liblunatix::prelude::syscall_abi::MapFlags;
-use liblunatix::prelude::CAddr;
-use liblunatix::{prelude::syscall_abi::identify::CapabilityVariant, println};
+use liblunatix::println;
 use mmap::RawRegion;
 use regs::{RO, RW, WO};
 
@@ -291,7 +291,11 @@ impl VirtQ {
     }
 }
 
-fn queue_alloc(mem: CAddr, vspace: CAddr, region: RawRegion) -> Result<(*mut u8, usize), ()> {
+fn queue_alloc(
+    mem: MemoryCap,
+    vspace: VSpaceCap,
+    region: RawRegion,
+) -> Result<(*mut u8, usize), ()> {
     let queue_bytes = region.bytes;
     let base_ptr = region.start;
     const PAGESIZE: usize = 4096;
@@ -305,31 +309,22 @@ fn queue_alloc(mem: CAddr, vspace: CAddr, region: RawRegion) -> Result<(*mut u8,
     // map one page as buffer because virtqueue pages have to be physically contigious
     // and we can't guarantee that, because mapping in a vspace uses pages..
     {
-        let page = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(mem, page, CapabilityVariant::Page, None).unwrap();
-        liblunatix::ipc::page::map_page(
-            page,
-            vspace,
-            mem,
-            addr as usize,
-            MapFlags::READ | MapFlags::WRITE,
-        )
-        .unwrap();
+        let page: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+        page.map(vspace, mem, addr as usize, MapFlags::READ | MapFlags::WRITE)
+            .unwrap();
     }
     let addr = (addr as usize + PAGESIZE) as *mut u8;
     let mut paddr = None;
     for i in 0..pages {
-        let page = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(mem, page, CapabilityVariant::Page, None).unwrap();
-        let this_paddr = liblunatix::ipc::page::get_paddr(page).unwrap();
+        let page: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+        let this_paddr = page.paddr().unwrap();
         paddr.get_or_insert(this_paddr);
         assert_eq!(
             paddr,
             Some(this_paddr - i * PAGESIZE),
             "non consecutive physical pages for virtio driver"
         );
-        liblunatix::ipc::page::map_page(
-            page,
+        page.map(
             vspace,
             mem,
             addr as usize + i * PAGESIZE,
@@ -374,8 +369,8 @@ pub fn queue_calc_layout(queue_len: usize) -> core::alloc::Layout {
 pub fn queue_setup(
     dev: &mut VirtDeviceMM,
     queue_num: u32,
-    mem: CAddr,
-    vspace: CAddr,
+    mem: MemoryCap,
+    vspace: VSpaceCap,
 ) -> Result<VirtQ, ()> {
     let max_items = queue_get_size(dev, queue_num)?;
     let queue_len = core::cmp::min(max_items as usize, 256);
@@ -446,7 +441,7 @@ pub fn queue_setup(
 pub struct VirtQMsgBuf {
     pub buf: &'static mut [u8],
     pub paddr: usize,
-    pub page: CAddr,
+    pub page: PageCap,
 }
 
 impl VirtQMsgBuf {
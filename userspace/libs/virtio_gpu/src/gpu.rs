@@ -2,10 +2,10 @@ use core::{alloc::Layout, borrow::Borrow, sync::atomic::AtomicU64};
 
 use alloc::vec;
 use liblunatix::{
-    prelude::{
-        syscall_abi::{identify::CapabilityVariant, MapFlags},
-        CAddr,
+    caps::{
+        CSpaceCap, DevmemCap, IrqCap, IrqControlCap, MemoryCap, NotificationCap, PageCap, VSpaceCap,
     },
+    prelude::{syscall_abi::MapFlags, CAddr, Capability},
     println, MemoryPage,
 };
 
@@ -235,14 +235,14 @@ pub struct GpuDriver {
     device: &'static mut VirtDeviceMM,
     ctrl_q: VirtQ,
     cursor_q: VirtQ,
-    noti: CAddr,
-    irq: CAddr,
+    noti: NotificationCap,
+    irq: IrqCap,
     req_buf: VirtQMsgBuf,
     res_buf: VirtQMsgBuf,
 }
 
 pub struct GpuFramebuffer {
-    pub page_cspace: CAddr,
+    pub page_cspace: CSpaceCap,
     pub resource_id: u32,
     pub scanout: u32,
     pub width: u32,
@@ -250,23 +250,22 @@ pub struct GpuFramebuffer {
     pub buf: &'static mut [u32],
 }
 
-fn alloc_msg_buf(mem: CAddr, vspace: CAddr) -> VirtQMsgBuf {
+fn alloc_msg_buf(mem: MemoryCap, vspace: VSpaceCap) -> VirtQMsgBuf {
     let region = mmap::allocate_raw(Layout::new::<MemoryPage>()).unwrap();
-    let page1 = caddr_alloc::alloc_caddr();
-    liblunatix::ipc::mem::derive(mem, page1, CapabilityVariant::Page, None).unwrap();
-    liblunatix::ipc::page::map_page(
-        page1,
-        vspace,
-        mem,
-        region.start as usize,
-        MapFlags::READ | MapFlags::WRITE,
-    )
-    .unwrap();
+    let page1: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+    page1
+        .map(
+            vspace,
+            mem,
+            region.start as usize,
+            MapFlags::READ | MapFlags::WRITE,
+        )
+        .unwrap();
 
     return VirtQMsgBuf {
         buf: unsafe { core::slice::from_raw_parts_mut(region.start, 4096) },
         page: page1,
-        paddr: liblunatix::ipc::page::get_paddr(page1).unwrap(),
+        paddr: page1.paddr().unwrap(),
     };
 }
 
@@ -308,7 +307,7 @@ fn gpu_do_request<'r, T, R>(
     // Last, but not least, a single used event can have multiple chained descriptors, so we handle those in a loop as well.
     let mut done = false;
     while !done {
-        liblunatix::syscalls::wait_on(driver.noti).unwrap();
+        driver.noti.wait().unwrap();
         let used_idx = *driver.ctrl_q.used.idx % driver.ctrl_q.descriptor_table.len() as u16;
         while last_used != used_idx {
             let used_elem = driver.ctrl_q.used.ring[last_used as usize];
@@ -329,22 +328,28 @@ fn gpu_do_request<'r, T, R>(
             last_used = (last_used + 1) % driver.ctrl_q.descriptor_table.len() as u16;
         }
 
-        liblunatix::ipc::irq::irq_complete(driver.irq).unwrap();
+        driver.irq.complete().unwrap();
     }
 
     unsafe { res_buf.buf.as_ptr().cast::<R>().as_ref().unwrap() }
 }
 
-fn assert_phys_cont(pages: &[CAddr]) {
+fn assert_phys_cont(pages: &[PageCap]) {
     for i in 1..pages.len() {
-        let prev = liblunatix::ipc::page::get_paddr(pages[i - 1]).unwrap();
-        let cur = liblunatix::ipc::page::get_paddr(pages[i]).unwrap();
+        let prev = pages[i - 1].paddr().unwrap();
+        let cur = pages[i].paddr().unwrap();
         assert_eq!(prev + 4096, cur, "pages are not physically contigous");
     }
 }
 
-pub fn init_gpu_driver(mem: CAddr, vspace: CAddr, devmem: CAddr, irq_control: CAddr) -> GpuDriver {
-    liblunatix::ipc::devmem::devmem_map(devmem, mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
+pub fn init_gpu_driver(
+    mem: MemoryCap,
+    vspace: VSpaceCap,
+    devmem: DevmemCap,
+    irq_control: IrqControlCap,
+) -> GpuDriver {
+    devmem
+        .map(mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
         .unwrap();
     let driver = unsafe {
         let device = VirtDeviceMM::at(VIRTIO_DEVICE as *mut VirtDeviceMM);
@@ -353,11 +358,10 @@ pub fn init_gpu_driver(mem: CAddr, vspace: CAddr, devmem: CAddr, irq_control: CA
         status = device.negotiate_features(status, 0 as u64);
 
         // setup an irq handler for the virtio device
-        let irq_notif = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(mem, irq_notif, CapabilityVariant::Notification, None)
+        let irq_notif: NotificationCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+        let irq = irq_control
+            .claim(0x07, caddr_alloc::alloc_caddr(), irq_notif)
             .unwrap();
-        let irq = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::irq_control::irq_control_claim(irq_control, 0x07, irq, irq_notif).unwrap();
 
         let ctrl_q = virtio::queue_setup(device, 0, mem, vspace).unwrap();
         let cursor_q = virtio::queue_setup(device, 1, mem, vspace).unwrap();
@@ -405,8 +409,8 @@ impl GpuDriver {
 
     pub fn create_resource(
         &mut self,
-        mem: CAddr,
-        vspace: CAddr,
+        mem: MemoryCap,
+        vspace: VSpaceCap,
         resource_id: u32,
         scanout: u32,
         width: u32,
@@ -432,45 +436,29 @@ impl GpuDriver {
             CtrlType::RESP_OK_NODATA
         );
 
-        let fb_cspace = caddr_alloc::alloc_caddr();
         const FB_BITS: usize = 10;
-        liblunatix::ipc::mem::derive(
-            mem,
-            fb_cspace,
-            CapabilityVariant::CSpace,
-            Some(1 << FB_BITS),
-        )
-        .expect("creating CSpace failed");
-        assert_eq!(
-            CapabilityVariant::CSpace,
-            liblunatix::syscalls::identify(fb_cspace).unwrap()
-        );
+        let fb_cspace = mem
+            .derive_cspace(caddr_alloc::alloc_caddr(), 1 << FB_BITS)
+            .expect("creating CSpace failed");
         let mut pages = vec![];
         for i in 0..page_count {
             let page_addr = CAddr::builder()
-                .part(fb_cspace.raw(), cspace_bits)
+                .part(fb_cspace.caddr().raw(), cspace_bits)
                 .part(i, FB_BITS)
                 .finish();
             // println!("{:064b} page cspace addr", page_addr);
-            liblunatix::ipc::mem::derive(mem, page_addr, CapabilityVariant::Page, None)
-                .expect("failed deriving page");
-            pages.push(page_addr);
+            let page: PageCap = mem.derive(page_addr).expect("failed deriving page");
+            pages.push(page);
         }
         assert_phys_cont(&pages);
-        let phys_addr = liblunatix::ipc::page::get_paddr(pages[0]).unwrap();
+        let phys_addr = pages[0].paddr().unwrap();
         let fb_region =
             mmap::allocate_raw(Layout::from_size_align(PAGESIZE * page_count, 4096).unwrap())
                 .unwrap();
         for (i, page) in pages.iter().enumerate() {
             let addr = unsafe { fb_region.start.add(i * PAGESIZE) };
-            liblunatix::ipc::page::map_page(
-                *page,
-                vspace,
-                mem,
-                addr as usize,
-                MapFlags::READ | MapFlags::WRITE,
-            )
-            .unwrap();
+            page.map(vspace, mem, addr as usize, MapFlags::READ | MapFlags::WRITE)
+                .unwrap();
         }
         let fb_buf = unsafe {
             core::slice::from_raw_parts_mut(
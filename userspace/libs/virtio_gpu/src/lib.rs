@@ -4,7 +4,8 @@ use core::cell::RefCell;
 
 use alloc::{rc::Rc, vec};
 use gpu::GpuDriver;
-use liblunatix::{prelude::CAddr, println};
+use liblunatix::caps::{MemoryCap, VSpaceCap};
+use liblunatix::println;
 use vga::FramebufferFlushWriter;
 
 use crate::vga::{Pos, VGABuffer, VGAChar};
@@ -17,8 +18,8 @@ pub mod vga;
 
 pub fn create_gpu_writer(
     gpu: Rc<RefCell<GpuDriver>>,
-    mem: CAddr,
-    vspace: CAddr,
+    mem: MemoryCap,
+    vspace: VSpaceCap,
     cspace_bits: usize,
 ) -> FramebufferFlushWriter {
     let mut driver = gpu.borrow_mut();
@@ -4,8 +4,10 @@ use core::{
 };
 
 use liblunatix::{
-    prelude::{syscall_abi::MapFlags, CAddr, CapabilityVariant},
+    caps::{DevmemCap, IrqCap, IrqControlCap, MemoryCap, NotificationCap, PageCap, VSpaceCap},
+    prelude::syscall_abi::MapFlags,
     println,
+    rt::Runtime,
 };
 use little_endian::LE;
 use virtio::{DescriptorFlags, DeviceId, VirtDevice, VirtDeviceMM, VirtQ, VirtQMsgBuf};
@@ -113,24 +115,23 @@ pub unsafe fn read_input_config(config: *mut InputConfig) {
 }
 
 /// Allocate two buffers from the memory capability that are used for storing the actual P9 messages
-fn prepare_msg_bufs(mem: CAddr, vspace: CAddr, size: usize) -> VirtQMsgBuf {
+fn prepare_msg_bufs(mem: MemoryCap, vspace: VSpaceCap, size: usize) -> VirtQMsgBuf {
     let buf_region = mmap::allocate_raw(Layout::array::<Event>(size).unwrap()).unwrap();
     assert!(buf_region.bytes < 4096);
-    let page1 = caddr_alloc::alloc_caddr();
-    liblunatix::ipc::mem::derive(mem, page1, CapabilityVariant::Page, None).unwrap();
-    liblunatix::ipc::page::map_page(
-        page1,
-        vspace,
-        mem,
-        buf_region.start as usize,
-        MapFlags::READ | MapFlags::WRITE,
-    )
-    .unwrap();
+    let page1: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+    page1
+        .map(
+            vspace,
+            mem,
+            buf_region.start as usize,
+            MapFlags::READ | MapFlags::WRITE,
+        )
+        .unwrap();
 
     VirtQMsgBuf {
         buf: unsafe { core::slice::from_raw_parts_mut(buf_region.start, buf_region.bytes) },
         page: page1,
-        paddr: liblunatix::ipc::page::get_paddr(page1).unwrap(),
+        paddr: page1.paddr().unwrap(),
     }
 }
 
@@ -140,19 +141,40 @@ pub struct InputDriver {
     event_q: VirtQ,
     status_q: VirtQ,
     event_used_ack: u16,
-    irq: CAddr,
-    noti: CAddr,
+    irq: IrqCap,
+    noti: NotificationCap,
     event_buf: VirtQMsgBuf,
 }
 
 impl InputDriver {
     pub unsafe fn read_event(&mut self) -> Event {
+        loop {
+            if let Some(event) = self.take_event() {
+                return event;
+            }
+            self.irq.complete().unwrap();
+            self.noti.wait().unwrap();
+        }
+    }
+
+    /// Like [`read_event()`](Self::read_event) but waits for the device through `rt` so that other futures can
+    /// make progress in the meantime
+    pub async unsafe fn next_event(&mut self, rt: &Runtime) -> Event {
+        loop {
+            if let Some(event) = self.take_event() {
+                return event;
+            }
+            self.irq.complete().unwrap();
+            rt.notification(self.noti).wait().await.unwrap();
+        }
+    }
+
+    /// Take the next event from the event queue if the device has produced one
+    unsafe fn take_event(&mut self) -> Option<Event> {
         let idx_addr = addr_of!(*self.event_q.used.idx);
-        while self.event_used_ack == idx_addr.read_volatile() {
-            liblunatix::ipc::irq::irq_complete(self.irq).unwrap();
-            liblunatix::syscalls::wait_on(self.noti).unwrap();
+        if self.event_used_ack == idx_addr.read_volatile() {
+            return None;
         }
-        assert_ne!(self.event_used_ack, idx_addr.read_volatile());
         let used_idx = self.event_used_ack % self.event_q.descriptor_table.len() as u16;
         self.event_used_ack = self.event_used_ack.wrapping_add(1);
         let used_elem = addr_of!(self.event_q.used.ring[used_idx as usize]).read_volatile();
@@ -166,18 +188,19 @@ impl InputDriver {
         );
         let event = addr_of!(buf[buf_idx]).read_volatile();
         self.event_q.avail.insert_request(desc_idx as u16);
-        return event;
+        Some(event)
     }
 }
 
 pub fn init_input_driver(
-    mem: CAddr,
-    vspace: CAddr,
-    devmem: CAddr,
-    irq_control: CAddr,
+    mem: MemoryCap,
+    vspace: VSpaceCap,
+    devmem: DevmemCap,
+    irq_control: IrqControlCap,
 ) -> InputDriver {
     println!("input driver:");
-    liblunatix::ipc::devmem::devmem_map(devmem, mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
+    devmem
+        .map(mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
         .unwrap();
     let driver = unsafe {
         let device: VirtDevice<InputConfig> = VirtDevice::at(VIRTIO_DEVICE as *mut VirtDeviceMM);
@@ -189,11 +212,10 @@ pub fn init_input_driver(
         status = device.mm.negotiate_features(status, 0 as u64);
 
         // setup an irq handler for the virtio device
-        let irq_notif = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(mem, irq_notif, CapabilityVariant::Notification, None)
+        let irq_notif: NotificationCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+        let irq = irq_control
+            .claim(0x06, caddr_alloc::alloc_caddr(), irq_notif)
             .unwrap();
-        let irq = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::irq_control::irq_control_claim(irq_control, 0x06, irq, irq_notif).unwrap();
 
         let mut event_q = virtio::queue_setup(device.mm, 0, mem, vspace).unwrap();
         let status_q = virtio::queue_setup(device.mm, 1, mem, vspace).unwrap();
@@ -1,6 +1,7 @@
 #![no_std]
 
 use io::read::ByteReader;
+use liblunatix::rt::Runtime;
 
 pub mod event_codes;
 pub mod input;
@@ -27,3 +28,15 @@ impl<K: Keyboard> ByteReader for VirtioByteReader<K> {
         }
     }
 }
+
+impl<K: Keyboard> VirtioByteReader<K> {
+    /// Wait through `rt` until a key that produces a character is pressed
+    pub async fn next_byte(&mut self, rt: &Runtime) -> u8 {
+        loop {
+            let event = unsafe { self.input.next_event(rt).await };
+            if let Some(c) = self.keyboard.process_evdev_event(event) {
+                return c as u8;
+            }
+        }
+    }
+}
@@ -7,9 +7,11 @@ use io::read::Reader;
 use virtio::{DeviceFeaturesLow, DeviceId, VirtDeviceMM};
 
 use caddr_alloc;
+use liblunatix::caps::{
+    DevmemCap, IrqCap, IrqControlCap, MemoryCap, NotificationCap, PageCap, VSpaceCap,
+};
 use liblunatix::prelude::syscall_abi::MapFlags;
-use liblunatix::prelude::CAddr;
-use liblunatix::{prelude::syscall_abi::identify::CapabilityVariant, MemoryPage};
+use liblunatix::MemoryPage;
 
 use p9::{
     P9FileFlags, P9FileMode, P9Qid, P9RequestBuilder, RClunk, ROpen, RRead, RVersion, RWalk,
@@ -21,52 +23,51 @@ const VIRTIO_DEVICE: usize = 0x10008000;
 const VIRTIO_DEVICE_LEN: usize = 0x1000;
 
 /// Allocate two buffers from the memory capability that are used for storing the actual P9 messages
-fn prepare_msg_bufs(mem: CAddr, vspace: CAddr) -> (VirtQMsgBuf, VirtQMsgBuf) {
+fn prepare_msg_bufs(mem: MemoryCap, vspace: VSpaceCap) -> (VirtQMsgBuf, VirtQMsgBuf) {
     let buf_region = mmap::allocate_raw(Layout::new::<MemoryPage>()).unwrap();
-    let page1 = caddr_alloc::alloc_caddr();
-    liblunatix::ipc::mem::derive(mem, page1, CapabilityVariant::Page, None).unwrap();
-    liblunatix::ipc::page::map_page(
-        page1,
-        vspace,
-        mem,
-        buf_region.start as usize,
-        MapFlags::READ | MapFlags::WRITE,
-    )
-    .unwrap();
+    let page1: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+    page1
+        .map(
+            vspace,
+            mem,
+            buf_region.start as usize,
+            MapFlags::READ | MapFlags::WRITE,
+        )
+        .unwrap();
 
     let buf2_region = mmap::allocate_raw(Layout::new::<MemoryPage>()).unwrap();
-    let page2 = caddr_alloc::alloc_caddr();
-    liblunatix::ipc::mem::derive(mem, page2, CapabilityVariant::Page, None).unwrap();
-    liblunatix::ipc::page::map_page(
-        page2,
-        vspace,
-        mem,
-        buf2_region.start as usize,
-        MapFlags::READ | MapFlags::WRITE,
-    )
-    .unwrap();
+    let page2: PageCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+    page2
+        .map(
+            vspace,
+            mem,
+            buf2_region.start as usize,
+            MapFlags::READ | MapFlags::WRITE,
+        )
+        .unwrap();
 
     (
         VirtQMsgBuf {
             buf: unsafe { core::slice::from_raw_parts_mut(buf_region.start, buf_region.bytes) },
             page: page1,
-            paddr: liblunatix::ipc::page::get_paddr(page1).unwrap(),
+            paddr: page1.paddr().unwrap(),
         },
         VirtQMsgBuf {
             buf: unsafe { core::slice::from_raw_parts_mut(buf2_region.start, buf2_region.bytes) },
             page: page2,
-            paddr: liblunatix::ipc::page::get_paddr(page2).unwrap(),
+            paddr: page2.paddr().unwrap(),
         },
     )
 }
 
 pub fn init_9p_driver(
-    mem: CAddr,
-    vspace: CAddr,
-    devmem: CAddr,
-    irq_control: CAddr,
+    mem: MemoryCap,
+    vspace: VSpaceCap,
+    devmem: DevmemCap,
+    irq_control: IrqControlCap,
 ) -> P9Driver<'static> {
-    liblunatix::ipc::devmem::devmem_map(devmem, mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
+    devmem
+        .map(mem, vspace, VIRTIO_DEVICE, VIRTIO_DEVICE_LEN)
         .unwrap();
     let mut driver = unsafe {
         let device = VirtDeviceMM::at(VIRTIO_DEVICE as *mut VirtDeviceMM);
@@ -79,11 +80,10 @@ pub fn init_9p_driver(
         status = device.negotiate_features(status, DeviceFeaturesLow::NINEP_TAGGED.bits() as u64);
 
         // setup an irq handler for the virtio device
-        let irq_notif = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::mem::derive(mem, irq_notif, CapabilityVariant::Notification, None)
+        let irq_notif: NotificationCap = mem.derive(caddr_alloc::alloc_caddr()).unwrap();
+        let irq = irq_control
+            .claim(0x08, caddr_alloc::alloc_caddr(), irq_notif)
             .unwrap();
-        let irq = caddr_alloc::alloc_caddr();
-        liblunatix::ipc::irq_control::irq_control_claim(irq_control, 0x08, irq, irq_notif).unwrap();
 
         let queue = virtio::queue_setup(device, 0, mem, vspace).unwrap();
         let (req_buf, resp_buf) = prepare_msg_bufs(mem, vspace);
@@ -118,8 +118,8 @@ pub fn init_9p_driver(
 pub struct P9Driver<'mm> {
     device: &'mm VirtDeviceMM,
     queue: VirtQ,
-    noti: CAddr,
-    irq: CAddr,
+    noti: NotificationCap,
+    irq: IrqCap,
     req: VirtQMsgBuf,
     res: VirtQMsgBuf,
 }
@@ -164,7 +164,7 @@ impl<'mm> P9Driver<'mm> {
         };
 
         self.device.notify(0);
-        liblunatix::syscalls::wait_on(self.noti).unwrap();
+        self.noti.wait().unwrap();
         self.queue.descriptor_table[resp_idx].free();
         self.queue.descriptor_table[req_idx].free();
     }
@@ -303,7 +303,7 @@ fn p9_handshake(driver: &mut P9Driver) {
     assert_eq!(msize, 4096);
     assert_eq!(version, "9P2000.u");
 
-    liblunatix::ipc::irq::irq_complete(irq).unwrap();
+    irq.complete().unwrap();
 }
 
 /// Attach us to a servers file tree
@@ -317,7 +317,7 @@ fn p9_attach(driver: &mut P9Driver, attach: TAttach) -> P9Qid {
         panic!()
     };
 
-    liblunatix::ipc::irq::irq_complete(driver.irq).unwrap();
+    driver.irq.complete().unwrap();
     resp.qid
 }
 
@@ -325,7 +325,7 @@ fn p9_attach(driver: &mut P9Driver, attach: TAttach) -> P9Qid {
 fn p9_walk(driver: &mut P9Driver, walk: TWalk) -> RWalk {
     let res = driver.do_request(p9::Request::Walk(walk)).unwrap();
     let Response::Walk(resp) = res else { panic!() };
-    liblunatix::ipc::irq::irq_complete(driver.irq).unwrap();
+    driver.irq.complete().unwrap();
     resp
 }
 
@@ -333,7 +333,7 @@ fn p9_open(driver: &mut P9Driver, open: TOpen) -> ROpen {
     let res = driver.do_request(p9::Request::Open(open)).unwrap();
     let Response::Open(resp) = res else { panic!() };
 
-    liblunatix::ipc::irq::irq_complete(driver.irq).unwrap();
+    driver.irq.complete().unwrap();
     resp
 }
 
@@ -341,7 +341,7 @@ fn p9_clunk(driver: &mut P9Driver, clunk: TClunk) -> RClunk {
     let res = driver.do_request(p9::Request::Clunk(clunk)).unwrap();
     let Response::Clunk(resp) = res else { panic!() };
 
-    liblunatix::ipc::irq::irq_complete(driver.irq).unwrap();
+    driver.irq.complete().unwrap();
     resp
 }
 
@@ -350,6 +350,6 @@ fn p9_read<'resp>(driver: &'resp mut P9Driver, read: TRead) -> RRead<'resp> {
     let res = driver.do_request(p9::Request::Read(read)).unwrap();
     let Response::Read(resp) = res else { panic!() };
 
-    liblunatix::ipc::irq::irq_complete(irq).unwrap();
+    irq.complete().unwrap();
     resp
 }